@@ -1,22 +1,26 @@
-use base64::Engine;
-use rand::Rng;
 use serde::Deserialize;
-use sha2::{Digest, Sha256};
 use std::fs;
 use std::path::PathBuf;
 use std::time::{SystemTime, UNIX_EPOCH};
 use url::Url;
 
-use crate::auth::common::{TokenInfo, analyze_token_source, choose_best_token_source};
+use crate::auth::OAuthProvider;
+use crate::auth::common::{
+    TokenInfo, analyze_token_source, choose_best_token_source, generate_pkce_pair, generate_state,
+};
 use crate::config::AuthConfig;
 use crate::error::{Result, PrismError};
 
 const CLIENT_ID: &str = "9d1c250a-e61b-44d9-88ed-5944d1962f5e";
+/// Redirect target that shows the code for pasting into the terminal
+const MANUAL_REDIRECT_URI: &str = "https://console.anthropic.com/oauth/code/callback";
 
 #[derive(Debug, Clone)]
 pub struct AuthorizeResult {
     pub url: String,
     pub verifier: String,
+    pub state: String,
+    pub redirect_uri: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -49,22 +53,9 @@ struct ClaudeCodeCredentials {
 pub struct AnthropicOAuth;
 
 impl AnthropicOAuth {
-    /// Attempt to read Claude Code OAuth credentials from ~/.config/claude/.credentials.json
+    /// Attempt to read Claude Code OAuth credentials from the Claude CLI credentials file
     pub fn try_claude_code_credentials() -> Result<AuthConfig> {
-        let credentials_path = Self::get_claude_credentials_path()?;
-
-        let contents = fs::read_to_string(&credentials_path).map_err(|e| {
-            PrismError::Other(format!(
-                "Failed to read Claude Code credentials from {}: {}",
-                credentials_path.display(),
-                e
-            ))
-        })?;
-
-        let credentials: ClaudeCodeCredentials = serde_json::from_str(&contents).map_err(|e| {
-            PrismError::Other(format!("Failed to parse Claude Code credentials: {}", e))
-        })?;
-
+        let credentials = Self::read_claude_credentials()?;
         let oauth = &credentials.claude_ai_oauth;
 
         // Validate token hasn't expired
@@ -88,74 +79,150 @@ impl AnthropicOAuth {
 
         tracing::info!("Successfully loaded Claude Code OAuth credentials");
 
-        Ok(AuthConfig {
+        Ok(Self::auth_config_from(oauth))
+    }
+
+    /// Read Claude CLI tokens as stored, even if expired
+    pub fn read_cli_tokens() -> Result<AuthConfig> {
+        let credentials = Self::read_claude_credentials()?;
+        Ok(Self::auth_config_from(&credentials.claude_ai_oauth))
+    }
+
+    /// Refresh the Claude CLI tokens and write them back to its credentials file
+    ///
+    /// Anthropic rotates refresh tokens, so the file must be updated or the Claude CLI
+    /// would be left holding a revoked one.
+    pub async fn refresh_cli_tokens() -> Result<AuthConfig> {
+        let mut auth_config = Self::read_cli_tokens()?;
+        Self::refresh_token(&mut auth_config).await?;
+
+        let credentials_path = Self::claude_credentials_path()?;
+        let contents = fs::read_to_string(&credentials_path)?;
+        let mut credentials: serde_json::Value = serde_json::from_str(&contents)?;
+        let oauth = credentials
+            .get_mut("claudeAiOauth")
+            .and_then(|oauth| oauth.as_object_mut())
+            .ok_or_else(|| {
+                PrismError::Other("Claude Code credentials have no claudeAiOauth entry".to_string())
+            })?;
+        oauth.insert(
+            "accessToken".to_string(),
+            serde_json::json!(auth_config.oauth_access_token),
+        );
+        oauth.insert(
+            "refreshToken".to_string(),
+            serde_json::json!(auth_config.oauth_refresh_token),
+        );
+        oauth.insert(
+            "expiresAt".to_string(),
+            serde_json::json!(auth_config.oauth_expires),
+        );
+        fs::write(
+            &credentials_path,
+            serde_json::to_string_pretty(&credentials)?,
+        )?;
+
+        tracing::info!("Updated Claude CLI OAuth credentials file");
+        Ok(auth_config)
+    }
+
+    fn read_claude_credentials() -> Result<ClaudeCodeCredentials> {
+        let credentials_path = Self::claude_credentials_path()?;
+
+        let contents = fs::read_to_string(&credentials_path).map_err(|e| {
+            PrismError::Other(format!(
+                "Failed to read Claude Code credentials from {}: {}",
+                credentials_path.display(),
+                e
+            ))
+        })?;
+
+        serde_json::from_str(&contents).map_err(|e| {
+            PrismError::Other(format!("Failed to parse Claude Code credentials: {}", e))
+        })
+    }
+
+    fn auth_config_from(oauth: &ClaudeCodeOAuth) -> AuthConfig {
+        AuthConfig {
             oauth_access_token: Some(oauth.access_token.clone()),
             oauth_refresh_token: Some(oauth.refresh_token.clone()),
             oauth_expires: Some(oauth.expires_at),
             project_id: None,
-        })
+        }
     }
 
-    fn get_claude_credentials_path() -> Result<PathBuf> {
-        // Try to get home directory
-        let home = std::env::var("HOME")
-            .map_err(|_| PrismError::Other("HOME environment variable not set".to_string()))?;
-
-        Ok(PathBuf::from(home)
-            .join(".config")
-            .join("claude")
-            .join(".credentials.json"))
+    /// Claude CLI credentials file
+    pub fn claude_credentials_path() -> Result<PathBuf> {
+        OAuthProvider::Anthropic.cli_credentials_path()
     }
 
     pub fn generate_pkce_pair() -> (String, String) {
-        let mut rng = rand::thread_rng();
-        let mut verifier_bytes = [0u8; 32];
-        rng.fill(&mut verifier_bytes);
-        let verifier = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(verifier_bytes);
+        generate_pkce_pair()
+    }
 
-        let mut hasher = Sha256::new();
-        hasher.update(verifier.as_bytes());
-        let challenge_bytes = hasher.finalize();
-        let challenge = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(challenge_bytes);
+    /// Authorization URL whose redirect page shows a `code#state` value to paste
+    pub fn create_authorization_url() -> Result<AuthorizeResult> {
+        Self::authorization_url(MANUAL_REDIRECT_URI, true)
+    }
 
-        (challenge, verifier)
+    /// Authorization URL that redirects to a local callback listener at `redirect_uri`
+    pub fn create_loopback_authorization_url(redirect_uri: &str) -> Result<AuthorizeResult> {
+        Self::authorization_url(redirect_uri, false)
     }
 
-    pub fn create_authorization_url() -> Result<AuthorizeResult> {
+    fn authorization_url(redirect_uri: &str, show_code: bool) -> Result<AuthorizeResult> {
         let (pkce_challenge, pkce_verifier) = Self::generate_pkce_pair();
+        let state = generate_state();
 
         let mut url = Url::parse("https://claude.ai/oauth/authorize")
             .map_err(|e| PrismError::Other(format!("Failed to parse OAuth URL: {}", e)))?;
 
-        url.query_pairs_mut()
-            .append_pair("code", "true")
+        let mut query = url.query_pairs_mut();
+        if show_code {
+            query.append_pair("code", "true");
+        }
+        query
             .append_pair("client_id", CLIENT_ID)
             .append_pair("response_type", "code")
-            .append_pair(
-                "redirect_uri",
-                "https://console.anthropic.com/oauth/code/callback",
-            )
+            .append_pair("redirect_uri", redirect_uri)
             .append_pair("scope", "org:create_api_key user:profile user:inference")
             .append_pair("code_challenge", &pkce_challenge)
             .append_pair("code_challenge_method", "S256")
-            .append_pair("state", &pkce_verifier);
+            .append_pair("state", &state);
+        drop(query);
 
         Ok(AuthorizeResult {
             url: url.to_string(),
             verifier: pkce_verifier,
+            state,
+            redirect_uri: redirect_uri.to_string(),
         })
     }
 
-    pub async fn exchange_code_for_token(code: &str, verifier: &str) -> Result<AuthConfig> {
-        let splits: Vec<&str> = code.split('#').collect();
+    /// Split a pasted `code#state` value, checking the state belongs to this login
+    pub fn parse_pasted_code(pasted: &str, expected_state: &str) -> Result<String> {
+        let (code, state) = pasted.trim().split_once('#').ok_or_else(|| {
+            PrismError::Other("Expected the full code#state value from the browser".to_string())
+        })?;
+        if state != expected_state {
+            return Err(PrismError::Other(
+                "OAuth state mismatch: the pasted code is not for this login".to_string(),
+            ));
+        }
+        Ok(code.to_string())
+    }
 
+    pub async fn exchange_code_for_token(
+        code: &str,
+        authorize: &AuthorizeResult,
+    ) -> Result<AuthConfig> {
         let request_body = serde_json::json!({
-            "code": splits[0],
-            "state": splits.get(1).unwrap_or(&""),
+            "code": code,
+            "state": authorize.state,
             "grant_type": "authorization_code",
             "client_id": CLIENT_ID,
-            "redirect_uri": "https://console.anthropic.com/oauth/code/callback",
-            "code_verifier": verifier,
+            "redirect_uri": authorize.redirect_uri,
+            "code_verifier": authorize.verifier,
         });
 
         let client = reqwest::Client::new();
@@ -0,0 +1,334 @@
+//! AWS credentials and SigV4 request signing (used by the Bedrock provider).
+//!
+//! Credentials come from the standard places: `AWS_ACCESS_KEY_ID` /
+//! `AWS_SECRET_ACCESS_KEY` / `AWS_SESSION_TOKEN`, then the shared credentials file
+//! (`AWS_SHARED_CREDENTIALS_FILE` or `~/.aws/credentials`) for `AWS_PROFILE`.
+
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+use crate::error::{PrismError, Result};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AwsCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+}
+
+impl AwsCredentials {
+    /// Load credentials from the environment, falling back to the shared credentials file
+    pub fn load() -> Result<Self> {
+        if let Some(credentials) = Self::from_env() {
+            return Ok(credentials);
+        }
+        let profile = profile_name();
+        Self::from_profile(&profile).ok_or_else(|| {
+            PrismError::Other(format!(
+                "No AWS credentials found (set AWS_ACCESS_KEY_ID/AWS_SECRET_ACCESS_KEY or add profile '{}' to ~/.aws/credentials)",
+                profile
+            ))
+        })
+    }
+
+    pub fn from_env() -> Option<Self> {
+        let access_key_id = std::env::var("AWS_ACCESS_KEY_ID").ok()?;
+        let secret_access_key = std::env::var("AWS_SECRET_ACCESS_KEY").ok()?;
+        Some(Self {
+            access_key_id,
+            secret_access_key,
+            session_token: std::env::var("AWS_SESSION_TOKEN")
+                .ok()
+                .filter(|t| !t.is_empty()),
+        })
+    }
+
+    pub fn from_profile(profile: &str) -> Option<Self> {
+        let path = std::env::var("AWS_SHARED_CREDENTIALS_FILE")
+            .ok()
+            .or_else(|| aws_dir().map(|dir| format!("{}/credentials", dir)))?;
+        let content = std::fs::read_to_string(path).ok()?;
+        let section = parse_ini(&content).remove(profile)?;
+        Some(Self {
+            access_key_id: section.get("aws_access_key_id")?.clone(),
+            secret_access_key: section.get("aws_secret_access_key")?.clone(),
+            session_token: section.get("aws_session_token").cloned(),
+        })
+    }
+}
+
+fn profile_name() -> String {
+    std::env::var("AWS_PROFILE").unwrap_or_else(|_| "default".to_string())
+}
+
+fn aws_dir() -> Option<String> {
+    std::env::var("HOME")
+        .ok()
+        .map(|home| format!("{}/.aws", home))
+}
+
+/// Region from `AWS_REGION`, `AWS_DEFAULT_REGION` or the profile in `~/.aws/config`
+pub fn default_region() -> Option<String> {
+    if let Ok(region) = std::env::var("AWS_REGION").or_else(|_| std::env::var("AWS_DEFAULT_REGION"))
+    {
+        return Some(region);
+    }
+    let path = std::env::var("AWS_CONFIG_FILE")
+        .ok()
+        .or_else(|| aws_dir().map(|dir| format!("{}/config", dir)))?;
+    let content = std::fs::read_to_string(path).ok()?;
+    let profile = profile_name();
+    let section_name = if profile == "default" {
+        profile
+    } else {
+        format!("profile {}", profile)
+    };
+    parse_ini(&content).remove(&section_name)?.remove("region")
+}
+
+/// Minimal INI parser for AWS config files: `[section]` headers and `key = value` lines
+fn parse_ini(content: &str) -> HashMap<String, HashMap<String, String>> {
+    let mut sections: HashMap<String, HashMap<String, String>> = HashMap::new();
+    let mut current = None;
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            current = Some(name.trim().to_string());
+            continue;
+        }
+        if let (Some(section), Some((key, value))) = (&current, line.split_once('=')) {
+            sections
+                .entry(section.clone())
+                .or_default()
+                .insert(key.trim().to_string(), value.trim().to_string());
+        }
+    }
+    sections
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    hex(&Sha256::digest(data))
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    const BLOCK_SIZE: usize = 64;
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner = Sha256::new();
+    inner.update(block.map(|b| b ^ 0x36));
+    inner.update(data);
+    let mut outer = Sha256::new();
+    outer.update(block.map(|b| b ^ 0x5c));
+    outer.update(inner.finalize());
+    outer.finalize().into()
+}
+
+/// Percent-encode per SigV4 (everything except unreserved characters)
+fn uri_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Percent-encode one URL path segment (e.g. a Bedrock model ID containing `:`)
+pub fn encode_path_segment(segment: &str) -> String {
+    uri_encode(segment)
+}
+
+/// The parts of an HTTP request covered by the signature
+pub struct SignableRequest<'a> {
+    pub method: &'a str,
+    pub url: &'a reqwest::Url,
+    /// Extra headers to sign besides `host` and `x-amz-*` (e.g. `content-type`)
+    pub headers: &'a [(&'a str, &'a str)],
+    pub body: &'a [u8],
+}
+
+/// SigV4-sign a request, returning the headers to add (`authorization`, `x-amz-date`, ...)
+///
+/// The canonical URI encodes the already-encoded path again, as all services except S3 expect.
+pub fn sign(
+    credentials: &AwsCredentials,
+    region: &str,
+    service: &str,
+    request: &SignableRequest,
+    now: DateTime<Utc>,
+) -> Vec<(String, String)> {
+    let SignableRequest {
+        method,
+        url,
+        headers,
+        body,
+    } = *request;
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+
+    let host = match url.port() {
+        Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+        None => url.host_str().unwrap_or_default().to_string(),
+    };
+    let mut signed: Vec<(String, String)> = vec![
+        ("host".to_string(), host),
+        ("x-amz-date".to_string(), amz_date.clone()),
+    ];
+    if let Some(token) = &credentials.session_token {
+        signed.push(("x-amz-security-token".to_string(), token.clone()));
+    }
+    for (name, value) in headers {
+        signed.push((name.to_lowercase(), value.trim().to_string()));
+    }
+    signed.sort();
+
+    let canonical_uri = url
+        .path()
+        .split('/')
+        .map(uri_encode)
+        .collect::<Vec<_>>()
+        .join("/");
+    let mut query: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(k, v)| (uri_encode(&k), uri_encode(&v)))
+        .collect();
+    query.sort();
+    let canonical_query = query
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect::<Vec<_>>()
+        .join("&");
+    let canonical_headers: String = signed
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value))
+        .collect();
+    let signed_headers = signed
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>()
+        .join(";");
+
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        method,
+        canonical_uri,
+        canonical_query,
+        canonical_headers,
+        signed_headers,
+        sha256_hex(body)
+    );
+    let scope = format!("{}/{}/{}/aws4_request", date, region, service);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        sha256_hex(canonical_request.as_bytes())
+    );
+
+    let k_date = hmac_sha256(
+        format!("AWS4{}", credentials.secret_access_key).as_bytes(),
+        date.as_bytes(),
+    );
+    let k_region = hmac_sha256(&k_date, region.as_bytes());
+    let k_service = hmac_sha256(&k_region, service.as_bytes());
+    let k_signing = hmac_sha256(&k_service, b"aws4_request");
+    let signature = hex(&hmac_sha256(&k_signing, string_to_sign.as_bytes()));
+
+    let mut out = vec![
+        (
+            "authorization".to_string(),
+            format!(
+                "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                credentials.access_key_id, scope, signed_headers, signature
+            ),
+        ),
+        ("x-amz-date".to_string(), amz_date),
+    ];
+    if let Some(token) = &credentials.session_token {
+        out.push(("x-amz-security-token".to_string(), token.clone()));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn example_credentials() -> AwsCredentials {
+        AwsCredentials {
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+            session_token: None,
+        }
+    }
+
+    /// `get-vanilla` from the AWS SigV4 test suite
+    #[test]
+    fn test_sign_get_vanilla() {
+        let url = reqwest::Url::parse("https://example.amazonaws.com/").unwrap();
+        let now = Utc.with_ymd_and_hms(2015, 8, 30, 12, 36, 0).unwrap();
+        let request = SignableRequest {
+            method: "GET",
+            url: &url,
+            headers: &[],
+            body: b"",
+        };
+        let headers = sign(
+            &example_credentials(),
+            "us-east-1",
+            "service",
+            &request,
+            now,
+        );
+
+        assert_eq!(
+            headers[0].1,
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, SignedHeaders=host;x-amz-date, Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        );
+        assert_eq!(headers[1].1, "20150830T123600Z");
+    }
+
+    #[test]
+    fn test_hmac_sha256_rfc4231_case_2() {
+        let mac = hmac_sha256(b"Jefe", b"what do ya want for nothing?");
+        assert_eq!(
+            hex(&mac),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_model_id_path_is_double_encoded_for_signing() {
+        assert_eq!(
+            encode_path_segment("anthropic.claude-sonnet-4-20250514-v1:0"),
+            "anthropic.claude-sonnet-4-20250514-v1%3A0"
+        );
+        assert_eq!(uri_encode("v1%3A0"), "v1%253A0");
+    }
+
+    #[test]
+    fn test_parse_ini_profiles() {
+        let sections = parse_ini(
+            "[default]\naws_access_key_id = AKID\naws_secret_access_key = SECRET\n\n[profile work]\nregion = eu-west-1\n",
+        );
+        assert_eq!(sections["default"]["aws_access_key_id"], "AKID");
+        assert_eq!(sections["profile work"]["region"], "eu-west-1");
+    }
+}
@@ -0,0 +1,256 @@
+//! Loopback HTTP listener that receives the browser redirect of an OAuth flow.
+
+use axum::Router;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::Html;
+use axum::routing::get;
+use rustc_hash::FxHashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+
+use crate::error::{PrismError, Result};
+
+/// How long to wait for the user to finish in the browser
+pub const CALLBACK_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Query parameters of a successful redirect
+#[derive(Debug, Clone)]
+pub struct CallbackParams {
+    pub code: String,
+    pub state: Option<String>,
+}
+
+#[derive(Clone)]
+struct CallbackState {
+    sender: Arc<Mutex<Option<oneshot::Sender<Result<CallbackParams>>>>>,
+    expected_state: Option<String>,
+}
+
+/// Listener bound before the authorization URL is built, so the URL can carry its port
+pub struct CallbackListener {
+    listener: TcpListener,
+    port: u16,
+}
+
+impl CallbackListener {
+    /// Listen on an ephemeral port of 127.0.0.1
+    pub async fn bind() -> Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await?;
+        let port = listener.local_addr()?.port();
+        Ok(Self { listener, port })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Redirect URI for `path`, e.g. `http://localhost:43117/callback`
+    pub fn redirect_uri(&self, path: &str) -> String {
+        format!("http://localhost:{}{}", self.port, path)
+    }
+
+    /// Serve `path` until one redirect carrying a `code` (or an OAuth `error`) arrives
+    ///
+    /// With `expected_state`, a redirect whose `state` differs is answered with an error
+    /// page and otherwise ignored: it was not started by this login, so the listener keeps
+    /// waiting for the real one.
+    pub async fn wait_for_code(
+        self,
+        path: &str,
+        expected_state: Option<&str>,
+        timeout: Duration,
+    ) -> Result<CallbackParams> {
+        let (code_tx, code_rx) = oneshot::channel();
+        let state = CallbackState {
+            sender: Arc::new(Mutex::new(Some(code_tx))),
+            expected_state: expected_state.map(str::to_string),
+        };
+        let app = Router::new()
+            .route(path, get(handle_callback))
+            .with_state(state);
+
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+        let server = tokio::spawn(async move {
+            axum::serve(self.listener, app)
+                .with_graceful_shutdown(async {
+                    let _ = shutdown_rx.await;
+                })
+                .await
+        });
+
+        let result = tokio::time::timeout(timeout, code_rx).await;
+
+        // Let the browser receive its response before the listener goes away
+        let _ = shutdown_tx.send(());
+        let _ = tokio::time::timeout(Duration::from_secs(2), server).await;
+
+        match result {
+            Ok(Ok(params)) => params,
+            Ok(Err(_)) => Err(PrismError::Other(
+                "OAuth callback listener stopped unexpectedly".to_string(),
+            )),
+            Err(_) => Err(PrismError::Other(format!(
+                "Timed out after {} seconds waiting for the browser to complete authorization",
+                timeout.as_secs()
+            ))),
+        }
+    }
+}
+
+async fn handle_callback(
+    State(callback): State<CallbackState>,
+    Query(params): Query<FxHashMap<String, String>>,
+) -> (StatusCode, Html<&'static str>) {
+    if !params.contains_key("error") && !params.contains_key("code") {
+        // Not the redirect (e.g. a reload without parameters); keep waiting
+        return (StatusCode::OK, Html("<h1>Waiting for authorization</h1>"));
+    }
+
+    // A redirect for another login (a stale tab, another local process) must not end this
+    // one, whether it carries a code or an error
+    let state = params.get("state").cloned();
+    if let Some(expected) = &callback.expected_state
+        && state.as_ref() != Some(expected)
+    {
+        tracing::warn!("Ignoring OAuth redirect with a state that doesn't match this login");
+        return (
+            StatusCode::BAD_REQUEST,
+            Html("<h1>Authorization failed</h1><p>This response is not for the current login.</p>"),
+        );
+    }
+
+    let result = match params.get("error") {
+        Some(error) => {
+            let description = params.get("error_description").unwrap_or(error);
+            Err(PrismError::Other(format!(
+                "Authorization was denied: {}",
+                description
+            )))
+        }
+        None => Ok(CallbackParams {
+            code: params["code"].clone(),
+            state,
+        }),
+    };
+
+    let page = if result.is_ok() {
+        "<h1>Authorization complete</h1><p>You can close this window and return to the terminal.</p>"
+    } else {
+        "<h1>Authorization failed</h1><p>See the terminal for details.</p>"
+    };
+    let sender = callback
+        .sender
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .take();
+    if let Some(sender) = sender {
+        let _ = sender.send(result);
+    }
+    (StatusCode::OK, Html(page))
+}
+
+/// Open `url` in the desktop browser; false if no opener could be started
+pub fn open_browser(url: &str) -> bool {
+    let opener = if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    std::process::Command::new(opener)
+        .arg(url)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_listener_returns_redirect_code() {
+        let listener = CallbackListener::bind().await.unwrap();
+        let port = listener.port();
+        assert_eq!(
+            listener.redirect_uri("/callback"),
+            format!("http://localhost:{}/callback", port)
+        );
+
+        let browser = tokio::spawn(async move {
+            reqwest::get(format!(
+                "http://127.0.0.1:{}/callback?code=abc123&state=xyz",
+                port
+            ))
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap()
+        });
+
+        let params = listener
+            .wait_for_code("/callback", Some("xyz"), Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(params.code, "abc123");
+        assert_eq!(params.state.as_deref(), Some("xyz"));
+        assert!(browser.await.unwrap().contains("Authorization complete"));
+    }
+
+    #[tokio::test]
+    async fn test_listener_reports_oauth_error() {
+        let listener = CallbackListener::bind().await.unwrap();
+        let port = listener.port();
+
+        tokio::spawn(async move {
+            let _ = reqwest::get(format!(
+                "http://127.0.0.1:{}/callback?error=access_denied",
+                port
+            ))
+            .await;
+        });
+
+        let err = listener
+            .wait_for_code("/callback", None, Duration::from_secs(5))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("access_denied"));
+    }
+
+    #[tokio::test]
+    async fn test_listener_ignores_unexpected_state() {
+        let listener = CallbackListener::bind().await.unwrap();
+        let port = listener.port();
+
+        let browser = tokio::spawn(async move {
+            for query in [
+                "code=abc123&state=forged",
+                "error=access_denied&state=stale",
+            ] {
+                let response =
+                    reqwest::get(format!("http://127.0.0.1:{}/callback?{}", port, query))
+                        .await
+                        .unwrap();
+                assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+            }
+            reqwest::get(format!(
+                "http://127.0.0.1:{}/callback?code=real&state=expected",
+                port
+            ))
+            .await
+            .unwrap();
+        });
+
+        let params = listener
+            .wait_for_code("/callback", Some("expected"), Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(params.code, "real");
+        browser.await.unwrap();
+    }
+}
@@ -1,4 +1,7 @@
+use base64::Engine;
+use rand::Rng;
 use serde::Serialize;
+use sha2::{Digest, Sha256};
 use std::fmt;
 use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -224,6 +227,28 @@ pub fn choose_best_token_source(
     }
 }
 
+/// PKCE (S256) pair as `(challenge, verifier)`
+pub fn generate_pkce_pair() -> (String, String) {
+    let mut rng = rand::thread_rng();
+    let mut verifier_bytes = [0u8; 32];
+    rng.fill(&mut verifier_bytes);
+    let verifier = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(verifier_bytes);
+
+    let mut hasher = Sha256::new();
+    hasher.update(verifier.as_bytes());
+    let challenge_bytes = hasher.finalize();
+    let challenge = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(challenge_bytes);
+
+    (challenge, verifier)
+}
+
+/// Random OAuth `state`, checked when the authorization response comes back
+pub fn generate_state() -> String {
+    let mut state_bytes = [0u8; 32];
+    rand::thread_rng().fill(&mut state_bytes);
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(state_bytes)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -2,8 +2,12 @@ use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
 use std::time::{SystemTime, UNIX_EPOCH};
+use url::Url;
 
-use crate::auth::common::{TokenInfo, analyze_token_source, choose_best_token_source};
+use crate::auth::OAuthProvider;
+use crate::auth::common::{
+    TokenInfo, analyze_token_source, choose_best_token_source, generate_pkce_pair, generate_state,
+};
 use crate::config::AuthConfig;
 use crate::error::{Result, PrismError};
 
@@ -29,6 +33,12 @@ const OAUTH_CLIENT_ID: &str =
     "681255809395-oo8ft2oprdrnp9e3aqf6av3hmdib135j.apps.googleusercontent.com";
 const OAUTH_CLIENT_SECRET: &str = "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";
 const OAUTH_TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+const OAUTH_AUTH_URL: &str = "https://accounts.google.com/o/oauth2/v2/auth";
+/// Redirect page that shows the code to paste (Gemini CLI's no-browser mode)
+const MANUAL_REDIRECT_URI: &str = "https://codeassist.google.com/authcode";
+const OAUTH_SCOPES: &str = "https://www.googleapis.com/auth/cloud-platform \
+                            https://www.googleapis.com/auth/userinfo.email \
+                            https://www.googleapis.com/auth/userinfo.profile";
 
 /// Get the token URL - can be overridden for testing
 fn get_token_url() -> String {
@@ -42,6 +52,24 @@ pub struct GeminiOAuthCredentials {
     pub expiry_date: u64,
     pub token_type: String,
     pub scope: String,
+    /// Fields Prism doesn't use (such as `id_token`), kept when the file is rewritten
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AuthorizeResult {
+    pub url: String,
+    pub verifier: String,
+    pub state: String,
+    pub redirect_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    refresh_token: Option<String>,
+    expires_in: u64,
 }
 
 #[derive(Debug, Deserialize)]
@@ -55,6 +83,93 @@ struct TokenRefreshResponse {
 pub struct GoogleOAuth;
 
 impl GoogleOAuth {
+    /// Authorization URL whose redirect page shows the code to paste
+    pub fn create_authorization_url() -> Result<AuthorizeResult> {
+        Self::authorization_url(MANUAL_REDIRECT_URI)
+    }
+
+    /// Authorization URL that redirects to a local callback listener at `redirect_uri`
+    pub fn create_loopback_authorization_url(redirect_uri: &str) -> Result<AuthorizeResult> {
+        Self::authorization_url(redirect_uri)
+    }
+
+    fn authorization_url(redirect_uri: &str) -> Result<AuthorizeResult> {
+        let (challenge, verifier) = generate_pkce_pair();
+        let state = generate_state();
+
+        let mut url = Url::parse(OAUTH_AUTH_URL)
+            .map_err(|e| PrismError::Other(format!("Failed to parse OAuth URL: {}", e)))?;
+        url.query_pairs_mut()
+            .append_pair("client_id", OAUTH_CLIENT_ID)
+            .append_pair("redirect_uri", redirect_uri)
+            .append_pair("response_type", "code")
+            .append_pair("scope", OAUTH_SCOPES)
+            // Offline access with consent, so Google issues a refresh token
+            .append_pair("access_type", "offline")
+            .append_pair("prompt", "consent")
+            .append_pair("code_challenge", &challenge)
+            .append_pair("code_challenge_method", "S256")
+            .append_pair("state", &state);
+
+        Ok(AuthorizeResult {
+            url: url.to_string(),
+            verifier,
+            state,
+            redirect_uri: redirect_uri.to_string(),
+        })
+    }
+
+    /// Exchange an authorization code for Gemini OAuth tokens
+    pub async fn exchange_code_for_token(
+        code: &str,
+        authorize: &AuthorizeResult,
+    ) -> Result<AuthConfig> {
+        let params = [
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", authorize.redirect_uri.as_str()),
+            ("code_verifier", authorize.verifier.as_str()),
+            ("client_id", OAUTH_CLIENT_ID),
+            ("client_secret", OAUTH_CLIENT_SECRET),
+        ];
+
+        let response = reqwest::Client::new()
+            .post(get_token_url())
+            .form(&params)
+            .send()
+            .await
+            .map_err(|e| PrismError::Other(format!("OAuth token exchange failed: {}", e)))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(PrismError::Other(format!(
+                "OAuth token exchange failed with status {}: {}",
+                status, error_text
+            )));
+        }
+
+        let token_response: TokenResponse = response
+            .json()
+            .await
+            .map_err(|e| PrismError::Other(format!("Failed to parse token response: {}", e)))?;
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| PrismError::Other(format!("Time error: {}", e)))?
+            .as_millis() as u64;
+
+        Ok(AuthConfig {
+            oauth_access_token: Some(token_response.access_token),
+            oauth_refresh_token: token_response.refresh_token,
+            oauth_expires: Some(now + (token_response.expires_in * 1000)),
+            project_id: Some(GEMINI_PROJECT_ID.to_string()),
+        })
+    }
+
     /// Refresh OAuth tokens using refresh token
     async fn refresh_token(refresh_token: &str) -> Result<TokenRefreshResponse> {
         let client = reqwest::Client::new();
@@ -119,7 +234,7 @@ impl GoogleOAuth {
         }
 
         // Write updated credentials back to file
-        let credentials_path = Self::get_gemini_credentials_path()?;
+        let credentials_path = Self::gemini_credentials_path()?;
         let contents = serde_json::to_string_pretty(&credentials)
             .map_err(|e| PrismError::Other(format!("Failed to serialize credentials: {}", e)))?;
 
@@ -135,23 +250,10 @@ impl GoogleOAuth {
         Ok(credentials)
     }
 
-    /// Attempt to read Gemini CLI OAuth credentials from ~/.gemini/oauth_creds.json
+    /// Attempt to read Gemini CLI OAuth credentials (`~/.gemini/oauth_creds.json` by default)
     /// If expired and refresh token is available, attempt to refresh
     pub async fn try_gemini_cli_credentials() -> Result<AuthConfig> {
-        let credentials_path = Self::get_gemini_credentials_path()?;
-
-        let contents = fs::read_to_string(&credentials_path).map_err(|e| {
-            PrismError::Other(format!(
-                "Failed to read Gemini CLI credentials from {}: {}",
-                credentials_path.display(),
-                e
-            ))
-        })?;
-
-        let mut credentials: GeminiOAuthCredentials =
-            serde_json::from_str(&contents).map_err(|e| {
-                PrismError::Other(format!("Failed to parse Gemini CLI credentials: {}", e))
-            })?;
+        let mut credentials = Self::read_gemini_credentials()?;
 
         // Check if token has expired
         let now = SystemTime::now()
@@ -199,20 +301,74 @@ impl GoogleOAuth {
         tracing::info!("Successfully loaded Gemini CLI OAuth credentials");
         tracing::info!("Setting Gemini project_id: {}", GEMINI_PROJECT_ID);
 
-        Ok(AuthConfig {
+        Ok(Self::auth_config_from(credentials))
+    }
+
+    /// Read Gemini CLI tokens as stored, even if expired
+    pub fn read_cli_tokens() -> Result<AuthConfig> {
+        Ok(Self::auth_config_from(Self::read_gemini_credentials()?))
+    }
+
+    /// Refresh the Gemini CLI tokens and write them back to its credentials file
+    pub async fn refresh_cli_tokens() -> Result<AuthConfig> {
+        let credentials = Self::read_gemini_credentials()?;
+        let refresh_token = credentials.refresh_token.clone().ok_or_else(|| {
+            PrismError::Other("Gemini CLI credentials have no refresh token".to_string())
+        })?;
+
+        let refresh_response = Self::refresh_token(&refresh_token).await?;
+        let credentials = Self::update_gemini_credentials(credentials, refresh_response).await?;
+        Ok(Self::auth_config_from(credentials))
+    }
+
+    /// Refresh OAuth tokens stored in the prism config
+    pub async fn refresh_auth_config(auth_config: &mut AuthConfig) -> Result<()> {
+        let refresh_token = auth_config
+            .oauth_refresh_token
+            .as_ref()
+            .ok_or_else(|| PrismError::Other("No refresh token available".to_string()))?;
+
+        let refresh_response = Self::refresh_token(refresh_token).await?;
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| PrismError::Other(format!("Time error: {}", e)))?
+            .as_millis() as u64;
+
+        auth_config.oauth_access_token = Some(refresh_response.access_token);
+        auth_config.oauth_expires = Some(now + (refresh_response.expires_in * 1000));
+        Self::ensure_project_id(auth_config);
+        Ok(())
+    }
+
+    fn read_gemini_credentials() -> Result<GeminiOAuthCredentials> {
+        let credentials_path = Self::gemini_credentials_path()?;
+
+        let contents = fs::read_to_string(&credentials_path).map_err(|e| {
+            PrismError::Other(format!(
+                "Failed to read Gemini CLI credentials from {}: {}",
+                credentials_path.display(),
+                e
+            ))
+        })?;
+
+        serde_json::from_str(&contents).map_err(|e| {
+            PrismError::Other(format!("Failed to parse Gemini CLI credentials: {}", e))
+        })
+    }
+
+    fn auth_config_from(credentials: GeminiOAuthCredentials) -> AuthConfig {
+        AuthConfig {
             oauth_access_token: Some(credentials.access_token),
             oauth_refresh_token: credentials.refresh_token,
             oauth_expires: Some(credentials.expiry_date),
             project_id: Some(GEMINI_PROJECT_ID.to_string()),
-        })
+        }
     }
 
-    fn get_gemini_credentials_path() -> Result<PathBuf> {
-        // Try to get home directory
-        let home = std::env::var("HOME")
-            .map_err(|_| PrismError::Other("HOME environment variable not set".to_string()))?;
-
-        Ok(PathBuf::from(home).join(".gemini").join("oauth_creds.json"))
+    /// Gemini CLI credentials file
+    pub fn gemini_credentials_path() -> Result<PathBuf> {
+        OAuthProvider::Gemini.cli_credentials_path()
     }
 
     /// Ensure the auth config has the required project_id for Gemini Cloud Code Assist API
@@ -0,0 +1,479 @@
+//! Credential pools: spread requests over several API keys / OAuth accounts per provider.
+//!
+//! Keys that come back with 429 or 401 are benched for the provider's cool-down period.
+//! Every selection is logged with a short key label so usage can be attributed per key.
+
+use ai_ox_common::CommonRequestError;
+use rustc_hash::FxHashMap;
+use std::future::Future;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::config::{Config, KeySelection, ProviderConfig};
+
+/// A candidate credential with a human-readable label (never the secret itself)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Credential {
+    pub label: String,
+    pub secret: String,
+}
+
+impl Credential {
+    pub fn new(label: impl Into<String>, secret: impl Into<String>) -> Self {
+        Self {
+            label: label.into(),
+            secret: secret.into(),
+        }
+    }
+}
+
+/// A credential handed out for one request; report failures back through it
+#[derive(Debug, Clone)]
+pub struct PooledKey {
+    pub provider: String,
+    pub label: String,
+    pub secret: String,
+}
+
+impl PooledKey {
+    /// Report an upstream HTTP status for this key (429/401 bench it)
+    pub fn report_status(&self, status: u16) {
+        report_status(&self.provider, &self.label, status);
+    }
+
+    /// Report a failed request; only an HTTP status carried by the error counts
+    pub fn report_error(&self, error: &(dyn std::error::Error + 'static)) {
+        report_error(&self.provider, &self.label, error);
+    }
+}
+
+#[derive(Debug, Default)]
+struct KeyState {
+    uses: u64,
+    benched_until: Option<Instant>,
+    cooldown: Duration,
+}
+
+#[derive(Default)]
+struct PoolState {
+    keys: FxHashMap<(String, String), KeyState>,
+    cursors: FxHashMap<String, usize>,
+}
+
+static POOL_STATE: OnceLock<Mutex<PoolState>> = OnceLock::new();
+
+tokio::task_local! {
+    /// Set while running a request whose failures must not bench keys (shadow traffic)
+    static REPORTING_MUTED: ();
+}
+
+fn state() -> &'static Mutex<PoolState> {
+    POOL_STATE.get_or_init(|| Mutex::new(PoolState::default()))
+}
+
+/// Collect API key candidates for a provider: env var first, then `api_key`, then `api_keys`
+pub fn api_key_candidates(config: &Config, provider: &str, env_var: &str) -> Vec<Credential> {
+    let mut candidates = Vec::new();
+
+    if let Ok(key) = std::env::var(env_var)
+        && !key.trim().is_empty()
+    {
+        candidates.push(Credential::new(env_var, key));
+    }
+    candidates.extend(configured_key_candidates(config, provider));
+    dedup_candidates(candidates)
+}
+
+/// API key candidates from the provider's own `api_key` and `api_keys` only
+pub fn configured_key_candidates(config: &Config, provider: &str) -> Vec<Credential> {
+    let mut candidates = Vec::new();
+    if let Some(provider_config) = config.providers.get(provider) {
+        if let Some(key) = &provider_config.api_key {
+            candidates.push(Credential::new("api_key", key.clone()));
+        }
+        for (i, key) in provider_config.api_keys.iter().enumerate() {
+            candidates.push(Credential::new(format!("api_keys[{}]", i), key.clone()));
+        }
+    }
+    dedup_candidates(candidates)
+}
+
+fn dedup_candidates(mut candidates: Vec<Credential>) -> Vec<Credential> {
+    // Same key configured twice (e.g. env var and ${VAR} in config) counts once
+    let mut seen = Vec::new();
+    candidates.retain(|c| {
+        if c.secret.trim().is_empty() || seen.contains(&c.secret) {
+            return false;
+        }
+        seen.push(c.secret.clone());
+        true
+    });
+    candidates
+}
+
+/// Pick an API key for `provider` using its configured selection strategy
+pub fn acquire_api_key(config: &Config, provider: &str, env_var: &str) -> Option<PooledKey> {
+    let candidates = api_key_candidates(config, provider, env_var);
+    acquire_for_provider(config.providers.get(provider), provider, &candidates)
+}
+
+/// Pick one of the given credentials using the provider's pool settings
+pub fn acquire_for_provider(
+    provider_config: Option<&ProviderConfig>,
+    provider: &str,
+    candidates: &[Credential],
+) -> Option<PooledKey> {
+    let (selection, cooldown) = provider_config
+        .map(|p| (p.key_selection, Duration::from_secs(p.key_cooldown_secs)))
+        .unwrap_or((KeySelection::default(), Duration::from_secs(60)));
+    acquire(provider, candidates, selection, cooldown)
+}
+
+/// Pick a credential, skipping benched ones
+///
+/// If every credential is benched, the one whose cool-down ends first is used anyway
+/// rather than failing the request outright.
+pub fn acquire(
+    provider: &str,
+    candidates: &[Credential],
+    selection: KeySelection,
+    cooldown: Duration,
+) -> Option<PooledKey> {
+    if candidates.is_empty() {
+        return None;
+    }
+
+    let now = Instant::now();
+    let mut state = state().lock().unwrap_or_else(|e| e.into_inner());
+
+    let benched_until = |state: &PoolState, c: &Credential| {
+        state
+            .keys
+            .get(&(provider.to_string(), c.label.clone()))
+            .and_then(|k| k.benched_until)
+            .filter(|until| *until > now)
+    };
+    let available: Vec<usize> = (0..candidates.len())
+        .filter(|&i| benched_until(&state, &candidates[i]).is_none())
+        .collect();
+
+    let index = if available.is_empty() {
+        let index = (0..candidates.len())
+            .min_by_key(|&i| benched_until(&state, &candidates[i]))
+            .unwrap_or(0);
+        tracing::warn!(
+            target: "prism::keys",
+            "All {} {} credentials are cooling down, using '{}'",
+            candidates.len(),
+            provider,
+            candidates[index].label
+        );
+        index
+    } else {
+        match selection {
+            KeySelection::RoundRobin => {
+                let cursor = state.cursors.entry(provider.to_string()).or_insert(0);
+                let start = *cursor % candidates.len();
+                let index = (0..candidates.len())
+                    .map(|offset| (start + offset) % candidates.len())
+                    .find(|i| available.contains(i))
+                    .unwrap_or(available[0]);
+                *cursor = index + 1;
+                index
+            }
+            KeySelection::LeastUsed => available
+                .iter()
+                .copied()
+                .min_by_key(|&i| {
+                    state
+                        .keys
+                        .get(&(provider.to_string(), candidates[i].label.clone()))
+                        .map(|k| k.uses)
+                        .unwrap_or(0)
+                })
+                .unwrap_or(available[0]),
+        }
+    };
+
+    let credential = &candidates[index];
+    let entry = state
+        .keys
+        .entry((provider.to_string(), credential.label.clone()))
+        .or_default();
+    entry.uses += 1;
+    entry.cooldown = cooldown;
+
+    if candidates.len() > 1 {
+        tracing::info!(
+            target: "prism::keys",
+            "🔑 {} → {} ({}) request #{}",
+            provider,
+            credential.label,
+            fingerprint(&credential.secret),
+            entry.uses
+        );
+    }
+
+    Some(PooledKey {
+        provider: provider.to_string(),
+        label: credential.label.clone(),
+        secret: credential.secret.clone(),
+    })
+}
+
+/// Run `future` with status reports ignored, so nothing it does benches a credential
+pub async fn without_reporting<F: Future>(future: F) -> F::Output {
+    REPORTING_MUTED.scope((), future).await
+}
+
+/// Record an upstream status for a credential; 429 and 401 bench it
+pub fn report_status(provider: &str, label: &str, status: u16) {
+    if !matches!(status, 401 | 429) || REPORTING_MUTED.try_with(|_| ()).is_ok() {
+        return;
+    }
+
+    let mut state = state().lock().unwrap_or_else(|e| e.into_inner());
+    let entry = state
+        .keys
+        .entry((provider.to_string(), label.to_string()))
+        .or_default();
+    if entry.cooldown.is_zero() {
+        return;
+    }
+    entry.benched_until = Some(Instant::now() + entry.cooldown);
+
+    tracing::warn!(
+        target: "prism::keys",
+        "{} credential '{}' returned {}, benched for {}s",
+        provider,
+        label,
+        status,
+        entry.cooldown.as_secs()
+    );
+}
+
+/// Record a failed request from a provider client error
+pub fn report_error(provider: &str, label: &str, error: &(dyn std::error::Error + 'static)) {
+    if let Some(status) = status_from_error(error) {
+        report_status(provider, label, status);
+    }
+}
+
+/// The upstream HTTP status of an error, from a status-bearing HTTP error or a provider
+/// client's rate-limit/auth error in its source chain
+///
+/// The message text is never inspected: request IDs, token counts or byte lengths in it
+/// would otherwise read as a 429 or 401.
+fn status_from_error(error: &(dyn std::error::Error + 'static)) -> Option<u16> {
+    let mut current = Some(error);
+    while let Some(error) = current {
+        if let Some(status) = error
+            .downcast_ref::<reqwest::Error>()
+            .and_then(|e| e.status())
+        {
+            return Some(status.as_u16());
+        }
+        if let Some(status) = error
+            .downcast_ref::<CommonRequestError>()
+            .and_then(client_error_status)
+        {
+            return Some(status);
+        }
+        current = error.source();
+    }
+    None
+}
+
+/// Status behind an ai-ox client error; only the variants that stand for an HTTP status
+fn client_error_status(error: &CommonRequestError) -> Option<u16> {
+    match error {
+        CommonRequestError::RateLimit => Some(429),
+        CommonRequestError::AuthenticationMissing => Some(401),
+        CommonRequestError::InvalidRequest {
+            code: Some(code), ..
+        } => code
+            .parse::<u16>()
+            .ok()
+            .filter(|status| (400..600).contains(status)),
+        _ => None,
+    }
+}
+
+/// Short, non-secret identifier for a key in logs
+fn fingerprint(secret: &str) -> String {
+    let tail: String = secret
+        .chars()
+        .rev()
+        .take(4)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect();
+    format!("…{}", tail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(n: usize) -> Vec<Credential> {
+        (0..n)
+            .map(|i| Credential::new(format!("api_keys[{}]", i), format!("sk-test-{}", i)))
+            .collect()
+    }
+
+    #[test]
+    fn test_round_robin_rotates() {
+        let candidates = keys(3);
+        let labels: Vec<String> = (0..4)
+            .map(|_| {
+                acquire(
+                    "test-rr",
+                    &candidates,
+                    KeySelection::RoundRobin,
+                    Duration::from_secs(60),
+                )
+                .unwrap()
+                .label
+            })
+            .collect();
+        assert_eq!(
+            labels,
+            vec!["api_keys[0]", "api_keys[1]", "api_keys[2]", "api_keys[0]"]
+        );
+    }
+
+    #[test]
+    fn test_benched_key_is_skipped() {
+        let candidates = keys(2);
+        let first = acquire(
+            "test-bench",
+            &candidates,
+            KeySelection::LeastUsed,
+            Duration::from_secs(60),
+        )
+        .unwrap();
+        assert_eq!(first.label, "api_keys[0]");
+        first.report_status(429);
+
+        for _ in 0..3 {
+            let next = acquire(
+                "test-bench",
+                &candidates,
+                KeySelection::LeastUsed,
+                Duration::from_secs(60),
+            )
+            .unwrap();
+            assert_eq!(next.label, "api_keys[1]");
+        }
+    }
+
+    #[test]
+    fn test_all_benched_still_returns_key() {
+        let candidates = keys(1);
+        let key = acquire(
+            "test-all-benched",
+            &candidates,
+            KeySelection::RoundRobin,
+            Duration::from_secs(60),
+        )
+        .unwrap();
+        key.report_status(401);
+
+        let again = acquire(
+            "test-all-benched",
+            &candidates,
+            KeySelection::RoundRobin,
+            Duration::from_secs(60),
+        );
+        assert_eq!(again.unwrap().label, "api_keys[0]");
+    }
+
+    #[tokio::test]
+    async fn test_muted_reports_do_not_bench() {
+        let candidates = keys(2);
+        let first = acquire(
+            "test-muted",
+            &candidates,
+            KeySelection::LeastUsed,
+            Duration::from_secs(60),
+        )
+        .unwrap();
+        without_reporting(async { first.report_status(429) }).await;
+
+        let state = state().lock().unwrap();
+        let key = &state.keys[&("test-muted".to_string(), first.label.clone())];
+        assert!(key.benched_until.is_none());
+    }
+
+    #[derive(Debug)]
+    struct TextError(&'static str);
+
+    impl std::fmt::Display for TextError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str(self.0)
+        }
+    }
+
+    impl std::error::Error for TextError {}
+
+    #[test]
+    fn test_status_from_error_ignores_message_text() {
+        for message in [
+            "HTTP 429 Too Many Requests",
+            "401 Unauthorized",
+            "req_4011 failed",
+        ] {
+            assert_eq!(status_from_error(&TextError(message)), None);
+        }
+    }
+
+    #[test]
+    fn test_client_rate_limit_error_benches_key() {
+        let candidates = keys(2);
+        let first = acquire(
+            "test-client-error",
+            &candidates,
+            KeySelection::LeastUsed,
+            Duration::from_secs(60),
+        )
+        .unwrap();
+        first.report_error(&CommonRequestError::RateLimit);
+
+        let next = acquire(
+            "test-client-error",
+            &candidates,
+            KeySelection::LeastUsed,
+            Duration::from_secs(60),
+        )
+        .unwrap();
+        assert_ne!(next.label, first.label);
+        assert_eq!(
+            status_from_error(&CommonRequestError::AuthenticationMissing),
+            Some(401)
+        );
+    }
+
+    #[test]
+    fn test_configured_candidates_skip_env_var() {
+        let mut config = Config::default();
+        config.providers.insert(
+            "openai-eu".to_string(),
+            ProviderConfig {
+                r#type: "openai".to_string(),
+                api_key: Some("sk-eu".to_string()),
+                api_keys: vec!["sk-eu".to_string(), String::new()],
+                ..Default::default()
+            },
+        );
+
+        let candidates = configured_key_candidates(&config, "openai-eu");
+        assert_eq!(candidates, vec![Credential::new("api_key", "sk-eu")]);
+        assert!(configured_key_candidates(&config, "openai-us").is_empty());
+    }
+
+    #[test]
+    fn test_fingerprint_hides_secret() {
+        assert_eq!(fingerprint("sk-or-v1-abcdef"), "…cdef");
+    }
+}
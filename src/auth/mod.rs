@@ -1,22 +1,194 @@
 pub mod anthropic;
+pub mod aws;
+pub mod callback;
 pub mod common;
 pub mod google;
+pub mod key_pool;
 pub mod openai;
+pub mod openrouter;
+pub mod paths;
+pub mod refresh;
+pub mod vertex;
 
+use crate::auth::refresh::RefreshState;
 use crate::config::AuthConfig;
 use crate::error::{Result, PrismError};
+use arc_swap::ArcSwap;
+use rustc_hash::FxHashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 
-/// Cached authentication method to avoid checking tokens on every request
+/// Source label for tokens refreshed into the Prism config
+pub const CONFIG_SOURCE: &str = "prism config";
+
+/// Providers whose OAuth tokens are tracked in the [`AuthCache`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OAuthProvider {
+    Anthropic,
+    Gemini,
+    OpenAI,
+}
+
+impl OAuthProvider {
+    pub const ALL: [OAuthProvider; 3] = [Self::Anthropic, Self::Gemini, Self::OpenAI];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Anthropic => "anthropic",
+            Self::Gemini => "gemini",
+            Self::OpenAI => "openai",
+        }
+    }
+
+    /// Source label of tokens read from the provider's CLI credentials file
+    pub fn cli_source(self) -> &'static str {
+        match self {
+            Self::Anthropic => "Claude CLI",
+            Self::Gemini => "Gemini CLI",
+            Self::OpenAI => "codex CLI",
+        }
+    }
+
+    /// Claude/Gemini/Codex CLI credentials file (see [`paths::locate`])
+    pub fn cli_credentials_path(self) -> Result<PathBuf> {
+        Ok(paths::locate(self)?.path().to_path_buf())
+    }
+
+    /// Resolve the provider's authentication method from its CLI credentials
+    async fn determine(self) -> AuthMethod {
+        match self {
+            Self::Anthropic => determine_anthropic_auth_method(),
+            Self::Gemini => determine_gemini_auth_method().await,
+            Self::OpenAI => determine_openai_auth_method().await,
+        }
+    }
+}
+
+/// Authentication methods at one point in time
 #[derive(Debug, Clone)]
-pub struct AuthCache {
+pub struct AuthSnapshot {
     pub anthropic_method: AuthMethod,
     pub gemini_method: AuthMethod,
     pub openai_method: AuthMethod,
     pub cached_at: SystemTime,
 }
 
-/// Authentication method determined at startup
+impl AuthSnapshot {
+    pub fn method(&self, provider: OAuthProvider) -> &AuthMethod {
+        match provider {
+            OAuthProvider::Anthropic => &self.anthropic_method,
+            OAuthProvider::Gemini => &self.gemini_method,
+            OAuthProvider::OpenAI => &self.openai_method,
+        }
+    }
+
+    fn method_mut(&mut self, provider: OAuthProvider) -> &mut AuthMethod {
+        match provider {
+            OAuthProvider::Anthropic => &mut self.anthropic_method,
+            OAuthProvider::Gemini => &mut self.gemini_method,
+            OAuthProvider::OpenAI => &mut self.openai_method,
+        }
+    }
+}
+
+/// Live authentication state shared by all request handlers
+///
+/// Handlers take a lock-free snapshot with [`AuthCache::load`]. Token refreshes publish
+/// the new token with [`AuthCache::set`], and [`AuthCache::reload_changed_cli_credentials`]
+/// picks up CLI credential files that changed on disk.
+#[derive(Debug)]
+pub struct AuthCache {
+    current: ArcSwap<AuthSnapshot>,
+    /// Modification time of each CLI credentials file when it was last read
+    cli_modified: std::sync::Mutex<FxHashMap<OAuthProvider, Option<SystemTime>>>,
+    /// Background refresh state of each provider
+    refresh_states: std::sync::Mutex<FxHashMap<OAuthProvider, RefreshState>>,
+}
+
+impl AuthCache {
+    pub fn new(snapshot: AuthSnapshot) -> Self {
+        let cli_modified = OAuthProvider::ALL
+            .into_iter()
+            .map(|provider| (provider, cli_modified_time(provider)))
+            .collect();
+        Self {
+            current: ArcSwap::from_pointee(snapshot),
+            cli_modified: std::sync::Mutex::new(cli_modified),
+            refresh_states: Default::default(),
+        }
+    }
+
+    /// Current authentication methods
+    pub fn load(&self) -> Arc<AuthSnapshot> {
+        self.current.load_full()
+    }
+
+    /// Publish a new authentication method for one provider
+    pub fn set(&self, provider: OAuthProvider, method: AuthMethod) {
+        self.current.rcu(|current| {
+            let mut next = AuthSnapshot::clone(current);
+            *next.method_mut(provider) = method.clone();
+            next.cached_at = SystemTime::now();
+            next
+        });
+    }
+
+    /// Background refresh state of one provider
+    pub fn refresh_state(&self, provider: OAuthProvider) -> RefreshState {
+        let states = self.refresh_states.lock().unwrap_or_else(|e| e.into_inner());
+        states.get(&provider).cloned().unwrap_or_default()
+    }
+
+    pub fn record_refresh_state(&self, provider: OAuthProvider, state: RefreshState) {
+        let mut states = self.refresh_states.lock().unwrap_or_else(|e| e.into_inner());
+        states.insert(provider, state);
+    }
+
+    /// Re-read CLI credentials files whose modification time changed since the last read
+    pub async fn reload_changed_cli_credentials(&self) {
+        for provider in OAuthProvider::ALL {
+            let modified = cli_modified_time(provider);
+            {
+                let mut seen = self.cli_modified.lock().unwrap_or_else(|e| e.into_inner());
+                if seen.get(&provider) == Some(&modified) {
+                    continue;
+                }
+                seen.insert(provider, modified);
+            }
+
+            let method = provider.determine().await;
+            let current = self.load();
+            if adopt_cli_method(current.method(provider), &method, provider.cli_source()) {
+                tracing::info!(
+                    "{} credentials changed on disk, {} is now {}",
+                    provider.cli_source(),
+                    provider.name(),
+                    method.describe()
+                );
+                self.set(provider, method);
+            }
+        }
+    }
+}
+
+fn cli_modified_time(provider: OAuthProvider) -> Option<SystemTime> {
+    let path = provider.cli_credentials_path().ok()?;
+    std::fs::metadata(path).ok()?.modified().ok()
+}
+
+/// Whether a method re-read from a CLI file should replace the current one
+///
+/// A working token from another source is kept if the CLI file no longer has one.
+fn adopt_cli_method(current: &AuthMethod, reread: &AuthMethod, cli_source: &str) -> bool {
+    match (current, reread) {
+        (_, AuthMethod::OAuth { .. }) => true,
+        (AuthMethod::OAuth { source, .. }, _) => source == cli_source,
+        _ => true,
+    }
+}
+
+/// Authentication method for a provider
 #[derive(Debug, Clone)]
 pub enum AuthMethod {
     /// OAuth authentication with token source and actual token
@@ -27,6 +199,17 @@ pub enum AuthMethod {
     Unavailable { reason: String },
 }
 
+impl AuthMethod {
+    /// Short description for logs and status output (never includes the token)
+    pub fn describe(&self) -> String {
+        match self {
+            AuthMethod::OAuth { source, .. } => format!("OAuth ({})", source),
+            AuthMethod::ApiKey => "API key".to_string(),
+            AuthMethod::Unavailable { reason } => format!("unavailable ({})", reason),
+        }
+    }
+}
+
 pub trait AuthProvider {
     fn is_oauth(&self) -> bool;
     fn get_auth_header(&self) -> Result<String>;
@@ -61,38 +244,42 @@ impl AuthProvider for AuthConfig {
     }
 }
 
-/// Initialize authentication cache by checking OAuth tokens once at startup
+/// Build the authentication cache from the CLI credentials available at startup
+///
+/// Never fails: providers without usable tokens are marked unavailable so the server can
+/// start degraded and pick them up once credentials appear or are refreshed.
 pub async fn initialize_auth_cache() -> Result<AuthCache> {
     use tracing::info;
 
     info!("Checking OAuth token availability...");
 
     let cached_at = SystemTime::now();
+    let anthropic_method = determine_anthropic_auth_method();
+    let gemini_method = determine_gemini_auth_method().await;
+    let openai_method = determine_openai_auth_method().await;
 
-    // Check Anthropic tokens - fail startup if expired
-    let anthropic_method = determine_anthropic_auth_method()?;
-
-    // Check Gemini tokens - fail startup if expired
-    let gemini_method = determine_gemini_auth_method().await?;
-
-    // Check OpenAI tokens - don't fail startup if unavailable
-    let openai_method = determine_openai_auth_method().await?;
-
-    info!("Authentication methods cached at startup");
-
-    Ok(AuthCache {
+    let snapshot = AuthSnapshot {
         anthropic_method,
         gemini_method,
         openai_method,
         cached_at,
-    })
+    };
+    for provider in OAuthProvider::ALL {
+        info!(
+            "{}: {}",
+            provider.name(),
+            snapshot.method(provider).describe()
+        );
+    }
+
+    Ok(AuthCache::new(snapshot))
 }
 
 /// Determine the best Anthropic authentication method
-fn determine_anthropic_auth_method() -> Result<AuthMethod> {
+fn determine_anthropic_auth_method() -> AuthMethod {
     use crate::auth::anthropic::AnthropicOAuth;
     use crate::auth::common::analyze_token_source;
-    use tracing::info;
+    use tracing::{info, warn};
 
     // Try to load Claude CLI tokens
     let claude_cli_result = AnthropicOAuth::try_claude_code_credentials();
@@ -107,139 +294,149 @@ fn determine_anthropic_auth_method() -> Result<AuthMethod> {
             );
 
             if let Some(token) = claude_config.oauth_access_token {
-                return Ok(AuthMethod::OAuth {
+                return AuthMethod::OAuth {
                     source: "Claude CLI".to_string(),
                     token,
-                });
+                };
             }
         } else {
-            use tracing::error;
-            error!("Found expired Claude CLI OAuth tokens - startup will fail");
-            // Return clean error for expired tokens - no fallback to API key
-            return Err(PrismError::Other(
-                "Anthropic OAuth tokens are expired!\n\n\
-                 Token Status:\n\
-                   • Claude CLI: expired\n\
-                   • Setu config: not configured\n\n\
+            warn!(
+                "Anthropic OAuth tokens are expired - Anthropic OAuth unavailable until refreshed\n\
                  To fix this issue:\n\
                    1. Run: claude auth refresh    (refresh Claude CLI tokens)\n\
-                   2. Run: setu auth anthropic   (get fresh setu tokens)\n\n\
-                 Setu will automatically use whichever tokens are newer."
-                    .to_string(),
-            ));
+                   2. Run: prism auth anthropic   (get fresh prism tokens)"
+            );
+            return AuthMethod::Unavailable {
+                reason: "Claude CLI OAuth tokens expired".to_string(),
+            };
         }
     } else {
         info!("No Claude CLI OAuth tokens found");
     }
 
-    // No OAuth tokens found anywhere - this is an error for OAuth providers
-    Err(PrismError::Other(
-        "No Anthropic OAuth tokens found!\n\n\
-         Token Status:\n\
-           • Claude CLI: not found\n\
-           • Setu config: not configured\n\n\
+    warn!(
+        "No Anthropic OAuth tokens found - Anthropic OAuth unavailable\n\
          To fix this issue:\n\
            1. Run: claude auth refresh    (if Claude CLI is installed)\n\
-           2. Run: setu auth anthropic   (get fresh setu tokens)\n\n\
-         Setu will automatically use whichever tokens are newer."
-            .to_string(),
-    ))
+           2. Run: prism auth anthropic   (get fresh prism tokens)"
+    );
+    AuthMethod::Unavailable {
+        reason: "No OAuth tokens found".to_string(),
+    }
 }
 
-/// Determine the best Gemini authentication method  
-async fn determine_gemini_auth_method() -> Result<AuthMethod> {
+/// Determine the best Gemini authentication method
+async fn determine_gemini_auth_method() -> AuthMethod {
     use crate::auth::google::GoogleOAuth;
-    use tracing::info;
+    use tracing::{info, warn};
 
     // Try to load Gemini CLI tokens
-    let gemini_cli_result = GoogleOAuth::try_gemini_cli_credentials().await;
-
-    if let Ok(gemini_config) = gemini_cli_result {
-        // Tokens are valid and not expired
-        info!("Found valid Gemini CLI OAuth tokens");
+    match GoogleOAuth::try_gemini_cli_credentials().await {
+        Ok(gemini_config) => {
+            // Tokens are valid and not expired
+            info!("Found valid Gemini CLI OAuth tokens");
 
-        if let Some(token) = gemini_config.oauth_access_token {
-            return Ok(AuthMethod::OAuth {
-                source: "Gemini CLI".to_string(),
-                token,
-            });
-        }
-    } else {
-        // Check if it's a specific expiration error
-        if let Err(ref error) = gemini_cli_result {
-            let error_str = error.to_string();
-            if error_str.contains("expired") {
-                use tracing::error;
-                error!("Found expired Gemini CLI OAuth tokens - startup will fail");
-                // Return clean error for expired tokens - no fallback to API key
-                return Err(PrismError::Other(
-                    "Gemini OAuth tokens are expired!\n\n\
-                     Token Status:\n\
-                       • Gemini CLI: expired\n\
-                       • Setu config: not configured\n\n\
-                     To fix this issue:\n\
-                       1. Try: gemini -p \"test\"      (may trigger automatic refresh)\n\
-                       2. Run: setu auth google      (copy CLI tokens to prism config)\n\n\
-                     If Gemini CLI refresh fails, you may need to re-authenticate with Google."
-                        .to_string(),
-                ));
-            } else {
-                info!("No Gemini CLI OAuth tokens found");
+            if let Some(token) = gemini_config.oauth_access_token {
+                return AuthMethod::OAuth {
+                    source: "Gemini CLI".to_string(),
+                    token,
+                };
             }
         }
+        Err(error) if error.to_string().contains("expired") => {
+            warn!(
+                "Gemini OAuth tokens are expired - Gemini OAuth unavailable until refreshed\n\
+                 To fix this issue:\n\
+                   1. Try: gemini -p \"test\"      (may trigger automatic refresh)\n\
+                   2. Run: prism auth google     (copy CLI tokens to prism config)"
+            );
+            return AuthMethod::Unavailable {
+                reason: "Gemini CLI OAuth tokens expired".to_string(),
+            };
+        }
+        Err(_) => info!("No Gemini CLI OAuth tokens found"),
     }
 
-    // No OAuth tokens found anywhere - this is an error for OAuth providers
-    Err(PrismError::Other(
-        "No Gemini OAuth tokens found!\n\n\
-         Token Status:\n\
-           • Gemini CLI: not found\n\
-           • Setu config: not configured\n\n\
-         To fix this issue:\n\
-           1. Try: gemini -p \"test\"      (may trigger authentication)\n\
-           2. Run: setu auth google      (set up OAuth tokens)\n\n\
-         If Gemini CLI is not installed, you may need to set up Google OAuth."
-            .to_string(),
-    ))
+    AuthMethod::Unavailable {
+        reason: "No OAuth tokens found".to_string(),
+    }
 }
 
-/// Determine the best OpenAI authentication method  
-async fn determine_openai_auth_method() -> Result<AuthMethod> {
+/// Determine the best OpenAI authentication method
+async fn determine_openai_auth_method() -> AuthMethod {
     use crate::auth::openai::OpenAIOAuth;
-    use tracing::info;
+    use tracing::{info, warn};
 
     // Try to load codex CLI tokens
-    let codex_cli_result = OpenAIOAuth::try_codex_cli_credentials().await;
-
-    if let Ok(openai_config) = codex_cli_result {
-        // Tokens are valid and not expired
-        info!("Found valid codex CLI OAuth tokens");
+    match OpenAIOAuth::try_codex_cli_credentials().await {
+        Ok(openai_config) => {
+            // Tokens are valid and not expired
+            info!("Found valid codex CLI OAuth tokens");
 
-        if let Some(token) = openai_config.oauth_access_token {
-            return Ok(AuthMethod::OAuth {
-                source: "codex CLI".to_string(),
-                token,
-            });
-        }
-    } else {
-        // Check if it's a specific expiration error
-        if let Err(ref error) = codex_cli_result {
-            let error_str = error.to_string();
-            if error_str.contains("expired") {
-                use tracing::warn;
-                warn!("Found expired codex CLI OAuth tokens - will continue without OpenAI");
-                // For OpenAI, we don't fail startup - it's optional
-                return Ok(AuthMethod::Unavailable {
-                    reason: "OAuth tokens expired".to_string(),
-                });
-            } else {
-                info!("No codex CLI OAuth tokens found - OpenAI unavailable");
+            if let Some(token) = openai_config.oauth_access_token {
+                return AuthMethod::OAuth {
+                    source: "codex CLI".to_string(),
+                    token,
+                };
             }
         }
+        Err(error) if error.to_string().contains("expired") => {
+            warn!("Found expired codex CLI OAuth tokens - will continue without OpenAI");
+            return AuthMethod::Unavailable {
+                reason: "OAuth tokens expired".to_string(),
+            };
+        }
+        Err(_) => info!("No codex CLI OAuth tokens found - OpenAI unavailable"),
     }
 
     // No OAuth tokens found - OpenAI is simply unavailable (not an error)
-    Ok(AuthMethod::Unavailable {
+    AuthMethod::Unavailable {
         reason: "No OAuth tokens found".to_string(),
-    })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn oauth(source: &str, token: &str) -> AuthMethod {
+        AuthMethod::OAuth {
+            source: source.to_string(),
+            token: token.to_string(),
+        }
+    }
+
+    fn unavailable() -> AuthMethod {
+        AuthMethod::Unavailable {
+            reason: "expired".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_set_publishes_to_existing_readers() {
+        let cache = Arc::new(AuthCache::new(AuthSnapshot {
+            anthropic_method: unavailable(),
+            gemini_method: AuthMethod::ApiKey,
+            openai_method: AuthMethod::ApiKey,
+            cached_at: UNIX_EPOCH,
+        }));
+        let before = cache.load();
+
+        cache.set(OAuthProvider::Anthropic, oauth(CONFIG_SOURCE, "fresh"));
+
+        assert!(matches!(before.anthropic_method, AuthMethod::Unavailable { .. }));
+        let after = cache.load();
+        assert!(matches!(&after.anthropic_method, AuthMethod::OAuth { token, .. } if token == "fresh"));
+        assert!(matches!(after.gemini_method, AuthMethod::ApiKey));
+        assert!(after.cached_at > UNIX_EPOCH);
+    }
+
+    #[test]
+    fn test_cli_reread_keeps_working_token_from_other_source() {
+        let cli = OAuthProvider::Anthropic.cli_source();
+        assert!(adopt_cli_method(&unavailable(), &oauth(cli, "new"), cli));
+        assert!(adopt_cli_method(&oauth(CONFIG_SOURCE, "a"), &oauth(cli, "b"), cli));
+        assert!(adopt_cli_method(&oauth(cli, "a"), &unavailable(), cli));
+        assert!(!adopt_cli_method(&oauth(CONFIG_SOURCE, "a"), &unavailable(), cli));
+    }
 }
@@ -3,17 +3,23 @@ use std::fs;
 use std::path::PathBuf;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use crate::auth::OAuthProvider;
 use crate::auth::common::{analyze_token_source, choose_best_token_source};
 use crate::config::AuthConfig;
 use crate::error::{Result, PrismError};
 
 const OAUTH_CLIENT_ID: &str = "app_EMoamEEZ73f0CkXaXp7hrann";
 const OAUTH_TOKEN_URL: &str = "https://auth.openai.com/oauth/token";
+/// Assumed token lifetime when the access token carries no readable expiry
+const TOKEN_LIFETIME_MS: u64 = 28 * 24 * 60 * 60 * 1000;
 
 #[derive(Debug, Deserialize, Serialize)]
 struct CodexAuthJson {
     tokens: Option<CodexTokenData>,
     last_refresh: Option<String>,
+    /// Fields Prism doesn't use (such as `OPENAI_API_KEY`), kept when the file is rewritten
+    #[serde(flatten)]
+    extra: serde_json::Map<String, serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -42,12 +48,14 @@ struct TokenRefreshRequest {
 pub struct OpenAIOAuth;
 
 impl OpenAIOAuth {
+    /// Codex CLI auth file (`$CODEX_HOME/auth.json` by default, `CODEX_HOME` being `~/.codex`)
+    pub fn codex_auth_path() -> Result<PathBuf> {
+        OAuthProvider::OpenAI.cli_credentials_path()
+    }
+
     /// Load codex CLI auth file
     fn load_codex_auth() -> Result<CodexAuthJson> {
-        let home = std::env::var("HOME")
-            .map_err(|_| PrismError::Other("HOME environment variable not set".to_string()))?;
-        let codex_home = std::env::var("CODEX_HOME").unwrap_or_else(|_| format!("{}/.codex", home));
-        let auth_path = PathBuf::from(codex_home).join("auth.json");
+        let auth_path = Self::codex_auth_path()?;
 
         let contents = fs::read_to_string(&auth_path)?;
         let auth_json: CodexAuthJson = serde_json::from_str(&contents)?;
@@ -56,10 +64,7 @@ impl OpenAIOAuth {
 
     /// Save codex CLI auth file
     fn save_codex_auth(auth_json: &CodexAuthJson) -> Result<()> {
-        let home = std::env::var("HOME")
-            .map_err(|_| PrismError::Other("HOME environment variable not set".to_string()))?;
-        let codex_home = std::env::var("CODEX_HOME").unwrap_or_else(|_| format!("{}/.codex", home));
-        let auth_path = PathBuf::from(codex_home).join("auth.json");
+        let auth_path = Self::codex_auth_path()?;
 
         let contents = serde_json::to_string_pretty(auth_json)?;
         fs::write(auth_path, contents)?;
@@ -80,6 +85,23 @@ impl OpenAIOAuth {
             .unwrap_or(true)
     }
 
+    /// When the access token expires, in milliseconds since the epoch
+    ///
+    /// Taken from the token's JWT `exp` claim, or 28 days after the last refresh when the
+    /// token can't be decoded.
+    fn token_expiry(auth_json: &CodexAuthJson, access_token: &str) -> u64 {
+        if let Some(exp) = jwt_expiry(access_token) {
+            return exp * 1000;
+        }
+        let last_refresh = auth_json
+            .last_refresh
+            .as_ref()
+            .and_then(|lr| chrono::DateTime::parse_from_rfc3339(lr).ok())
+            .map(|lr| lr.timestamp_millis().max(0) as u64)
+            .unwrap_or(0);
+        last_refresh + TOKEN_LIFETIME_MS
+    }
+
     /// Refresh OAuth tokens
     async fn refresh_token(refresh_token: &str) -> Result<TokenRefreshResponse> {
         let client = reqwest::Client::new();
@@ -113,30 +135,92 @@ impl OpenAIOAuth {
         Ok(refresh_response)
     }
 
-    /// Try to load OpenAI OAuth credentials from codex CLI
-    pub async fn try_codex_cli_credentials() -> Result<AuthConfig> {
-        let mut auth_json = Self::load_codex_auth()?;
+    /// Refresh the tokens in a codex auth file and record the refresh time
+    async fn refresh_codex_auth(auth_json: &mut CodexAuthJson) -> Result<()> {
         let tokens = auth_json.tokens.clone().ok_or_else(|| {
             PrismError::Other("No OAuth tokens found in codex CLI auth file".to_string())
         })?;
+        let refresh_response = Self::refresh_token(&tokens.refresh_token).await?;
+
+        auth_json.tokens = Some(CodexTokenData {
+            id_token: refresh_response.id_token,
+            access_token: refresh_response.access_token.unwrap_or(tokens.access_token),
+            refresh_token: refresh_response
+                .refresh_token
+                .unwrap_or(tokens.refresh_token),
+            account_id: tokens.account_id,
+        });
+        auth_json.last_refresh = Some(chrono::Utc::now().to_rfc3339());
+        Ok(())
+    }
+
+    fn auth_config_from(auth_json: &CodexAuthJson) -> Result<AuthConfig> {
+        let tokens = auth_json.tokens.as_ref().ok_or_else(|| {
+            PrismError::Other("No OAuth tokens found in codex CLI auth file".to_string())
+        })?;
+
+        Ok(AuthConfig {
+            oauth_access_token: Some(tokens.access_token.clone()),
+            oauth_refresh_token: Some(tokens.refresh_token.clone()),
+            oauth_expires: Some(Self::token_expiry(auth_json, &tokens.access_token)),
+            project_id: tokens.account_id.clone(),
+        })
+    }
+
+    /// Read codex CLI tokens as stored, even if expired
+    pub fn read_cli_tokens() -> Result<AuthConfig> {
+        Self::auth_config_from(&Self::load_codex_auth()?)
+    }
+
+    /// Refresh the codex CLI tokens and write them back to its auth file
+    pub async fn refresh_cli_tokens() -> Result<AuthConfig> {
+        let mut auth_json = Self::load_codex_auth()?;
+        Self::refresh_codex_auth(&mut auth_json).await?;
+        Self::save_codex_auth(&auth_json)?;
+        tracing::info!("Updated codex CLI OAuth credentials file");
+        Self::auth_config_from(&auth_json)
+    }
+
+    /// Refresh OAuth tokens stored in the prism config
+    pub async fn refresh_auth_config(auth_config: &mut AuthConfig) -> Result<()> {
+        let refresh_token = auth_config
+            .oauth_refresh_token
+            .as_ref()
+            .ok_or_else(|| PrismError::Other("No refresh token available".to_string()))?;
+
+        let refresh_response = Self::refresh_token(refresh_token).await?;
+        let access_token = refresh_response.access_token.ok_or_else(|| {
+            PrismError::Other("Token refresh response has no access token".to_string())
+        })?;
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+
+        auth_config.oauth_expires =
+            Some(jwt_expiry(&access_token).map_or(now + TOKEN_LIFETIME_MS, |exp| exp * 1000));
+        auth_config.oauth_access_token = Some(access_token);
+        if let Some(refresh_token) = refresh_response.refresh_token {
+            auth_config.oauth_refresh_token = Some(refresh_token);
+        }
+        Ok(())
+    }
+
+    /// Try to load OpenAI OAuth credentials from codex CLI
+    pub async fn try_codex_cli_credentials() -> Result<AuthConfig> {
+        let mut auth_json = Self::load_codex_auth()?;
+        if auth_json.tokens.is_none() {
+            return Err(PrismError::Other(
+                "No OAuth tokens found in codex CLI auth file".to_string(),
+            ));
+        }
 
         // Refresh if needed
         if Self::needs_refresh(&auth_json) {
             tracing::info!("Refreshing OpenAI OAuth token");
-            match Self::refresh_token(&tokens.refresh_token).await {
-                Ok(refresh_response) => {
-                    // Create updated tokens
-                    let updated_tokens = CodexTokenData {
-                        id_token: refresh_response.id_token,
-                        access_token: refresh_response.access_token.unwrap_or(tokens.access_token),
-                        refresh_token: refresh_response
-                            .refresh_token
-                            .unwrap_or(tokens.refresh_token),
-                        account_id: tokens.account_id,
-                    };
-
-                    auth_json.tokens = Some(updated_tokens);
-                    auth_json.last_refresh = Some(chrono::Utc::now().to_rfc3339());
+            match Self::refresh_codex_auth(&mut auth_json).await {
+                Ok(()) => {
                     Self::save_codex_auth(&auth_json)?;
                     tracing::info!("Successfully refreshed OpenAI OAuth token");
                 }
@@ -147,19 +231,7 @@ impl OpenAIOAuth {
             }
         }
 
-        // Get final tokens
-        let final_tokens = auth_json.tokens.unwrap();
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_millis() as u64;
-
-        Ok(AuthConfig {
-            oauth_access_token: Some(final_tokens.access_token),
-            oauth_refresh_token: Some(final_tokens.refresh_token),
-            oauth_expires: Some(now + (28 * 24 * 60 * 60 * 1000)), // 28 days
-            project_id: final_tokens.account_id,
-        })
+        Self::auth_config_from(&auth_json)
     }
 
     /// Validate and refresh OpenAI OAuth config, choosing the best available tokens
@@ -215,6 +287,18 @@ impl OpenAIOAuth {
     }
 }
 
+/// `exp` claim (seconds since the epoch) of a JWT, read without verifying the signature
+fn jwt_expiry(token: &str) -> Option<u64> {
+    use base64::Engine;
+
+    let payload = token.split('.').nth(1)?;
+    let bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(payload.trim_end_matches('='))
+        .ok()?;
+    let claims: serde_json::Value = serde_json::from_slice(&bytes).ok()?;
+    claims.get("exp")?.as_u64()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -224,6 +308,7 @@ mod tests {
         let auth_json = CodexAuthJson {
             tokens: None,
             last_refresh: None,
+            extra: Default::default(),
         };
         assert!(OpenAIOAuth::needs_refresh(&auth_json));
     }
@@ -234,6 +319,7 @@ mod tests {
         let auth_json = CodexAuthJson {
             tokens: None,
             last_refresh: Some(old_time.to_rfc3339()),
+            extra: Default::default(),
         };
         assert!(OpenAIOAuth::needs_refresh(&auth_json));
     }
@@ -244,7 +330,31 @@ mod tests {
         let auth_json = CodexAuthJson {
             tokens: None,
             last_refresh: Some(recent_time.to_rfc3339()),
+            extra: Default::default(),
         };
         assert!(!OpenAIOAuth::needs_refresh(&auth_json));
     }
+
+    #[test]
+    fn test_token_expiry_prefers_jwt_exp_claim() {
+        use base64::Engine;
+
+        let payload = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .encode(r#"{"exp":1900000000,"sub":"user"}"#);
+        let token = format!("header.{}.signature", payload);
+        let auth_json = CodexAuthJson {
+            tokens: None,
+            last_refresh: Some("2025-01-01T00:00:00Z".to_string()),
+            extra: Default::default(),
+        };
+
+        assert_eq!(
+            OpenAIOAuth::token_expiry(&auth_json, &token),
+            1_900_000_000_000
+        );
+        assert_eq!(
+            OpenAIOAuth::token_expiry(&auth_json, "not-a-jwt"),
+            1_735_689_600_000 + TOKEN_LIFETIME_MS
+        );
+    }
 }
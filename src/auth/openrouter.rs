@@ -0,0 +1,92 @@
+use serde::Deserialize;
+use url::Url;
+
+use crate::auth::common::generate_pkce_pair;
+use crate::error::{PrismError, Result};
+
+const AUTH_URL: &str = "https://openrouter.ai/auth";
+const KEYS_URL: &str = "https://openrouter.ai/api/v1/auth/keys";
+
+#[derive(Debug, Clone)]
+pub struct AuthorizeResult {
+    pub url: String,
+    pub verifier: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct KeyResponse {
+    key: String,
+}
+
+/// OpenRouter's PKCE flow, which issues a user-controlled API key rather than OAuth tokens
+pub struct OpenRouterOAuth;
+
+impl OpenRouterOAuth {
+    /// Authorization URL that sends the browser back to `callback_url` with a `code`
+    pub fn create_authorization_url(callback_url: &str) -> Result<AuthorizeResult> {
+        let (challenge, verifier) = generate_pkce_pair();
+
+        let mut url = Url::parse(AUTH_URL)
+            .map_err(|e| PrismError::Other(format!("Failed to parse OAuth URL: {}", e)))?;
+        url.query_pairs_mut()
+            .append_pair("callback_url", callback_url)
+            .append_pair("code_challenge", &challenge)
+            .append_pair("code_challenge_method", "S256");
+
+        Ok(AuthorizeResult {
+            url: url.to_string(),
+            verifier,
+        })
+    }
+
+    /// Exchange the authorization code for an OpenRouter API key
+    pub async fn exchange_code_for_key(code: &str, verifier: &str) -> Result<String> {
+        let request_body = serde_json::json!({
+            "code": code,
+            "code_verifier": verifier,
+            "code_challenge_method": "S256",
+        });
+
+        let response = reqwest::Client::new()
+            .post(KEYS_URL)
+            .json(&request_body)
+            .send()
+            .await
+            .map_err(|e| PrismError::Other(format!("OpenRouter key exchange failed: {}", e)))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_body = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Unable to read error body".to_string());
+            return Err(PrismError::Other(format!(
+                "OpenRouter key exchange failed with status: {} - Body: {}",
+                status, error_body
+            )));
+        }
+
+        let key_response: KeyResponse = response
+            .json()
+            .await
+            .map_err(|e| PrismError::Other(format!("Failed to parse key response: {}", e)))?;
+        Ok(key_response.key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_authorization_url_carries_callback_and_challenge() {
+        let result =
+            OpenRouterOAuth::create_authorization_url("http://localhost:3000/callback").unwrap();
+        let url = Url::parse(&result.url).unwrap();
+        let params: std::collections::HashMap<_, _> = url.query_pairs().into_owned().collect();
+
+        assert_eq!(params["callback_url"], "http://localhost:3000/callback");
+        assert_eq!(params["code_challenge_method"], "S256");
+        assert_ne!(params["code_challenge"], result.verifier);
+    }
+}
@@ -0,0 +1,227 @@
+//! Locations of the Claude, Gemini and codex CLI credentials files.
+//!
+//! Each provider has a list of candidate paths and the first one that exists is used. The
+//! list comes from an environment override, else `[cli_credentials]` in the config, else
+//! the CLI's default locations.
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use crate::auth::OAuthProvider;
+use crate::config::CliCredentialsConfig;
+use crate::error::{PrismError, Result};
+
+/// `[cli_credentials]` of the loaded config
+static CONFIGURED: RwLock<Option<CliCredentialsConfig>> = RwLock::new(None);
+
+/// Where a provider's candidate paths came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathOrigin {
+    /// The provider's `PRISM_*_CREDENTIALS` environment variable
+    Env,
+    /// `[cli_credentials]` in the config file
+    Config,
+    /// The CLI's default locations
+    Default,
+}
+
+impl PathOrigin {
+    pub fn describe(self, provider: OAuthProvider) -> String {
+        match self {
+            Self::Env => format!("from {}", env_var(provider)),
+            Self::Config => format!("from cli_credentials.{}", config_key(provider)),
+            Self::Default => "default locations".to_string(),
+        }
+    }
+}
+
+/// Candidate paths of one CLI credentials file, and the one in use
+#[derive(Debug, Clone)]
+pub struct CredentialsLocation {
+    pub candidates: Vec<PathBuf>,
+    pub origin: PathOrigin,
+    /// First candidate that exists
+    pub found: Option<PathBuf>,
+}
+
+impl CredentialsLocation {
+    /// The file to read and write: the first existing candidate, else the first candidate
+    pub fn path(&self) -> &Path {
+        self.found.as_deref().unwrap_or(&self.candidates[0])
+    }
+}
+
+/// Use the `[cli_credentials]` section of a freshly loaded config
+pub fn configure(config: &CliCredentialsConfig) {
+    let mut configured = CONFIGURED.write().unwrap_or_else(|e| e.into_inner());
+    *configured = Some(config.clone());
+}
+
+/// Environment variable overriding a provider's candidate paths (a `PATH`-style list)
+pub fn env_var(provider: OAuthProvider) -> &'static str {
+    match provider {
+        OAuthProvider::Anthropic => "PRISM_CLAUDE_CREDENTIALS",
+        OAuthProvider::Gemini => "PRISM_GEMINI_CREDENTIALS",
+        OAuthProvider::OpenAI => "PRISM_CODEX_CREDENTIALS",
+    }
+}
+
+/// Key of a provider in `[cli_credentials]`
+pub fn config_key(provider: OAuthProvider) -> &'static str {
+    match provider {
+        OAuthProvider::Anthropic => "claude",
+        OAuthProvider::Gemini => "gemini",
+        OAuthProvider::OpenAI => "codex",
+    }
+}
+
+/// Find the provider's CLI credentials file
+pub fn locate(provider: OAuthProvider) -> Result<CredentialsLocation> {
+    let configured = {
+        let configured = CONFIGURED.read().unwrap_or_else(|e| e.into_inner());
+        configured
+            .as_ref()
+            .map(|c| configured_paths(c, provider).to_vec())
+            .unwrap_or_default()
+    };
+    let (candidates, origin) =
+        candidate_paths(std::env::var_os(env_var(provider)), &configured, || {
+            default_paths(provider)
+        })?;
+    Ok(first_existing(candidates, origin))
+}
+
+fn configured_paths(config: &CliCredentialsConfig, provider: OAuthProvider) -> &[String] {
+    match provider {
+        OAuthProvider::Anthropic => &config.claude,
+        OAuthProvider::Gemini => &config.gemini,
+        OAuthProvider::OpenAI => &config.codex,
+    }
+}
+
+/// Candidate paths by precedence: environment, then config, then defaults
+fn candidate_paths(
+    env_value: Option<OsString>,
+    configured: &[String],
+    defaults: impl FnOnce() -> Result<Vec<PathBuf>>,
+) -> Result<(Vec<PathBuf>, PathOrigin)> {
+    if let Some(value) = env_value.filter(|v| !v.is_empty()) {
+        let paths = std::env::split_paths(&value)
+            .filter(|p| !p.as_os_str().is_empty())
+            .map(|p| expand_home(&p.to_string_lossy()))
+            .collect::<Result<Vec<_>>>()?;
+        if !paths.is_empty() {
+            return Ok((paths, PathOrigin::Env));
+        }
+    }
+    if !configured.is_empty() {
+        let paths = configured
+            .iter()
+            .map(|p| expand_home(p))
+            .collect::<Result<Vec<_>>>()?;
+        return Ok((paths, PathOrigin::Config));
+    }
+    Ok((defaults()?, PathOrigin::Default))
+}
+
+fn first_existing(candidates: Vec<PathBuf>, origin: PathOrigin) -> CredentialsLocation {
+    let found = candidates.iter().find(|p| p.is_file()).cloned();
+    CredentialsLocation {
+        candidates,
+        origin,
+        found,
+    }
+}
+
+/// Where each CLI keeps its credentials when not told otherwise
+fn default_paths(provider: OAuthProvider) -> Result<Vec<PathBuf>> {
+    let home = home_dir()?;
+    Ok(match provider {
+        OAuthProvider::Anthropic => {
+            let mut paths = Vec::new();
+            if let Some(dir) = std::env::var_os("CLAUDE_CONFIG_DIR") {
+                paths.push(PathBuf::from(dir).join(".credentials.json"));
+            }
+            paths.push(home.join(".claude").join(".credentials.json"));
+            paths.push(
+                home.join(".config")
+                    .join("claude")
+                    .join(".credentials.json"),
+            );
+            paths
+        }
+        OAuthProvider::Gemini => vec![home.join(".gemini").join("oauth_creds.json")],
+        OAuthProvider::OpenAI => {
+            let codex_home = std::env::var_os("CODEX_HOME")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| home.join(".codex"));
+            vec![codex_home.join("auth.json")]
+        }
+    })
+}
+
+fn expand_home(path: &str) -> Result<PathBuf> {
+    match path.strip_prefix("~/") {
+        Some(rest) => Ok(home_dir()?.join(rest)),
+        None => Ok(PathBuf::from(path)),
+    }
+}
+
+fn home_dir() -> Result<PathBuf> {
+    std::env::var_os("HOME")
+        .map(PathBuf::from)
+        .ok_or_else(|| PrismError::Other("HOME environment variable not set".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixed_defaults() -> Result<Vec<PathBuf>> {
+        Ok(vec![PathBuf::from("/default/auth.json")])
+    }
+
+    #[test]
+    fn test_candidate_precedence() {
+        let configured = vec!["/config/a.json".to_string(), "/config/b.json".to_string()];
+
+        let (paths, origin) = candidate_paths(
+            Some(OsString::from("/env/a.json:/env/b.json")),
+            &configured,
+            fixed_defaults,
+        )
+        .unwrap();
+        assert_eq!(origin, PathOrigin::Env);
+        assert_eq!(
+            paths,
+            vec![PathBuf::from("/env/a.json"), PathBuf::from("/env/b.json")]
+        );
+
+        let (paths, origin) =
+            candidate_paths(Some(OsString::new()), &configured, fixed_defaults).unwrap();
+        assert_eq!(origin, PathOrigin::Config);
+        assert_eq!(paths.len(), 2);
+
+        let (paths, origin) = candidate_paths(None, &[], fixed_defaults).unwrap();
+        assert_eq!(origin, PathOrigin::Default);
+        assert_eq!(paths, vec![PathBuf::from("/default/auth.json")]);
+    }
+
+    #[test]
+    fn test_first_existing_candidate_is_used() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing.json");
+        let present = dir.path().join("present.json");
+        std::fs::write(&present, "{}").unwrap();
+
+        let location = first_existing(vec![missing.clone(), present.clone()], PathOrigin::Config);
+        assert_eq!(location.found.as_deref(), Some(present.as_path()));
+        assert_eq!(location.path(), present);
+
+        // With nothing on disk, the first candidate is where a file would be created
+        let location = first_existing(vec![missing.clone()], PathOrigin::Config);
+        assert_eq!(location.found, None);
+        assert_eq!(location.path(), missing);
+    }
+}
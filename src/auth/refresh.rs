@@ -0,0 +1,289 @@
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::Mutex;
+
+use crate::auth::anthropic::AnthropicOAuth;
+use crate::auth::google::GoogleOAuth;
+use crate::auth::openai::OpenAIOAuth;
+use crate::auth::{AuthCache, AuthMethod, CONFIG_SOURCE, OAuthProvider};
+use crate::config::{AuthConfig, Config};
+use crate::error::{PrismError, Result};
+
+/// Refresh tokens this long before they expire
+const REFRESH_LEAD_MS: u64 = 10 * 60 * 1000;
+
+/// Longest wait between checks, so credentials added or replaced on disk are noticed
+pub const MAX_CHECK_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Wait before retrying a failed refresh
+const RETRY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Where a provider's OAuth tokens live, and where refreshed tokens are written back
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CredentialOwner {
+    /// `providers.<name>.auth` in the prism config
+    Config,
+    /// The provider's CLI credentials file (Claude/Gemini/codex CLI)
+    Cli,
+}
+
+impl CredentialOwner {
+    /// Source label published in the [`AuthCache`]
+    pub fn source(self, provider: OAuthProvider) -> &'static str {
+        match self {
+            Self::Config => CONFIG_SOURCE,
+            Self::Cli => provider.cli_source(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RefreshStatus {
+    /// No OAuth tokens with a refresh token were found
+    #[default]
+    NotConfigured,
+    /// Tokens are valid and the next refresh is scheduled
+    Valid,
+    /// The last refresh failed; it is retried until it succeeds
+    Failed,
+}
+
+/// Refresh state of one provider, reported by `/health`
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RefreshState {
+    pub status: RefreshStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<CredentialOwner>,
+    /// Token expiry, in milliseconds since the epoch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+    /// Last successful refresh, in seconds since the epoch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_refresh: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+/// Check every OAuth provider and refresh the tokens that expire soon
+///
+/// Returns how long to wait before the next check.
+pub async fn refresh_due_tokens(config: &Arc<Mutex<Config>>, auth_cache: &AuthCache) -> Duration {
+    let mut next_check = MAX_CHECK_INTERVAL;
+    for provider in OAuthProvider::ALL {
+        next_check = next_check.min(refresh_provider_if_due(provider, config, auth_cache).await);
+    }
+    next_check
+}
+
+async fn refresh_provider_if_due(
+    provider: OAuthProvider,
+    config: &Arc<Mutex<Config>>,
+    auth_cache: &AuthCache,
+) -> Duration {
+    let Some((owner, mut auth)) = owned_credentials(provider, config, auth_cache).await else {
+        auth_cache.record_refresh_state(provider, RefreshState::default());
+        return MAX_CHECK_INTERVAL;
+    };
+
+    let mut state = auth_cache.refresh_state(provider);
+    state.owner = Some(owner);
+
+    if is_due(auth.oauth_expires, now_millis()) {
+        tracing::info!(
+            "Refreshing {} OAuth tokens ({})",
+            provider.name(),
+            owner.source(provider)
+        );
+        match refresh(provider, owner, config).await {
+            Ok(refreshed) => {
+                auth = refreshed;
+                state.last_refresh = Some(now_millis() / 1000);
+                state.last_error = None;
+            }
+            Err(e) => {
+                tracing::error!("{} OAuth token refresh failed: {}", provider.name(), e);
+                state.status = RefreshStatus::Failed;
+                state.expires_at = auth.oauth_expires;
+                state.last_error = Some(e.to_string());
+                auth_cache.record_refresh_state(provider, state);
+                return RETRY_INTERVAL;
+            }
+        }
+    }
+
+    state.status = RefreshStatus::Valid;
+    state.expires_at = auth.oauth_expires;
+    auth_cache.record_refresh_state(provider, state);
+
+    // Publish tokens the handlers don't have yet (refreshed, or found in the config)
+    if !auth.is_token_expired()
+        && let Some(token) = auth.oauth_access_token
+    {
+        let current = auth_cache.load();
+        let published =
+            matches!(current.method(provider), AuthMethod::OAuth { token: t, .. } if *t == token);
+        if !published {
+            auth_cache.set(
+                provider,
+                AuthMethod::OAuth {
+                    source: owner.source(provider).to_string(),
+                    token,
+                },
+            );
+        }
+    }
+
+    next_check_delay(auth.oauth_expires, now_millis())
+}
+
+/// The provider's OAuth tokens and their owner
+///
+/// Tokens currently served from the CLI file are owned by the CLI; otherwise tokens in the
+/// prism config win, falling back to the CLI file.
+async fn owned_credentials(
+    provider: OAuthProvider,
+    config: &Arc<Mutex<Config>>,
+    auth_cache: &AuthCache,
+) -> Option<(CredentialOwner, AuthConfig)> {
+    let config_auth = config
+        .lock()
+        .await
+        .providers
+        .get(provider.name())
+        .map(|p| p.auth.clone())
+        .filter(|auth| auth.oauth_refresh_token.is_some());
+    let cli_auth = read_cli_tokens(provider)
+        .ok()
+        .filter(|auth| auth.oauth_refresh_token.is_some());
+
+    let serving_cli = matches!(
+        auth_cache.load().method(provider),
+        AuthMethod::OAuth { source, .. } if source == provider.cli_source()
+    );
+    match choose_owner(serving_cli, config_auth.is_some(), cli_auth.is_some())? {
+        CredentialOwner::Config => config_auth.map(|auth| (CredentialOwner::Config, auth)),
+        CredentialOwner::Cli => cli_auth.map(|auth| (CredentialOwner::Cli, auth)),
+    }
+}
+
+fn choose_owner(serving_cli: bool, in_config: bool, in_cli: bool) -> Option<CredentialOwner> {
+    match (serving_cli, in_config, in_cli) {
+        (true, _, true) | (false, false, true) => Some(CredentialOwner::Cli),
+        (_, true, _) => Some(CredentialOwner::Config),
+        _ => None,
+    }
+}
+
+fn read_cli_tokens(provider: OAuthProvider) -> Result<AuthConfig> {
+    match provider {
+        OAuthProvider::Anthropic => AnthropicOAuth::read_cli_tokens(),
+        OAuthProvider::Gemini => GoogleOAuth::read_cli_tokens(),
+        OAuthProvider::OpenAI => OpenAIOAuth::read_cli_tokens(),
+    }
+}
+
+/// Refresh the provider's tokens and write them back to their owner
+async fn refresh(
+    provider: OAuthProvider,
+    owner: CredentialOwner,
+    config: &Arc<Mutex<Config>>,
+) -> Result<AuthConfig> {
+    if owner == CredentialOwner::Cli {
+        return match provider {
+            OAuthProvider::Anthropic => AnthropicOAuth::refresh_cli_tokens().await,
+            OAuthProvider::Gemini => GoogleOAuth::refresh_cli_tokens().await,
+            OAuthProvider::OpenAI => OpenAIOAuth::refresh_cli_tokens().await,
+        };
+    }
+
+    // Refresh a copy so the config lock isn't held during the network call
+    let mut auth = config
+        .lock()
+        .await
+        .providers
+        .get(provider.name())
+        .map(|p| p.auth.clone())
+        .ok_or_else(|| PrismError::ProviderNotFound(provider.name().to_string()))?;
+    match provider {
+        OAuthProvider::Anthropic => AnthropicOAuth::refresh_token(&mut auth).await?,
+        OAuthProvider::Gemini => GoogleOAuth::refresh_auth_config(&mut auth).await?,
+        OAuthProvider::OpenAI => OpenAIOAuth::refresh_auth_config(&mut auth).await?,
+    }
+
+    let config_to_save = {
+        let mut config_guard = config.lock().await;
+        if let Some(p) = config_guard.providers.get_mut(provider.name()) {
+            p.auth = auth.clone();
+        }
+        config_guard.clone()
+    };
+    // Persist refreshed tokens to config file WITHOUT holding lock
+    if let Err(e) = config_to_save.save() {
+        tracing::error!("Failed to save refreshed tokens to config: {}", e);
+    }
+    Ok(auth)
+}
+
+/// Whether a token expiring at `expires_at` should be refreshed now
+fn is_due(expires_at: Option<u64>, now: u64) -> bool {
+    expires_at.is_none_or(|expires| now + REFRESH_LEAD_MS >= expires)
+}
+
+/// Time until a token expiring at `expires_at` becomes due, capped at [`MAX_CHECK_INTERVAL`]
+fn next_check_delay(expires_at: Option<u64>, now: u64) -> Duration {
+    let Some(expires) = expires_at else {
+        return MAX_CHECK_INTERVAL;
+    };
+    let due_in = expires.saturating_sub(REFRESH_LEAD_MS).saturating_sub(now);
+    Duration::from_millis(due_in).min(MAX_CHECK_INTERVAL)
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refresh_schedule_follows_expiry() {
+        let now = 1_000_000_000;
+
+        assert!(is_due(None, now));
+        assert!(is_due(Some(now + 60_000), now));
+        assert!(!is_due(Some(now + 3_600_000), now));
+
+        assert_eq!(
+            next_check_delay(Some(now + REFRESH_LEAD_MS + 90_000), now),
+            Duration::from_secs(90)
+        );
+        assert_eq!(
+            next_check_delay(Some(now + 3_600_000), now),
+            MAX_CHECK_INTERVAL
+        );
+        assert_eq!(next_check_delay(Some(now - 1), now), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_refreshed_tokens_go_back_to_their_owner() {
+        // Tokens currently served from the CLI file stay with the CLI
+        assert_eq!(choose_owner(true, true, true), Some(CredentialOwner::Cli));
+        assert_eq!(
+            choose_owner(false, true, true),
+            Some(CredentialOwner::Config)
+        );
+        assert_eq!(choose_owner(false, false, true), Some(CredentialOwner::Cli));
+        assert_eq!(
+            choose_owner(true, true, false),
+            Some(CredentialOwner::Config)
+        );
+        assert_eq!(choose_owner(false, false, false), None);
+    }
+}
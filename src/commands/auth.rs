@@ -7,61 +7,83 @@ use crate::{Config, Result, PrismError};
 #[derive(Subcommand, Debug)]
 pub enum AuthCommands {
     /// Authenticate with Anthropic using OAuth
-    Anthropic,
-
-    /// Authenticate with Google/Gemini using CLI credentials
-    Google,
+    Anthropic {
+        /// Print the URL and paste the code back instead of using a local callback
+        #[arg(long)]
+        no_browser: bool,
+    },
+
+    /// Authenticate with Google/Gemini using CLI credentials, or OAuth if there are none
+    Google {
+        /// Print the URL and paste the code back instead of using a local callback
+        #[arg(long)]
+        no_browser: bool,
+    },
 
     /// Check OpenAI/codex CLI OAuth credentials
     Openai,
+
+    /// Get an OpenRouter API key through OpenRouter's browser login
+    Openrouter,
+
+    /// Move API keys and OAuth tokens from the config file into the secret store
+    MigrateSecrets,
 }
 
 pub async fn handle_auth_command(auth_command: AuthCommands) -> Result<()> {
     match auth_command {
-        AuthCommands::Anthropic => handle_anthropic_auth().await,
-        AuthCommands::Google => handle_google_auth().await,
+        AuthCommands::Anthropic { no_browser } => handle_anthropic_auth(no_browser).await,
+        AuthCommands::Google { no_browser } => handle_google_auth(no_browser).await,
         AuthCommands::Openai => handle_openai_auth().await,
+        AuthCommands::Openrouter => handle_openrouter_auth().await,
+        AuthCommands::MigrateSecrets => handle_migrate_secrets(),
     }
 }
 
-async fn handle_anthropic_auth() -> Result<()> {
+/// Path the loopback listener receives OAuth redirects on
+const CALLBACK_PATH: &str = "/callback";
+
+async fn handle_anthropic_auth(no_browser: bool) -> Result<()> {
     use crate::auth::anthropic::AnthropicOAuth;
+    use crate::auth::callback::{CALLBACK_TIMEOUT, CallbackListener};
 
     info!("Starting Anthropic OAuth authentication...");
 
     // Load existing config or create new one
-    let mut config = Config::load().unwrap_or_default();
-
-    // Generate authorization URL and get verifier
-    let auth_result = AnthropicOAuth::create_authorization_url()?;
+    let mut config = Config::load()?;
 
     println!("Anthropic OAuth Authentication");
     println!("==================================");
     println!();
-    println!("Please visit this URL to authorize Setu:");
-    println!("{}", auth_result.url);
-    println!();
-    println!("After authorization, you'll be redirected to a localhost URL.");
-    println!("Copy the 'code' parameter from the URL and paste it here:");
-    print!("> ");
-    io::stdout().flush().unwrap();
-
-    // Read authorization code from user
-    let mut auth_code = String::new();
-    io::stdin()
-        .read_line(&mut auth_code)
-        .map_err(|e| PrismError::Other(format!("Failed to read input: {}", e)))?;
-    let auth_code = auth_code.trim().to_string();
 
-    if auth_code.is_empty() {
-        println!("No authorization code provided");
-        return Ok(());
-    }
+    let (auth_result, auth_code) = if no_browser {
+        let auth_result = AnthropicOAuth::create_authorization_url()?;
+        show_authorization_url(&auth_result.url, false);
+        println!("After authorization, copy the code shown on the page and paste it here:");
+        let Some(pasted) = read_pasted_code()? else {
+            println!("No authorization code provided");
+            return Ok(());
+        };
+        let auth_code = AnthropicOAuth::parse_pasted_code(&pasted, &auth_result.state)?;
+        (auth_result, auth_code)
+    } else {
+        // The browser is sent back to this listener, which checks the state
+        let listener = CallbackListener::bind().await?;
+        let auth_result = AnthropicOAuth::create_loopback_authorization_url(
+            &listener.redirect_uri(CALLBACK_PATH),
+        )?;
+        show_authorization_url(&auth_result.url, true);
+        println!("Waiting for the browser to complete authorization...");
+        let callback = listener
+            .wait_for_code(CALLBACK_PATH, Some(&auth_result.state), CALLBACK_TIMEOUT)
+            .await?;
+        (auth_result, callback.code)
+    };
 
     // Exchange code for tokens
     println!("Exchanging authorization code for tokens...");
 
-    match AnthropicOAuth::exchange_code_for_token(&auth_code, &auth_result.verifier).await {
+    match AnthropicOAuth::exchange_code_for_token(&auth_code, &auth_result).await {
         Ok(received_auth_config) => {
             // Save to config
             let mut provider_config =
@@ -77,11 +99,13 @@ async fn handle_anthropic_auth() -> Result<()> {
                         api_key: None,
                         api_key_fallback: false,
                         fallback_on_errors: vec![429],
+                        ..Default::default()
                     });
             provider_config.auth = received_auth_config;
             config
                 .providers
                 .insert("anthropic".to_string(), provider_config);
+            crate::secrets::store_new_credentials(&mut config, &oauth_token_keys("anthropic"));
 
             // Save config
             config.save()?;
@@ -98,16 +122,29 @@ async fn handle_anthropic_auth() -> Result<()> {
     Ok(())
 }
 
-async fn handle_google_auth() -> Result<()> {
+async fn handle_google_auth(no_browser: bool) -> Result<()> {
     use crate::auth::google::GoogleOAuth;
 
     println!("Setting up Google/Gemini authentication...");
 
     // Load current config
-    let mut config = Config::load().unwrap_or_default();
+    let mut config = Config::load()?;
 
-    // Try to read existing Gemini CLI credentials
-    match GoogleOAuth::try_gemini_cli_credentials().await {
+    // Existing Gemini CLI credentials first, then a browser login
+    let auth_result = match GoogleOAuth::try_gemini_cli_credentials().await {
+        Ok(auth_config) => {
+            println!("   Using Gemini CLI credentials.");
+            Ok(auth_config)
+        }
+        Err(e) => {
+            println!("Could not load Gemini CLI OAuth credentials: {}", e);
+            println!("Signing in with Google instead.");
+            println!();
+            google_browser_login(no_browser).await
+        }
+    };
+
+    match auth_result {
         Ok(auth_config) => {
             // Save to config
             let mut provider_config =
@@ -120,26 +157,28 @@ async fn handle_google_auth() -> Result<()> {
                         api_key: None,
                         api_key_fallback: false,
                         fallback_on_errors: vec![429],
+                        ..Default::default()
                     }
                 });
             provider_config.auth = auth_config;
             config
                 .providers
                 .insert("gemini".to_string(), provider_config);
+            crate::secrets::store_new_credentials(&mut config, &oauth_token_keys("gemini"));
 
             // Save config
             config.save()?;
 
             println!("Google/Gemini authentication successful!");
-            println!("   OAuth tokens loaded from Gemini CLI credentials.");
+            println!("   Tokens have been saved to your configuration.");
             println!("   You can now use Gemini models through Setu.");
         }
         Err(e) => {
-            println!("Could not load Gemini CLI OAuth credentials: {}", e);
+            println!("Google authentication failed: {}", e);
             println!();
             println!("To use Gemini with OAuth:");
-            println!("1. Install the Gemini CLI");
-            println!("2. Run: gemini auth login");
+            println!("1. Retry: setu auth google (add --no-browser on a headless machine)");
+            println!("2. Or install the Gemini CLI, run: gemini auth login");
             println!("3. Then retry: setu auth google");
             println!();
             println!(
@@ -151,13 +190,65 @@ async fn handle_google_auth() -> Result<()> {
     Ok(())
 }
 
+/// Browser login for Gemini, through a local callback or a pasted code
+async fn google_browser_login(no_browser: bool) -> Result<crate::config::AuthConfig> {
+    use crate::auth::callback::{CALLBACK_TIMEOUT, CallbackListener};
+    use crate::auth::google::GoogleOAuth;
+
+    let (auth_result, auth_code) = if no_browser {
+        let auth_result = GoogleOAuth::create_authorization_url()?;
+        show_authorization_url(&auth_result.url, false);
+        println!("After authorization, copy the code shown on the page and paste it here:");
+        let auth_code = read_pasted_code()?
+            .ok_or_else(|| PrismError::Other("No authorization code provided".to_string()))?;
+        (auth_result, auth_code)
+    } else {
+        let listener = CallbackListener::bind().await?;
+        let auth_result =
+            GoogleOAuth::create_loopback_authorization_url(&listener.redirect_uri(CALLBACK_PATH))?;
+        show_authorization_url(&auth_result.url, true);
+        println!("Waiting for the browser to complete authorization...");
+        let callback = listener
+            .wait_for_code(CALLBACK_PATH, Some(&auth_result.state), CALLBACK_TIMEOUT)
+            .await?;
+        (auth_result, callback.code)
+    };
+
+    println!("Exchanging authorization code for tokens...");
+    GoogleOAuth::exchange_code_for_token(&auth_code, &auth_result).await
+}
+
+/// Print the authorization URL, and open it when a local browser will be redirected back
+fn show_authorization_url(url: &str, open: bool) {
+    println!("Please visit this URL to authorize Setu:");
+    println!("{}", url);
+    println!();
+    if open && crate::auth::callback::open_browser(url) {
+        println!("(Opened in your browser. Use --no-browser on a machine without one.)");
+        println!();
+    }
+}
+
+/// Read a pasted authorization code; `None` if nothing was entered
+fn read_pasted_code() -> Result<Option<String>> {
+    print!("> ");
+    io::stdout().flush().unwrap();
+
+    let mut auth_code = String::new();
+    io::stdin()
+        .read_line(&mut auth_code)
+        .map_err(|e| PrismError::Other(format!("Failed to read input: {}", e)))?;
+    let auth_code = auth_code.trim();
+    Ok((!auth_code.is_empty()).then(|| auth_code.to_string()))
+}
+
 async fn handle_openai_auth() -> Result<()> {
     use crate::auth::openai::OpenAIOAuth;
 
     println!("Checking OpenAI/codex CLI authentication...");
 
     // Load current config
-    let mut config = Config::load().unwrap_or_default();
+    let mut config = Config::load()?;
 
     // Try to read existing codex CLI credentials
     match OpenAIOAuth::try_codex_cli_credentials().await {
@@ -173,12 +264,14 @@ async fn handle_openai_auth() -> Result<()> {
                         api_key: None,
                         api_key_fallback: false,
                         fallback_on_errors: vec![429],
+                        ..Default::default()
                     }
                 });
             provider_config.auth = auth_config;
             config
                 .providers
                 .insert("openai".to_string(), provider_config);
+            crate::secrets::store_new_credentials(&mut config, &oauth_token_keys("openai"));
 
             // Save config
             config.save()?;
@@ -192,7 +285,9 @@ async fn handle_openai_auth() -> Result<()> {
             println!("Token Info:");
             println!("   • Tokens are automatically refreshed every 28 days");
             println!("   • Access token expires and refreshes as needed");
-            println!("   • Shared with codex CLI (~/.codex/auth.json)");
+            if let Ok(path) = OpenAIOAuth::codex_auth_path() {
+                println!("   • Shared with codex CLI ({})", path.display());
+            }
         }
         Err(e) => {
             println!("Could not load codex CLI OAuth credentials: {}", e);
@@ -214,3 +309,96 @@ async fn handle_openai_auth() -> Result<()> {
 
     Ok(())
 }
+
+async fn handle_openrouter_auth() -> Result<()> {
+    use crate::auth::callback::{CALLBACK_TIMEOUT, CallbackListener};
+    use crate::auth::openrouter::OpenRouterOAuth;
+
+    info!("Starting OpenRouter authentication...");
+
+    let mut config = Config::load()?;
+
+    // OpenRouter redirects back to this listener with the authorization code
+    let listener = CallbackListener::bind().await?;
+    let auth_result =
+        OpenRouterOAuth::create_authorization_url(&listener.redirect_uri(CALLBACK_PATH))?;
+
+    println!("OpenRouter Authentication");
+    println!("=========================");
+    println!();
+    println!("Please visit this URL to authorize Setu:");
+    println!("{}", auth_result.url);
+    println!();
+    println!("Waiting for the browser to complete authorization...");
+
+    let callback = listener
+        .wait_for_code(CALLBACK_PATH, None, CALLBACK_TIMEOUT)
+        .await?;
+
+    println!("Exchanging authorization code for an API key...");
+    let api_key =
+        OpenRouterOAuth::exchange_code_for_key(&callback.code, &auth_result.verifier).await?;
+
+    let provider_config = config
+        .providers
+        .entry("openrouter".to_string())
+        .or_insert_with(|| crate::config::ProviderConfig {
+            r#type: "openrouter".to_string(),
+            endpoint: "https://openrouter.ai/api/v1".to_string(),
+            auth: Default::default(),
+            retry: crate::config::RetryConfig::default(),
+            api_key: None,
+            api_key_fallback: false,
+            fallback_on_errors: vec![429],
+            ..Default::default()
+        });
+    provider_config.api_key = Some(api_key);
+    crate::secrets::store_new_credentials(&mut config, &["openrouter".to_string()]);
+
+    config.save()?;
+
+    println!("OpenRouter authentication successful!");
+    println!("   The API key has been saved to providers.openrouter.");
+    println!("   You can now use OpenRouter models through Setu.");
+    Ok(())
+}
+
+/// Secret field keys of the OAuth tokens a login writes for `provider`
+fn oauth_token_keys(provider: &str) -> Vec<String> {
+    vec![
+        format!("{}.oauth_access_token", provider),
+        format!("{}.oauth_refresh_token", provider),
+    ]
+}
+
+fn handle_migrate_secrets() -> Result<()> {
+    use crate::secrets;
+
+    // Work on the file as written so ${VAR}, file: and cmd: values stay references
+    let mut config = Config::load_unresolved()?;
+    let store = secrets::open_store(config.secrets.kind())?;
+
+    let migrated = secrets::migrate_secrets(&mut config, store.as_ref())?;
+    if migrated.is_empty() {
+        println!("No plaintext credentials found in the configuration.");
+        return Ok(());
+    }
+    config.save()?;
+
+    println!(
+        "Moved {} credential(s) to the {}:",
+        migrated.len(),
+        store.name()
+    );
+    for key in &migrated {
+        println!("   {} -> secret:{}", key, key);
+    }
+    if store.name() == "encrypted file" {
+        println!();
+        println!(
+            "Set {} when starting Prism so the secrets can be read.",
+            secrets::file::PASSPHRASE_ENV
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,41 @@
+use clap::Subcommand;
+
+use crate::{Config, PrismError, Result};
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommands {
+    /// Report unknown keys, undefined providers, alias cycles, invalid endpoints and
+    /// unresolved values, with line numbers
+    Check,
+
+    /// Print the JSON Schema of the config file, for editor completion
+    Schema,
+}
+
+pub async fn handle_config_command(config_command: ConfigCommands) -> Result<()> {
+    match config_command {
+        ConfigCommands::Check => handle_check(),
+        ConfigCommands::Schema => handle_schema(),
+    }
+}
+
+fn handle_check() -> Result<()> {
+    let issues = crate::config::lint::check()?;
+    if issues.is_empty() {
+        println!("No problems found in {}", Config::config_file()?.display());
+        return Ok(());
+    }
+
+    for issue in &issues {
+        println!("{}", issue);
+    }
+    Err(PrismError::Other(format!(
+        "{} problem(s) found in the configuration",
+        issues.len()
+    )))
+}
+
+fn handle_schema() -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(&Config::json_schema()?)?);
+    Ok(())
+}
@@ -0,0 +1,413 @@
+//! Config files merged into the effective config, in order:
+//!
+//! 1. `prism.toml`, after the files it lists in `include` (the including file wins)
+//! 2. `conf.d/*.toml` drop-ins by file name, each after its own includes
+//! 3. the active profile (see [`super::profiles`])
+//! 4. the nearest `.prism.toml` from the working directory up, limited to `[routing]` and
+//!    `server.port` so a repository can't redirect credentials or run commands
+//!
+//! Later files win key by key; arrays are replaced, not appended.
+
+use std::path::{Path, PathBuf};
+
+use crate::error::{PrismError, Result};
+
+/// Drop-in directory next to `prism.toml`
+pub const DROP_IN_DIR: &str = "conf.d";
+
+/// Project-local config file, looked up from the working directory
+pub const PROJECT_FILE_NAME: &str = ".prism.toml";
+
+/// `prism.toml`, the drop-ins and everything they include, in merge order
+pub fn user_files(config_file: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Includes::default();
+    files.add(config_file)?;
+    if let Some(config_dir) = config_file.parent() {
+        for drop_in in drop_ins(&config_dir.join(DROP_IN_DIR))? {
+            files.add(&drop_in)?;
+        }
+    }
+    Ok(files.files)
+}
+
+/// The nearest `.prism.toml` in the working directory or one of its parents
+pub fn project_file() -> Result<Option<PathBuf>> {
+    let cwd = std::env::current_dir()?;
+    Ok(cwd
+        .ancestors()
+        .map(|dir| dir.join(PROJECT_FILE_NAME))
+        .find(|file| file.is_file()))
+}
+
+/// Reject a project-local file that sets more than routing and the server port
+pub(crate) fn check_project_file(path: &Path) -> Result<()> {
+    let value = read_toml(path)?;
+    let violations = project_violations(&value);
+    if violations.is_empty() {
+        return Ok(());
+    }
+    let problems: Vec<String> = violations
+        .iter()
+        .map(|(key, problem)| format!("{}: {}", key.join("."), problem))
+        .collect();
+    Err(config_error(format!(
+        "{}: {}",
+        path.display(),
+        problems.join("; ")
+    )))
+}
+
+/// Keys of a project-local file that it may not set, with the reason
+pub(crate) fn project_violations(value: &toml::Value) -> Vec<(Vec<String>, String)> {
+    let mut violations = Vec::new();
+    let Some(table) = value.as_table() else {
+        return violations;
+    };
+    for (key, item) in table {
+        match key.as_str() {
+            "routing" => find_references(item, &mut vec![key.clone()], &mut violations),
+            "server" => {
+                for server_key in item.as_table().into_iter().flat_map(|t| t.keys()) {
+                    if server_key != "port" {
+                        violations.push((
+                            vec![key.clone(), server_key.clone()],
+                            "only server.port may be set in a project config".to_string(),
+                        ));
+                    }
+                }
+            }
+            _ => violations.push((
+                vec![key.clone()],
+                "only [routing] and server.port may be set in a project config".to_string(),
+            )),
+        }
+    }
+    violations
+}
+
+/// `${VAR}`, `file:`, `cmd:` and `secret:` values, which a project file may not use
+fn find_references(
+    value: &toml::Value,
+    path: &mut Vec<String>,
+    violations: &mut Vec<(Vec<String>, String)>,
+) {
+    match value {
+        toml::Value::String(s) => {
+            if super::sources::is_reference(s) || s.starts_with(crate::secrets::SECRET_PREFIX) {
+                violations.push((
+                    path.clone(),
+                    "values are not resolved in a project config".to_string(),
+                ));
+            }
+        }
+        toml::Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                path.push(i.to_string());
+                find_references(item, path, violations);
+                path.pop();
+            }
+        }
+        toml::Value::Table(table) => {
+            for (key, item) in table {
+                path.push(key.clone());
+                find_references(item, path, violations);
+                path.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Write the changes from `loaded` to `saved` into `target`, the table of the file being saved
+///
+/// Keys that didn't change are left alone, so values from other files aren't copied in.
+pub(crate) fn apply_changes(target: &mut toml::Table, loaded: &toml::Table, saved: &toml::Table) {
+    for (key, value) in saved {
+        match (loaded.get(key), value) {
+            (Some(before), _) if before == value => {}
+            (Some(toml::Value::Table(before)), toml::Value::Table(after)) => {
+                let entry = target
+                    .entry(key.clone())
+                    .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+                match entry {
+                    toml::Value::Table(table) => apply_changes(table, before, after),
+                    entry => *entry = value.clone(),
+                }
+            }
+            _ => {
+                target.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    for key in loaded.keys() {
+        if !saved.contains_key(key) {
+            target.remove(key);
+        }
+    }
+}
+
+/// Files in merge order, expanding includes
+#[derive(Default)]
+struct Includes {
+    files: Vec<PathBuf>,
+    /// Canonical paths of `files`
+    added: Vec<PathBuf>,
+    /// Canonical paths of the files whose includes are being added
+    including: Vec<PathBuf>,
+}
+
+impl Includes {
+    /// Add `file`'s includes, then `file`; a file already added is skipped
+    fn add(&mut self, file: &Path) -> Result<()> {
+        let canonical = file.canonicalize().unwrap_or_else(|_| file.to_path_buf());
+        if self.including.contains(&canonical) {
+            return Err(config_error(format!("{}: include cycle", file.display())));
+        }
+        if self.added.contains(&canonical) {
+            return Ok(());
+        }
+
+        if file.is_file() {
+            let dir = file.parent().unwrap_or(Path::new("."));
+            self.including.push(canonical.clone());
+            for pattern in include_patterns(&read_toml(file)?) {
+                for included in resolve_include(dir, &pattern)
+                    .map_err(|e| config_error(format!("{}: include {}", file.display(), e)))?
+                {
+                    self.add(&included)?;
+                }
+            }
+            self.including.pop();
+        }
+        self.files.push(file.to_path_buf());
+        self.added.push(canonical);
+        Ok(())
+    }
+}
+
+/// `include = "path"` or `include = ["path", ...]`
+fn include_patterns(value: &toml::Value) -> Vec<String> {
+    match value.get("include") {
+        Some(toml::Value::String(pattern)) => vec![pattern.clone()],
+        Some(toml::Value::Array(patterns)) => patterns
+            .iter()
+            .filter_map(|p| p.as_str().map(str::to_string))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Files an include pattern names, relative to the including file's directory
+///
+/// A glob may match nothing; a plain path must exist.
+fn resolve_include(dir: &Path, pattern: &str) -> std::result::Result<Vec<PathBuf>, String> {
+    let path = match pattern.strip_prefix("~/") {
+        Some(rest) => std::env::var_os("HOME")
+            .map(|home| PathBuf::from(home).join(rest))
+            .ok_or_else(|| "HOME is not set".to_string())?,
+        None => dir.join(pattern),
+    };
+    if !pattern.contains(['*', '?', '[']) {
+        return if path.is_file() {
+            Ok(vec![path])
+        } else {
+            Err(format!("`{}`: {} not found", pattern, path.display()))
+        };
+    }
+
+    // Only the pattern's own characters are wildcards, not those of the directory
+    let glob = match pattern.strip_prefix("~/") {
+        Some(_) => path.to_string_lossy().into_owned(),
+        None => format!(
+            "{}/{}",
+            glob::Pattern::escape(&dir.to_string_lossy()),
+            pattern
+        ),
+    };
+    let mut matches: Vec<PathBuf> = glob::glob(&glob)
+        .map_err(|e| format!("`{}`: {}", pattern, e))?
+        .filter_map(|entry| entry.ok())
+        .filter(|path| path.is_file())
+        .collect();
+    matches.sort();
+    Ok(matches)
+}
+
+/// `*.toml` files of a drop-in directory, by file name
+fn drop_ins(dir: &Path) -> Result<Vec<PathBuf>> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Ok(Vec::new());
+    };
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "toml"))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+fn read_toml(path: &Path) -> Result<toml::Value> {
+    let contents = std::fs::read_to_string(path)?;
+    toml::from_str(&contents).map_err(|e| config_error(format!("{}: {}", path.display(), e)))
+}
+
+fn config_error(message: String) -> PrismError {
+    PrismError::Config(Box::new(figment::Error::from(message)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use figment::Figment;
+    use figment::providers::{Format, Toml};
+
+    #[test]
+    fn test_includes_and_drop_ins_merge_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_file = dir.path().join("prism.toml");
+        std::fs::write(
+            &config_file,
+            r#"
+            include = ["aliases/*.toml"]
+
+            [routing.models]
+            fast = "openrouter/z-ai/glm-4.5"
+            "#,
+        )
+        .unwrap();
+        std::fs::create_dir_all(dir.path().join("aliases")).unwrap();
+        std::fs::write(
+            dir.path().join("aliases").join("agents.toml"),
+            "[routing.models]\nfast = \"openai/gpt-5-mini\"\nreview = \"anthropic/claude-opus-4\"\n",
+        )
+        .unwrap();
+        std::fs::create_dir_all(dir.path().join(DROP_IN_DIR)).unwrap();
+        std::fs::write(
+            dir.path().join(DROP_IN_DIR).join("10-review.toml"),
+            "[routing.models]\nreview = \"gemini/gemini-2.5-pro\"\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join(DROP_IN_DIR).join("notes.txt"), "").unwrap();
+
+        let files = user_files(&config_file).unwrap();
+        assert_eq!(
+            files,
+            vec![
+                dir.path().join("aliases").join("agents.toml"),
+                config_file.clone(),
+                dir.path().join(DROP_IN_DIR).join("10-review.toml"),
+            ]
+        );
+
+        let figment = files.iter().fold(Figment::new(), |figment, file| {
+            figment.merge(Toml::file(file))
+        });
+        let models: toml::Table = figment.extract_inner("routing.models").unwrap();
+        // The including file wins over its includes; drop-ins win over both
+        assert_eq!(models["fast"].as_str(), Some("openrouter/z-ai/glm-4.5"));
+        assert_eq!(models["review"].as_str(), Some("gemini/gemini-2.5-pro"));
+    }
+
+    #[test]
+    fn test_missing_include_and_cycles_are_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_file = dir.path().join("prism.toml");
+        std::fs::write(&config_file, "include = \"aliases.toml\"\n").unwrap();
+        assert!(user_files(&config_file).is_err());
+
+        std::fs::write(&config_file, "include = \"aliases/*.toml\"\n").unwrap();
+        assert_eq!(user_files(&config_file).unwrap(), vec![config_file.clone()]);
+
+        std::fs::write(dir.path().join("loop.toml"), "include = \"prism.toml\"\n").unwrap();
+        std::fs::write(&config_file, "include = \"loop.toml\"\n").unwrap();
+        let err = user_files(&config_file).unwrap_err();
+        assert!(err.to_string().contains("include cycle"));
+    }
+
+    #[test]
+    fn test_project_config_is_limited_to_routing() {
+        let allowed: toml::Value = toml::from_str(
+            r#"
+            [server]
+            port = 3801
+
+            [routing.models]
+            agent = "openrouter/z-ai/glm-4.5"
+            "#,
+        )
+        .unwrap();
+        assert!(project_violations(&allowed).is_empty());
+
+        let rejected: toml::Value = toml::from_str(
+            r#"
+            [server]
+            host = "0.0.0.0"
+
+            [providers.anthropic]
+            type = "anthropic"
+            endpoint = "https://example.com"
+
+            [routing.models]
+            agent = "cmd:curl example.com"
+            "#,
+        )
+        .unwrap();
+        let keys: Vec<String> = project_violations(&rejected)
+            .into_iter()
+            .map(|(key, _)| key.join("."))
+            .collect();
+        assert_eq!(
+            keys,
+            vec!["providers", "routing.models.agent", "server.host"]
+        );
+    }
+
+    #[test]
+    fn test_apply_changes_keeps_other_layers_out() {
+        let loaded: toml::Table = toml::from_str(
+            r#"
+            [server]
+            port = 3742
+
+            [providers.openrouter]
+            api_key = "sk-or-old"
+
+            [routing.models]
+            fast = "openrouter/z-ai/glm-4.5"
+            "#,
+        )
+        .unwrap();
+        let mut saved = loaded.clone();
+        saved["providers"]["openrouter"]
+            .as_table_mut()
+            .unwrap()
+            .insert("api_key".to_string(), "sk-or-new".into());
+
+        // `routing.models` came from an include, so it isn't in the file being saved
+        let mut target: toml::Table = toml::from_str(
+            r#"
+            [server]
+            port = 3742
+
+            [providers.openrouter]
+            api_key = "sk-or-old"
+            "#,
+        )
+        .unwrap();
+        apply_changes(&mut target, &loaded, &saved);
+        assert_eq!(
+            target,
+            toml::from_str::<toml::Table>(
+                r#"
+                [server]
+                port = 3742
+
+                [providers.openrouter]
+                api_key = "sk-or-new"
+                "#,
+            )
+            .unwrap()
+        );
+    }
+}
@@ -0,0 +1,806 @@
+//! `prism config check`: problems in the config files, reported with file and line.
+//!
+//! Reports keys the schema doesn't define, routing targets on undefined providers, alias
+//! cycles, invalid model-string parameters in alias targets, malformed endpoints, and
+//! `${VAR}`/`file:`/`cmd:`/`secret:` values that don't resolve. Resolving runs `cmd:`
+//! commands, as loading the config does. Each problem is reported in the file that sets
+//! the value: an include, a drop-in, the profile file or the project config.
+
+use rustc_hash::FxHashMap;
+use serde_json::Value as Json;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::config::{BUILTIN_PROVIDERS, Config, layers, profiles, sources};
+use crate::error::{PrismError, Result};
+use crate::secrets::{self, SECRET_PREFIX, SecretStore};
+use crate::server::parameter_mapping;
+
+/// Serde aliases of config keys; the schema only lists the canonical names
+const KEY_ALIASES: &[(&str, &str)] =
+    &[("model_ids", "deployments"), ("api-version", "api_version")];
+
+/// A problem in a config file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    pub file: PathBuf,
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.file.display(), line, self.message),
+            None => write!(f, "{}: {}", self.file.display(), self.message),
+        }
+    }
+}
+
+/// Check every config file: `prism.toml` with its includes and drop-ins, the active
+/// profile's overlay and the project config
+pub fn check() -> Result<Vec<Issue>> {
+    let base_path = Config::config_file()?;
+    let mut checker = Checker::new(base_path.clone());
+
+    let user_files = match layers::user_files(&base_path) {
+        Ok(files) => files,
+        Err(e) => {
+            // A syntax error in the base file is reported with its line
+            checker.read(&base_path)?;
+            if checker.issues.is_empty() {
+                checker.report(&[], e.to_string());
+            }
+            return Ok(checker.issues);
+        }
+    };
+    for file in &user_files {
+        checker.read(file)?;
+    }
+    if let Some(name) = profiles::active() {
+        if let Some(base) = checker.files.iter().position(|f| f.path == base_path) {
+            checker
+                .views
+                .push((base, vec!["profiles".to_string(), name.clone()]));
+        }
+        checker.read(&profiles::profile_file(&name)?)?;
+    }
+    let project_file = layers::project_file()?;
+    if let Some(project_file) = &project_file {
+        checker.read(project_file)?;
+    }
+    // Syntax errors and project settings that stop the config from loading come first
+    if !checker.issues.is_empty() || checker.check_project(project_file.as_deref()) {
+        return Ok(checker.issues);
+    }
+
+    Ok(checker.run(Config::load_unresolved(), &Config::json_schema()?))
+}
+
+/// A parsed config file with the line of every key
+struct SourceFile {
+    path: PathBuf,
+    value: toml::Value,
+    lines: FxHashMap<Vec<String>, usize>,
+}
+
+impl SourceFile {
+    fn parse(path: &Path, text: &str) -> std::result::Result<Self, Issue> {
+        let syntax_error = |line, message| Issue {
+            file: path.to_path_buf(),
+            line,
+            message,
+        };
+        let document = toml_edit::ImDocument::parse(text).map_err(|e| {
+            syntax_error(
+                e.span().map(|span| line_of(text, span.start)),
+                e.message().to_string(),
+            )
+        })?;
+        let value = toml::from_str(text).map_err(|e| syntax_error(None, e.to_string()))?;
+
+        let mut lines = FxHashMap::default();
+        record_keys(&*document, &mut Vec::new(), text, &mut lines);
+        Ok(Self {
+            path: path.to_path_buf(),
+            value,
+            lines,
+        })
+    }
+
+    /// Line of `path`, or of its closest ancestor in the file
+    fn nearest_line(&self, path: &[String]) -> Option<usize> {
+        (1..=path.len())
+            .rev()
+            .find_map(|len| self.lines.get(&path[..len]).copied())
+    }
+}
+
+fn record_keys(
+    table: &dyn toml_edit::TableLike,
+    path: &mut Vec<String>,
+    text: &str,
+    lines: &mut FxHashMap<Vec<String>, usize>,
+) {
+    for (key, item) in table.iter() {
+        path.push(key.to_string());
+        if let Some(span) = table.get_key_value(key).and_then(|(key, _)| key.span()) {
+            lines.insert(path.clone(), line_of(text, span.start));
+        }
+        record_item(item, path, text, lines);
+        path.pop();
+    }
+}
+
+fn record_item(
+    item: &toml_edit::Item,
+    path: &mut Vec<String>,
+    text: &str,
+    lines: &mut FxHashMap<Vec<String>, usize>,
+) {
+    if let Some(table) = item.as_table_like() {
+        record_keys(table, path, text, lines);
+    } else if let Some(tables) = item.as_array_of_tables() {
+        for (i, table) in tables.iter().enumerate() {
+            path.push(i.to_string());
+            if let Some(span) = table.span() {
+                lines.insert(path.clone(), line_of(text, span.start));
+            }
+            record_keys(table, path, text, lines);
+            path.pop();
+        }
+    } else if let Some(array) = item.as_array() {
+        for (i, value) in array.iter().enumerate() {
+            path.push(i.to_string());
+            if let Some(span) = value.span() {
+                lines.insert(path.clone(), line_of(text, span.start));
+            }
+            if let Some(table) = value.as_inline_table() {
+                record_keys(table, path, text, lines);
+            }
+            path.pop();
+        }
+    }
+}
+
+fn line_of(text: &str, offset: usize) -> usize {
+    text.as_bytes()[..offset.min(text.len())]
+        .iter()
+        .filter(|&&b| b == b'\n')
+        .count()
+        + 1
+}
+
+struct Checker {
+    base_path: PathBuf,
+    files: Vec<SourceFile>,
+    /// Tables merged into the config, in merge order: a file and the key path of the
+    /// table within it (`profiles.<name>` for an inline profile)
+    views: Vec<(usize, Vec<String>)>,
+    issues: Vec<Issue>,
+}
+
+impl Checker {
+    fn new(base_path: PathBuf) -> Self {
+        Self {
+            base_path,
+            files: Vec::new(),
+            views: Vec::new(),
+            issues: Vec::new(),
+        }
+    }
+
+    /// Parse `path` if it exists, reporting a syntax error
+    fn read(&mut self, path: &Path) -> Result<()> {
+        if !path.exists() {
+            return Ok(());
+        }
+        let text = std::fs::read_to_string(path)?;
+        match SourceFile::parse(path, &text) {
+            Ok(file) => self.add(file),
+            Err(issue) => self.issues.push(issue),
+        }
+        Ok(())
+    }
+
+    fn add(&mut self, file: SourceFile) {
+        self.views.push((self.files.len(), Vec::new()));
+        self.files.push(file);
+    }
+
+    /// Report the keys the project config may not set; true if there are any
+    fn check_project(&mut self, project_file: Option<&Path>) -> bool {
+        let Some(file) = project_file.and_then(|path| self.files.iter().find(|f| f.path == path))
+        else {
+            return false;
+        };
+        let issues: Vec<Issue> = layers::project_violations(&file.value)
+            .into_iter()
+            .map(|(path, problem)| Issue {
+                file: file.path.clone(),
+                line: file.nearest_line(&path),
+                message: format!("`{}`: {}", path.join("."), problem),
+            })
+            .collect();
+        let found = !issues.is_empty();
+        self.issues.extend(issues);
+        found
+    }
+
+    fn run(mut self, config: Result<Config>, schema: &Json) -> Vec<Issue> {
+        self.check_keys(schema);
+        match config {
+            Ok(config) => {
+                let resolved = self.check_references(&config);
+                self.check_routing(&config);
+                self.check_endpoints(&config, &resolved);
+            }
+            Err(PrismError::Config(e)) if !e.path.is_empty() => {
+                let message = format!("{}: {}", e.path.join("."), e.kind);
+                self.report(&e.path, message);
+            }
+            Err(e) => self.report(&[], e.to_string()),
+        }
+
+        let mut issues = self.issues;
+        issues.sort_by(|a, b| a.file.cmp(&b.file).then(a.line.cmp(&b.line)));
+        issues
+    }
+
+    /// Report `message` at the line that sets `path` in the effective config: the last
+    /// file to set it, else the last to set its closest ancestor
+    fn report(&mut self, path: &[String], message: String) {
+        let found = self
+            .views
+            .iter()
+            .filter_map(|(index, prefix)| {
+                let file = &self.files[*index];
+                let mut full = prefix.clone();
+                full.extend_from_slice(path);
+                (prefix.len() + 1..=full.len()).rev().find_map(|len| {
+                    let line = *file.lines.get(&full[..len])?;
+                    Some((len - prefix.len(), &file.path, line))
+                })
+            })
+            .max_by_key(|(matched, _, _)| *matched);
+
+        let (file, line) = match found {
+            Some((_, file, line)) => (file.clone(), Some(line)),
+            None => (self.base_path.clone(), None),
+        };
+        self.issues.push(Issue {
+            file,
+            line,
+            message,
+        });
+    }
+
+    fn check_keys(&mut self, schema: &Json) {
+        let mut issues = Vec::new();
+        for file in &self.files {
+            let mut unknown = Vec::new();
+            unknown_keys(&file.value, schema, schema, &mut Vec::new(), &mut unknown);
+
+            // `[profiles.<name>]` tables are partial configs
+            if let Some(profiles) = file.value.get("profiles").and_then(|p| p.as_table()) {
+                for (name, overlay) in profiles {
+                    let mut path = vec!["profiles".to_string(), name.clone()];
+                    unknown_keys(overlay, schema, schema, &mut path, &mut unknown);
+                }
+            }
+
+            issues.extend(unknown.into_iter().map(|path| Issue {
+                file: file.path.clone(),
+                line: file.nearest_line(&path),
+                message: format!("unknown key `{}`", path.join(".")),
+            }));
+        }
+        self.issues.extend(issues);
+    }
+
+    /// Resolve every reference, reporting the ones that fail; returns the resolved values
+    /// by key path
+    fn check_references(&mut self, config: &Config) -> FxHashMap<Vec<String>, String> {
+        let mut resolved = FxHashMap::default();
+        let Ok(mut value) = toml::Value::try_from(config) else {
+            return resolved;
+        };
+
+        let mut store: Option<std::result::Result<Arc<dyn SecretStore>, String>> = None;
+        let mut problems = Vec::new();
+        let _ = sources::walk_strings(&mut value, &mut Vec::new(), &mut |path, s| {
+            // Profiles other than the active one (already merged) are not used
+            if path.first().is_some_and(|key| key == "profiles") {
+                return Ok(());
+            }
+            if let Some(name) = s.strip_prefix(SECRET_PREFIX) {
+                let store = store.get_or_insert_with(|| {
+                    secrets::open_store(config.secrets.kind()).map_err(|e| e.to_string())
+                });
+                let problem = match store {
+                    Ok(store) => match store.get(name) {
+                        Ok(Some(_)) => None,
+                        Ok(None) => {
+                            Some(format!("secret `{}` is not in the {}", name, store.name()))
+                        }
+                        Err(e) => Some(format!("secret `{}` can't be read: {}", name, e)),
+                    },
+                    Err(e) => Some(format!("secret `{}` can't be read: {}", name, e)),
+                };
+                problems.extend(problem.map(|problem| (path.to_vec(), problem)));
+            } else if sources::is_reference(s) {
+                match sources::resolve_string(s) {
+                    Ok(value) => {
+                        resolved.insert(path.to_vec(), value);
+                    }
+                    Err(e) => {
+                        problems.push((path.to_vec(), format!("`{}` doesn't resolve: {}", s, e)))
+                    }
+                }
+            }
+            Ok(())
+        });
+
+        for (path, problem) in problems {
+            self.report(&path, problem);
+        }
+        resolved
+    }
+
+    fn check_routing(&mut self, config: &Config) {
+        let routing = &config.routing;
+        let mut aliases: Vec<&String> = routing.models.keys().collect();
+        aliases.sort();
+
+        for alias in &aliases {
+            for target in routing.models[*alias].targets() {
+                if let Some(provider) = undefined_provider(config, target) {
+                    self.report(
+                        &key_path(&["routing", "models", alias.as_str()]),
+                        format!(
+                            "alias `{}` points to `{}` on undefined provider `{}`",
+                            alias, target, provider
+                        ),
+                    );
+                }
+            }
+        }
+        for (i, rule) in routing.rules.iter().enumerate() {
+            if let Some(provider) = undefined_provider(config, &rule.target) {
+                self.report(
+                    &key_path(&["routing", "rules", i.to_string().as_str(), "target"]),
+                    format!(
+                        "rule target `{}` uses undefined provider `{}`",
+                        rule.target, provider
+                    ),
+                );
+            }
+        }
+        let mut shadowed: Vec<(&String, &String)> = routing.shadow.iter().collect();
+        shadowed.sort();
+        for (model, target) in shadowed {
+            if let Some(provider) = undefined_provider(config, target) {
+                self.report(
+                    &key_path(&["routing", "shadow", model.as_str()]),
+                    format!(
+                        "shadow target `{}` uses undefined provider `{}`",
+                        target, provider
+                    ),
+                );
+            }
+        }
+
+        for problem in parameter_mapping::validate_alias_params(config) {
+            self.report(
+                &key_path(&["routing", "models", problem.alias.as_str()]),
+                format!(
+                    "alias `{}` → `{}`: {}",
+                    problem.alias, problem.target, problem.error
+                ),
+            );
+        }
+
+        for cycle in alias_cycles(config, &aliases) {
+            let mut chain = cycle.clone();
+            chain.push(cycle[0].clone());
+            self.report(
+                &key_path(&["routing", "models", cycle[0].as_str()]),
+                format!("alias cycle: {}", chain.join(" → ")),
+            );
+        }
+    }
+
+    fn check_endpoints(&mut self, config: &Config, resolved: &FxHashMap<Vec<String>, String>) {
+        let mut providers: Vec<&String> = config.providers.keys().collect();
+        providers.sort();
+        for name in providers {
+            let endpoint = &config.providers[name].endpoint;
+            let path = key_path(&["providers", name.as_str(), "endpoint"]);
+            let endpoint = match resolved.get(&path) {
+                Some(value) => value,
+                // An unresolved reference is already reported
+                None if sources::is_reference(endpoint) => continue,
+                None => endpoint,
+            };
+            if endpoint.is_empty() {
+                continue;
+            }
+            if let Err(problem) = validate_endpoint(endpoint) {
+                self.report(
+                    &path,
+                    format!("invalid endpoint `{}`: {}", endpoint, problem),
+                );
+            }
+        }
+    }
+}
+
+fn key_path(keys: &[&str]) -> Vec<String> {
+    keys.iter().map(|key| key.to_string()).collect()
+}
+
+fn validate_endpoint(endpoint: &str) -> std::result::Result<(), String> {
+    let url = url::Url::parse(endpoint).map_err(|e| e.to_string())?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(format!("unsupported scheme `{}`", url.scheme()));
+    }
+    if url.host_str().is_none_or(str::is_empty) {
+        return Err("missing host".to_string());
+    }
+    Ok(())
+}
+
+/// Provider of a `provider/model` target that is neither configured nor built in
+fn undefined_provider<'a>(config: &Config, target: &'a str) -> Option<&'a str> {
+    if config.routing.models.contains_key(target) {
+        return None;
+    }
+    let model = target.split('?').next().unwrap_or(target);
+    let (provider, _) = model.split_once('/')?;
+    let defined = config.providers.contains_key(provider) || BUILTIN_PROVIDERS.contains(&provider);
+    (!defined).then_some(provider)
+}
+
+/// Alias cycles, each listed once starting from its first alias by name
+fn alias_cycles(config: &Config, aliases: &[&String]) -> Vec<Vec<String>> {
+    fn visit(
+        alias: &str,
+        config: &Config,
+        stack: &mut Vec<String>,
+        done: &mut Vec<String>,
+        cycles: &mut Vec<Vec<String>>,
+    ) {
+        if let Some(pos) = stack.iter().position(|a| a == alias) {
+            let mut cycle = stack[pos..].to_vec();
+            let first = (0..cycle.len()).min_by_key(|&i| &cycle[i]).unwrap_or(0);
+            cycle.rotate_left(first);
+            if !cycles.contains(&cycle) {
+                cycles.push(cycle);
+            }
+            return;
+        }
+        if done.iter().any(|a| a == alias) {
+            return;
+        }
+        let Some(route) = config.routing.models.get(alias) else {
+            return;
+        };
+        stack.push(alias.to_string());
+        for target in route.targets() {
+            visit(target, config, stack, done, cycles);
+        }
+        stack.pop();
+        done.push(alias.to_string());
+    }
+
+    let mut cycles = Vec::new();
+    let mut done = Vec::new();
+    for alias in aliases {
+        visit(alias, config, &mut Vec::new(), &mut done, &mut cycles);
+    }
+    cycles
+}
+
+/// How a schema treats one key of a table
+enum KeySchema<'a> {
+    Known(&'a Json),
+    /// Any key is allowed and its value isn't described
+    Free,
+    Unknown,
+}
+
+/// Collect the key paths in `value` that `schema` doesn't define
+fn unknown_keys(
+    value: &toml::Value,
+    schema: &Json,
+    root: &Json,
+    path: &mut Vec<String>,
+    found: &mut Vec<Vec<String>>,
+) {
+    match value {
+        toml::Value::Table(table) => {
+            let shapes: Vec<Vec<&Json>> = alternatives(schema, root)
+                .into_iter()
+                .filter(|shape| is_object(shape))
+                .collect();
+            // For untagged enums, judge the table by the variant that knows most of its keys
+            let Some(shape) = shapes.iter().min_by_key(|shape| {
+                table
+                    .keys()
+                    .filter(|key| matches!(key_schema(shape, key), KeySchema::Unknown))
+                    .count()
+            }) else {
+                return;
+            };
+            for (key, item) in table {
+                path.push(key.clone());
+                match key_schema(shape, key) {
+                    KeySchema::Known(schema) => unknown_keys(item, schema, root, path, found),
+                    KeySchema::Free => {}
+                    KeySchema::Unknown => found.push(path.clone()),
+                }
+                path.pop();
+            }
+        }
+        toml::Value::Array(items) => {
+            let item_schema = alternatives(schema, root)
+                .into_iter()
+                .flatten()
+                .find_map(|part| part.get("items").filter(|items| items.is_object()));
+            if let Some(item_schema) = item_schema {
+                for (i, item) in items.iter().enumerate() {
+                    path.push(i.to_string());
+                    unknown_keys(item, item_schema, root, path, found);
+                    path.pop();
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The alternatives (`anyOf`/`oneOf`) a value may match, each as the schemas it must
+/// satisfy together (`allOf`), with `$ref`s resolved
+fn alternatives<'a>(schema: &'a Json, root: &'a Json) -> Vec<Vec<&'a Json>> {
+    let schema = resolve(schema, root);
+    if let Some(options) = schema
+        .get("anyOf")
+        .or_else(|| schema.get("oneOf"))
+        .and_then(Json::as_array)
+    {
+        return options
+            .iter()
+            .flat_map(|option| alternatives(option, root))
+            .collect();
+    }
+
+    let mut shape = vec![schema];
+    if let Some(parts) = schema.get("allOf").and_then(Json::as_array) {
+        for part in parts {
+            shape.extend(
+                alternatives(part, root)
+                    .into_iter()
+                    .next()
+                    .unwrap_or_default(),
+            );
+        }
+    }
+    vec![shape]
+}
+
+fn resolve<'a>(schema: &'a Json, root: &'a Json) -> &'a Json {
+    let definition = schema
+        .get("$ref")
+        .and_then(Json::as_str)
+        .and_then(|reference| reference.strip_prefix("#/definitions/"))
+        .and_then(|name| root.get("definitions")?.get(name));
+    match definition {
+        Some(definition) => resolve(definition, root),
+        None => schema,
+    }
+}
+
+fn is_object(shape: &[&Json]) -> bool {
+    shape.iter().any(|part| {
+        let typed_object = match part.get("type") {
+            Some(Json::String(t)) => t == "object",
+            Some(Json::Array(types)) => types.iter().any(|t| t == "object"),
+            _ => false,
+        };
+        typed_object || part.get("properties").is_some()
+    })
+}
+
+fn key_schema<'a>(shape: &[&'a Json], key: &str) -> KeySchema<'a> {
+    let key = KEY_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == key)
+        .map_or(key, |(_, canonical)| *canonical);
+
+    if let Some(schema) = shape
+        .iter()
+        .find_map(|part| part.get("properties")?.get(key))
+    {
+        return KeySchema::Known(schema);
+    }
+    for part in shape {
+        match part.get("additionalProperties") {
+            Some(Json::Bool(true)) => return KeySchema::Free,
+            Some(schema @ Json::Object(_)) => return KeySchema::Known(schema),
+            _ => {}
+        }
+    }
+    if shape.iter().any(|part| part.get("properties").is_some()) {
+        KeySchema::Unknown
+    } else {
+        KeySchema::Free
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lint(text: &str) -> Vec<String> {
+        let path = PathBuf::from("prism.toml");
+        let mut checker = Checker::new(path.clone());
+        checker.add(SourceFile::parse(&path, text).unwrap());
+        let config = toml::from_str::<Config>(text).map_err(|e| PrismError::Other(e.to_string()));
+        checker
+            .run(config, &Config::json_schema().unwrap())
+            .iter()
+            .map(|issue| format!("{}: {}", issue.line.unwrap_or(0), issue.message))
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_config_has_no_issues() {
+        let issues = lint(
+            r#"
+[server]
+port = 3742
+
+[providers.openrouter]
+type = "openrouter"
+endpoint = "https://openrouter.ai/api/v1"
+
+[providers.azure]
+type = "azure"
+resource = "my-resource"
+api-version = "2024-10-21"
+
+[routing.models]
+fast = "openrouter/z-ai/glm-4.5"
+smart = ["fast", "anthropic/claude-sonnet-4"]
+
+[routing.models.glm]
+strategy = "round_robin"
+targets = [{ model = "openrouter/z-ai/glm-4.5:groq", weight = 2 }]
+
+[[routing.rules]]
+min_prompt_tokens = 150000
+target = "smart"
+"#,
+        );
+        assert_eq!(issues, Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_unknown_keys_are_reported_with_lines() {
+        let issues = lint(
+            r#"[server]
+prot = 3800
+
+[providers.openrouter]
+type = "openrouter"
+endpont = "https://openrouter.ai/api/v1"
+
+[routing.models.glm]
+strategy = "weighted"
+targets = [{ model = "openrouter/z-ai/glm-4.5", wieght = 2 }]
+
+[[routing.rules]]
+target = "glm"
+min_tokens = 1000
+"#,
+        );
+        assert_eq!(
+            issues,
+            vec![
+                "2: unknown key `server.prot`",
+                "6: unknown key `providers.openrouter.endpont`",
+                "10: unknown key `routing.models.glm.targets.0.wieght`",
+                "14: unknown key `routing.rules.0.min_tokens`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extended_route_typos_are_reported() {
+        // Serde can't deny unknown fields next to the flattened policy, so the lint has to
+        let text = r#"[server]
+[providers.openrouter]
+type = "openrouter"
+endpoint = "https://openrouter.ai/api/v1"
+
+[routing.models.fast]
+target = "openrouter/z-ai/glm-4.5"
+defualts = { temperature = 0.2 }
+clamp = { max_tokens = [1, 16000] }
+"#;
+        assert!(toml::from_str::<Config>(text).is_ok());
+        assert_eq!(
+            lint(text),
+            vec!["8: unknown key `routing.models.fast.defualts`"]
+        );
+    }
+
+    #[test]
+    fn test_routing_problems() {
+        let issues = lint(
+            r#"[server]
+[providers.openrouter]
+type = "openrouter"
+endpoint = "openrouter.ai/api/v1"
+
+[routing.models]
+fast = "groq/llama-3.3-70b"
+a = "b"
+b = ["openrouter/z-ai/glm-4.5", "a"]
+"#,
+        );
+        assert_eq!(
+            issues,
+            vec![
+                "4: invalid endpoint `openrouter.ai/api/v1`: relative URL without a base",
+                "7: alias `fast` points to `groq/llama-3.3-70b` on undefined provider `groq`",
+                "8: alias cycle: a → b → a",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_alias_parameters_are_checked() {
+        let issues = lint(
+            r#"[server]
+[routing.models]
+fast = "anthropic/claude-sonnet-4?temperature=9"
+smart = ["openai/gpt-5?effort=high", "anthropic/claude-sonnet-4?top_p=2"]
+"#,
+        );
+        assert_eq!(
+            issues,
+            vec![
+                "3: alias `fast` → `anthropic/claude-sonnet-4?temperature=9`: `temperature=9` must be between 0 and 1",
+                "4: alias `smart` → `anthropic/claude-sonnet-4?top_p=2`: `top_p=2` must be between 0 and 1",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unresolved_references() {
+        let issues = lint(
+            r#"[server]
+[routing]
+[providers.openrouter]
+type = "openrouter"
+api_key = "${LINT_UNSET_OPENROUTER_KEY:?export it first}"
+"#,
+        );
+        assert_eq!(
+            issues,
+            vec![
+                "5: `${LINT_UNSET_OPENROUTER_KEY:?export it first}` doesn't resolve: LINT_UNSET_OPENROUTER_KEY: export it first"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_syntax_error_has_line() {
+        let issue = SourceFile::parse(Path::new("prism.toml"), "[server]\nport = \n")
+            .err()
+            .unwrap();
+        assert_eq!(issue.line, Some(2));
+    }
+}
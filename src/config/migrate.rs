@@ -0,0 +1,94 @@
+//! One-time move of a legacy `setu.toml` (and its `profiles/`) to `prism.toml`.
+//!
+//! The file is copied as written, comments included; the legacy file is left in place.
+
+use std::path::{Path, PathBuf};
+
+use crate::error::Result;
+
+/// Name of the config file before the rename
+pub const LEGACY_FILE_NAME: &str = "setu.toml";
+
+/// Copy the first `setu.toml` found in `legacy_dirs` to `config_file`
+///
+/// Returns the legacy file that was migrated, if any.
+pub(crate) fn migrate_legacy(
+    config_file: &Path,
+    legacy_dirs: &[PathBuf],
+) -> Result<Option<PathBuf>> {
+    if config_file.exists() {
+        return Ok(None);
+    }
+    let Some(legacy_file) = legacy_dirs
+        .iter()
+        .map(|dir| dir.join(LEGACY_FILE_NAME))
+        .find(|file| file.is_file())
+    else {
+        return Ok(None);
+    };
+
+    let contents = std::fs::read_to_string(&legacy_file)?;
+    super::write_private(config_file, &contents)?;
+
+    if let (Some(legacy_dir), Some(config_dir)) = (legacy_file.parent(), config_file.parent()) {
+        copy_profiles(&legacy_dir.join("profiles"), &config_dir.join("profiles"))?;
+    }
+
+    tracing::warn!(
+        "Migrated legacy config {} to {}; the old file is no longer read",
+        legacy_file.display(),
+        config_file.display()
+    );
+    Ok(Some(legacy_file))
+}
+
+/// Copy profile files that don't exist in `to` yet
+fn copy_profiles(from: &Path, to: &Path) -> Result<()> {
+    if from == to {
+        return Ok(());
+    }
+    let Ok(entries) = std::fs::read_dir(from) else {
+        return Ok(());
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Some(name) = path.file_name() else {
+            continue;
+        };
+        if path.extension().is_none_or(|ext| ext != "toml") || to.join(name).exists() {
+            continue;
+        }
+        std::fs::create_dir_all(to)?;
+        super::write_private(&to.join(name), &std::fs::read_to_string(&path)?)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_legacy_config_is_copied_with_profiles() {
+        let legacy = tempfile::tempdir().unwrap();
+        let current = tempfile::tempdir().unwrap();
+        let contents = "# my providers\n[server]\nport = 3800\n";
+        std::fs::write(legacy.path().join(LEGACY_FILE_NAME), contents).unwrap();
+        std::fs::create_dir_all(legacy.path().join("profiles")).unwrap();
+        std::fs::write(legacy.path().join("profiles").join("work.toml"), "").unwrap();
+
+        let config_file = current.path().join("prism.toml");
+        let legacy_dirs = vec![current.path().to_path_buf(), legacy.path().to_path_buf()];
+        let migrated = migrate_legacy(&config_file, &legacy_dirs).unwrap();
+
+        assert_eq!(migrated, Some(legacy.path().join(LEGACY_FILE_NAME)));
+        assert_eq!(std::fs::read_to_string(&config_file).unwrap(), contents);
+        assert!(current.path().join("profiles").join("work.toml").exists());
+        assert!(legacy.path().join(LEGACY_FILE_NAME).exists());
+
+        // Once prism.toml exists the legacy file is ignored
+        std::fs::write(legacy.path().join(LEGACY_FILE_NAME), "[server]\nport = 1\n").unwrap();
+        assert_eq!(migrate_legacy(&config_file, &legacy_dirs).unwrap(), None);
+        assert_eq!(std::fs::read_to_string(&config_file).unwrap(), contents);
+    }
+}
@@ -4,23 +4,53 @@ use figment::{
     providers::{Env, Format, Toml},
 };
 use rustc_hash::FxHashMap;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
 use crate::error::{Result, PrismError};
 
+pub mod layers;
+pub mod lint;
+mod migrate;
 pub mod models;
+pub mod profiles;
+pub mod sources;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Name of the base config file in the config directory
+pub const CONFIG_FILE_NAME: &str = "prism.toml";
+
+/// Providers that are routed without a `[providers.<name>]` section
+pub const BUILTIN_PROVIDERS: &[&str] = &["anthropic", "openai", "openrouter", "gemini", "google"];
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Config {
     pub server: ServerConfig,
     pub providers: FxHashMap<String, ProviderConfig>,
     pub routing: RoutingConfig,
     #[serde(default)]
     pub auth: FxHashMap<String, AuthConfig>,
+    /// Where `secret:<name>` references are stored
+    #[serde(default, skip_serializing_if = "SecretsConfig::is_default")]
+    pub secrets: SecretsConfig,
+    /// Where the Claude, Gemini and codex CLIs keep their OAuth tokens
+    #[serde(default, skip_serializing_if = "CliCredentialsConfig::is_default")]
+    pub cli_credentials: CliCredentialsConfig,
+    /// `[profiles.<name>]` overlays (see [`profiles`]), kept as written
+    #[serde(default, skip_serializing_if = "FxHashMap::is_empty")]
+    #[schemars(with = "FxHashMap<String, serde_json::Value>")]
+    pub profiles: FxHashMap<String, toml::Table>,
+    /// Files merged before this one, relative to it; globs allowed (see [`layers`])
+    #[serde(
+        default,
+        deserialize_with = "path_list",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    #[schemars(with = "PathList")]
+    pub include: Vec<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ServerConfig {
     #[serde(default = "default_host")]
     pub host: String,
@@ -52,7 +82,7 @@ impl Default for ServerConfig {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct RetryConfig {
     #[serde(default = "default_max_retries")]
     pub max_retries: u32,
@@ -75,15 +105,17 @@ impl Default for RetryConfig {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ProviderConfig {
     pub r#type: String,
+    /// Base URL; Azure providers may leave it empty and set `resource` instead
+    #[serde(default)]
     pub endpoint: String,
     #[serde(default)]
     pub auth: AuthConfig,
     #[serde(default)]
     pub retry: RetryConfig,
-    /// API key for direct authentication (supports ${VAR} environment variable interpolation)
+    /// API key for direct authentication (`${VAR}`, `file:`, `cmd:` and `secret:` values are resolved)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub api_key: Option<String>,
     /// Enable fallback to API key authentication when OAuth fails
@@ -92,25 +124,411 @@ pub struct ProviderConfig {
     /// HTTP error codes that trigger fallback authentication
     #[serde(default = "default_fallback_errors")]
     pub fallback_on_errors: Vec<u16>,
+    /// Additional API keys pooled with `api_key` (resolved like `api_key`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub api_keys: Vec<String>,
+    /// Extra OAuth credential sets, by name of their `[auth.<name>]` section
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub oauth_accounts: Vec<String>,
+    /// How to pick between pooled credentials
+    #[serde(default)]
+    pub key_selection: KeySelection,
+    /// Seconds a credential is benched after a 429 or 401 response
+    #[serde(default = "default_key_cooldown_secs")]
+    pub key_cooldown_secs: u64,
+    /// Request/response differences of an `openai-compatible` vendor (also applied to
+    /// Chat Completions requests built from Anthropic-format input)
+    #[serde(default, skip_serializing_if = "ProviderQuirks::is_default")]
+    pub quirks: ProviderQuirks,
+    /// Azure OpenAI resource name (`https://<resource>.openai.azure.com`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resource: Option<String>,
+    /// Upstream model ID per model (Azure deployment names, Bedrock model IDs);
+    /// unmapped models are used as-is
+    #[serde(default, alias = "model_ids", skip_serializing_if = "FxHashMap::is_empty")]
+    pub deployments: FxHashMap<String, String>,
+    /// Azure OpenAI `api-version` query parameter
+    #[serde(default, alias = "api-version", skip_serializing_if = "Option::is_none")]
+    pub api_version: Option<String>,
+    /// Cloud region (Bedrock: defaults to AWS_REGION / ~/.aws/config; Vertex AI: us-central1)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    /// Google Cloud project (Vertex AI; defaults to the service account's project)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    /// Path to a service-account JSON key (Vertex AI; defaults to GOOGLE_APPLICATION_CREDENTIALS)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credentials: Option<String>,
+    /// OpenAI API used for Anthropic-format requests (OpenAI and Azure OpenAI providers)
+    #[serde(default)]
+    pub api: OpenAIApi,
+}
+
+impl Default for ProviderConfig {
+    fn default() -> Self {
+        Self {
+            r#type: String::new(),
+            endpoint: String::new(),
+            auth: AuthConfig::default(),
+            retry: RetryConfig::default(),
+            api_key: None,
+            api_key_fallback: false,
+            fallback_on_errors: default_fallback_errors(),
+            api_keys: Vec::new(),
+            oauth_accounts: Vec::new(),
+            key_selection: KeySelection::default(),
+            key_cooldown_secs: default_key_cooldown_secs(),
+            quirks: ProviderQuirks::default(),
+            resource: None,
+            deployments: FxHashMap::default(),
+            api_version: None,
+            region: None,
+            project: None,
+            credentials: None,
+            api: OpenAIApi::default(),
+        }
+    }
+}
+
+/// Which OpenAI API serves requests that arrive in Anthropic format
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OpenAIApi {
+    /// `/v1/responses`
+    #[default]
+    Responses,
+    /// `/v1/chat/completions`, for servers without the Responses API
+    Chat,
+}
+
+/// How an OpenAI-compatible vendor deviates from the OpenAI Chat Completions API
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default, PartialEq)]
+#[serde(default)]
+pub struct ProviderQuirks {
+    /// Request parameters the vendor rejects; they are removed before sending
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unsupported_params: Vec<String>,
+    /// Name of the output token limit field
+    pub max_tokens_field: MaxTokensField,
+    /// Response field carrying reasoning text (e.g. `reasoning_content`, `reasoning`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning_field: Option<String>,
+    /// Which `tool_choice` values the vendor accepts
+    pub tool_choice: ToolChoiceSupport,
+    /// Fields merged into every request body
+    #[serde(skip_serializing_if = "FxHashMap::is_empty")]
+    pub extra_body: FxHashMap<String, serde_json::Value>,
+}
+
+impl ProviderQuirks {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Field used for the output token limit
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MaxTokensField {
+    #[default]
+    MaxTokens,
+    MaxCompletionTokens,
+}
+
+/// `tool_choice` support of an OpenAI-compatible vendor
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolChoiceSupport {
+    /// Strings and `{"type": "function", ...}` objects
+    #[default]
+    Full,
+    /// Only `"auto"`, `"none"` and `"required"`; a named function becomes `"required"`
+    StringOnly,
+    /// `tool_choice` is dropped
+    Unsupported,
+}
+
+/// Selection strategy for pooled credentials
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KeySelection {
+    #[default]
+    RoundRobin,
+    LeastUsed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(untagged)]
 pub enum ModelRoute {
     Single(String),
     Multiple(Vec<String>),
+    /// Traffic split across several targets; the rest act as fallbacks
+    Balanced(BalancedRoute),
+    /// Target with default, forced and clamped request parameters
+    Extended(ExtendedRoute),
+}
+
+impl ModelRoute {
+    /// Every target string of the route, in order
+    pub fn targets(&self) -> Vec<&str> {
+        match self {
+            ModelRoute::Single(model) => vec![model.as_str()],
+            ModelRoute::Multiple(models) => models.iter().map(String::as_str).collect(),
+            ModelRoute::Balanced(route) => route.targets.iter().map(|t| t.model.as_str()).collect(),
+            ModelRoute::Extended(route) => route.target.targets(),
+        }
+    }
+}
+
+/// Alias definition with a parameter policy
+/// Example:
+/// ```toml
+/// [routing.models.fast]
+/// target = "openai/gpt-5-mini"
+/// defaults = { temperature = 0.2 }
+/// force = { max_tokens = 8000 }
+/// clamp = { max_tokens = [1, 16000] }
+/// ```
+///
+/// Serde ignores unknown keys next to a flattened struct, so misspelled policy tables
+/// (`defualts`) load without error; `prism config check` reports them.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ExtendedRoute {
+    /// Model, fallback list or balanced table the alias resolves to
+    pub target: Box<ModelRoute>,
+    #[serde(flatten)]
+    pub policy: ParamPolicy,
+}
+
+/// Request parameters an alias sets or limits, by canonical name
+/// (`temperature`, `top_p`, `top_k`, `max_tokens`, ...)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default, PartialEq)]
+#[serde(default)]
+pub struct ParamPolicy {
+    /// Used only when the client didn't set the parameter
+    #[serde(skip_serializing_if = "FxHashMap::is_empty")]
+    pub defaults: FxHashMap<String, serde_json::Value>,
+    /// Always replace the client's value
+    #[serde(skip_serializing_if = "FxHashMap::is_empty")]
+    pub force: FxHashMap<String, serde_json::Value>,
+    /// `[min, max]` bounds for numeric parameters
+    #[serde(skip_serializing_if = "FxHashMap::is_empty")]
+    pub clamp: FxHashMap<String, [f64; 2]>,
+}
+
+impl ParamPolicy {
+    /// Combine with the policy of an alias that points at this one; `outer` wins per key
+    pub fn merged_with(mut self, outer: &ParamPolicy) -> Self {
+        self.defaults
+            .extend(outer.defaults.iter().map(|(k, v)| (k.clone(), v.clone())));
+        self.force
+            .extend(outer.force.iter().map(|(k, v)| (k.clone(), v.clone())));
+        self.clamp
+            .extend(outer.clamp.iter().map(|(k, v)| (k.clone(), *v)));
+        self
+    }
+}
+
+/// Load-balanced alias definition
+/// Example:
+/// ```toml
+/// [routing.models.glm]
+/// strategy = "weighted"
+/// targets = [
+///   { model = "openrouter/z-ai/glm-4.5:fireworks", weight = 70 },
+///   { model = "openrouter/z-ai/glm-4.5:groq", weight = 30 },
+/// ]
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BalancedRoute {
+    #[serde(default)]
+    pub strategy: BalanceStrategy,
+    pub targets: Vec<WeightedTarget>,
+    /// Keep a conversation on the same target (session header or first user message hash)
+    #[serde(default = "default_sticky")]
+    pub sticky: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct WeightedTarget {
+    pub model: String,
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BalanceStrategy {
+    #[default]
+    Weighted,
+    RoundRobin,
+    LeastLatency,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
 pub struct RoutingConfig {
     /// Model-to-model routing with fallback support
     /// Maps model names to either a single model or array of fallback models
     /// Example: "haiku-3.5" = "openai/gpt-4o" or ["openai/gpt-4o", "openrouter/glm-4.5:fireworks"]
     #[serde(default)]
     pub models: FxHashMap<String, ModelRoute>,
+    /// Ordered content-aware routing rules, evaluated before alias resolution.
+    /// The first matching rule rewrites the requested model to its `target`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<RoutingRule>,
+    /// Shadow targets per requested model/alias. Matching requests are mirrored
+    /// to the shadow model in the background and both outcomes are recorded.
+    /// Example: "sonnet" = "openrouter/z-ai/glm-4.6"
+    #[serde(default, skip_serializing_if = "FxHashMap::is_empty")]
+    pub shadow: FxHashMap<String, String>,
+    /// Hedging delay in milliseconds per requested model/alias. If the first
+    /// target hasn't produced a byte within the delay, the next one is fired too.
+    /// Example: "complete" = 300
+    #[serde(default, skip_serializing_if = "FxHashMap::is_empty")]
+    pub hedge: FxHashMap<String, u64>,
+    /// What to do with unknown or malformed model-string query parameters
+    #[serde(default)]
+    pub param_validation: ParamValidation,
+}
+
+/// Handling of model-string query parameters that fail the provider's schema
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ParamValidation {
+    /// Reject the request with a 400
+    #[default]
+    Strict,
+    /// Log a warning and drop the offending parameters
+    Lenient,
+}
+
+/// A single `[[routing.rules]]` entry
+///
+/// Every condition that is set must match; unset conditions match anything.
+/// Example:
+/// ```toml
+/// [[routing.rules]]
+/// name = "long-context"
+/// min_prompt_tokens = 150000
+/// target = "gemini/gemini-2.5-pro"
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
+pub struct RoutingRule {
+    /// Optional label used in logs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Requested model names this rule applies to (supports trailing `*`), empty = any
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub models: Vec<String>,
+    /// Match when the estimated prompt size is at least this many tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_prompt_tokens: Option<u64>,
+    /// Match when the estimated prompt size is at most this many tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_prompt_tokens: Option<u64>,
+    /// Match on presence (true) or absence (false) of image inputs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub has_images: Option<bool>,
+    /// Match on presence (true) or absence (false) of tool definitions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub has_tools: Option<bool>,
+    /// Match on whether the client requested thinking/reasoning
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thinking: Option<bool>,
+    /// Match on the inbound client: "claude-code" or "other"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client: Option<String>,
+    /// Local time-of-day window as "HH-HH" (end exclusive, may wrap midnight, e.g. "22-6")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hours: Option<String>,
+    /// Alias or provider/model string the request is rewritten to
+    pub target: String,
+}
+
+/// `[secrets]` section: the store behind `secret:<name>` references
+///
+/// API keys and OAuth tokens may be written as `secret:<name>`; they are looked up in the
+/// store when the config is loaded, and written back to it (not to the TOML) when saved.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
+pub struct SecretsConfig {
+    /// Unset means `auto`; once set, new logins keep their keys and tokens in the store
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub store: Option<SecretStoreKind>,
+    /// Secrets resolved at load time by field key, as (secret name, resolved value)
+    #[serde(skip)]
+    pub(crate) resolved: FxHashMap<String, (String, String)>,
+    /// `${VAR}`/`file:`/`cmd:` values resolved at load time, by key path
+    #[serde(skip)]
+    pub(crate) sources: sources::ResolvedSources,
+}
+
+impl SecretsConfig {
+    pub fn is_default(&self) -> bool {
+        self.store.is_none()
+    }
+
+    /// The store to open, `auto` when none is configured
+    pub fn kind(&self) -> SecretStoreKind {
+        self.store.unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum SecretStoreKind {
+    /// The OS keyring when reachable, otherwise the encrypted file
+    #[default]
+    Auto,
+    /// Secret Service keyring (GNOME Keyring, KWallet) on Linux
+    Keyring,
+    /// Passphrase-encrypted age file in the data directory
+    File,
+}
+
+/// `[cli_credentials]` section: CLI credentials files to read tokens from
+///
+/// Each key takes a path or a list of candidate paths, and the first that exists is used.
+/// `PRISM_CLAUDE_CREDENTIALS`, `PRISM_GEMINI_CREDENTIALS` and `PRISM_CODEX_CREDENTIALS`
+/// override them with a `:`-separated list.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct CliCredentialsConfig {
+    #[serde(deserialize_with = "path_list", skip_serializing_if = "Vec::is_empty")]
+    #[schemars(with = "PathList")]
+    pub claude: Vec<String>,
+    #[serde(deserialize_with = "path_list", skip_serializing_if = "Vec::is_empty")]
+    #[schemars(with = "PathList")]
+    pub gemini: Vec<String>,
+    #[serde(deserialize_with = "path_list", skip_serializing_if = "Vec::is_empty")]
+    #[schemars(with = "PathList")]
+    pub codex: Vec<String>,
+}
+
+impl CliCredentialsConfig {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// A single path or a list of paths
+#[derive(Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum PathList {
+    One(String),
+    Many(Vec<String>),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+fn path_list<'de, D>(deserializer: D) -> std::result::Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(match PathList::deserialize(deserializer)? {
+        PathList::One(path) => vec![path],
+        PathList::Many(paths) => paths,
+    })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
 pub struct AuthConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub oauth_access_token: Option<String>,
@@ -167,6 +585,18 @@ fn default_fallback_errors() -> Vec<u16> {
     vec![429] // Rate limit error
 }
 
+fn default_key_cooldown_secs() -> u64 {
+    60
+}
+
+fn default_sticky() -> bool {
+    true
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
 fn default_log_file_enabled() -> bool {
     true
 }
@@ -176,7 +606,7 @@ fn default_log_rotation() -> String {
 }
 
 fn default_log_file_prefix() -> String {
-    "setu".to_string()
+    "prism".to_string()
 }
 
 fn default_max_retries() -> u32 {
@@ -200,18 +630,19 @@ impl Default for Config {
         Self {
             server: ServerConfig::default(),
             providers: FxHashMap::default(),
-            routing: RoutingConfig {
-                models: FxHashMap::default(),
-            },
+            routing: RoutingConfig::default(),
             auth: FxHashMap::default(),
+            secrets: Default::default(),
+            cli_credentials: Default::default(),
+            profiles: FxHashMap::default(),
+            include: Vec::new(),
         }
     }
 }
 
 impl Config {
     pub fn load() -> Result<Self> {
-        let config_dir = get_config_dir()?;
-        let config_file = config_dir.join("setu.toml");
+        let config_file = Self::config_file()?;
 
         // Create default config if file doesn't exist
         if !config_file.exists() {
@@ -219,34 +650,120 @@ impl Config {
                 "No config file found, creating default at: {:?}",
                 config_file
             );
-            let default_config = Self::default();
-            default_config.save()?;
+            write_private(&config_file, &to_toml(&Self::default())?)?;
         }
 
-        let config = Figment::new()
-            .merge(Toml::file(&config_file))
-            .merge(Env::prefixed("SETU_"))
-            .extract()
-            .map_err(|e| PrismError::Config(Box::new(e)))?;
+        Self::load_from(&config_file)
+    }
 
-        let mut config: Config = config;
-        config.interpolate_api_keys();
+    /// Load `config_file` with its layers and environment overrides, resolving references
+    /// and secrets
+    ///
+    /// Fails if any reference doesn't resolve. Callers that save must not fall back to
+    /// [`Config::default`] on error: saving writes the difference from the loaded files,
+    /// so a defaulted config would delete every section it lacks.
+    pub fn load_from(config_file: &std::path::Path) -> Result<Self> {
+        let mut config = Self::load_layered(config_file)?;
+        config.resolve_string_sources()?;
+        crate::secrets::resolve_secrets(&mut config)?;
+        crate::auth::paths::configure(&config.cli_credentials);
         Ok(config)
     }
 
-    /// Interpolate environment variables in API keys after loading config
-    pub fn interpolate_api_keys(&mut self) {
-        for provider in self.providers.values_mut() {
-            if let Some(ref api_key) = provider.api_key {
-                provider.api_key = Some(interpolate_env_vars(api_key));
-            }
+    /// Load the config files as written: no `${VAR}`/`file:`/`cmd:` resolution or secret lookup
+    pub fn load_unresolved() -> Result<Self> {
+        Self::figment(&Self::config_file()?)?
+            .extract()
+            .map_err(|e| PrismError::Config(Box::new(e)))
+    }
+
+    /// The config files and environment overrides, before values are resolved
+    fn load_layered(config_file: &std::path::Path) -> Result<Self> {
+        Self::figment(config_file)?
+            .merge(legacy_env_overrides("SETU_"))
+            .merge(env_overrides("SETU_"))
+            .merge(env_overrides("PRISM_"))
+            .extract()
+            .map_err(|e| PrismError::Config(Box::new(e)))
+    }
+
+    /// The base config file with its includes and drop-ins, overlaid with the active
+    /// profile and the project config
+    fn figment(config_file: &std::path::Path) -> Result<Figment> {
+        let mut figment = Figment::new();
+        for file in layers::user_files(config_file)? {
+            figment = figment.merge(Toml::file(file));
+        }
+        if let Some(name) = profiles::active() {
+            let profile_file = profiles::profile_file(&name)?;
+            figment = profiles::with_overlay(figment, config_file, &name, &profile_file);
+        }
+        if let Some(project_file) = layers::project_file()? {
+            layers::check_project_file(&project_file)?;
+            figment = figment.merge(Toml::file(project_file));
+        }
+        Ok(figment)
+    }
+
+    /// Every file the config is read from, for watching: `prism.toml`, its includes, the
+    /// drop-in directory and drop-ins, the active profile's file and the project config
+    pub fn source_files() -> Result<Vec<PathBuf>> {
+        let config_file = Self::config_file()?;
+        let mut files = layers::user_files(&config_file)?;
+        if let Some(config_dir) = config_file.parent() {
+            files.push(config_dir.join(layers::DROP_IN_DIR));
+        }
+        if let Some(name) = profiles::active() {
+            files.push(profiles::profile_file(&name)?);
+        }
+        files.extend(layers::project_file()?);
+        Ok(files)
+    }
+
+    /// Resolve `${VAR}`, `${VAR:-default}`, `${VAR:?message}`, `file:` and `cmd:` values
+    /// in every string field
+    pub fn resolve_string_sources(&mut self) -> Result<()> {
+        sources::resolve_sources(self)
+    }
+
+    /// Dispatch key for a provider: built-in providers by name, others by their configured `type`
+    ///
+    /// Lets e.g. `[providers.gpu-box] type = "ollama"` be routed as `gpu-box/llama3.1:8b`.
+    pub fn provider_kind(&self, provider: &str) -> String {
+        if BUILTIN_PROVIDERS.contains(&provider) {
+            return provider.to_string();
         }
+        self.providers
+            .get(provider)
+            .map(|p| p.r#type.clone())
+            .unwrap_or_else(|| provider.to_string())
     }
 
     pub fn config_dir() -> Result<PathBuf> {
         get_config_dir()
     }
 
+    /// The base config file, `prism.toml` in the config directory
+    ///
+    /// A legacy `setu.toml` is copied to it the first time it is looked up.
+    pub fn config_file() -> Result<PathBuf> {
+        let config_dir = get_config_dir()?;
+        let config_file = config_dir.join(CONFIG_FILE_NAME);
+        if !config_file.exists() {
+            let mut legacy_dirs = vec![config_dir];
+            if let Some(setu_dirs) = ProjectDirs::from("", "", "setu") {
+                legacy_dirs.push(setu_dirs.config_dir().to_path_buf());
+            }
+            migrate::migrate_legacy(&config_file, &legacy_dirs)?;
+        }
+        Ok(config_file)
+    }
+
+    /// JSON Schema of the config file, for editor completion and validation
+    pub fn json_schema() -> Result<serde_json::Value> {
+        Ok(serde_json::to_value(schemars::schema_for!(Config))?)
+    }
+
     pub fn data_dir() -> Result<PathBuf> {
         get_data_dir()
     }
@@ -264,29 +781,90 @@ impl Config {
         }
     }
 
+    /// Write what changed since the config files were read to the base file, or with a
+    /// profile active, to the profile's file
+    ///
+    /// Values that come from other files (includes, drop-ins, the project config) and
+    /// haven't changed are not copied into the file being written.
     pub fn save(&self) -> Result<()> {
-        let config_dir = get_config_dir()?;
-        let config_file = config_dir.join("setu.toml");
-
-        let toml_string = toml::to_string_pretty(self)
-            .map_err(|e| PrismError::Other(format!("Failed to serialize config: {}", e)))?;
+        let serialize_error = |e: &dyn std::fmt::Display| {
+            PrismError::Other(format!("Failed to serialize config: {}", e))
+        };
 
-        // Write config file with restricted permissions (600 - owner read/write only)
-        std::fs::write(&config_file, toml_string)?;
+        // Secrets go back to their store; the file keeps the `secret:<name>` references
+        let config = crate::secrets::with_secret_references(self)?;
+        let saved: toml::Table = toml::from_str(&sources::to_toml_with_references(&config)?)
+            .map_err(|e| serialize_error(&e))?;
 
-        #[cfg(unix)]
+        let config_file = Self::config_file()?;
+        let loaded = match toml::Value::try_from(Self::load_layered(&config_file)?)
+            .map_err(|e| serialize_error(&e))?
         {
-            use std::os::unix::fs::PermissionsExt;
-            let mut perms = std::fs::metadata(&config_file)?.permissions();
-            perms.set_mode(0o600); // Read/write for owner only
-            std::fs::set_permissions(&config_file, perms)?;
+            toml::Value::Table(table) => table,
+            _ => toml::Table::new(),
+        };
+
+        let target = match profiles::active() {
+            Some(profile) => profiles::profile_file(&profile)?,
+            None => config_file,
+        };
+        let mut contents = if target.exists() {
+            toml::from_str(&std::fs::read_to_string(&target)?).map_err(|e| {
+                PrismError::Config(Box::new(figment::Error::from(format!(
+                    "{}: {}",
+                    target.display(),
+                    e
+                ))))
+            })?
+        } else {
+            toml::Table::new()
+        };
+        layers::apply_changes(&mut contents, &loaded, &saved);
+
+        if let Some(dir) = target.parent() {
+            std::fs::create_dir_all(dir)?;
         }
-        Ok(())
+        write_private(&target, &to_toml(&contents)?)
+    }
+}
+
+/// `<prefix>SERVER_<KEY>` environment overrides of `[server]` (e.g. `PRISM_SERVER_PORT`)
+fn env_overrides(prefix: &str) -> Env {
+    Env::prefixed(prefix)
+        .filter(|key| is_server_key(key.as_str()))
+        .map(|key| format!("server.{}", &key.as_str()["server_".len()..]).into())
+}
+
+/// Other `<prefix><KEY>` variables set top-level keys, as `SETU_` variables always have
+fn legacy_env_overrides(prefix: &str) -> Env {
+    Env::prefixed(prefix).filter(|key| !is_server_key(key.as_str()))
+}
+
+fn is_server_key(key: &str) -> bool {
+    key.to_ascii_lowercase().starts_with("server_")
+}
+
+fn to_toml(value: &impl Serialize) -> Result<String> {
+    toml::to_string_pretty(value)
+        .map_err(|e| PrismError::Other(format!("Failed to serialize config: {}", e)))
+}
+
+/// Write a config file with restricted permissions (600 - owner read/write only)
+fn write_private(path: &std::path::Path, contents: &str) -> Result<()> {
+    std::fs::write(path, contents)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mut perms = std::fs::metadata(path)?.permissions();
+        perms.set_mode(0o600); // Read/write for owner only
+        std::fs::set_permissions(path, perms)?;
     }
+    Ok(())
 }
 
 fn get_config_dir() -> Result<PathBuf> {
-    let project_dirs = ProjectDirs::from("", "", "setu")
+    let project_dirs = ProjectDirs::from("", "", "prism")
         .ok_or_else(|| PrismError::Other("Could not determine config directory".to_string()))?;
 
     let config_dir = project_dirs.config_dir();
@@ -296,105 +874,27 @@ fn get_config_dir() -> Result<PathBuf> {
 }
 
 fn get_data_dir() -> Result<PathBuf> {
-    let project_dirs = ProjectDirs::from("", "", "setu")
+    let project_dirs = ProjectDirs::from("", "", "prism")
         .ok_or_else(|| PrismError::Other("Could not determine data directory".to_string()))?;
-
     let data_dir = project_dirs.data_dir();
-    std::fs::create_dir_all(data_dir)?;
-
-    Ok(data_dir.to_path_buf())
-}
-
-/// Interpolate environment variables in a string
-/// Supports ${VAR} syntax, e.g., "${ANTHROPIC_API_KEY}"
-fn interpolate_env_vars(value: &str) -> String {
-    let mut result = value.to_string();
 
-    // Find all ${VAR} patterns
-    while let Some(start) = result.find("${") {
-        if let Some(end) = result[start..].find('}') {
-            let var_name = &result[start + 2..start + end];
-            let replacement = std::env::var(var_name).unwrap_or_default();
-            result.replace_range(start..start + end + 1, &replacement);
-        } else {
-            break; // Malformed pattern, stop processing
-        }
+    // Keep using the legacy setu data directory (logs, secrets, shadow records) until a
+    // prism one exists
+    if !data_dir.exists()
+        && let Some(setu_dirs) = ProjectDirs::from("", "", "setu")
+        && setu_dirs.data_dir().is_dir()
+    {
+        return Ok(setu_dirs.data_dir().to_path_buf());
     }
+    std::fs::create_dir_all(data_dir)?;
 
-    result
+    Ok(data_dir.to_path_buf())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_interpolate_env_vars_valid() {
-        unsafe {
-            std::env::set_var("TEST_VAR", "test_value");
-            std::env::set_var("ANOTHER_VAR", "another_value");
-        }
-
-        assert_eq!(interpolate_env_vars("${TEST_VAR}"), "test_value");
-        assert_eq!(
-            interpolate_env_vars("prefix_${TEST_VAR}_suffix"),
-            "prefix_test_value_suffix"
-        );
-        assert_eq!(
-            interpolate_env_vars("${TEST_VAR}_${ANOTHER_VAR}"),
-            "test_value_another_value"
-        );
-
-        unsafe {
-            std::env::remove_var("TEST_VAR");
-            std::env::remove_var("ANOTHER_VAR");
-        }
-    }
-
-    #[test]
-    fn test_interpolate_env_vars_missing() {
-        assert_eq!(interpolate_env_vars("${NONEXISTENT_VAR}"), "");
-        assert_eq!(
-            interpolate_env_vars("prefix_${NONEXISTENT_VAR}_suffix"),
-            "prefix__suffix"
-        );
-    }
-
-    #[test]
-    fn test_interpolate_env_vars_malformed() {
-        // Test malformed patterns - should stop processing and return partial result
-        assert_eq!(interpolate_env_vars("${BROKEN"), "${BROKEN");
-        assert_eq!(interpolate_env_vars("${INCOMPLETE_VAR"), "${INCOMPLETE_VAR");
-        assert_eq!(interpolate_env_vars("${}"), "");
-
-        // Test mixed valid/invalid
-        unsafe {
-            std::env::set_var("VALID_VAR", "valid");
-        }
-        assert_eq!(
-            interpolate_env_vars("${VALID_VAR}_${BROKEN"),
-            "valid_${BROKEN"
-        );
-        unsafe {
-            std::env::remove_var("VALID_VAR");
-        }
-    }
-
-    #[test]
-    fn test_interpolate_env_vars_empty() {
-        assert_eq!(interpolate_env_vars(""), "");
-        assert_eq!(interpolate_env_vars("no_vars"), "no_vars");
-    }
-
-    #[test]
-    fn test_interpolate_env_vars_nested_patterns() {
-        // Edge case: patterns that look nested - processes first ${VAR} and leaves rest
-        assert_eq!(interpolate_env_vars("${${VAR}}"), "}"); // ${VAR} becomes empty, leaving }
-
-        // More realistic nested-looking case - processes ${SUFFIX} first, leaving malformed pattern
-        assert_eq!(interpolate_env_vars("${PREFIX_${SUFFIX}}"), "}"); // ${SUFFIX} becomes empty, leaving }
-    }
-
     #[test]
     fn test_model_route_serialization() {
         use serde_json;
@@ -442,6 +942,7 @@ mod tests {
             api_key: Some("${ANTHROPIC_API_KEY}".to_string()),
             api_key_fallback: true,
             fallback_on_errors: vec![429, 401],
+            ..Default::default()
         };
 
         // Test serialization
@@ -479,6 +980,89 @@ mod tests {
         assert_eq!(provider_config.retry.initial_interval_ms, 1000);
     }
 
+    #[test]
+    fn test_provider_quirks_deserialization() {
+        let toml_str = r#"
+            type = "openai-compatible"
+            endpoint = "https://api.deepseek.com/v1"
+
+            [quirks]
+            unsupported_params = ["top_k"]
+            max_tokens_field = "max_completion_tokens"
+            tool_choice = "unsupported"
+
+            [quirks.extra_body]
+            safe_prompt = true
+        "#;
+
+        let provider_config: ProviderConfig = toml::from_str(toml_str).unwrap();
+        let quirks = &provider_config.quirks;
+        assert_eq!(quirks.unsupported_params, vec!["top_k".to_string()]);
+        assert_eq!(quirks.max_tokens_field, MaxTokensField::MaxCompletionTokens);
+        assert_eq!(quirks.tool_choice, ToolChoiceSupport::Unsupported);
+        assert_eq!(quirks.reasoning_field, None);
+        assert_eq!(quirks.extra_body.get("safe_prompt"), Some(&serde_json::json!(true)));
+        assert!(!quirks.is_default());
+    }
+
+    #[test]
+    fn test_balanced_route_deserialization() {
+        let toml_str = r#"
+            [models.glm]
+            strategy = "round_robin"
+            targets = [
+                { model = "openrouter/z-ai/glm-4.5:fireworks", weight = 70 },
+                { model = "openrouter/z-ai/glm-4.5:groq" },
+            ]
+        "#;
+
+        let routing: RoutingConfig = toml::from_str(toml_str).unwrap();
+        match routing.models.get("glm").unwrap() {
+            ModelRoute::Balanced(route) => {
+                assert_eq!(route.strategy, BalanceStrategy::RoundRobin);
+                assert!(route.sticky); // default_sticky()
+                assert_eq!(route.targets.len(), 2);
+                assert_eq!(route.targets[0].weight, 70);
+                assert_eq!(route.targets[1].weight, 1); // default_weight()
+            }
+            _ => panic!("Should be Balanced variant"),
+        }
+    }
+
+    #[test]
+    fn test_extended_route_deserialization() {
+        let toml_str = r#"
+            [models.fast]
+            target = "openai/gpt-5-mini"
+            defaults = { temperature = 0.2 }
+            force = { max_tokens = 8000 }
+            clamp = { max_tokens = [1, 16000] }
+
+            [models.chain]
+            target = ["fast", "anthropic/claude-haiku-4"]
+        "#;
+
+        let routing: RoutingConfig = toml::from_str(toml_str).unwrap();
+        match routing.models.get("fast").unwrap() {
+            ModelRoute::Extended(route) => {
+                assert!(
+                    matches!(&*route.target, ModelRoute::Single(m) if m == "openai/gpt-5-mini")
+                );
+                assert_eq!(route.policy.defaults["temperature"], 0.2);
+                assert_eq!(route.policy.force["max_tokens"], 8000);
+                assert_eq!(route.policy.clamp["max_tokens"], [1.0, 16000.0]);
+            }
+            _ => panic!("Should be Extended variant"),
+        }
+        match routing.models.get("chain").unwrap() {
+            ModelRoute::Extended(route) => {
+                assert!(matches!(&*route.target, ModelRoute::Multiple(m) if m.len() == 2));
+                assert_eq!(route.policy, ParamPolicy::default());
+            }
+            _ => panic!("Should be Extended variant"),
+        }
+    }
+
     #[test]
     fn test_routing_config_serialization() {
         let mut models = FxHashMap::default();
@@ -494,7 +1078,10 @@ mod tests {
             ]),
         );
 
-        let routing_config = RoutingConfig { models };
+        let routing_config = RoutingConfig {
+            models,
+            ..Default::default()
+        };
 
         // Test serialization
         let toml_string = toml::to_string(&routing_config).unwrap();
@@ -540,6 +1127,7 @@ mod tests {
                 api_key: Some("${TEST_ANTHROPIC_KEY}".to_string()),
                 api_key_fallback: true,
                 fallback_on_errors: vec![429],
+                ..Default::default()
             },
         );
 
@@ -548,12 +1136,14 @@ mod tests {
             providers,
             routing: RoutingConfig {
                 models: FxHashMap::default(),
+                ..Default::default()
             },
             auth: FxHashMap::default(),
+            ..Default::default()
         };
 
         // Test interpolation
-        config.interpolate_api_keys();
+        config.resolve_string_sources().unwrap();
 
         let anthropic_config = config.providers.get("anthropic").unwrap();
         assert_eq!(anthropic_config.api_key, Some("sk-ant-test123".to_string()));
@@ -623,4 +1213,88 @@ mod tests {
         assert!(!refresh_soon_config.is_token_expired());
         assert!(refresh_soon_config.needs_refresh());
     }
+
+    #[test]
+    fn test_cli_credentials_accept_path_or_list() {
+        let config: CliCredentialsConfig = toml::from_str(
+            r#"
+            claude = ["~/.claude/.credentials.json", "~/.config/claude/.credentials.json"]
+            codex = "/srv/codex/auth.json"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.claude.len(), 2);
+        assert_eq!(config.codex, vec!["/srv/codex/auth.json".to_string()]);
+        assert!(config.gemini.is_empty());
+        assert!(!config.is_default());
+    }
+
+    #[test]
+    fn test_failed_load_leaves_file_untouched() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join(CONFIG_FILE_NAME);
+        let contents = r#"[server]
+
+[providers.openrouter]
+type = "openrouter"
+endpoint = "https://openrouter.ai/api/v1"
+api_key = "${PRISM_TEST_UNSET_OPENROUTER_KEY}"
+
+[routing.models]
+fast = "openrouter/z-ai/glm-4.5"
+"#;
+        std::fs::write(&file, contents).unwrap();
+
+        assert!(Config::load_from(&file).is_err());
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), contents);
+    }
+
+    #[test]
+    fn test_env_overrides_set_server_keys() {
+        unsafe {
+            std::env::set_var("ENVTEST_SERVER_PORT", "4000");
+            std::env::set_var("ENVTEST_SERVER_LOG_LEVEL", "debug");
+            std::env::set_var("ENVTEST_PROFILE", "work");
+        }
+
+        let figment = Figment::new().merge(env_overrides("ENVTEST_"));
+        assert_eq!(figment.extract_inner::<u16>("server.port").unwrap(), 4000);
+        assert_eq!(
+            figment.extract_inner::<String>("server.log_level").unwrap(),
+            "debug"
+        );
+        // Other variables with the prefix belong to other settings
+        assert!(figment.find_value("profile").is_err());
+
+        unsafe {
+            std::env::remove_var("ENVTEST_SERVER_PORT");
+            std::env::remove_var("ENVTEST_SERVER_LOG_LEVEL");
+            std::env::remove_var("ENVTEST_PROFILE");
+        }
+    }
+
+    #[test]
+    fn test_legacy_env_overrides_keep_top_level_keys() {
+        unsafe {
+            std::env::set_var("LEGACYENVTEST_SERVER_PORT", "4000");
+            std::env::set_var("LEGACYENVTEST_ROUTING", "{ default_model = \"fast\" }");
+        }
+
+        let figment = Figment::new()
+            .merge(legacy_env_overrides("LEGACYENVTEST_"))
+            .merge(env_overrides("LEGACYENVTEST_"));
+        assert_eq!(figment.extract_inner::<u16>("server.port").unwrap(), 4000);
+        assert_eq!(
+            figment
+                .extract_inner::<String>("routing.default_model")
+                .unwrap(),
+            "fast"
+        );
+        assert!(figment.find_value("server_port").is_err());
+
+        unsafe {
+            std::env::remove_var("LEGACYENVTEST_SERVER_PORT");
+            std::env::remove_var("LEGACYENVTEST_ROUTING");
+        }
+    }
 }
@@ -0,0 +1,180 @@
+//! Named profiles: overlays on the base config, selected with `--profile` or `PRISM_PROFILE`.
+//!
+//! A profile is `[profiles.<name>]` in the base file and/or `profiles/<name>.toml` in the
+//! config directory; the file wins where both set a key. While a profile is active, saving
+//! the config writes the changes to the profile's file.
+
+use figment::Figment;
+use figment::providers::{Format, Toml};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use crate::config::{Config, get_config_dir};
+use crate::error::{PrismError, Result};
+
+/// Environment variable naming the active profile
+pub const PROFILE_ENV: &str = "PRISM_PROFILE";
+
+static ACTIVE: RwLock<Option<String>> = RwLock::new(None);
+
+/// Make `name` the profile every later config load and save uses
+pub fn activate(name: &str) -> Result<()> {
+    validate_name(name)?;
+    if !exists(name)? {
+        return Err(config_error(format!(
+            "Profile '{}' not found. Create {} or add [profiles.{}] to {}{}",
+            name,
+            profile_file(name)?.display(),
+            name,
+            Config::config_file()?.display(),
+            available_hint()?
+        )));
+    }
+    let mut active = ACTIVE.write().unwrap_or_else(|e| e.into_inner());
+    *active = Some(name.to_string());
+    Ok(())
+}
+
+/// The active profile, if any
+pub fn active() -> Option<String> {
+    ACTIVE.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// `profiles/<name>.toml` in the config directory
+pub fn profile_file(name: &str) -> Result<PathBuf> {
+    Ok(get_config_dir()?
+        .join("profiles")
+        .join(format!("{}.toml", name)))
+}
+
+/// Names of all profiles, from `[profiles.*]` and `profiles/*.toml`
+pub fn list() -> Result<Vec<String>> {
+    let mut names = base_profiles()?;
+    if let Ok(entries) = std::fs::read_dir(get_config_dir()?.join("profiles")) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == "toml")
+                && let Some(stem) = path.file_stem().and_then(|s| s.to_str())
+            {
+                names.push(stem.to_string());
+            }
+        }
+    }
+    names.sort();
+    names.dedup();
+    Ok(names)
+}
+
+/// Add the overlays of profile `name` on top of the base file
+pub(crate) fn with_overlay(
+    figment: Figment,
+    base_file: &Path,
+    name: &str,
+    profile_file: &Path,
+) -> Figment {
+    let figment =
+        figment.merge(Figment::from(Toml::file(base_file)).focus(&format!("profiles.{}", name)));
+    if profile_file.exists() {
+        figment.merge(Toml::file(profile_file))
+    } else {
+        figment
+    }
+}
+
+fn exists(name: &str) -> Result<bool> {
+    // The base file first: looking it up migrates a legacy config with its profiles
+    Ok(base_profiles()?.iter().any(|p| p == name) || profile_file(name)?.exists())
+}
+
+/// Profiles defined as `[profiles.<name>]` in the base file
+fn base_profiles() -> Result<Vec<String>> {
+    let base_file = Config::config_file()?;
+    let Ok(contents) = std::fs::read_to_string(&base_file) else {
+        return Ok(Vec::new());
+    };
+    let value: toml::Value = toml::from_str(&contents)
+        .map_err(|e| config_error(format!("{}: {}", base_file.display(), e)))?;
+    Ok(value
+        .get("profiles")
+        .and_then(|p| p.as_table())
+        .map(|profiles| profiles.keys().cloned().collect())
+        .unwrap_or_default())
+}
+
+fn available_hint() -> Result<String> {
+    let names = list()?;
+    Ok(if names.is_empty() {
+        String::new()
+    } else {
+        format!(" (available: {})", names.join(", "))
+    })
+}
+
+/// Profile names become file names, so keep them to a safe alphabet
+fn validate_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(config_error(format!(
+            "Invalid profile name '{}': use letters, digits, '-' and '_'",
+            name
+        )))
+    }
+}
+
+fn config_error(message: String) -> PrismError {
+    PrismError::Config(Box::new(figment::Error::from(message)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_overlays_merge_over_base() {
+        let dir = tempfile::tempdir().unwrap();
+        let base_file = dir.path().join("prism.toml");
+        std::fs::write(
+            &base_file,
+            r#"
+            [server]
+            port = 3742
+
+            [profiles.work.server]
+            port = 3800
+            "#,
+        )
+        .unwrap();
+
+        let work_file = dir.path().join("profiles").join("work.toml");
+        let load = |name: &str| {
+            with_overlay(
+                Figment::from(Toml::file(&base_file)),
+                &base_file,
+                name,
+                &work_file,
+            )
+            .extract_inner::<u16>("server.port")
+            .unwrap()
+        };
+        assert_eq!(load("personal"), 3742);
+        assert_eq!(load("work"), 3800);
+
+        // The profile file wins over [profiles.work]
+        std::fs::create_dir_all(work_file.parent().unwrap()).unwrap();
+        std::fs::write(&work_file, "[server]\nport = 3900\n").unwrap();
+        assert_eq!(load("work"), 3900);
+    }
+
+    #[test]
+    fn test_profile_names_are_safe_file_names() {
+        assert!(validate_name("work").is_ok());
+        assert!(validate_name("team_a-2").is_ok());
+        assert!(validate_name("").is_err());
+        assert!(validate_name("../setu").is_err());
+    }
+}
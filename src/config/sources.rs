@@ -0,0 +1,355 @@
+//! Indirect config values: `${VAR}`, `${VAR:-default}`, `${VAR:?message}`, `file:<path>`
+//! and `cmd:<command>`.
+//!
+//! Every string field of the config is resolved when it is loaded. A value that can't be
+//! resolved is a config error, never an empty string. The original references are kept
+//! so saving the config writes them back instead of the resolved values.
+
+use rustc_hash::FxHashMap;
+
+use crate::config::Config;
+use crate::error::{PrismError, Result};
+
+pub const FILE_PREFIX: &str = "file:";
+pub const CMD_PREFIX: &str = "cmd:";
+
+/// Whether a config value is resolved from somewhere else
+pub fn is_reference(value: &str) -> bool {
+    value.contains("${") || value.starts_with(FILE_PREFIX) || value.starts_with(CMD_PREFIX)
+}
+
+/// Resolve one config value
+///
+/// `file:` and `cmd:` apply to the whole value; `${...}` may appear anywhere in it.
+pub fn resolve_string(value: &str) -> std::result::Result<String, String> {
+    if let Some(path) = value.strip_prefix(FILE_PREFIX) {
+        return read_file(path.trim());
+    }
+    if let Some(command) = value.strip_prefix(CMD_PREFIX) {
+        return run_command(command.trim());
+    }
+    interpolate_env_vars(value)
+}
+
+/// Interpolate `${VAR}`, `${VAR:-default}` and `${VAR:?message}`
+///
+/// An unterminated `${` is kept as written.
+fn interpolate_env_vars(value: &str) -> std::result::Result<String, String> {
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(start) = rest.find("${") {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find('}') else {
+            result.push_str(&rest[start..]);
+            return Ok(result);
+        };
+        result.push_str(&expand(&after[..end])?);
+        rest = &after[end + 1..];
+    }
+
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// Expand the inside of one `${...}`
+fn expand(expr: &str) -> std::result::Result<String, String> {
+    let (name, modifier) = match expr.split_once(':') {
+        Some((name, modifier)) => (name, Some(modifier)),
+        None => (expr, None),
+    };
+    let valid_name = !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid_name {
+        return Err(format!("invalid variable reference ${{{}}}", expr));
+    }
+
+    let value = std::env::var(name).ok();
+    let Some(modifier) = modifier else {
+        return value.ok_or_else(|| format!("environment variable {} is not set", name));
+    };
+
+    // Like the shell, `:-` and `:?` treat an empty variable as unset
+    if let Some(value) = value.filter(|v| !v.is_empty()) {
+        return Ok(value);
+    }
+    if let Some(default) = modifier.strip_prefix('-') {
+        Ok(default.to_string())
+    } else if let Some(message) = modifier.strip_prefix('?') {
+        Err(if message.is_empty() {
+            format!("environment variable {} is not set", name)
+        } else {
+            format!("{}: {}", name, message)
+        })
+    } else {
+        Err(format!("invalid variable reference ${{{}}}", expr))
+    }
+}
+
+fn read_file(path: &str) -> std::result::Result<String, String> {
+    let path = match path.strip_prefix("~/") {
+        Some(rest) => match std::env::var("HOME") {
+            Ok(home) => format!("{}/{}", home, rest),
+            Err(_) => return Err("HOME is not set".to_string()),
+        },
+        None => path.to_string(),
+    };
+    let contents =
+        std::fs::read_to_string(&path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+    Ok(contents.trim_end_matches(['\n', '\r']).to_string())
+}
+
+fn run_command(command: &str) -> std::result::Result<String, String> {
+    let output = std::process::Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(std::process::Stdio::null())
+        .output()
+        .map_err(|e| format!("cannot run `{}`: {}", command, e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!(
+            "`{}` failed ({}): {}",
+            command,
+            output.status,
+            stderr.trim()
+        ));
+    }
+    let stdout = String::from_utf8(output.stdout)
+        .map_err(|_| format!("`{}` printed invalid UTF-8", command))?;
+    Ok(stdout.trim_end_matches(['\n', '\r']).to_string())
+}
+
+/// Original and resolved value of each resolved field, by key path
+pub(crate) type ResolvedSources = FxHashMap<Vec<String>, (String, String)>;
+
+/// Resolve every string field of the config
+pub(crate) fn resolve_sources(config: &mut Config) -> Result<()> {
+    let mut value = toml::Value::try_from(&*config)
+        .map_err(|e| PrismError::Other(format!("Failed to serialize config: {}", e)))?;
+
+    let mut sources = ResolvedSources::default();
+    walk_strings(&mut value, &mut Vec::new(), &mut |path, s| {
+        // Inactive profiles may reference variables that are only set where they are used
+        if !is_reference(s) || path.first().is_some_and(|key| key == "profiles") {
+            return Ok(());
+        }
+        let resolved = resolve_string(s).map_err(|e| {
+            PrismError::Config(Box::new(figment::Error::from(format!(
+                "{}: {}",
+                path.join("."),
+                e
+            ))))
+        })?;
+        let original = std::mem::replace(s, resolved.clone());
+        sources.insert(path.to_vec(), (original, resolved));
+        Ok(())
+    })?;
+    if sources.is_empty() {
+        return Ok(());
+    }
+
+    *config = value.try_into().map_err(|e| {
+        PrismError::Config(Box::new(figment::Error::from(format!(
+            "Resolved config is invalid: {}",
+            e
+        ))))
+    })?;
+    config.secrets.sources = sources;
+    Ok(())
+}
+
+/// Serialize the config, putting back the references of fields that still hold the
+/// value they were resolved to
+pub(crate) fn to_toml_with_references(config: &Config) -> Result<String> {
+    let serialize_error =
+        |e: &dyn std::fmt::Display| PrismError::Other(format!("Failed to serialize config: {}", e));
+    if config.secrets.sources.is_empty() {
+        return toml::to_string_pretty(config).map_err(|e| serialize_error(&e));
+    }
+
+    let mut value = toml::Value::try_from(config).map_err(|e| serialize_error(&e))?;
+    walk_strings(&mut value, &mut Vec::new(), &mut |path, s| {
+        if let Some((original, resolved)) = config.secrets.sources.get(path)
+            && *s == *resolved
+        {
+            *s = original.clone();
+        }
+        Ok(())
+    })?;
+    toml::to_string_pretty(&value).map_err(|e| serialize_error(&e))
+}
+
+/// Call `f` with the key path of every string in `value`
+pub(crate) fn walk_strings(
+    value: &mut toml::Value,
+    path: &mut Vec<String>,
+    f: &mut impl FnMut(&[String], &mut String) -> Result<()>,
+) -> Result<()> {
+    match value {
+        toml::Value::String(s) => f(path, s),
+        toml::Value::Array(items) => {
+            for (i, item) in items.iter_mut().enumerate() {
+                path.push(i.to_string());
+                walk_strings(item, path, f)?;
+                path.pop();
+            }
+            Ok(())
+        }
+        toml::Value::Table(table) => {
+            for (key, item) in table.iter_mut() {
+                path.push(key.clone());
+                walk_strings(item, path, f)?;
+                path.pop();
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interpolate_env_vars_valid() {
+        unsafe {
+            std::env::set_var("TEST_VAR", "test_value");
+            std::env::set_var("ANOTHER_VAR", "another_value");
+        }
+
+        assert_eq!(interpolate_env_vars("${TEST_VAR}").unwrap(), "test_value");
+        assert_eq!(
+            interpolate_env_vars("prefix_${TEST_VAR}_suffix").unwrap(),
+            "prefix_test_value_suffix"
+        );
+        assert_eq!(
+            interpolate_env_vars("${TEST_VAR}_${ANOTHER_VAR}").unwrap(),
+            "test_value_another_value"
+        );
+
+        unsafe {
+            std::env::remove_var("TEST_VAR");
+            std::env::remove_var("ANOTHER_VAR");
+        }
+    }
+
+    #[test]
+    fn test_interpolate_env_vars_missing() {
+        // A missing variable is an error, not an empty string
+        let err = interpolate_env_vars("${NONEXISTENT_VAR}").unwrap_err();
+        assert!(err.contains("NONEXISTENT_VAR is not set"));
+        assert!(interpolate_env_vars("prefix_${NONEXISTENT_VAR}_suffix").is_err());
+    }
+
+    #[test]
+    fn test_interpolate_env_vars_default_and_required() {
+        unsafe {
+            std::env::set_var("SOURCES_EMPTY_VAR", "");
+        }
+
+        assert_eq!(
+            interpolate_env_vars("${SOURCES_UNSET_VAR:-fallback}").unwrap(),
+            "fallback"
+        );
+        assert_eq!(
+            interpolate_env_vars("${SOURCES_EMPTY_VAR:-fallback}").unwrap(),
+            "fallback"
+        );
+        assert_eq!(interpolate_env_vars("${SOURCES_UNSET_VAR:-}").unwrap(), "");
+        assert_eq!(interpolate_env_vars("${SOURCES_EMPTY_VAR}").unwrap(), "");
+        assert_eq!(
+            interpolate_env_vars("${SOURCES_UNSET_VAR:?set it in .env}").unwrap_err(),
+            "SOURCES_UNSET_VAR: set it in .env"
+        );
+
+        unsafe {
+            std::env::remove_var("SOURCES_EMPTY_VAR");
+        }
+    }
+
+    #[test]
+    fn test_interpolate_env_vars_malformed() {
+        // Unterminated patterns are kept as written
+        assert_eq!(interpolate_env_vars("${BROKEN").unwrap(), "${BROKEN");
+        assert_eq!(
+            interpolate_env_vars("${INCOMPLETE_VAR").unwrap(),
+            "${INCOMPLETE_VAR"
+        );
+        assert!(interpolate_env_vars("${}").is_err());
+        assert!(interpolate_env_vars("${VAR:+alt}").is_err());
+
+        // Test mixed valid/invalid
+        unsafe {
+            std::env::set_var("VALID_VAR", "valid");
+        }
+        assert_eq!(
+            interpolate_env_vars("${VALID_VAR}_${BROKEN").unwrap(),
+            "valid_${BROKEN"
+        );
+        unsafe {
+            std::env::remove_var("VALID_VAR");
+        }
+    }
+
+    #[test]
+    fn test_interpolate_env_vars_empty() {
+        assert_eq!(interpolate_env_vars("").unwrap(), "");
+        assert_eq!(interpolate_env_vars("no_vars").unwrap(), "no_vars");
+    }
+
+    #[test]
+    fn test_interpolate_env_vars_nested_patterns() {
+        // `${${VAR}}` reads as a variable named `${VAR`, which is rejected
+        assert!(interpolate_env_vars("${${VAR}}").is_err());
+        assert!(interpolate_env_vars("${PREFIX_${SUFFIX}}").is_err());
+    }
+
+    #[test]
+    fn test_file_and_cmd_sources() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("key");
+        std::fs::write(&path, "sk-from-file\n").unwrap();
+
+        assert_eq!(
+            resolve_string(&format!("file:{}", path.display())).unwrap(),
+            "sk-from-file"
+        );
+        assert!(resolve_string("file:/nonexistent/prism-key").is_err());
+
+        assert_eq!(
+            resolve_string("cmd:echo sk-from-cmd").unwrap(),
+            "sk-from-cmd"
+        );
+        assert!(resolve_string("cmd:exit 3").is_err());
+    }
+
+    #[test]
+    fn test_resolved_fields_are_saved_as_references() {
+        let mut config: Config = toml::from_str(
+            r#"
+            [server]
+            [routing]
+            [providers.openrouter]
+            type = "openrouter"
+            endpoint = "${SOURCES_ENDPOINT:-https://openrouter.ai/api/v1}"
+            api_key = "cmd:echo sk-or-cmd"
+            "#,
+        )
+        .unwrap();
+
+        resolve_sources(&mut config).unwrap();
+        let provider = &config.providers["openrouter"];
+        assert_eq!(provider.endpoint, "https://openrouter.ai/api/v1");
+        assert_eq!(provider.api_key.as_deref(), Some("sk-or-cmd"));
+
+        let written = to_toml_with_references(&config).unwrap();
+        assert!(written.contains("cmd:echo sk-or-cmd"));
+        assert!(!written.contains("\"sk-or-cmd\""));
+        assert!(written.contains("${SOURCES_ENDPOINT:-https://openrouter.ai/api/v1}"));
+    }
+}
@@ -5,6 +5,7 @@ pub mod error;
 pub mod process;
 pub mod retry;
 pub mod router;
+pub mod secrets;
 pub mod server;
 
 pub use config::Config;
@@ -1,5 +1,6 @@
 use clap::{ArgAction, Parser, Subcommand, ValueEnum};
 use prism::commands::auth::AuthCommands;
+use prism::commands::config::ConfigCommands;
 use prism::commands::run::RunCommands;
 use prism::{Config, Result};
 use tracing::{error, info};
@@ -28,6 +29,10 @@ struct Cli {
     /// auto -> truncated when -v or higher is set, else off
     #[arg(long = "payload-log", value_enum, default_value = "auto", global = true)]
     payload_log: PayloadLogModeCli,
+
+    /// Config profile to use (profiles/<name>.toml or [profiles.<name>])
+    #[arg(long, global = true, env = prism::config::profiles::PROFILE_ENV)]
+    profile: Option<String>,
 }
 
 #[derive(Subcommand)]
@@ -49,8 +54,11 @@ enum Commands {
     /// Check server status (placeholder)
     Status,
 
-    /// Validate configuration
-    Config,
+    /// Validate configuration, or check it in depth and print its schema
+    Config {
+        #[command(subcommand)]
+        config_command: Option<ConfigCommands>,
+    },
 
     /// Manage authentication for AI providers
     Auth {
@@ -72,6 +80,10 @@ async fn handle_auth_command(auth_command: AuthCommands) -> Result<()> {
     prism::commands::auth::handle_auth_command(auth_command).await
 }
 
+async fn handle_config_command(config_command: ConfigCommands) -> Result<()> {
+    prism::commands::config::handle_config_command(config_command).await
+}
+
 async fn handle_run_command(run_command: RunCommands) -> Result<()> {
     prism::commands::run::handle_run_command(run_command).await
 }
@@ -80,6 +92,11 @@ async fn handle_run_command(run_command: RunCommands) -> Result<()> {
 async fn main() -> Result<()> {
     let cli = Cli::parse();
 
+    // Select the profile before anything loads the config
+    if let Some(profile) = &cli.profile {
+        prism::config::profiles::activate(profile)?;
+    }
+
     // Initialize tracing
     init_tracing(cli.verbose, cli.payload_log)?;
 
@@ -93,7 +110,12 @@ async fn main() -> Result<()> {
             println!("Status command not yet implemented");
             Ok(())
         }
-        Commands::Config => validate_config().await,
+        Commands::Config {
+            config_command: None,
+        } => validate_config().await,
+        Commands::Config {
+            config_command: Some(config_command),
+        } => handle_config_command(config_command).await,
         Commands::Auth { auth_command } => handle_auth_command(auth_command).await,
         Commands::Diagnose => diagnose_tokens().await,
         Commands::Run { run_command } => handle_run_command(run_command).await,
@@ -127,7 +149,7 @@ async fn start_server(host: Option<String>, port: Option<u16>) -> Result<()> {
     // Save config in case tokens were refreshed during validation
     config.save()?;
 
-    // Initialize authentication cache - fails startup if any OAuth tokens are expired
+    // Initialize authentication cache - providers without valid tokens start unavailable
     let auth_cache = match prism::auth::initialize_auth_cache().await {
         Ok(cache) => cache,
         Err(e) => {
@@ -186,6 +208,9 @@ async fn validate_config() -> Result<()> {
     match Config::load() {
         Ok(config) => {
             println!("Configuration is valid");
+            if let Some(profile) = prism::config::profiles::active() {
+                println!("  Profile: {}", profile);
+            }
             println!("  Server: {}:{}", config.server.host, config.server.port);
             println!("  Providers: {}", config.providers.len());
 
@@ -193,7 +218,21 @@ async fn validate_config() -> Result<()> {
                 println!("  Config directory: {}", config_dir.display());
             }
 
-            Ok(())
+            let problems = prism::server::parameter_mapping::validate_alias_params(&config);
+            if problems.is_empty() {
+                return Ok(());
+            }
+            println!();
+            println!("Invalid model parameters:");
+            for problem in &problems {
+                println!("  {}", problem);
+            }
+            let e = prism::PrismError::InvalidModel(format!(
+                "{} invalid model parameter(s) in routing.models",
+                problems.len()
+            ));
+            error!("Configuration validation failed: {}", e);
+            Err(e)
         }
         Err(e) => {
             error!("Configuration validation failed: {}", e);
@@ -222,6 +261,8 @@ async fn diagnose_tokens() -> Result<()> {
         }
     };
 
+    report_cli_credentials();
+
     // Check if anthropic provider exists
     let anthropic_provider = match config.providers.get("anthropic") {
         Some(provider) => {
@@ -330,13 +371,60 @@ async fn diagnose_tokens() -> Result<()> {
     println!("=======");
     println!(
         "Config file: {:?}",
-        Config::config_dir().unwrap_or_default().join("prism.toml")
+        Config::config_file().unwrap_or_default()
     );
+    if let Some(profile) = prism::config::profiles::active() {
+        println!("Profile: {}", profile);
+    }
     println!("Providers configured: {}", config.providers.len());
 
     Ok(())
 }
 
+/// Print which CLI credentials file each provider uses, and the candidates considered
+fn report_cli_credentials() {
+    use prism::auth::{OAuthProvider, paths};
+
+    println!();
+    println!("CLI Credentials");
+    println!("===============");
+    for provider in OAuthProvider::ALL {
+        let location = match paths::locate(provider) {
+            Ok(location) => location,
+            Err(e) => {
+                println!("{}: {}", provider.cli_source(), e);
+                continue;
+            }
+        };
+        match &location.found {
+            Some(path) => println!(
+                "{}: using {} ({})",
+                provider.cli_source(),
+                path.display(),
+                location.origin.describe(provider)
+            ),
+            None => println!(
+                "{}: not found ({})",
+                provider.cli_source(),
+                location.origin.describe(provider)
+            ),
+        }
+        for candidate in &location.candidates {
+            let marker = if location.found.as_ref() == Some(candidate) {
+                "*"
+            } else {
+                " "
+            };
+            println!("   {} {}", marker, candidate.display());
+        }
+    }
+    println!(
+        "   Override with [cli_credentials] in the config or {}",
+        OAuthProvider::ALL.map(paths::env_var).join(", ")
+    );
+    println!();
+}
+
 fn init_tracing(verbose: u8, payload_log_cli: PayloadLogModeCli) -> Result<()> {
     use tracing_appender::rolling::{RollingFileAppender, Rotation};
     use tracing_subscriber::{EnvFilter, fmt, layer::SubscriberExt, util::SubscriberInitExt};
@@ -11,7 +11,18 @@ const SERVER_PROBE_TIMEOUT_SECS: u64 = 2;
 const SERVER_READY_MAX_ATTEMPTS: u32 = 20;
 const SERVER_READY_RETRY_INTERVAL_MS: u64 = 500;
 
-/// Check if Setu server is running by attempting to connect and verify health
+/// What answered the health check on the server port
+enum HealthProbe {
+    /// A Prism server, serving the given profile and project config
+    Prism {
+        profile: Option<String>,
+        project: Option<String>,
+    },
+    /// Some other service
+    Other,
+}
+
+/// Check if Prism server is running by attempting to connect and verify health
 pub async fn is_server_running() -> Result<Option<String>> {
     let config = Config::load()?;
     let addr = format!("{}:{}", config.server.host, config.server.port);
@@ -27,16 +38,44 @@ pub async fn is_server_running() -> Result<Option<String>> {
     .await
     {
         Ok(Ok(_)) => {
-            debug!("Port {} is open, checking if it's a Setu server", addr);
+            debug!("Port {} is open, checking if it's a Prism server", addr);
 
-            // Verify it's actually a Setu server by checking the health endpoint
+            // Verify it's actually a Prism server by checking the health endpoint
             match check_server_health(&server_url).await {
-                Ok(true) => {
-                    debug!("Confirmed Setu server is running on {}", addr);
+                Ok(HealthProbe::Prism { profile, project }) => {
+                    let active = crate::config::profiles::active();
+                    if profile != active {
+                        let describe = |p: Option<String>| {
+                            p.map(|p| format!("profile '{}'", p))
+                                .unwrap_or_else(|| "no profile".to_string())
+                        };
+                        return Err(PrismError::Other(format!(
+                            "Port {} is used by a Prism server running {}, but {} is active. Give each profile its own server.port.",
+                            config.server.port,
+                            describe(profile),
+                            describe(active)
+                        )));
+                    }
+                    let current = crate::config::layers::project_file()?
+                        .map(|file| file.display().to_string());
+                    if project != current {
+                        let describe = |p: Option<String>| {
+                            p.map(|p| format!("project config {}", p))
+                                .unwrap_or_else(|| "no project config".to_string())
+                        };
+                        return Err(PrismError::Other(format!(
+                            "Port {} is used by a Prism server with {}, but {} applies here. Set server.port in {} to run a server per project.",
+                            config.server.port,
+                            describe(project),
+                            describe(current),
+                            crate::config::layers::PROJECT_FILE_NAME
+                        )));
+                    }
+                    debug!("Confirmed Prism server is running on {}", addr);
                     Ok(Some(server_url))
                 }
-                Ok(false) => {
-                    debug!("Port {} is occupied by a non-Setu service", addr);
+                Ok(HealthProbe::Other) => {
+                    debug!("Port {} is occupied by a non-Prism service", addr);
                     Err(PrismError::Other(format!(
                         "Port {} is occupied by another service. Please stop it or use a different port.",
                         config.server.port
@@ -58,7 +97,7 @@ pub async fn is_server_running() -> Result<Option<String>> {
 }
 
 /// Check if the server responds correctly to health endpoint
-async fn check_server_health(server_url: &str) -> Result<bool> {
+async fn check_server_health(server_url: &str) -> Result<HealthProbe> {
     let client = reqwest::Client::new();
     let health_url = format!("{}/health", server_url);
 
@@ -70,35 +109,53 @@ async fn check_server_health(server_url: &str) -> Result<bool> {
     {
         Ok(Ok(response)) => {
             if response.status().is_success() {
-                // Try to parse the response to verify it's a Setu server
+                // Try to parse the response to verify it's a Prism server
                 match response.json::<serde_json::Value>().await {
                     Ok(json) => {
-                        let is_setu = json
+                        // Servers from before the rename report "setu"
+                        let is_prism = json
                             .get("service")
                             .and_then(|s| s.as_str())
-                            .map(|s| s == "setu")
+                            .map(|s| s == "prism" || s == "setu")
                             .unwrap_or(false);
-                        Ok(is_setu)
+                        if is_prism {
+                            let profile = json
+                                .get("profile")
+                                .and_then(|p| p.as_str())
+                                .map(str::to_string);
+                            let project = json
+                                .get("project")
+                                .and_then(|p| p.as_str())
+                                .map(str::to_string);
+                            Ok(HealthProbe::Prism { profile, project })
+                        } else {
+                            Ok(HealthProbe::Other)
+                        }
                     }
-                    Err(_) => Ok(false),
+                    Err(_) => Ok(HealthProbe::Other),
                 }
             } else {
-                Ok(false)
+                Ok(HealthProbe::Other)
             }
         }
-        Ok(Err(_)) | Err(_) => Ok(false),
+        Ok(Err(_)) | Err(_) => Ok(HealthProbe::Other),
     }
 }
 
-/// Spawn the Setu server in the background
+/// Spawn the Prism server in the background
 pub fn spawn_server_background() -> Result<Child> {
-    info!("Starting Setu server in background");
+    info!("Starting Prism server in background");
 
     // Get the current executable path so we can spawn another instance of ourselves
     let current_exe = std::env::current_exe()
         .map_err(|e| PrismError::Other(format!("Failed to get current executable path: {}", e)))?;
 
-    let child = Command::new(current_exe)
+    // The server must load the same profile as this process
+    let mut command = Command::new(current_exe);
+    if let Some(profile) = crate::config::profiles::active() {
+        command.args(["--profile", &profile]);
+    }
+    let child = command
         .args(["start"])
         .stdout(Stdio::null())
         .stderr(Stdio::null())
@@ -0,0 +1,216 @@
+use rand::Rng;
+use rustc_hash::FxHashMap;
+use sha2::{Digest, Sha256};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use crate::config::{BalanceStrategy, BalancedRoute};
+
+/// Upper bound on remembered sticky assignments before the table is reset
+const MAX_STICKY_ASSIGNMENTS: usize = 10_000;
+
+/// Smoothing factor for the latency moving average (weight of the newest sample)
+const LATENCY_EWMA_ALPHA: f64 = 0.3;
+
+/// Process-wide balancing state shared by all requests
+#[derive(Default)]
+struct BalancerState {
+    round_robin: FxHashMap<String, usize>,
+    latencies_ms: FxHashMap<String, f64>,
+    assignments: FxHashMap<(String, String), usize>,
+}
+
+static BALANCER_STATE: OnceLock<Mutex<BalancerState>> = OnceLock::new();
+
+fn state() -> &'static Mutex<BalancerState> {
+    BALANCER_STATE.get_or_init(|| Mutex::new(BalancerState::default()))
+}
+
+/// Pick the target index for a balanced alias
+///
+/// `latency_keys` holds the provider/model key of each target (same order as
+/// `route.targets`) and is only consulted by the least-latency strategy.
+pub fn select_index(
+    alias: &str,
+    route: &BalancedRoute,
+    session_key: Option<&str>,
+    latency_keys: &[String],
+) -> usize {
+    let len = route.targets.len();
+    if len <= 1 {
+        return 0;
+    }
+
+    let sticky_key = session_key.filter(|_| route.sticky);
+
+    // Weighted picks are deterministic per session, no bookkeeping needed
+    if route.strategy == BalanceStrategy::Weighted {
+        let point = match sticky_key {
+            Some(key) => stable_hash(&format!("{}\u{0}{}", alias, key)),
+            None => rand::thread_rng().r#gen::<u64>(),
+        };
+        return weighted_pick(route, point);
+    }
+
+    let mut state = state().lock().unwrap_or_else(|e| e.into_inner());
+
+    if let Some(key) = sticky_key
+        && let Some(&index) = state.assignments.get(&(alias.to_string(), key.to_string()))
+        && index < len
+    {
+        return index;
+    }
+
+    let index = match route.strategy {
+        BalanceStrategy::RoundRobin => {
+            let counter = state.round_robin.entry(alias.to_string()).or_insert(0);
+            let index = *counter % len;
+            *counter = counter.wrapping_add(1);
+            index
+        }
+        BalanceStrategy::LeastLatency => {
+            // Untried targets count as 0ms so every target gets sampled
+            (0..len)
+                .min_by(|&a, &b| {
+                    let la = latency_of(&state, latency_keys.get(a));
+                    let lb = latency_of(&state, latency_keys.get(b));
+                    la.total_cmp(&lb)
+                })
+                .unwrap_or(0)
+        }
+        BalanceStrategy::Weighted => unreachable!("handled above"),
+    };
+
+    if let Some(key) = sticky_key {
+        if state.assignments.len() >= MAX_STICKY_ASSIGNMENTS {
+            state.assignments.clear();
+        }
+        state
+            .assignments
+            .insert((alias.to_string(), key.to_string()), index);
+    }
+
+    index
+}
+
+/// Record an observed upstream latency for a provider/model key
+pub fn record_latency(key: &str, elapsed: Duration) {
+    let sample = elapsed.as_secs_f64() * 1000.0;
+    let mut state = state().lock().unwrap_or_else(|e| e.into_inner());
+    state
+        .latencies_ms
+        .entry(key.to_string())
+        .and_modify(|avg| *avg = LATENCY_EWMA_ALPHA * sample + (1.0 - LATENCY_EWMA_ALPHA) * *avg)
+        .or_insert(sample);
+}
+
+/// Stable hash of a string, used for sticky session keys
+pub fn stable_hash(value: &str) -> u64 {
+    let digest = Sha256::digest(value.as_bytes());
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&digest[..8]);
+    u64::from_be_bytes(bytes)
+}
+
+fn latency_of(state: &BalancerState, key: Option<&String>) -> f64 {
+    key.and_then(|k| state.latencies_ms.get(k))
+        .copied()
+        .unwrap_or(0.0)
+}
+
+/// Map a point onto the cumulative weight distribution
+fn weighted_pick(route: &BalancedRoute, point: u64) -> usize {
+    let total: u64 = route.targets.iter().map(|t| t.weight as u64).sum();
+    if total == 0 {
+        // All weights zero - fall back to an even split
+        return (point % route.targets.len() as u64) as usize;
+    }
+
+    let mut remaining = point % total;
+    for (index, target) in route.targets.iter().enumerate() {
+        let weight = target.weight as u64;
+        if remaining < weight {
+            return index;
+        }
+        remaining -= weight;
+    }
+    route.targets.len() - 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::WeightedTarget;
+
+    fn route(strategy: BalanceStrategy, weights: &[u32]) -> BalancedRoute {
+        BalancedRoute {
+            strategy,
+            targets: weights
+                .iter()
+                .enumerate()
+                .map(|(i, w)| WeightedTarget {
+                    model: format!("openrouter/model-{}", i),
+                    weight: *w,
+                })
+                .collect(),
+            sticky: true,
+        }
+    }
+
+    #[test]
+    fn test_weighted_distribution() {
+        let route = route(BalanceStrategy::Weighted, &[70, 30]);
+        let mut counts = [0usize; 2];
+        for i in 0..1000u64 {
+            counts[weighted_pick(&route, i)] += 1;
+        }
+        assert_eq!(counts, [700, 300]);
+    }
+
+    #[test]
+    fn test_weighted_zero_weight_never_chosen() {
+        let route = route(BalanceStrategy::Weighted, &[0, 5]);
+        for i in 0..100u64 {
+            assert_eq!(weighted_pick(&route, i), 1);
+        }
+    }
+
+    #[test]
+    fn test_weighted_sticky_session() {
+        let route = route(BalanceStrategy::Weighted, &[50, 50]);
+        let first = select_index("test-weighted-sticky", &route, Some("session-a"), &[]);
+        for _ in 0..20 {
+            assert_eq!(
+                select_index("test-weighted-sticky", &route, Some("session-a"), &[]),
+                first
+            );
+        }
+    }
+
+    #[test]
+    fn test_round_robin_cycles_and_sticks() {
+        let mut rr = route(BalanceStrategy::RoundRobin, &[1, 1, 1]);
+        rr.sticky = false;
+        let picks: Vec<usize> = (0..6)
+            .map(|_| select_index("test-rr-cycle", &rr, None, &[]))
+            .collect();
+        assert_eq!(picks, vec![0, 1, 2, 0, 1, 2]);
+
+        rr.sticky = true;
+        let first = select_index("test-rr-sticky", &rr, Some("s1"), &[]);
+        let second = select_index("test-rr-sticky", &rr, Some("s2"), &[]);
+        assert_ne!(first, second);
+        assert_eq!(select_index("test-rr-sticky", &rr, Some("s1"), &[]), first);
+    }
+
+    #[test]
+    fn test_least_latency_prefers_fastest() {
+        let mut route = route(BalanceStrategy::LeastLatency, &[1, 1]);
+        route.sticky = false;
+        let keys = vec!["test-ll/slow".to_string(), "test-ll/fast".to_string()];
+        record_latency("test-ll/slow", Duration::from_millis(900));
+        record_latency("test-ll/fast", Duration::from_millis(100));
+
+        assert_eq!(select_index("test-ll", &route, None, &keys), 1);
+    }
+}
@@ -1,7 +1,10 @@
 // Simple name-based router - the only routing we need
+pub mod balancer;
 pub mod model_router;
 pub mod name_based;
+pub mod rules;
 
 // Export the router and its types
 pub use model_router::ModelRouter;
 pub use name_based::{NameBasedRouter, RoutingDecision};
+pub use rules::{ClientKind, RequestFeatures};
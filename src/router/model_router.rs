@@ -1,6 +1,8 @@
-use crate::config::{Config, ModelRoute};
+use crate::config::{Config, ModelRoute, ParamPolicy};
 use crate::error::{Result, PrismError};
 use crate::router::name_based::{NameBasedRouter, RoutingDecision};
+use crate::router::balancer;
+use crate::router::rules::{self, RequestFeatures};
 use std::collections::HashSet;
 
 /// Model-to-model router with fallback chain support
@@ -19,50 +21,143 @@ impl ModelRouter {
     }
 
     /// Recursively resolve model mappings, preventing infinite loops
+    ///
+    /// Each resolved model carries the parameter policy of the extended aliases it was
+    /// reached through, if any.
     fn resolve_model_mapping(
         &self,
         model_name: &str,
+        session_key: Option<&str>,
         visited: &mut HashSet<String>,
-    ) -> Result<Vec<String>> {
+    ) -> Result<Vec<(String, Option<ParamPolicy>)>> {
         // Prevent infinite recursion
         if visited.contains(model_name) {
             tracing::warn!(
                 "Circular model mapping detected for '{}', breaking cycle",
                 model_name
             );
-            return Ok(vec![model_name.to_string()]);
+            return Ok(vec![(model_name.to_string(), None)]);
         }
         visited.insert(model_name.to_string());
 
         // Check if we have an explicit model mapping
         if let Some(model_route) = self.config.routing.models.get(model_name) {
-            let mapped_models = match model_route {
-                ModelRoute::Single(model) => vec![model.clone()],
-                ModelRoute::Multiple(models) => models.clone(),
+            let (mapped_models, policy) = match model_route {
+                ModelRoute::Extended(route) => (
+                    self.route_targets(model_name, &route.target, session_key),
+                    Some(&route.policy),
+                ),
+                route => (self.route_targets(model_name, route, session_key), None),
             };
 
             // Recursively resolve each mapped model
             let mut resolved = Vec::new();
             for mapped_model in mapped_models {
                 // Check if the mapped model itself has a mapping (recursive resolution)
-                let sub_resolved = self.resolve_model_mapping(&mapped_model, visited)?;
-                resolved.extend(sub_resolved);
+                let sub_resolved = self.resolve_model_mapping(&mapped_model, session_key, visited)?;
+                resolved.extend(sub_resolved.into_iter().map(|(model, inner)| {
+                    let merged = match (inner, policy) {
+                        (Some(inner), Some(outer)) => Some(inner.merged_with(outer)),
+                        (inner, outer) => inner.or_else(|| outer.cloned()),
+                    };
+                    (model, merged)
+                }));
             }
             return Ok(resolved);
         }
 
         // No mapping found, return the model as-is
-        Ok(vec![model_name.to_string()])
+        Ok(vec![(model_name.to_string(), None)])
+    }
+
+    /// Models an alias route points at, in the order they should be tried
+    fn route_targets(
+        &self,
+        model_name: &str,
+        model_route: &ModelRoute,
+        session_key: Option<&str>,
+    ) -> Vec<String> {
+        match model_route {
+            ModelRoute::Single(model) => vec![model.clone()],
+            ModelRoute::Multiple(models) => models.clone(),
+            ModelRoute::Balanced(route) => {
+                // Chosen target first, the rest stay behind it as fallbacks
+                let latency_keys: Vec<String> = route
+                    .targets
+                    .iter()
+                    .map(|t| self.latency_key(&t.model))
+                    .collect();
+                let chosen = balancer::select_index(model_name, route, session_key, &latency_keys);
+                tracing::debug!(
+                    target: "prism::routing",
+                    "Balanced alias '{}' ({:?}) selected '{}'",
+                    model_name,
+                    route.strategy,
+                    route.targets.get(chosen).map(|t| t.model.as_str()).unwrap_or("")
+                );
+
+                let mut ordered: Vec<String> =
+                    route.targets.iter().map(|t| t.model.clone()).collect();
+                if chosen < ordered.len() {
+                    let target = ordered.remove(chosen);
+                    ordered.insert(0, target);
+                }
+                ordered
+            }
+            ModelRoute::Extended(route) => {
+                self.route_targets(model_name, &route.target, session_key)
+            }
+        }
+    }
+
+    /// Route a request, applying content-aware routing rules before alias resolution
+    pub fn route_request(
+        &self,
+        model_name: &str,
+        features: &RequestFeatures,
+    ) -> Result<Vec<RoutingDecision>> {
+        match rules::select_rule(&self.config.routing.rules, model_name, features) {
+            Some(rule) => {
+                tracing::info!(
+                    target: "prism::routing",
+                    "Routing rule '{}' matched for '{}' (~{} tokens, images={}, tools={}, thinking={}) → {}",
+                    rule.name.as_deref().unwrap_or("unnamed"),
+                    model_name,
+                    features.prompt_tokens,
+                    features.has_images,
+                    features.has_tools,
+                    features.thinking,
+                    rule.target
+                );
+                self.route_model_for_session(&rule.target, features.session_key.as_deref())
+            }
+            None => self.route_model_for_session(model_name, features.session_key.as_deref()),
+        }
+    }
+
+    /// The alias a request resolves through: the target of the matching routing rule, if any
+    pub fn routed_alias<'a>(&'a self, model_name: &'a str, features: &RequestFeatures) -> &'a str {
+        rules::select_rule(&self.config.routing.rules, model_name, features)
+            .map_or(model_name, |rule| rule.target.as_str())
     }
 
     /// Route a model name, checking for explicit mappings first, then falling back to name-based routing
     pub fn route_model(&self, model_name: &str) -> Result<Vec<RoutingDecision>> {
+        self.route_model_for_session(model_name, None)
+    }
+
+    /// Route a model name, keeping balanced aliases sticky for the given session
+    fn route_model_for_session(
+        &self,
+        model_name: &str,
+        session_key: Option<&str>,
+    ) -> Result<Vec<RoutingDecision>> {
         // Recursively resolve model mappings
         let mut visited = HashSet::new();
-        let resolved_models = self.resolve_model_mapping(model_name, &mut visited)?;
+        let resolved_models = self.resolve_model_mapping(model_name, session_key, &mut visited)?;
 
         // If we got back the same model name, it means there was no mapping
-        if resolved_models.len() == 1 && resolved_models[0] == model_name {
+        if resolved_models.len() == 1 && resolved_models[0].0 == model_name {
             // No explicit mapping found, use name-based routing as-is
             let decision = self.name_based_router.route_model(model_name)?;
             return Ok(vec![decision]);
@@ -70,13 +165,20 @@ impl ModelRouter {
 
         // Convert each resolved model to a routing decision
         let mut routing_decisions = Vec::new();
-        for resolved_model in resolved_models {
+        for (resolved_model, param_policy) in resolved_models {
             // Route each resolved model through the name-based router
             match self.name_based_router.route_model(&resolved_model) {
                 Ok(mut decision) => {
                     // Override the model name with the target model
                     decision.model = self.extract_model_name(&resolved_model);
                     decision.original_model = model_name.to_string();
+                    // Forced values win over parameters embedded in the target string
+                    if let (Some(policy), Some(params)) =
+                        (&param_policy, decision.query_params.as_mut())
+                    {
+                        params.retain(|key, _| !policy.force.contains_key(key));
+                    }
+                    decision.param_policy = param_policy;
                     routing_decisions.push(decision);
                 }
                 Err(e) => {
@@ -96,9 +198,19 @@ impl ModelRouter {
         Ok(routing_decisions)
     }
 
+    /// Latency bookkeeping key for a target, matching `RoutingDecision::target_key`
+    fn latency_key(&self, model_spec: &str) -> String {
+        match self.name_based_router.route_model(model_spec) {
+            Ok(decision) => decision.target_key(),
+            Err(_) => model_spec.to_string(),
+        }
+    }
+
     /// Extract the actual model name from a provider/model string
     /// Examples: "openai/gpt-4o" -> "gpt-4o", "anthropic/claude-3" -> "claude-3"
     fn extract_model_name(&self, model_spec: &str) -> String {
+        // Query parameters are carried separately in the routing decision
+        let model_spec = model_spec.split('?').next().unwrap_or(model_spec);
         if let Some(slash_pos) = model_spec.find('/') {
             let after_slash = &model_spec[slash_pos + 1..];
             // Handle provider preferences like ":fireworks"
@@ -131,6 +243,7 @@ mod tests {
                 api_key: None,
                 api_key_fallback: false,
                 fallback_on_errors: vec![429],
+                ..Default::default()
             },
         );
         providers.insert(
@@ -143,6 +256,7 @@ mod tests {
                 api_key: None,
                 api_key_fallback: false,
                 fallback_on_errors: vec![429],
+                ..Default::default()
             },
         );
 
@@ -164,8 +278,10 @@ mod tests {
             providers,
             routing: RoutingConfig {
                 models: model_routes,
+                ..Default::default()
             },
             auth: FxHashMap::default(),
+            ..Default::default()
         }
     }
 
@@ -258,6 +374,7 @@ mod tests {
                 api_key: None,
                 api_key_fallback: false,
                 fallback_on_errors: vec![429],
+                ..Default::default()
             },
         );
 
@@ -266,8 +383,10 @@ mod tests {
             providers,
             routing: RoutingConfig {
                 models: model_routes,
+                ..Default::default()
             },
             auth: FxHashMap::default(),
+            ..Default::default()
         };
 
         let router = ModelRouter::new(config);
@@ -300,6 +419,7 @@ mod tests {
                 api_key: None,
                 api_key_fallback: false,
                 fallback_on_errors: vec![429],
+                ..Default::default()
             },
         );
 
@@ -308,8 +428,10 @@ mod tests {
             providers,
             routing: RoutingConfig {
                 models: model_routes,
+                ..Default::default()
             },
             auth: FxHashMap::default(),
+            ..Default::default()
         };
 
         let router = ModelRouter::new(config);
@@ -324,4 +446,185 @@ mod tests {
         assert_eq!(decision.model, "foo");
         assert_eq!(decision.original_model, "foo");
     }
+
+    #[test]
+    fn test_routing_rule_rewrites_target_alias() {
+        use crate::config::RoutingRule;
+        use crate::router::rules::ClientKind;
+
+        let mut config = create_test_config_with_model_routing();
+        config.routing.rules.push(RoutingRule {
+            name: Some("long-context".to_string()),
+            min_prompt_tokens: Some(150_000),
+            target: "gemini/gemini-2.5-pro".to_string(),
+            ..Default::default()
+        });
+        let router = ModelRouter::new(config);
+
+        let mut features = RequestFeatures {
+            prompt_tokens: 10,
+            has_images: false,
+            has_tools: false,
+            thinking: false,
+            client: ClientKind::Other,
+            hour: 12,
+            session_key: None,
+        };
+
+        // Below threshold: regular alias resolution
+        let decisions = router.route_request("haiku-3.5", &features).unwrap();
+        assert_eq!(decisions[0].provider, "openai");
+
+        // Above threshold: rule target wins
+        features.prompt_tokens = 200_000;
+        let decisions = router.route_request("haiku-3.5", &features).unwrap();
+        assert_eq!(decisions.len(), 1);
+        assert_eq!(decisions[0].provider, "gemini");
+        assert_eq!(decisions[0].model, "gemini-2.5-pro");
+    }
+
+    #[test]
+    fn test_balanced_route_orders_chosen_target_first() {
+        use crate::config::{BalanceStrategy, BalancedRoute, WeightedTarget};
+        use crate::router::rules::ClientKind;
+
+        let mut config = create_test_config_with_model_routing();
+        config.routing.models.insert(
+            "test-split".to_string(),
+            ModelRoute::Balanced(BalancedRoute {
+                strategy: BalanceStrategy::Weighted,
+                targets: vec![
+                    WeightedTarget {
+                        model: "openai/gpt-4o".to_string(),
+                        weight: 0,
+                    },
+                    WeightedTarget {
+                        model: "openrouter/z-ai/glm-4.5".to_string(),
+                        weight: 1,
+                    },
+                ],
+                sticky: true,
+            }),
+        );
+        let router = ModelRouter::new(config);
+
+        let features = RequestFeatures {
+            prompt_tokens: 10,
+            has_images: false,
+            has_tools: false,
+            thinking: false,
+            client: ClientKind::Other,
+            hour: 12,
+            session_key: Some("session".to_string()),
+        };
+
+        let decisions = router.route_request("test-split", &features).unwrap();
+        assert_eq!(decisions.len(), 2);
+        assert_eq!(decisions[0].provider, "openrouter");
+        assert_eq!(decisions[0].model, "z-ai/glm-4.5");
+        assert_eq!(decisions[0].original_model, "test-split");
+        assert_eq!(decisions[1].provider, "openai");
+    }
+
+    #[test]
+    fn test_provider_preferences_keep_separate_latency() {
+        use crate::config::{BalanceStrategy, BalancedRoute, WeightedTarget};
+
+        let mut config = create_test_config_with_model_routing();
+        config.routing.models.insert(
+            "test-latency-pref".to_string(),
+            ModelRoute::Balanced(BalancedRoute {
+                strategy: BalanceStrategy::LeastLatency,
+                targets: vec![
+                    WeightedTarget {
+                        model: "openrouter/test-pref/glm-4.5:fireworks".to_string(),
+                        weight: 1,
+                    },
+                    WeightedTarget {
+                        model: "openrouter/test-pref/glm-4.5:groq".to_string(),
+                        weight: 1,
+                    },
+                ],
+                sticky: false,
+            }),
+        );
+        let router = ModelRouter::new(config);
+
+        let decisions = router.route_model("test-latency-pref").unwrap();
+        let keys: Vec<String> = decisions.iter().map(|d| d.target_key()).collect();
+        assert!(keys.contains(&"openrouter/test-pref/glm-4.5:fireworks".to_string()));
+        assert!(keys.contains(&"openrouter/test-pref/glm-4.5:groq".to_string()));
+
+        balancer::record_latency(
+            "openrouter/test-pref/glm-4.5:fireworks",
+            std::time::Duration::from_millis(900),
+        );
+        balancer::record_latency(
+            "openrouter/test-pref/glm-4.5:groq",
+            std::time::Duration::from_millis(100),
+        );
+        let decisions = router.route_model("test-latency-pref").unwrap();
+        assert_eq!(decisions[0].model, "test-pref/glm-4.5");
+        assert_eq!(decisions[0].provider_preference.as_deref(), Some("groq"));
+    }
+
+    #[test]
+    fn test_extended_alias_carries_param_policy() {
+        use crate::config::{ExtendedRoute, ParamPolicy};
+
+        let mut inner = ParamPolicy::default();
+        inner.defaults.insert("temperature".to_string(), serde_json::json!(0.2));
+        inner.force.insert("max_tokens".to_string(), serde_json::json!(8000));
+        let mut outer = ParamPolicy::default();
+        outer.defaults.insert("temperature".to_string(), serde_json::json!(0.7));
+        outer.clamp.insert("max_tokens".to_string(), [1.0, 16000.0]);
+
+        let mut config = create_test_config_with_model_routing();
+        config.routing.models.insert(
+            "fast".to_string(),
+            ModelRoute::Extended(ExtendedRoute {
+                target: Box::new(ModelRoute::Single(
+                    "openai/gpt-4o?max_tokens=100&temperature=1".to_string(),
+                )),
+                policy: inner,
+            }),
+        );
+        config.routing.models.insert(
+            "faster".to_string(),
+            ModelRoute::Extended(ExtendedRoute {
+                target: Box::new(ModelRoute::Multiple(vec![
+                    "fast".to_string(),
+                    "anthropic/claude-3-haiku".to_string(),
+                ])),
+                policy: outer,
+            }),
+        );
+        let router = ModelRouter::new(config);
+
+        let decisions = router.route_model("fast").unwrap();
+        assert_eq!(decisions[0].model, "gpt-4o");
+        let policy = decisions[0].param_policy.as_ref().unwrap();
+        assert_eq!(policy.force["max_tokens"], 8000);
+        // Forced keys are dropped from the target's own parameters
+        let params = decisions[0].query_params.as_ref().unwrap();
+        assert!(!params.contains_key("max_tokens"));
+        assert_eq!(params.get("temperature"), Some(&"1".to_string()));
+
+        let decisions = router.route_model("faster").unwrap();
+        assert_eq!(decisions.len(), 2);
+        let merged = decisions[0].param_policy.as_ref().unwrap();
+        assert_eq!(merged.defaults["temperature"], 0.7);
+        assert_eq!(merged.force["max_tokens"], 8000);
+        assert_eq!(merged.clamp["max_tokens"], [1.0, 16000.0]);
+        let haiku = decisions[1].param_policy.as_ref().unwrap();
+        assert!(haiku.force.is_empty());
+        assert_eq!(haiku.clamp["max_tokens"], [1.0, 16000.0]);
+    }
+
+    #[test]
+    fn test_plain_alias_has_no_param_policy() {
+        let router = ModelRouter::new(create_test_config_with_model_routing());
+        let decisions = router.route_model("claude-3").unwrap();
+        assert!(decisions.iter().all(|d| d.param_policy.is_none()));
+    }
 }
@@ -1,4 +1,7 @@
-use crate::{config::Config, error::Result};
+use crate::{
+    config::{Config, ParamPolicy},
+    error::Result,
+};
 use std::collections::HashMap;
 
 #[derive(Debug, Clone)]
@@ -8,6 +11,40 @@ pub struct RoutingDecision {
     pub original_model: String,
     pub provider_preference: Option<String>, // New: stores "fireworks", "nitro", "floor", etc.
     pub query_params: Option<HashMap<String, String>>, // New: stores query parameters like "think=1000&effort=high"
+    /// Defaults, forced values and clamps from the alias that resolved to this target
+    pub param_policy: Option<ParamPolicy>,
+}
+
+impl RoutingDecision {
+    /// "provider/model[:preference]" key used for per-target bookkeeping such as latency tracking
+    pub fn target_key(&self) -> String {
+        match &self.provider_preference {
+            Some(preference) => format!("{}/{}:{}", self.provider, self.model, preference),
+            None => format!("{}/{}", self.provider, self.model),
+        }
+    }
+}
+
+/// Decode `%XX` escapes in a query key or value; malformed escapes are kept as-is
+fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(byte) = input
+                .get(i + 1..i + 3)
+                .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            decoded.push(byte);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
 }
 
 /// Name-based router that routes requests based on model name format
@@ -37,10 +74,10 @@ impl NameBasedRouter {
                     if let Some(eq_pos) = param.find('=') {
                         let key = &param[..eq_pos];
                         let value = &param[eq_pos + 1..];
-                        map.insert(key.to_string(), value.to_string());
+                        map.insert(percent_decode(key), percent_decode(value));
                     } else {
                         // Handle key without value (e.g., "flag")
-                        map.insert(param.to_string(), "true".to_string());
+                        map.insert(percent_decode(param), "true".to_string());
                     }
                 }
                 Some(map)
@@ -72,6 +109,7 @@ impl NameBasedRouter {
                 original_model: model_name.to_string(),
                 provider_preference,
                 query_params,
+                param_policy: None,
             });
         }
 
@@ -84,6 +122,7 @@ impl NameBasedRouter {
             original_model: model_name.to_string(),
             provider_preference: None,
             query_params,
+            param_policy: None,
         })
     }
 
@@ -129,6 +168,7 @@ mod tests {
                 api_key: None,
                 api_key_fallback: false,
                 fallback_on_errors: vec![429],
+                ..Default::default()
             },
         );
         providers.insert(
@@ -146,6 +186,7 @@ mod tests {
                 api_key: None,
                 api_key_fallback: false,
                 fallback_on_errors: vec![429],
+                ..Default::default()
             },
         );
 
@@ -154,8 +195,10 @@ mod tests {
             providers,
             routing: RoutingConfig {
                 models: FxHashMap::default(),
+                ..Default::default()
             },
             auth: FxHashMap::default(),
+            ..Default::default()
         }
     }
 
@@ -516,4 +559,33 @@ mod tests {
         assert_eq!(decision.original_model, "openrouter/meta-llama/llama-3.1-8b:nitro");
         assert_eq!(decision.provider_preference, Some("nitro".to_string()));
     }
+
+    #[test]
+    fn test_ollama_tagged_model_routing() {
+        let config = create_test_config();
+        let router = NameBasedRouter::new(config);
+
+        // The Ollama tag lands in provider_preference; the provider rejoins it
+        let decision = router
+            .route_model("ollama/qwen2.5-coder:32b?num_ctx=32768")
+            .unwrap();
+        assert_eq!(decision.provider, "ollama");
+        assert_eq!(decision.model, "qwen2.5-coder");
+        assert_eq!(decision.provider_preference, Some("32b".to_string()));
+        let params = decision.query_params.unwrap();
+        assert_eq!(params.get("num_ctx"), Some(&"32768".to_string()));
+    }
+
+    #[test]
+    fn test_query_values_are_percent_decoded() {
+        let router = NameBasedRouter::new(create_test_config());
+
+        let decision = router
+            .route_model("openai/gpt-5?stop=%0A%0A&user=a%20b&bad=%zz")
+            .unwrap();
+        let params = decision.query_params.unwrap();
+        assert_eq!(params.get("stop"), Some(&"\n\n".to_string()));
+        assert_eq!(params.get("user"), Some(&"a b".to_string()));
+        assert_eq!(params.get("bad"), Some(&"%zz".to_string()));
+    }
 }
@@ -0,0 +1,386 @@
+use chrono::Timelike;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::config::RoutingRule;
+use crate::router::balancer;
+
+/// Rough characters-per-token ratio used for prompt size estimation
+const CHARS_PER_TOKEN: u64 = 4;
+
+/// Inbound client type, derived from request headers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientKind {
+    ClaudeCode,
+    Other,
+}
+
+impl ClientKind {
+    pub fn from_is_claude_code(is_claude_code: bool) -> Self {
+        if is_claude_code {
+            ClientKind::ClaudeCode
+        } else {
+            ClientKind::Other
+        }
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        match self {
+            ClientKind::ClaudeCode => matches!(name, "claude-code" | "claude_code" | "claude"),
+            ClientKind::Other => name == "other",
+        }
+    }
+}
+
+/// Request properties that routing rules can match on
+#[derive(Debug, Clone)]
+pub struct RequestFeatures {
+    pub prompt_tokens: u64,
+    pub has_images: bool,
+    pub has_tools: bool,
+    pub thinking: bool,
+    pub client: ClientKind,
+    pub hour: u32,
+    /// Conversation identity used to keep balanced aliases sticky
+    pub session_key: Option<String>,
+}
+
+impl RequestFeatures {
+    /// Extract features from any inbound request (Anthropic, OpenAI or Gemini format)
+    pub fn from_request<T: Serialize>(request: &T, is_claude_code: bool) -> Self {
+        match serde_json::to_value(request) {
+            Ok(value) => Self::from_value(&value, is_claude_code),
+            Err(_) => Self::from_value(&Value::Null, is_claude_code),
+        }
+    }
+
+    /// Extract features from a JSON request body
+    pub fn from_value(value: &Value, is_claude_code: bool) -> Self {
+        let mut chars = 0u64;
+        let mut has_images = false;
+
+        for key in [
+            "system",
+            "messages",
+            "contents",
+            "systemInstruction",
+            "system_instruction",
+            "tools",
+        ] {
+            if let Some(v) = value.get(key) {
+                scan_content(v, &mut chars, &mut has_images);
+            }
+        }
+
+        let has_tools = value
+            .get("tools")
+            .and_then(|t| t.as_array())
+            .is_some_and(|t| !t.is_empty());
+
+        Self {
+            prompt_tokens: chars.div_ceil(CHARS_PER_TOKEN),
+            has_images,
+            has_tools,
+            thinking: requests_thinking(value),
+            client: ClientKind::from_is_claude_code(is_claude_code),
+            hour: chrono::Local::now().hour(),
+            session_key: first_user_message_key(value),
+        }
+    }
+
+    /// Prefer an explicit session identifier (e.g. from a header) over the message hash
+    pub fn with_session_id(mut self, session_id: Option<String>) -> Self {
+        if session_id.is_some() {
+            self.session_key = session_id;
+        }
+        self
+    }
+}
+
+/// Hash of the first user message text, stable across turns of one conversation
+fn first_user_message_key(value: &Value) -> Option<String> {
+    let first = value
+        .get("messages")
+        .or_else(|| value.get("contents"))
+        .and_then(|m| m.as_array())?
+        .iter()
+        .find(|m| m.get("role").and_then(|r| r.as_str()) == Some("user"))?;
+
+    let mut text = String::new();
+    collect_text(first, &mut text);
+    if text.is_empty() {
+        return None;
+    }
+    Some(format!("{:016x}", balancer::stable_hash(&text)))
+}
+
+fn collect_text(value: &Value, out: &mut String) {
+    match value {
+        Value::String(s) => out.push_str(s),
+        Value::Array(arr) => arr.iter().for_each(|v| collect_text(v, out)),
+        Value::Object(map) => {
+            for (key, v) in map {
+                if key != "role" {
+                    collect_text(v, out);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Walk request content, counting text and detecting image parts
+fn scan_content(value: &Value, chars: &mut u64, has_images: &mut bool) {
+    match value {
+        Value::String(s) => *chars += s.chars().count() as u64,
+        Value::Array(arr) => arr.iter().for_each(|v| scan_content(v, chars, has_images)),
+        Value::Object(map) => {
+            let is_image = matches!(
+                map.get("type").and_then(|t| t.as_str()),
+                Some("image" | "image_url" | "input_image")
+            ) || map.contains_key("inlineData")
+                || map.contains_key("inline_data")
+                || map.contains_key("image_url");
+
+            if is_image {
+                // Don't count base64 payloads as prompt text
+                *has_images = true;
+                return;
+            }
+            map.values().for_each(|v| scan_content(v, chars, has_images));
+        }
+        _ => {}
+    }
+}
+
+/// Detect whether thinking/reasoning was requested in any of the supported formats
+fn requests_thinking(value: &Value) -> bool {
+    // Anthropic: {"thinking": {"type": "enabled", "budget_tokens": N}}
+    if let Some(thinking) = value.get("thinking")
+        && !thinking.is_null()
+    {
+        return thinking.get("type").and_then(|t| t.as_str()) != Some("disabled");
+    }
+
+    // OpenAI: reasoning_effort / reasoning
+    if value.get("reasoning_effort").is_some_and(|v| !v.is_null())
+        || value.get("reasoning").is_some_and(|v| !v.is_null())
+    {
+        return true;
+    }
+
+    // Gemini: generationConfig.thinkingConfig
+    ["generationConfig", "generation_config"]
+        .iter()
+        .filter_map(|k| value.get(*k))
+        .any(|gc| {
+            gc.get("thinkingConfig")
+                .or_else(|| gc.get("thinking_config"))
+                .is_some_and(|v| !v.is_null())
+        })
+}
+
+/// Return the first rule matching the requested model and request features
+pub fn select_rule<'a>(
+    rules: &'a [RoutingRule],
+    model: &str,
+    features: &RequestFeatures,
+) -> Option<&'a RoutingRule> {
+    rules.iter().find(|rule| rule_matches(rule, model, features))
+}
+
+fn rule_matches(rule: &RoutingRule, model: &str, features: &RequestFeatures) -> bool {
+    if !rule.models.is_empty() && !rule.models.iter().any(|p| model_matches(p, model)) {
+        return false;
+    }
+    if rule
+        .min_prompt_tokens
+        .is_some_and(|min| features.prompt_tokens < min)
+    {
+        return false;
+    }
+    if rule
+        .max_prompt_tokens
+        .is_some_and(|max| features.prompt_tokens > max)
+    {
+        return false;
+    }
+    if rule.has_images.is_some_and(|v| v != features.has_images) {
+        return false;
+    }
+    if rule.has_tools.is_some_and(|v| v != features.has_tools) {
+        return false;
+    }
+    if rule.thinking.is_some_and(|v| v != features.thinking) {
+        return false;
+    }
+    if let Some(client) = &rule.client
+        && !features.client.matches(client)
+    {
+        return false;
+    }
+    if let Some(hours) = &rule.hours
+        && !hour_in_window(hours, features.hour)
+    {
+        return false;
+    }
+    true
+}
+
+/// Match a model name against a pattern with optional trailing `*`
+fn model_matches(pattern: &str, model: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => model.starts_with(prefix),
+        None => pattern == model,
+    }
+}
+
+/// Check if `hour` falls within an "HH-HH" window (end exclusive, wraps midnight)
+fn hour_in_window(window: &str, hour: u32) -> bool {
+    let Some((start, end)) = window.split_once('-') else {
+        tracing::warn!("Invalid routing rule hours '{}', expected HH-HH", window);
+        return false;
+    };
+    let (Ok(start), Ok(end)) = (start.trim().parse::<u32>(), end.trim().parse::<u32>()) else {
+        tracing::warn!("Invalid routing rule hours '{}', expected HH-HH", window);
+        return false;
+    };
+
+    if start <= end {
+        hour >= start && hour < end
+    } else {
+        hour >= start || hour < end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn features() -> RequestFeatures {
+        RequestFeatures {
+            prompt_tokens: 1000,
+            has_images: false,
+            has_tools: false,
+            thinking: false,
+            client: ClientKind::Other,
+            hour: 12,
+            session_key: None,
+        }
+    }
+
+    fn rule(target: &str) -> RoutingRule {
+        RoutingRule {
+            target: target.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_features_from_anthropic_request() {
+        let body = json!({
+            "model": "claude-3-5-haiku",
+            "system": "abcd".repeat(100),
+            "messages": [{
+                "role": "user",
+                "content": [
+                    {"type": "text", "text": "abcd"},
+                    {"type": "image", "source": {"type": "base64", "data": "x".repeat(10000)}}
+                ]
+            }],
+            "tools": [{"name": "read", "input_schema": {}}],
+            "thinking": {"type": "enabled", "budget_tokens": 1024}
+        });
+
+        let f = RequestFeatures::from_value(&body, true);
+        assert!(f.has_images);
+        assert!(f.has_tools);
+        assert!(f.thinking);
+        assert_eq!(f.client, ClientKind::ClaudeCode);
+        // Image payload is not counted; tool schema text is
+        assert!(f.prompt_tokens >= 101 && f.prompt_tokens < 200);
+    }
+
+    #[test]
+    fn test_features_from_gemini_request() {
+        let body = json!({
+            "contents": [{"role": "user", "parts": [
+                {"text": "hello"},
+                {"inlineData": {"mimeType": "image/png", "data": "AAAA"}}
+            ]}],
+            "generationConfig": {"thinkingConfig": {"thinkingBudget": 100}}
+        });
+
+        let f = RequestFeatures::from_value(&body, false);
+        assert!(f.has_images);
+        assert!(!f.has_tools);
+        assert!(f.thinking);
+        assert_eq!(f.client, ClientKind::Other);
+    }
+
+    #[test]
+    fn test_first_matching_rule_wins() {
+        let mut long = rule("gemini/gemini-2.5-pro");
+        long.min_prompt_tokens = Some(150_000);
+        let mut haiku = rule("openrouter/z-ai/glm-4.5-air:free");
+        haiku.models = vec!["claude-3-5-haiku*".to_string()];
+        haiku.client = Some("claude-code".to_string());
+        let rules = vec![long, haiku];
+
+        let mut f = features();
+        assert!(select_rule(&rules, "claude-3-5-haiku-20241022", &f).is_none());
+
+        f.client = ClientKind::ClaudeCode;
+        let selected = select_rule(&rules, "claude-3-5-haiku-20241022", &f).unwrap();
+        assert_eq!(selected.target, "openrouter/z-ai/glm-4.5-air:free");
+
+        f.prompt_tokens = 200_000;
+        let selected = select_rule(&rules, "claude-3-5-haiku-20241022", &f).unwrap();
+        assert_eq!(selected.target, "gemini/gemini-2.5-pro");
+    }
+
+    #[test]
+    fn test_image_rule() {
+        let mut vision = rule("anthropic/claude-sonnet-4");
+        vision.has_images = Some(true);
+        let rules = vec![vision];
+
+        let mut f = features();
+        assert!(select_rule(&rules, "text-only", &f).is_none());
+        f.has_images = true;
+        assert!(select_rule(&rules, "text-only", &f).is_some());
+    }
+
+    #[test]
+    fn test_session_key_stable_across_turns() {
+        let first_turn = json!({
+            "messages": [{"role": "user", "content": "refactor the parser"}]
+        });
+        let later_turn = json!({
+            "messages": [
+                {"role": "user", "content": "refactor the parser"},
+                {"role": "assistant", "content": "done"},
+                {"role": "user", "content": "now add tests"}
+            ]
+        });
+
+        let a = RequestFeatures::from_value(&first_turn, false);
+        let b = RequestFeatures::from_value(&later_turn, false);
+        assert!(a.session_key.is_some());
+        assert_eq!(a.session_key, b.session_key);
+
+        let c = b.with_session_id(Some("abc".to_string()));
+        assert_eq!(c.session_key.as_deref(), Some("abc"));
+    }
+
+    #[test]
+    fn test_hour_window() {
+        assert!(hour_in_window("9-17", 9));
+        assert!(!hour_in_window("9-17", 17));
+        assert!(hour_in_window("22-6", 23));
+        assert!(hour_in_window("22-6", 2));
+        assert!(!hour_in_window("22-6", 12));
+        assert!(!hour_in_window("garbage", 12));
+    }
+}
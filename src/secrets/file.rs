@@ -0,0 +1,148 @@
+//! Passphrase-encrypted secrets file (age format, scrypt passphrase recipient).
+
+use age::secrecy::SecretString;
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::config::Config;
+use crate::error::{PrismError, Result};
+use crate::secrets::SecretStore;
+
+/// Environment variable holding the passphrase of the secrets file
+pub const PASSPHRASE_ENV: &str = "PRISM_SECRETS_PASSPHRASE";
+
+/// Secrets kept as one encrypted JSON object in `<data dir>/secrets.age`
+pub struct EncryptedFileStore {
+    path: PathBuf,
+    passphrase: SecretString,
+    /// Decrypted contents, read once (decryption is deliberately slow)
+    secrets: Mutex<Option<BTreeMap<String, String>>>,
+}
+
+impl EncryptedFileStore {
+    /// Open `<data dir>/secrets.age` with the passphrase from `PRISM_SECRETS_PASSPHRASE`
+    pub fn open_default() -> Result<Self> {
+        let passphrase = std::env::var(PASSPHRASE_ENV).map_err(|_| {
+            PrismError::Other(format!(
+                "Set {} to unlock the encrypted secrets file",
+                PASSPHRASE_ENV
+            ))
+        })?;
+        Ok(Self::new(
+            Config::data_dir()?.join("secrets.age"),
+            SecretString::from(passphrase),
+        ))
+    }
+
+    pub fn new(path: PathBuf, passphrase: SecretString) -> Self {
+        Self {
+            path,
+            passphrase,
+            secrets: Mutex::new(None),
+        }
+    }
+
+    fn read(&self) -> Result<BTreeMap<String, String>> {
+        if !self.path.exists() {
+            return Ok(BTreeMap::new());
+        }
+        let encrypted = std::fs::read(&self.path)?;
+
+        let decryptor = age::Decryptor::new(&encrypted[..]).map_err(|e| self.error(e))?;
+        let identity = age::scrypt::Identity::new(self.passphrase.clone());
+        let mut reader = decryptor
+            .decrypt(std::iter::once(&identity as &dyn age::Identity))
+            .map_err(|e| self.error(e))?;
+        let mut plaintext = Vec::new();
+        reader.read_to_end(&mut plaintext)?;
+
+        Ok(serde_json::from_slice(&plaintext)?)
+    }
+
+    fn write(&self, secrets: &BTreeMap<String, String>) -> Result<()> {
+        let plaintext = serde_json::to_vec(secrets)?;
+
+        let encryptor = age::Encryptor::with_user_passphrase(self.passphrase.clone());
+        let mut encrypted = Vec::new();
+        let mut writer = encryptor
+            .wrap_output(&mut encrypted)
+            .map_err(|e| self.error(e))?;
+        writer.write_all(&plaintext)?;
+        writer.finish()?;
+
+        // Write next to the target and rename, so a crash never leaves a torn file
+        let tmp_path = self.path.with_extension("age.tmp");
+        std::fs::write(&tmp_path, &encrypted)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&tmp_path, std::fs::Permissions::from_mode(0o600))?;
+        }
+        std::fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+
+    fn error(&self, e: impl std::fmt::Display) -> PrismError {
+        PrismError::Other(format!(
+            "Failed to access secrets file {}: {}",
+            self.path.display(),
+            e
+        ))
+    }
+}
+
+impl SecretStore for EncryptedFileStore {
+    fn name(&self) -> &'static str {
+        "encrypted file"
+    }
+
+    fn get(&self, name: &str) -> Result<Option<String>> {
+        let mut secrets = self.secrets.lock().unwrap_or_else(|e| e.into_inner());
+        if secrets.is_none() {
+            *secrets = Some(self.read()?);
+        }
+        Ok(secrets.as_ref().and_then(|s| s.get(name).cloned()))
+    }
+
+    fn set(&self, name: &str, value: &str) -> Result<()> {
+        let mut secrets = self.secrets.lock().unwrap_or_else(|e| e.into_inner());
+        let mut updated = match secrets.take() {
+            Some(current) => current,
+            None => self.read()?,
+        };
+        updated.insert(name.to_string(), value.to_string());
+        let result = self.write(&updated);
+        *secrets = Some(updated);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypted_file_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("secrets.age");
+        let passphrase = SecretString::from("correct horse".to_string());
+
+        let store = EncryptedFileStore::new(path.clone(), passphrase.clone());
+        store.set("openrouter", "sk-or-123").unwrap();
+
+        let contents = std::fs::read(&path).unwrap();
+        assert!(!contents.windows(9).any(|w| w == b"sk-or-123"));
+
+        let reopened = EncryptedFileStore::new(path.clone(), passphrase);
+        assert_eq!(
+            reopened.get("openrouter").unwrap().as_deref(),
+            Some("sk-or-123")
+        );
+        assert_eq!(reopened.get("missing").unwrap(), None);
+
+        let wrong = EncryptedFileStore::new(path, SecretString::from("wrong".to_string()));
+        assert!(wrong.get("openrouter").is_err());
+    }
+}
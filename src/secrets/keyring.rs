@@ -0,0 +1,76 @@
+//! OS keyring store (Secret Service on Linux: GNOME Keyring, KWallet).
+
+use crate::error::{PrismError, Result};
+use crate::secrets::SecretStore;
+
+/// Keyring service name under which secrets are stored
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+const SERVICE: &str = "prism";
+
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub struct KeyringStore;
+
+#[cfg(target_os = "linux")]
+impl KeyringStore {
+    /// Connect to the Secret Service, failing if it isn't running or is locked
+    pub fn open() -> Result<Self> {
+        // Looking up an absent entry proves the service is reachable
+        match entry("prism-probe")?.get_password() {
+            Ok(_) | Err(::keyring::Error::NoEntry) => Ok(Self),
+            Err(e) => Err(keyring_error(e)),
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+impl KeyringStore {
+    pub fn open() -> Result<Self> {
+        Err(PrismError::Other(
+            "The keyring secret store is only supported on Linux; use store = \"file\"".to_string(),
+        ))
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn entry(name: &str) -> Result<::keyring::Entry> {
+    ::keyring::Entry::new(SERVICE, name).map_err(keyring_error)
+}
+
+#[cfg(target_os = "linux")]
+fn keyring_error(e: ::keyring::Error) -> PrismError {
+    PrismError::Other(format!("Keyring error: {}", e))
+}
+
+impl SecretStore for KeyringStore {
+    fn name(&self) -> &'static str {
+        "keyring"
+    }
+
+    #[cfg(target_os = "linux")]
+    fn get(&self, name: &str) -> Result<Option<String>> {
+        match entry(name)?.get_password() {
+            Ok(secret) => Ok(Some(secret)),
+            Err(::keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(keyring_error(e)),
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn set(&self, name: &str, value: &str) -> Result<()> {
+        entry(name)?.set_password(value).map_err(keyring_error)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn get(&self, _name: &str) -> Result<Option<String>> {
+        Err(PrismError::Other(
+            "Keyring is not supported on this platform".to_string(),
+        ))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn set(&self, _name: &str, _value: &str) -> Result<()> {
+        Err(PrismError::Other(
+            "Keyring is not supported on this platform".to_string(),
+        ))
+    }
+}
@@ -0,0 +1,303 @@
+//! Secret storage for API keys and OAuth tokens.
+//!
+//! Config fields that hold credentials may reference a secret as `secret:<name>`. The
+//! reference is resolved against the configured store when the config is loaded, and
+//! values changed at runtime (refreshed OAuth tokens) are written back to the store
+//! instead of the TOML file.
+
+use rustc_hash::FxHashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use crate::config::{AuthConfig, Config, SecretStoreKind, sources};
+use crate::error::{PrismError, Result};
+
+pub mod file;
+pub mod keyring;
+
+/// Prefix of a config value that references a stored secret
+pub const SECRET_PREFIX: &str = "secret:";
+
+/// A place secrets can be read from and written to
+pub trait SecretStore: Send + Sync {
+    /// Short name for messages ("keyring", "encrypted file")
+    fn name(&self) -> &'static str;
+    fn get(&self, name: &str) -> Result<Option<String>>;
+    fn set(&self, name: &str, value: &str) -> Result<()>;
+}
+
+static STORES: OnceLock<Mutex<FxHashMap<SecretStoreKind, Arc<dyn SecretStore>>>> = OnceLock::new();
+
+/// Open the configured store (kept open for the life of the process)
+///
+/// `auto` uses the keyring when the Secret Service is reachable, else the encrypted file.
+pub fn open_store(kind: SecretStoreKind) -> Result<Arc<dyn SecretStore>> {
+    let stores = STORES.get_or_init(Default::default);
+    let mut stores = stores.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(store) = stores.get(&kind) {
+        return Ok(store.clone());
+    }
+
+    let store: Arc<dyn SecretStore> = match kind {
+        SecretStoreKind::Keyring => Arc::new(keyring::KeyringStore::open()?),
+        SecretStoreKind::File => Arc::new(file::EncryptedFileStore::open_default()?),
+        SecretStoreKind::Auto => match keyring::KeyringStore::open() {
+            Ok(store) => Arc::new(store),
+            Err(e) => {
+                tracing::debug!("Keyring unavailable ({}), using encrypted file", e);
+                Arc::new(file::EncryptedFileStore::open_default()?)
+            }
+        },
+    };
+    stores.insert(kind, store.clone());
+    Ok(store)
+}
+
+/// Visit every config field that may hold a secret, with the field's key
+///
+/// Keys double as default secret names: `<provider>` for `api_key`,
+/// `<provider>.api_keys.<i>`, `<provider>.oauth_access_token` and
+/// `auth.<account>.oauth_refresh_token`.
+pub fn for_each_secret_field(
+    config: &mut Config,
+    mut f: impl FnMut(&str, &mut String) -> Result<()>,
+) -> Result<()> {
+    for (name, provider) in config.providers.iter_mut() {
+        if let Some(key) = provider.api_key.as_mut() {
+            f(name, key)?;
+        }
+        for (i, key) in provider.api_keys.iter_mut().enumerate() {
+            f(&format!("{}.api_keys.{}", name, i), key)?;
+        }
+        visit_auth(name, &mut provider.auth, &mut f)?;
+    }
+    for (name, auth) in config.auth.iter_mut() {
+        visit_auth(&format!("auth.{}", name), auth, &mut f)?;
+    }
+    Ok(())
+}
+
+fn visit_auth(
+    prefix: &str,
+    auth: &mut AuthConfig,
+    f: &mut impl FnMut(&str, &mut String) -> Result<()>,
+) -> Result<()> {
+    if let Some(token) = auth.oauth_access_token.as_mut() {
+        f(&format!("{}.oauth_access_token", prefix), token)?;
+    }
+    if let Some(token) = auth.oauth_refresh_token.as_mut() {
+        f(&format!("{}.oauth_refresh_token", prefix), token)?;
+    }
+    Ok(())
+}
+
+/// Replace `secret:<name>` references with their values from the configured store
+///
+/// The store is only opened when the config references a secret. A missing secret is an
+/// error rather than an empty credential.
+pub fn resolve_secrets(config: &mut Config) -> Result<()> {
+    let mut referenced = false;
+    for_each_secret_field(config, |_, value| {
+        referenced |= value.starts_with(SECRET_PREFIX);
+        Ok(())
+    })?;
+    if !referenced {
+        return Ok(());
+    }
+
+    let store = open_store(config.secrets.kind())?;
+    resolve_with(config, store.as_ref())
+}
+
+fn resolve_with(config: &mut Config, store: &dyn SecretStore) -> Result<()> {
+    let mut resolved = FxHashMap::default();
+    for_each_secret_field(config, |key, value| {
+        let Some(name) = value.strip_prefix(SECRET_PREFIX) else {
+            return Ok(());
+        };
+        let name = name.to_string();
+        let secret = store.get(&name)?.ok_or_else(|| {
+            PrismError::Other(format!(
+                "Secret '{}' referenced by {} not found in {}",
+                name,
+                key,
+                store.name()
+            ))
+        })?;
+        *value = secret.clone();
+        resolved.insert(key.to_string(), (name, secret));
+        Ok(())
+    })?;
+    config.secrets.resolved = resolved;
+    Ok(())
+}
+
+/// Copy of the config to write to disk, with resolved secrets turned back into references
+///
+/// Values that changed since loading (e.g. refreshed OAuth tokens) are saved to the store.
+pub fn with_secret_references(config: &Config) -> Result<Config> {
+    let mut output = config.clone();
+    if config.secrets.resolved.is_empty() {
+        return Ok(output);
+    }
+
+    let mut store = None;
+    for_each_secret_field(&mut output, |key, value| {
+        let Some((name, loaded)) = config.secrets.resolved.get(key) else {
+            return Ok(());
+        };
+        if *value != *loaded {
+            if store.is_none() {
+                store = Some(open_store(config.secrets.kind())?);
+            }
+            if let Some(store) = &store {
+                store.set(name, value)?;
+            }
+        }
+        *value = format!("{}{}", SECRET_PREFIX, name);
+        Ok(())
+    })?;
+    Ok(output)
+}
+
+/// Mark credentials just obtained by a login (by field key) for the store
+///
+/// Applies when `[secrets] store` is set or the config already uses `secret:` references;
+/// the next [`Config::save`] then writes the values to the store and leaves
+/// `secret:<key>` references in the file.
+pub fn store_new_credentials(config: &mut Config, keys: &[String]) {
+    if config.secrets.store.is_none() && config.secrets.resolved.is_empty() {
+        return;
+    }
+    for key in keys {
+        // An empty "loaded" value makes saving write the current one to the store
+        config
+            .secrets
+            .resolved
+            .entry(key.clone())
+            .or_insert_with(|| (key.clone(), String::new()));
+    }
+}
+
+/// Move plaintext credentials into the store, leaving `secret:<name>` references
+///
+/// `${VAR}`, `file:` and `cmd:` values and existing references are left alone. Returns
+/// the keys moved.
+pub fn migrate_secrets(config: &mut Config, store: &dyn SecretStore) -> Result<Vec<String>> {
+    let mut migrated = Vec::new();
+    for_each_secret_field(config, |key, value| {
+        if value.is_empty() || value.starts_with(SECRET_PREFIX) || sources::is_reference(value) {
+            return Ok(());
+        }
+        store.set(key, value)?;
+        *value = format!("{}{}", SECRET_PREFIX, key);
+        migrated.push(key.to_string());
+        Ok(())
+    })?;
+    migrated.sort();
+    Ok(migrated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ProviderConfig;
+
+    #[derive(Default)]
+    struct MemoryStore(Mutex<FxHashMap<String, String>>);
+
+    impl SecretStore for MemoryStore {
+        fn name(&self) -> &'static str {
+            "memory"
+        }
+
+        fn get(&self, name: &str) -> Result<Option<String>> {
+            Ok(self.0.lock().unwrap().get(name).cloned())
+        }
+
+        fn set(&self, name: &str, value: &str) -> Result<()> {
+            self.0
+                .lock()
+                .unwrap()
+                .insert(name.to_string(), value.to_string());
+            Ok(())
+        }
+    }
+
+    fn config_with(api_key: &str, refresh_token: &str) -> Config {
+        let provider: ProviderConfig = serde_json::from_value(serde_json::json!({
+            "type": "openrouter",
+            "endpoint": "https://openrouter.ai/api/v1",
+            "api_key": api_key,
+            "auth": {"oauth_refresh_token": refresh_token}
+        }))
+        .unwrap();
+        let mut config = Config::default();
+        config.providers.insert("openrouter".to_string(), provider);
+        config
+    }
+
+    #[test]
+    fn test_migrate_and_resolve_secret_references() {
+        let store = MemoryStore::default();
+        let mut config = config_with("sk-or-plain", "${REFRESH_TOKEN}");
+
+        let migrated = migrate_secrets(&mut config, &store).unwrap();
+        assert_eq!(migrated, vec!["openrouter".to_string()]);
+        let provider = &config.providers["openrouter"];
+        assert_eq!(provider.api_key.as_deref(), Some("secret:openrouter"));
+        assert_eq!(
+            provider.auth.oauth_refresh_token.as_deref(),
+            Some("${REFRESH_TOKEN}")
+        );
+
+        resolve_with(&mut config, &store).unwrap();
+        assert_eq!(
+            config.providers["openrouter"].api_key.as_deref(),
+            Some("sk-or-plain")
+        );
+        // Writing the config back restores the reference, never the value
+        let written = with_secret_references(&config).unwrap();
+        assert_eq!(
+            written.providers["openrouter"].api_key.as_deref(),
+            Some("secret:openrouter")
+        );
+    }
+
+    #[test]
+    fn test_missing_secret_is_an_error() {
+        let store = MemoryStore::default();
+        let mut config = config_with("secret:nope", "refresh");
+
+        let err = resolve_with(&mut config, &store).unwrap_err().to_string();
+        assert!(err.contains("'nope'"));
+        assert!(err.contains("openrouter"));
+    }
+
+    #[test]
+    fn test_new_credentials_go_to_configured_store() {
+        let store = Arc::new(MemoryStore::default());
+        STORES
+            .get_or_init(Default::default)
+            .lock()
+            .unwrap()
+            .insert(SecretStoreKind::Keyring, store.clone());
+
+        let mut config = config_with("sk-or-new", "${REFRESH_TOKEN}");
+        store_new_credentials(&mut config, &["openrouter".to_string()]);
+        assert!(config.secrets.resolved.is_empty());
+
+        config.secrets.store = Some(SecretStoreKind::Keyring);
+        store_new_credentials(&mut config, &["openrouter".to_string()]);
+        let written = with_secret_references(&config).unwrap();
+        let provider = &written.providers["openrouter"];
+        assert_eq!(provider.api_key.as_deref(), Some("secret:openrouter"));
+        assert_eq!(
+            provider.auth.oauth_refresh_token.as_deref(),
+            Some("${REFRESH_TOKEN}")
+        );
+        assert_eq!(
+            store.get("openrouter").unwrap().as_deref(),
+            Some("sk-or-new")
+        );
+    }
+}
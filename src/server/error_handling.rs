@@ -1,7 +1,11 @@
 use axum::http::StatusCode;
+use axum::response::IntoResponse;
 use tracing::error;
 use std::sync::OnceLock;
 use serde::Serialize;
+use serde_json::{Value, json};
+
+use crate::server::providers::local::InboundFormat;
 
 /// Truncate a string to `max_len` characters, appending `...` if truncated
 pub fn truncate_str(s: &str, max_len: usize) -> String {
@@ -561,6 +565,121 @@ pub fn internal_error(msg: &str, err: &impl std::fmt::Display) -> StatusCode {
     StatusCode::INTERNAL_SERVER_ERROR
 }
 
+/// 400 response with `message` in the client's own error format
+pub fn invalid_request_response(
+    format: InboundFormat,
+    message: &str,
+) -> axum::response::Response {
+    ApiError {
+        status: StatusCode::BAD_REQUEST,
+        message: message.to_string(),
+        code: Some("invalid_model_parameter".to_string()),
+        param: None,
+    }
+    .into_response(format)
+}
+
+/// An error returned to the client in its own API's error format
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub message: String,
+    /// Machine-readable code, e.g. `context_length_exceeded` (OpenAI `code`)
+    pub code: Option<String>,
+    /// Request parameter the error is about (OpenAI `param`)
+    pub param: Option<String>,
+}
+
+impl ApiError {
+    /// Read an upstream error body in any of the common shapes: `{"error": {"message": ...}}`
+    /// (OpenAI, Anthropic, Gemini), `{"error": "..."}` (Ollama, llama.cpp) or plain text
+    pub fn from_upstream(status: StatusCode, vendor: &str, text: &str) -> Self {
+        let parsed: Value = serde_json::from_str(text).unwrap_or(Value::Null);
+        let error = &parsed["error"];
+        let message = error["message"]
+            .as_str()
+            .or_else(|| error.as_str())
+            .or_else(|| parsed["message"].as_str())
+            .map(str::to_string)
+            .unwrap_or_else(|| {
+                if text.trim().is_empty() {
+                    format!("{} returned HTTP {}", vendor, status.as_u16())
+                } else {
+                    text.trim().to_string()
+                }
+            });
+        Self {
+            status,
+            message,
+            code: error["code"].as_str().map(str::to_string),
+            param: error["param"].as_str().map(str::to_string),
+        }
+    }
+
+    /// The error body in `format`
+    pub fn to_value(&self, format: InboundFormat) -> Value {
+        let status = self.status.as_u16();
+        match format {
+            InboundFormat::Anthropic => json!({
+                "type": "error",
+                "error": {"type": anthropic_error_type(status), "message": self.message},
+            }),
+            InboundFormat::OpenAI => json!({
+                "error": {
+                    "message": self.message,
+                    "type": openai_error_type(status),
+                    "param": self.param,
+                    "code": self.code,
+                }
+            }),
+            InboundFormat::Gemini => json!({
+                "error": {"code": status, "message": self.message, "status": gemini_error_status(status)}
+            }),
+        }
+    }
+
+    pub fn into_response(self, format: InboundFormat) -> axum::response::Response {
+        (self.status, axum::Json(self.to_value(format))).into_response()
+    }
+}
+
+fn anthropic_error_type(status: u16) -> &'static str {
+    match status {
+        401 => "authentication_error",
+        403 => "permission_error",
+        404 => "not_found_error",
+        413 => "request_too_large",
+        429 => "rate_limit_error",
+        529 => "overloaded_error",
+        400..=499 => "invalid_request_error",
+        _ => "api_error",
+    }
+}
+
+fn openai_error_type(status: u16) -> &'static str {
+    match status {
+        401 => "authentication_error",
+        403 => "permission_error",
+        404 => "not_found_error",
+        429 => "rate_limit_error",
+        400..=499 => "invalid_request_error",
+        _ => "server_error",
+    }
+}
+
+fn gemini_error_status(status: u16) -> &'static str {
+    match status {
+        401 => "UNAUTHENTICATED",
+        403 => "PERMISSION_DENIED",
+        404 => "NOT_FOUND",
+        429 => "RESOURCE_EXHAUSTED",
+        400..=499 => "INVALID_ARGUMENT",
+        503 => "UNAVAILABLE",
+        504 => "DEADLINE_EXCEEDED",
+        _ => "INTERNAL",
+    }
+}
+
 /// Compact request for logging (truncates large request payloads for debugging)
 /// - Truncates text fields > 100 characters to 97 chars + "..."
 /// - Preserves important structural fields: model, role, type, id, name
@@ -584,6 +703,34 @@ mod tests {
     use openrouter_ox::{ChatRequest, ReasoningConfig};
     use openrouter_ox::message::Message;
 
+    #[test]
+    fn test_upstream_error_in_each_format() {
+        let ollama = ApiError::from_upstream(
+            StatusCode::NOT_FOUND,
+            "Ollama",
+            r#"{"error":"model 'qwen3:32b' not found"}"#,
+        );
+        let anthropic = ollama.to_value(InboundFormat::Anthropic);
+        assert_eq!(anthropic["type"], "error");
+        assert_eq!(anthropic["error"]["type"], "not_found_error");
+        assert_eq!(anthropic["error"]["message"], "model 'qwen3:32b' not found");
+
+        let openai = ApiError::from_upstream(
+            StatusCode::BAD_REQUEST,
+            "llama.cpp",
+            r#"{"error":{"message":"context too long","code":"context_length_exceeded"}}"#,
+        );
+        let value = openai.to_value(InboundFormat::OpenAI);
+        assert_eq!(value["error"]["type"], "invalid_request_error");
+        assert_eq!(value["error"]["code"], "context_length_exceeded");
+        let gemini = openai.to_value(InboundFormat::Gemini);
+        assert_eq!(gemini["error"]["code"], 400);
+        assert_eq!(gemini["error"]["status"], "INVALID_ARGUMENT");
+
+        let empty = ApiError::from_upstream(StatusCode::BAD_GATEWAY, "Ollama", "");
+        assert_eq!(empty.message, "Ollama returned HTTP 502");
+    }
+
     #[test]
     fn test_anthropic_detailed_logging() {
         let mut request = anthropic_ox::ChatRequest::builder()
@@ -0,0 +1,341 @@
+//! Hedged requests: race fallback chain members when the first one is slow to respond.
+//!
+//! The first target is fired immediately. If no response byte arrives within the configured
+//! delay (or an attempt fails with a 5xx, 429 or transport error), the next target is fired as
+//! well. The first attempt to produce a byte wins; the remaining attempts are dropped, which
+//! aborts their upstream HTTP calls.
+
+use axum::body::Body;
+use axum::http::StatusCode;
+use axum::response::Response;
+use futures_util::StreamExt;
+use futures_util::stream::{self, FuturesUnordered};
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+
+/// Hedge delay configured for a requested model/alias
+pub fn hedge_delay(config: &Config, model: &str) -> Option<Duration> {
+    config
+        .routing
+        .hedge
+        .get(model)
+        .map(|ms| Duration::from_millis(*ms))
+}
+
+/// Race the given attempts, firing the next one after each `delay` without a first byte
+///
+/// Attempts are labelled (e.g. with the routing target key). Returns the label of the
+/// winning attempt, or of the last failed one if every attempt failed, with the time that
+/// attempt took from its own start.
+///
+/// Only 5xx, 429 and transport errors move on to the next attempt. Any other client
+/// error is returned as is, since the next member would reject the request too.
+pub async fn race<Fut>(
+    delay: Duration,
+    attempts: Vec<(String, Fut)>,
+) -> (String, Result<Response, StatusCode>, Duration)
+where
+    Fut: Future<Output = Result<Response, StatusCode>>,
+{
+    let mut pending = attempts.into_iter();
+    let mut in_flight = FuturesUnordered::new();
+    let mut last_failure = (String::new(), Err(StatusCode::BAD_GATEWAY), Duration::ZERO);
+
+    let launch = |label: String, attempt: Fut| async move {
+        let started = Instant::now();
+        let outcome = wait_for_first_byte(attempt.await).await;
+        (label, outcome, started.elapsed())
+    };
+
+    loop {
+        if in_flight.is_empty() {
+            match pending.next() {
+                Some((label, attempt)) => in_flight.push(launch(label, attempt)),
+                None => return last_failure,
+            }
+        }
+
+        tokio::select! {
+            Some((label, outcome, elapsed)) = in_flight.next() => match outcome {
+                Attempt::Done(result) => {
+                    if pending.len() > 0 || !in_flight.is_empty() {
+                        tracing::debug!(target: "prism::hedge", "Hedged request won by '{}'", label);
+                    }
+                    // Dropping `in_flight` cancels the losing attempts
+                    return (label, result, elapsed);
+                }
+                Attempt::Failed(result) => {
+                    tracing::debug!(
+                        target: "prism::hedge",
+                        "Hedged attempt '{}' failed with {}",
+                        label,
+                        status_of(&result)
+                    );
+                    last_failure = (label, result, elapsed);
+                    // Fire the next member right away instead of waiting out the delay
+                    if let Some((label, attempt)) = pending.next() {
+                        in_flight.push(launch(label, attempt));
+                    }
+                }
+            },
+            _ = tokio::time::sleep(delay), if pending.len() > 0 => {
+                if let Some((label, attempt)) = pending.next() {
+                    tracing::debug!(
+                        target: "prism::hedge",
+                        "No first byte after {}ms, hedging with '{}'",
+                        delay.as_millis(),
+                        label
+                    );
+                    in_flight.push(launch(label, attempt));
+                }
+            }
+        }
+    }
+}
+
+/// How a hedged attempt ended
+enum Attempt {
+    /// A response for the client: a success or a client error
+    Done(Result<Response, StatusCode>),
+    /// A failure the next attempt may get past; kept in case every attempt fails
+    Failed(Result<Response, StatusCode>),
+}
+
+/// Whether a status is worth trying the next chain member for
+fn is_retryable(status: StatusCode) -> bool {
+    status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+fn status_of(result: &Result<Response, StatusCode>) -> StatusCode {
+    match result {
+        Ok(response) => response.status(),
+        Err(status) => *status,
+    }
+}
+
+/// Resolve once the response has produced its first body chunk
+///
+/// Streaming responses are returned before the upstream sends anything, so the body
+/// has to be polled to know whether the provider is actually responding. Error
+/// responses are passed on with their body untouched.
+async fn wait_for_first_byte(result: Result<Response, StatusCode>) -> Attempt {
+    let response = match result {
+        Ok(response) if response.status().is_success() => response,
+        result if is_retryable(status_of(&result)) => return Attempt::Failed(result),
+        result => return Attempt::Done(result),
+    };
+
+    let (parts, body) = response.into_parts();
+    let mut data = body.into_data_stream();
+    let first = match data.next().await {
+        Some(Ok(chunk)) => Some(chunk),
+        Some(Err(e)) => {
+            tracing::debug!(target: "prism::hedge", "Hedged attempt body failed: {}", e);
+            return Attempt::Failed(Err(StatusCode::BAD_GATEWAY));
+        }
+        None => None,
+    };
+
+    let body = Body::from_stream(stream::iter(first.map(Ok)).chain(data));
+    Attempt::Done(Ok(Response::from_parts(parts, body)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::future::BoxFuture;
+
+    fn attempt(
+        delay_ms: u64,
+        result: Result<&'static str, StatusCode>,
+    ) -> BoxFuture<'static, Result<Response, StatusCode>> {
+        Box::pin(async move {
+            tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+            result.map(|text| Response::new(Body::from(text)))
+        })
+    }
+
+    fn error_response(
+        delay_ms: u64,
+        status: StatusCode,
+        body: &'static str,
+    ) -> BoxFuture<'static, Result<Response, StatusCode>> {
+        Box::pin(async move {
+            tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+            let mut response = Response::new(Body::from(body));
+            *response.status_mut() = status;
+            Ok(response)
+        })
+    }
+
+    async fn body_text(response: Response) -> String {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_hedge_delay_follows_rule_rewritten_alias() {
+        use crate::config::{ModelRoute, RoutingRule};
+        use crate::router::model_router::ModelRouter;
+        use crate::router::rules::{ClientKind, RequestFeatures};
+
+        let mut config = Config::default();
+        config.routing.models.insert(
+            "smart".to_string(),
+            ModelRoute::Multiple(vec![
+                "anthropic/claude-sonnet-4".to_string(),
+                "openrouter/z-ai/glm-4.5".to_string(),
+            ]),
+        );
+        config.routing.hedge.insert("smart".to_string(), 300);
+        config.routing.rules.push(RoutingRule {
+            min_prompt_tokens: Some(1000),
+            target: "smart".to_string(),
+            ..Default::default()
+        });
+        let router = ModelRouter::new(config.clone());
+        let features = RequestFeatures {
+            prompt_tokens: 5000,
+            has_images: false,
+            has_tools: false,
+            thinking: false,
+            client: ClientKind::Other,
+            hour: 12,
+            session_key: None,
+        };
+
+        let alias = router.routed_alias("fast", &features);
+        assert_eq!(alias, "smart");
+        assert_eq!(
+            hedge_delay(&config, alias),
+            Some(Duration::from_millis(300))
+        );
+        assert_eq!(hedge_delay(&config, "fast"), None);
+    }
+
+    #[tokio::test]
+    async fn test_fast_primary_wins_without_hedging() {
+        let (label, result, _) = race(
+            Duration::from_millis(200),
+            vec![
+                ("primary".to_string(), attempt(5, Ok("primary"))),
+                ("backup".to_string(), attempt(0, Ok("backup"))),
+            ],
+        )
+        .await;
+        assert_eq!(label, "primary");
+        assert_eq!(body_text(result.unwrap()).await, "primary");
+    }
+
+    #[tokio::test]
+    async fn test_slow_primary_loses_to_hedge() {
+        let (label, result, _) = race(
+            Duration::from_millis(20),
+            vec![
+                ("primary".to_string(), attempt(2_000, Ok("primary"))),
+                ("backup".to_string(), attempt(10, Ok("backup"))),
+            ],
+        )
+        .await;
+        assert_eq!(label, "backup");
+        assert_eq!(body_text(result.unwrap()).await, "backup");
+    }
+
+    #[tokio::test]
+    async fn test_failure_fires_next_immediately() {
+        let started = std::time::Instant::now();
+        let (label, result, _) = race(
+            Duration::from_secs(10),
+            vec![
+                (
+                    "primary".to_string(),
+                    attempt(0, Err(StatusCode::TOO_MANY_REQUESTS)),
+                ),
+                ("backup".to_string(), attempt(0, Ok("backup"))),
+            ],
+        )
+        .await;
+        assert_eq!(label, "backup");
+        assert!(result.is_ok());
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_all_attempts_fail() {
+        let (label, result, _) = race(
+            Duration::from_millis(10),
+            vec![
+                (
+                    "primary".to_string(),
+                    attempt(0, Err(StatusCode::BAD_GATEWAY)),
+                ),
+                (
+                    "backup".to_string(),
+                    attempt(0, Err(StatusCode::GATEWAY_TIMEOUT)),
+                ),
+            ],
+        )
+        .await;
+        assert_eq!(label, "backup");
+        assert_eq!(result.unwrap_err(), StatusCode::GATEWAY_TIMEOUT);
+    }
+
+    #[tokio::test]
+    async fn test_client_error_is_returned_without_hedging() {
+        let (label, result, _) = race(
+            Duration::from_millis(10),
+            vec![
+                (
+                    "primary".to_string(),
+                    error_response(0, StatusCode::UNPROCESSABLE_ENTITY, r#"{"error":"bad"}"#),
+                ),
+                ("backup".to_string(), attempt(50, Ok("backup"))),
+            ],
+        )
+        .await;
+        assert_eq!(label, "primary");
+        let response = result.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body_text(response).await, r#"{"error":"bad"}"#);
+    }
+
+    #[tokio::test]
+    async fn test_all_failed_returns_last_response_body() {
+        let (label, result, _) = race(
+            Duration::from_millis(10),
+            vec![
+                (
+                    "primary".to_string(),
+                    error_response(0, StatusCode::SERVICE_UNAVAILABLE, "overloaded"),
+                ),
+                (
+                    "backup".to_string(),
+                    error_response(5, StatusCode::TOO_MANY_REQUESTS, "slow down"),
+                ),
+            ],
+        )
+        .await;
+        assert_eq!(label, "backup");
+        let response = result.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(body_text(response).await, "slow down");
+    }
+
+    #[tokio::test]
+    async fn test_latency_excludes_hedge_delay() {
+        let (label, _, elapsed) = race(
+            Duration::from_millis(100),
+            vec![
+                ("primary".to_string(), attempt(5_000, Ok("primary"))),
+                ("backup".to_string(), attempt(10, Ok("backup"))),
+            ],
+        )
+        .await;
+        assert_eq!(label, "backup");
+        assert!(elapsed < Duration::from_millis(100));
+    }
+}
@@ -1,5 +1,6 @@
 use axum::{
     Router,
+    extract::State,
     response::Json,
     routing::{get, post},
 };
@@ -16,15 +17,20 @@ use tower_http::{cors::CorsLayer, trace::TraceLayer};
 use tracing::info;
 
 use crate::{
-    auth::{AuthCache, anthropic::AnthropicOAuth},
+    auth::{
+        AuthCache, OAuthProvider,
+        refresh::{self, RefreshStatus},
+    },
     config::Config,
     error::Result,
 };
 
 pub mod error_handling;
+pub mod hedging;
 pub mod parameter_mapping;
 pub mod providers;
 pub mod routes;
+pub mod shadow;
 
 // Global timestamp for background task monitoring
 static LAST_TOKEN_CHECK: AtomicU64 = AtomicU64::new(0);
@@ -40,19 +46,22 @@ pub struct AppState {
 
 pub struct PrismServer {
     config: Config,
-    auth_cache: AuthCache,
+    auth_cache: Arc<AuthCache>,
 }
 
 impl PrismServer {
     pub fn new(config: Config, auth_cache: AuthCache) -> Self {
-        Self { config, auth_cache }
+        Self {
+            config,
+            auth_cache: Arc::new(auth_cache),
+        }
     }
 
     pub async fn start(&self) -> Result<()> {
-        let config_path = Config::config_dir()?.join("setu.toml");
+        let config_path = Config::config_file()?;
         let app_state = AppState {
             config: Arc::new(Mutex::new(self.config.clone())),
-            auth_cache: Arc::new(self.auth_cache.clone()),
+            auth_cache: self.auth_cache.clone(),
             last_config_check: Arc::new(AtomicU64::new(0)),
             config_path,
         };
@@ -82,17 +91,20 @@ impl PrismServer {
         let addr = format!("{}:{}", self.config.server.host, self.config.server.port);
         let listener = TcpListener::bind(&addr).await?;
 
-        info!("Setu server starting on http://{}", addr);
+        info!("Prism server starting on http://{}", addr);
 
         // Spawn background token maintenance task with panic recovery
         tokio::spawn({
             let config = app_state.config.clone();
+            let auth_cache = app_state.auth_cache.clone();
             async move {
                 loop {
-                    let result =
-                        std::panic::AssertUnwindSafe(background_token_maintenance(config.clone()))
-                            .catch_unwind()
-                            .await;
+                    let result = std::panic::AssertUnwindSafe(background_token_maintenance(
+                        config.clone(),
+                        auth_cache.clone(),
+                    ))
+                    .catch_unwind()
+                    .await;
 
                     match result {
                         Ok(()) => {
@@ -138,24 +150,40 @@ impl PrismServer {
     }
 }
 
-async fn health_check() -> Json<Value> {
+async fn health_check(State(app_state): State<AppState>) -> Json<Value> {
     let now = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap_or_default()
         .as_secs();
 
     let last_check = LAST_TOKEN_CHECK.load(Ordering::Relaxed);
-    let token_task_healthy = last_check > 0 && (now - last_check) < 600; // Healthy if checked within 10 minutes
+    // Healthy if checked within 10 minutes (the scheduler wakes at least every 5)
+    let token_task_healthy = last_check > 0 && (now - last_check) < 600;
+
+    let mut oauth = serde_json::Map::new();
+    let mut refresh_failed = false;
+    for provider in OAuthProvider::ALL {
+        let state = app_state.auth_cache.refresh_state(provider);
+        refresh_failed |= state.status == RefreshStatus::Failed;
+        oauth.insert(provider.name().to_string(), json!(state));
+    }
+    let healthy = token_task_healthy && !refresh_failed;
 
     Json(json!({
-        "status": if token_task_healthy { "healthy" } else { "degraded" },
-        "service": "setu",
+        "status": if healthy { "healthy" } else { "degraded" },
+        "service": "prism",
         "version": env!("CARGO_PKG_VERSION"),
+        "profile": crate::config::profiles::active(),
+        "project": crate::config::layers::project_file()
+            .ok()
+            .flatten()
+            .map(|file| file.display().to_string()),
         "background_token_task": {
             "healthy": token_task_healthy,
             "last_check": last_check,
             "seconds_since_last_check": if last_check > 0 { now - last_check } else { 0 }
-        }
+        },
+        "oauth": oauth
     }))
 }
 
@@ -174,10 +202,17 @@ async fn check_and_reload_config(app_state: &AppState) {
 
     app_state.last_config_check.store(now, Ordering::Relaxed);
 
-    // Check if file has been modified
-    if let Ok(metadata) = std::fs::metadata(&app_state.config_path)
-        && let Ok(modified) = metadata.modified()
-    {
+    // Pick up CLI credentials refreshed or replaced outside Prism
+    app_state.auth_cache.reload_changed_cli_credentials().await;
+
+    // Check if any config file (includes, drop-ins, profile, project) has been modified
+    let config_files =
+        Config::source_files().unwrap_or_else(|_| vec![app_state.config_path.clone()]);
+    let last_modified = config_files
+        .iter()
+        .filter_map(|file| std::fs::metadata(file).ok()?.modified().ok())
+        .max();
+    if let Some(modified) = last_modified {
         let modified_timestamp = modified
             .duration_since(SystemTime::UNIX_EPOCH)
             .unwrap_or_default()
@@ -260,12 +295,9 @@ async fn shutdown_signal() {
     }
 }
 
-async fn background_token_maintenance(config: Arc<Mutex<Config>>) {
-    let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(300)); // 5 minutes
-
+/// Refresh OAuth tokens of every provider ahead of their expiry
+async fn background_token_maintenance(config: Arc<Mutex<Config>>, auth_cache: Arc<AuthCache>) {
     loop {
-        interval.tick().await;
-
         // Update monitoring timestamp
         let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
@@ -273,41 +305,7 @@ async fn background_token_maintenance(config: Arc<Mutex<Config>>) {
             .as_secs();
         LAST_TOKEN_CHECK.store(now, Ordering::Relaxed);
 
-        let mut config_guard = config.lock().await;
-
-        // Check if we have an anthropic provider that needs refresh
-        let needs_refresh = if let Some(provider) = config_guard.providers.get("anthropic") {
-            provider.auth.oauth_refresh_token.is_some() && provider.auth.needs_refresh()
-        } else {
-            false
-        };
-
-        if needs_refresh {
-            tracing::info!("Background token refresh: Token expires soon, attempting refresh");
-
-            // Get mutable reference to auth config
-            if let Some(provider) = config_guard.providers.get_mut("anthropic") {
-                match AnthropicOAuth::refresh_token(&mut provider.auth).await {
-                    Ok(()) => {
-                        tracing::info!(
-                            "Background token refresh: Successfully refreshed OAuth tokens"
-                        );
-
-                        // Clone config for I/O operation and release lock BEFORE file save
-                        let config_to_save = config_guard.clone();
-                        drop(config_guard); // Explicitly release lock
-
-                        // Persist refreshed tokens to config file WITHOUT holding lock
-                        if let Err(e) = config_to_save.save() {
-                            tracing::error!("Failed to save refreshed tokens to config: {}", e);
-                        }
-                    }
-                    Err(e) => {
-                        tracing::error!("Background token refresh failed: {}", e);
-                        tracing::warn!("Tokens may need manual refresh via 'prism auth anthropic'");
-                    }
-                }
-            }
-        }
+        let next_check = refresh::refresh_due_tokens(&config, &auth_cache).await;
+        tokio::time::sleep(next_check).await;
     }
 }
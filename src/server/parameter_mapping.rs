@@ -1,3 +1,9 @@
+use serde_json::{Map, Value};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use crate::config::{Config, ParamPolicy};
+use crate::server::providers::local::InboundFormat;
+use crate::router::model_router::ModelRouter;
 use std::collections::HashMap;
 use std::str::FromStr;
 
@@ -159,7 +165,8 @@ pub fn create_gemini_thinking_config(
         .and_then(|v| v.parse::<bool>().ok())
         .unwrap_or(false);
 
-    if thinking_budget > 0 || include_thoughts {
+    // -1 asks Gemini for a dynamic budget
+    if thinking_budget > 0 || thinking_budget == -1 || include_thoughts {
         Some(gemini_ox::generate_content::ThinkingConfig {
             include_thoughts,
             thinking_budget,
@@ -169,6 +176,533 @@ pub fn create_gemini_thinking_config(
     }
 }
 
+/// Set a JSON field from a query parameter, ignoring values that don't parse as `T`
+fn apply_json_param<T: FromStr + Into<Value>>(
+    target: &mut Map<String, Value>,
+    params: &HashMap<String, String>,
+    key: &str,
+    field: &str,
+) {
+    if let Some(value_str) = params.get(key)
+        && let Ok(value) = value_str.parse::<T>()
+    {
+        target.insert(field.to_string(), value.into());
+    }
+}
+
+/// Apply query parameters to an Ollama `/api/chat` body
+///
+/// Sampling settings go into `options` (merged with what the request already set);
+/// `think` is a top-level field and accepts `true`/`false` or an effort level.
+pub fn apply_ollama_parameters(body: &mut Value, query_params: &HashMap<String, String>) {
+    let Some(body) = body.as_object_mut() else {
+        return;
+    };
+
+    let mut options = body
+        .get("options")
+        .and_then(|o| o.as_object())
+        .cloned()
+        .unwrap_or_default();
+    apply_json_param::<f64>(&mut options, query_params, "temperature", "temperature");
+    apply_json_param::<f64>(&mut options, query_params, "top_p", "top_p");
+    apply_json_param::<f64>(&mut options, query_params, "min_p", "min_p");
+    apply_json_param::<f64>(&mut options, query_params, "repeat_penalty", "repeat_penalty");
+    apply_json_param::<u64>(&mut options, query_params, "top_k", "top_k");
+    apply_json_param::<u64>(&mut options, query_params, "num_ctx", "num_ctx");
+    apply_json_param::<i64>(&mut options, query_params, "seed", "seed");
+    apply_json_param::<i64>(&mut options, query_params, "max_tokens", "num_predict");
+    apply_json_param::<i64>(&mut options, query_params, "num_predict", "num_predict");
+    if !options.is_empty() {
+        body.insert("options".to_string(), Value::Object(options));
+    }
+
+    if let Some(think) = query_params.get("think") {
+        let value = match think.as_str() {
+            "true" | "1" => Value::Bool(true),
+            "false" | "0" => Value::Bool(false),
+            level => Value::String(level.to_string()),
+        };
+        body.insert("think".to_string(), value);
+    }
+}
+
+/// Apply query parameters to a Chat Completions body (llama.cpp, OpenAI-compatible vendors)
+///
+/// Sampling extensions (`top_k`, `min_p`, ...) go at the top level; vendors that reject
+/// them drop them through their `quirks.unsupported_params`.
+pub fn apply_chat_completions_parameters(body: &mut Value, query_params: &HashMap<String, String>) {
+    let Some(body) = body.as_object_mut() else {
+        return;
+    };
+
+    apply_json_param::<f64>(body, query_params, "temperature", "temperature");
+    apply_json_param::<f64>(body, query_params, "top_p", "top_p");
+    apply_json_param::<f64>(body, query_params, "min_p", "min_p");
+    apply_json_param::<f64>(body, query_params, "repeat_penalty", "repeat_penalty");
+    apply_json_param::<u64>(body, query_params, "top_k", "top_k");
+    apply_json_param::<i64>(body, query_params, "seed", "seed");
+    apply_json_param::<i64>(body, query_params, "max_tokens", "max_tokens");
+    apply_json_param::<i64>(body, query_params, "n_predict", "max_tokens");
+}
+
+/// Apply query parameters to an OpenAI Chat Completions body
+///
+/// `max_tokens` is sent as `max_completion_tokens` (required by reasoning models);
+/// `effort` is accepted as a shorthand for `reasoning_effort`.
+pub fn apply_openai_parameters(body: &mut Value, query_params: &HashMap<String, String>) {
+    let Some(body) = body.as_object_mut() else {
+        return;
+    };
+
+    apply_json_param::<f64>(body, query_params, "temperature", "temperature");
+    apply_json_param::<f64>(body, query_params, "top_p", "top_p");
+    apply_json_param::<f64>(body, query_params, "frequency_penalty", "frequency_penalty");
+    apply_json_param::<f64>(body, query_params, "presence_penalty", "presence_penalty");
+    apply_json_param::<i64>(body, query_params, "seed", "seed");
+    apply_json_param::<String>(body, query_params, "effort", "reasoning_effort");
+    apply_json_param::<String>(body, query_params, "reasoning_effort", "reasoning_effort");
+    apply_json_param::<String>(body, query_params, "verbosity", "verbosity");
+    apply_json_param::<String>(body, query_params, "service_tier", "service_tier");
+    if query_params.contains_key("max_tokens") {
+        apply_json_param::<u64>(body, query_params, "max_tokens", "max_completion_tokens");
+        if body.contains_key("max_completion_tokens") {
+            body.remove("max_tokens");
+        }
+    }
+}
+
+/// Apply query parameters to an OpenAI Responses API body
+///
+/// Same parameters as [`apply_openai_parameters`] where the Responses API has them:
+/// effort and verbosity are nested under `reasoning` and `text`; seed and penalties
+/// don't exist there and are ignored.
+pub fn apply_openai_responses_parameters(body: &mut Value, query_params: &HashMap<String, String>) {
+    let Some(body) = body.as_object_mut() else {
+        return;
+    };
+
+    apply_json_param::<f64>(body, query_params, "temperature", "temperature");
+    apply_json_param::<f64>(body, query_params, "top_p", "top_p");
+    apply_json_param::<u64>(body, query_params, "max_tokens", "max_output_tokens");
+    apply_json_param::<String>(body, query_params, "service_tier", "service_tier");
+
+    let mut nested = |key: &str, params: &[&str], field: &str| {
+        let mut section = body
+            .get(key)
+            .and_then(|v| v.as_object())
+            .cloned()
+            .unwrap_or_default();
+        for param in params {
+            apply_json_param::<String>(&mut section, query_params, param, field);
+        }
+        if !section.is_empty() {
+            body.insert(key.to_string(), Value::Object(section));
+        }
+    };
+    nested("reasoning", &["effort", "reasoning_effort"], "effort");
+    nested("text", &["verbosity"], "verbosity");
+}
+
+/// Query parameters that map onto Gemini's `GenerationConfig`
+const GEMINI_GENERATION_PARAMS: &[&str] = &[
+    "temperature",
+    "max_tokens",
+    "top_p",
+    "top_k",
+    "seed",
+    "stop",
+    "response_mime_type",
+    "think",
+    "thoughts",
+];
+
+/// Merge query parameters into a Gemini `GenerationConfig`
+///
+/// Fields the request already set are kept unless a query parameter overrides them.
+/// `stop` takes a comma-separated list.
+pub fn apply_gemini_parameters(
+    config: Option<gemini_ox::generate_content::GenerationConfig>,
+    query_params: &HashMap<String, String>,
+) -> Option<gemini_ox::generate_content::GenerationConfig> {
+    if !GEMINI_GENERATION_PARAMS
+        .iter()
+        .any(|key| query_params.contains_key(*key))
+    {
+        return config;
+    }
+
+    let mut config = config.unwrap_or_default();
+    apply_param(&mut config.temperature, query_params, "temperature");
+    apply_param(&mut config.max_output_tokens, query_params, "max_tokens");
+    apply_param(&mut config.top_p, query_params, "top_p");
+    apply_param(&mut config.top_k, query_params, "top_k");
+    apply_param(&mut config.seed, query_params, "seed");
+    apply_param(&mut config.response_mime_type, query_params, "response_mime_type");
+    if let Some(stop) = query_params.get("stop") {
+        config.stop_sequences = Some(stop.split(',').map(|s| s.to_string()).collect());
+    }
+    if let Some(thinking_config) = create_gemini_thinking_config(query_params) {
+        config.thinking_config = Some(thinking_config);
+    }
+    Some(config)
+}
+
+/// Expected type of a model-string query parameter
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParamKind {
+    /// Number within an inclusive range
+    Float(f64, f64),
+    /// Signed integer
+    Int,
+    /// Non-negative integer
+    UInt,
+    /// `true` or `false`
+    Bool,
+    /// Any string
+    Text,
+    /// One of a fixed set of values
+    OneOf(&'static [&'static str]),
+}
+
+impl ParamKind {
+    fn check(self, value: &str) -> std::result::Result<(), String> {
+        match self {
+            ParamKind::Float(min, max) => match value.parse::<f64>() {
+                Ok(v) if (min..=max).contains(&v) => Ok(()),
+                Ok(_) => Err(format!("must be between {} and {}", min, max)),
+                Err(_) => Err("must be a number".to_string()),
+            },
+            ParamKind::Int => value
+                .parse::<i64>()
+                .map(|_| ())
+                .map_err(|_| "must be an integer".to_string()),
+            ParamKind::UInt => value
+                .parse::<u64>()
+                .map(|_| ())
+                .map_err(|_| "must be a non-negative integer".to_string()),
+            ParamKind::Bool => value
+                .parse::<bool>()
+                .map(|_| ())
+                .map_err(|_| "must be true or false".to_string()),
+            ParamKind::Text => Ok(()),
+            ParamKind::OneOf(values) if values.contains(&value) => Ok(()),
+            ParamKind::OneOf(values) => Err(format!("must be one of {}", values.join(", "))),
+        }
+    }
+}
+
+/// A query parameter the provider's schema rejects
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParamError {
+    pub key: String,
+    pub value: String,
+    pub reason: String,
+}
+
+impl std::fmt::Display for ParamError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "`{}={}` {}", self.key, self.value, self.reason)
+    }
+}
+
+const EFFORT_LEVELS: &[&str] = &["minimal", "low", "medium", "high"];
+
+const NO_PARAMS: &[(&str, ParamKind)] = &[];
+
+const ANTHROPIC_PARAMS: &[(&str, ParamKind)] = &[
+    ("temperature", ParamKind::Float(0.0, 1.0)),
+    ("top_p", ParamKind::Float(0.0, 1.0)),
+    ("top_k", ParamKind::UInt),
+    ("max_tokens", ParamKind::UInt),
+    ("think", ParamKind::UInt),
+];
+
+const OPENROUTER_PARAMS: &[(&str, ParamKind)] = &[
+    ("temperature", ParamKind::Float(0.0, 2.0)),
+    ("max_tokens", ParamKind::UInt),
+    ("top_p", ParamKind::Float(0.0, 1.0)),
+    ("seed", ParamKind::Int),
+    ("frequency_penalty", ParamKind::Float(-2.0, 2.0)),
+    ("presence_penalty", ParamKind::Float(-2.0, 2.0)),
+    ("repetition_penalty", ParamKind::Float(0.0, 2.0)),
+    ("top_k", ParamKind::UInt),
+    ("min_p", ParamKind::Float(0.0, 1.0)),
+    ("top_a", ParamKind::Float(0.0, 1.0)),
+    ("top_logprobs", ParamKind::UInt),
+    ("reasoning", ParamKind::Bool),
+    ("effort", ParamKind::OneOf(EFFORT_LEVELS)),
+    ("reasoning_max_tokens", ParamKind::UInt),
+    ("reasoning_exclude", ParamKind::Bool),
+    ("sort", ParamKind::OneOf(&["price", "throughput"])),
+    (
+        "quantization",
+        ParamKind::OneOf(&["int4", "int8", "fp4", "fp6", "fp8", "fp16", "bf16", "fp32"]),
+    ),
+];
+
+const OPENAI_PARAMS: &[(&str, ParamKind)] = &[
+    ("temperature", ParamKind::Float(0.0, 2.0)),
+    ("top_p", ParamKind::Float(0.0, 1.0)),
+    ("max_tokens", ParamKind::UInt),
+    ("frequency_penalty", ParamKind::Float(-2.0, 2.0)),
+    ("presence_penalty", ParamKind::Float(-2.0, 2.0)),
+    ("seed", ParamKind::Int),
+    ("effort", ParamKind::OneOf(EFFORT_LEVELS)),
+    ("reasoning_effort", ParamKind::OneOf(EFFORT_LEVELS)),
+    ("verbosity", ParamKind::OneOf(&["low", "medium", "high"])),
+    (
+        "service_tier",
+        ParamKind::OneOf(&["auto", "default", "flex", "priority", "scale"]),
+    ),
+];
+
+const GEMINI_PARAMS: &[(&str, ParamKind)] = &[
+    ("temperature", ParamKind::Float(0.0, 2.0)),
+    ("max_tokens", ParamKind::UInt),
+    ("top_p", ParamKind::Float(0.0, 1.0)),
+    ("top_k", ParamKind::UInt),
+    ("seed", ParamKind::Int),
+    ("stop", ParamKind::Text),
+    ("response_mime_type", ParamKind::Text),
+    // -1 asks for a dynamic budget
+    ("think", ParamKind::Int),
+    ("thoughts", ParamKind::Bool),
+];
+
+const OLLAMA_PARAMS: &[(&str, ParamKind)] = &[
+    ("temperature", ParamKind::Float(0.0, 2.0)),
+    ("top_p", ParamKind::Float(0.0, 1.0)),
+    ("min_p", ParamKind::Float(0.0, 1.0)),
+    ("repeat_penalty", ParamKind::Float(0.0, 2.0)),
+    ("top_k", ParamKind::UInt),
+    ("num_ctx", ParamKind::UInt),
+    ("seed", ParamKind::Int),
+    ("max_tokens", ParamKind::Int),
+    ("num_predict", ParamKind::Int),
+    (
+        "think",
+        ParamKind::OneOf(&["true", "false", "1", "0", "low", "medium", "high"]),
+    ),
+];
+
+const CHAT_COMPLETIONS_PARAMS: &[(&str, ParamKind)] = &[
+    ("temperature", ParamKind::Float(0.0, 2.0)),
+    ("top_p", ParamKind::Float(0.0, 1.0)),
+    ("min_p", ParamKind::Float(0.0, 1.0)),
+    ("repeat_penalty", ParamKind::Float(0.0, 2.0)),
+    ("top_k", ParamKind::UInt),
+    ("seed", ParamKind::Int),
+    ("max_tokens", ParamKind::Int),
+    ("n_predict", ParamKind::Int),
+];
+
+/// Query parameters a provider type understands, or `None` if it isn't known here
+///
+/// Bedrock and Vertex AI don't take query parameters, so any parameter is rejected.
+pub fn param_schema(provider_kind: &str) -> Option<&'static [(&'static str, ParamKind)]> {
+    match provider_kind {
+        "anthropic" => Some(ANTHROPIC_PARAMS),
+        "openrouter" => Some(OPENROUTER_PARAMS),
+        "openai" | "azure-openai" => Some(OPENAI_PARAMS),
+        "gemini" | "google" => Some(GEMINI_PARAMS),
+        "ollama" => Some(OLLAMA_PARAMS),
+        "llamacpp" | "openai-compatible" => Some(CHAT_COMPLETIONS_PARAMS),
+        "bedrock" | "vertex" => Some(NO_PARAMS),
+        _ => None,
+    }
+}
+
+/// Check query parameters against a provider type's schema
+///
+/// Returns one error per unknown or malformed parameter, sorted by key.
+pub fn validate_params(provider_kind: &str, query_params: &HashMap<String, String>) -> Vec<ParamError> {
+    let Some(schema) = param_schema(provider_kind) else {
+        return Vec::new();
+    };
+
+    let mut keys: Vec<&String> = query_params.keys().collect();
+    keys.sort();
+    keys.into_iter()
+        .filter_map(|key| {
+            let value = &query_params[key];
+            let reason = match schema.iter().find(|(name, _)| *name == key.as_str()) {
+                Some((_, kind)) => kind.check(value).err()?,
+                None => {
+                    let known: Vec<&str> = schema.iter().map(|(name, _)| *name).collect();
+                    if known.is_empty() {
+                        format!("is not supported ({} takes no parameters)", provider_kind)
+                    } else {
+                        format!("is not a known {} parameter (expected one of {})", provider_kind, known.join(", "))
+                    }
+                }
+            };
+            Some(ParamError {
+                key: key.clone(),
+                value: value.clone(),
+                reason,
+            })
+        })
+        .collect()
+}
+
+/// Request-body field holding a canonical parameter in the client's format
+///
+/// Gemini fields are those inside `generationConfig`.
+fn policy_field(format: InboundFormat, key: &str, completion_tokens: bool) -> String {
+    match (format, key) {
+        (InboundFormat::OpenAI, "max_tokens") if completion_tokens => {
+            "max_completion_tokens".to_string()
+        }
+        (InboundFormat::Gemini, "max_tokens") => "maxOutputTokens".to_string(),
+        (InboundFormat::Gemini, "top_p") => "topP".to_string(),
+        (InboundFormat::Gemini, "top_k") => "topK".to_string(),
+        (InboundFormat::Gemini, "stop") => "stopSequences".to_string(),
+        _ => key.to_string(),
+    }
+}
+
+/// `value` limited to `[min, max]`, keeping integers integral; `None` if already inside
+fn clamp_value(value: &Value, [min, max]: [f64; 2]) -> Option<Value> {
+    let n = value.as_f64()?;
+    if min.is_nan() || max.is_nan() || min > max {
+        return None;
+    }
+    let clamped = n.clamp(min, max);
+    if clamped == n {
+        None
+    } else if value.is_f64() {
+        Some(Value::from(clamped))
+    } else {
+        Some(Value::from(clamped.round() as i64))
+    }
+}
+
+/// Apply an alias's defaults, forced values and clamps to a request body
+pub fn apply_param_policy(body: &mut Value, format: InboundFormat, policy: &ParamPolicy) {
+    let completion_tokens = body.get("max_completion_tokens").is_some();
+    let Some(obj) = body.as_object_mut() else {
+        return;
+    };
+    let fields = match format {
+        InboundFormat::Gemini => {
+            let key = if obj.contains_key("generation_config") {
+                "generation_config"
+            } else {
+                "generationConfig"
+            };
+            match obj
+                .entry(key)
+                .or_insert_with(|| Value::Object(Map::new()))
+                .as_object_mut()
+            {
+                Some(fields) => fields,
+                None => return,
+            }
+        }
+        _ => obj,
+    };
+    let field = |key: &str| policy_field(format, key, completion_tokens);
+
+    for (key, value) in &policy.defaults {
+        if fields.get(&field(key)).is_none_or(Value::is_null) {
+            fields.insert(field(key), value.clone());
+        }
+    }
+    for (key, value) in &policy.force {
+        fields.insert(field(key), value.clone());
+    }
+    for (key, bounds) in &policy.clamp {
+        if let Some(clamped) = fields.get(&field(key)).and_then(|v| clamp_value(v, *bounds)) {
+            tracing::debug!("Clamped {} to {}", key, clamped);
+            fields.insert(field(key), clamped);
+        }
+    }
+}
+
+/// [`apply_param_policy`] for a typed request, which is round-tripped through JSON
+///
+/// The request is returned unchanged if it has no policy or doesn't convert back.
+pub fn apply_param_policy_to<T: Serialize + DeserializeOwned>(
+    request: T,
+    format: InboundFormat,
+    policy: Option<&ParamPolicy>,
+) -> T {
+    let Some(policy) = policy.filter(|p| **p != ParamPolicy::default()) else {
+        return request;
+    };
+    let Ok(mut body) = serde_json::to_value(&request) else {
+        return request;
+    };
+    apply_param_policy(&mut body, format, policy);
+    match serde_json::from_value(body) {
+        Ok(updated) => updated,
+        Err(e) => {
+            tracing::warn!("Ignoring alias parameter policy that doesn't fit the request: {}", e);
+            request
+        }
+    }
+}
+
+/// A problem with the model-string parameters of an alias in `routing.models`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AliasParamProblem {
+    pub alias: String,
+    /// Target string as written in the alias
+    pub target: String,
+    pub error: String,
+}
+
+impl std::fmt::Display for AliasParamProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "alias '{}' → '{}': {}", self.alias, self.target, self.error)
+    }
+}
+
+/// Check the parameters of every alias target in `routing.models`
+///
+/// Returns one problem per bad parameter (or target that doesn't route), naming the alias
+/// and the offending target string as written.
+pub fn validate_alias_params(config: &Config) -> Vec<AliasParamProblem> {
+    let router = ModelRouter::new(config.clone());
+    let mut aliases: Vec<&String> = config.routing.models.keys().collect();
+    aliases.sort();
+
+    let mut problems = Vec::new();
+    for alias in aliases {
+        for target in config.routing.models[alias].targets() {
+            // Targets that are aliases themselves are checked under their own name
+            if config.routing.models.contains_key(target) {
+                continue;
+            }
+            let decision = match router.route_model(target) {
+                Ok(mut decisions) if !decisions.is_empty() => decisions.remove(0),
+                Ok(_) => continue,
+                Err(e) => {
+                    problems.push(AliasParamProblem {
+                        alias: alias.clone(),
+                        target: target.to_string(),
+                        error: e.to_string(),
+                    });
+                    continue;
+                }
+            };
+            let Some(params) = &decision.query_params else {
+                continue;
+            };
+            let kind = config.provider_kind(&decision.provider);
+            for error in validate_params(&kind, params) {
+                problems.push(AliasParamProblem {
+                    alias: alias.clone(),
+                    target: target.to_string(),
+                    error: error.to_string(),
+                });
+            }
+        }
+    }
+    problems
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -234,6 +768,16 @@ mod tests {
         assert!(config.include_thoughts);
     }
 
+    #[test]
+    fn test_gemini_dynamic_thinking_budget() {
+        let mut params = HashMap::new();
+        params.insert("think".to_string(), "-1".to_string());
+
+        let config = create_gemini_thinking_config(&params).unwrap();
+        assert_eq!(config.thinking_budget, -1);
+        assert!(!config.include_thoughts);
+    }
+
     #[test]
     fn test_openrouter_reasoning_config() {
         let mut params = HashMap::new();
@@ -328,4 +872,170 @@ mod tests {
         assert_eq!(modified_request.temperature, None);
         assert_eq!(modified_request.max_tokens, 4096); // Default from builder
     }
+
+    #[test]
+    fn test_ollama_parameter_mapping() {
+        let mut params = HashMap::new();
+        params.insert("num_ctx".to_string(), "32768".to_string());
+        params.insert("temperature".to_string(), "0.2".to_string());
+        params.insert("top_k".to_string(), "20".to_string());
+        params.insert("think".to_string(), "false".to_string());
+
+        let mut body = serde_json::json!({
+            "model": "qwen2.5-coder:32b",
+            "options": {"num_predict": 512, "temperature": 1.0}
+        });
+        apply_ollama_parameters(&mut body, &params);
+
+        assert_eq!(body["options"]["num_ctx"], 32768);
+        assert_eq!(body["options"]["temperature"], 0.2);
+        assert_eq!(body["options"]["top_k"], 20);
+        // Options not given as query params are kept
+        assert_eq!(body["options"]["num_predict"], 512);
+        assert_eq!(body["think"], false);
+    }
+
+    #[test]
+    fn test_chat_completions_parameter_mapping() {
+        let mut params = HashMap::new();
+        params.insert("top_k".to_string(), "40".to_string());
+        params.insert("min_p".to_string(), "0.05".to_string());
+        params.insert("seed".to_string(), "nope".to_string());
+
+        let mut body = serde_json::json!({"model": "local"});
+        apply_chat_completions_parameters(&mut body, &params);
+
+        assert_eq!(body["top_k"], 40);
+        assert_eq!(body["min_p"], 0.05);
+        assert!(body.get("seed").is_none());
+    }
+
+    #[test]
+    fn test_openai_parameter_mapping() {
+        let mut params = HashMap::new();
+        params.insert("effort".to_string(), "high".to_string());
+        params.insert("verbosity".to_string(), "low".to_string());
+        params.insert("service_tier".to_string(), "flex".to_string());
+        params.insert("seed".to_string(), "7".to_string());
+        params.insert("presence_penalty".to_string(), "0.5".to_string());
+        params.insert("max_tokens".to_string(), "4000".to_string());
+
+        let mut body = serde_json::json!({"model": "o3", "max_tokens": 100});
+        apply_openai_parameters(&mut body, &params);
+
+        assert_eq!(body["reasoning_effort"], "high");
+        assert_eq!(body["verbosity"], "low");
+        assert_eq!(body["service_tier"], "flex");
+        assert_eq!(body["seed"], 7);
+        assert_eq!(body["presence_penalty"], 0.5);
+        assert_eq!(body["max_completion_tokens"], 4000);
+        assert!(body.get("max_tokens").is_none());
+
+        let mut responses = serde_json::json!({"model": "o3", "reasoning": {"summary": "auto"}});
+        apply_openai_responses_parameters(&mut responses, &params);
+        assert_eq!(responses["reasoning"]["effort"], "high");
+        assert_eq!(responses["reasoning"]["summary"], "auto");
+        assert_eq!(responses["text"]["verbosity"], "low");
+        assert_eq!(responses["max_output_tokens"], 4000);
+        assert!(responses.get("seed").is_none());
+    }
+
+    #[test]
+    fn test_gemini_parameters_merge_into_generation_config() {
+        let mut params = HashMap::new();
+        params.insert("temperature".to_string(), "0.2".to_string());
+        params.insert("max_tokens".to_string(), "2048".to_string());
+        params.insert("stop".to_string(), "END,STOP".to_string());
+        params.insert("response_mime_type".to_string(), "application/json".to_string());
+        params.insert("think".to_string(), "1024".to_string());
+
+        let existing = gemini_ox::generate_content::GenerationConfig {
+            top_k: Some(20),
+            ..Default::default()
+        };
+        let config = apply_gemini_parameters(Some(existing), &params).unwrap();
+
+        assert_eq!(config.temperature, Some(0.2));
+        assert_eq!(config.max_output_tokens, Some(2048));
+        assert_eq!(config.top_k, Some(20));
+        assert_eq!(
+            config.stop_sequences,
+            Some(vec!["END".to_string(), "STOP".to_string()])
+        );
+        assert_eq!(config.response_mime_type.as_deref(), Some("application/json"));
+        assert_eq!(config.thinking_config.unwrap().thinking_budget, 1024);
+
+        assert!(apply_gemini_parameters(None, &HashMap::new()).is_none());
+    }
+
+    #[test]
+    fn test_param_validation() {
+        let mut params = HashMap::new();
+        params.insert("temperature".to_string(), "hot".to_string());
+        params.insert("effort".to_string(), "extreme".to_string());
+        params.insert("think".to_string(), "1000".to_string());
+
+        let errors = validate_params("openai", &params);
+        let keys: Vec<&str> = errors.iter().map(|e| e.key.as_str()).collect();
+        assert_eq!(keys, vec!["effort", "temperature", "think"]);
+        assert!(errors[0].reason.contains("one of"));
+        assert!(errors[1].reason.contains("number"));
+        assert!(errors[2].reason.contains("not a known openai parameter"));
+
+        assert_eq!(validate_params("anthropic", &params)[0].key, "effort");
+        assert!(validate_params("custom-type", &params).is_empty());
+        assert_eq!(validate_params("bedrock", &params).len(), 3);
+
+        let mut range = HashMap::new();
+        range.insert("temperature".to_string(), "1.5".to_string());
+        assert_eq!(validate_params("anthropic", &range)[0].reason, "must be between 0 and 1");
+        assert!(validate_params("openai", &range).is_empty());
+    }
+
+    #[test]
+    fn test_alias_param_validation() {
+        let mut config = Config::default();
+        config.routing.models.insert(
+            "fast".to_string(),
+            crate::config::ModelRoute::Multiple(vec![
+                "openai/gpt-5?effort=low".to_string(),
+                "anthropic/claude-sonnet-4?temperature=hot".to_string(),
+            ]),
+        );
+
+        let problems = validate_alias_params(&config);
+        assert_eq!(problems.len(), 1);
+        assert!(
+            problems[0]
+                .to_string()
+                .starts_with("alias 'fast' → 'anthropic/claude-sonnet-4?temperature=hot'")
+        );
+    }
+
+    #[test]
+    fn test_param_policy_defaults_force_clamp() {
+        let mut policy = ParamPolicy::default();
+        policy.defaults.insert("temperature".to_string(), serde_json::json!(0.2));
+        policy.defaults.insert("top_p".to_string(), serde_json::json!(0.9));
+        policy.force.insert("top_k".to_string(), serde_json::json!(40));
+        policy.clamp.insert("max_tokens".to_string(), [1.0, 16000.0]);
+
+        let mut body = serde_json::json!({"max_tokens": 64000, "top_p": 0.5, "top_k": 5});
+        apply_param_policy(&mut body, InboundFormat::Anthropic, &policy);
+        assert_eq!(body["temperature"], 0.2);
+        assert_eq!(body["top_p"], 0.5);
+        assert_eq!(body["top_k"], 40);
+        assert_eq!(body["max_tokens"], 16000);
+
+        let mut body = serde_json::json!({"max_completion_tokens": 0});
+        apply_param_policy(&mut body, InboundFormat::OpenAI, &policy);
+        assert_eq!(body["max_completion_tokens"], 1);
+        assert!(body.get("max_tokens").is_none());
+
+        let mut body = serde_json::json!({"contents": [], "generationConfig": {"maxOutputTokens": 20000}});
+        apply_param_policy(&mut body, InboundFormat::Gemini, &policy);
+        assert_eq!(body["generationConfig"]["maxOutputTokens"], 16000);
+        assert_eq!(body["generationConfig"]["topP"], 0.9);
+        assert_eq!(body["generationConfig"]["topK"], 40);
+    }
 }
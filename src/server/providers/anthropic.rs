@@ -5,6 +5,7 @@ use tokio::sync::Mutex;
 use tracing::info;
 
 use crate::auth::anthropic::AnthropicOAuth;
+use crate::auth::key_pool::{self, PooledKey};
 use crate::config::Config;
 use crate::error::PrismError;
 use crate::router::name_based::RoutingDecision;
@@ -19,7 +20,7 @@ pub async fn handle_direct_anthropic_request(
 ) -> Result<axum::response::Response, StatusCode> {
     let is_claude_code = super::auth::is_claude_code_request(&headers);
 
-    let anthropic_client = match create_anthropic_client(config, is_claude_code).await {
+    let (anthropic_client, key) = match create_anthropic_client(config, is_claude_code).await {
         Ok(client) => client,
         Err(e) => {
             return Err(error_handling::internal_error(
@@ -76,6 +77,9 @@ pub async fn handle_direct_anthropic_request(
                 .unwrap())
         }
         Err(e) => {
+            if let Some(key) = &key {
+                key.report_error(&e);
+            }
             let compacted_request =
                 super::super::error_handling::compact_request_for_logging(&anthropic_request);
             tracing::error!(
@@ -92,10 +96,12 @@ pub async fn handle_direct_anthropic_request(
 }
 
 /// Create Anthropic client with OAuth or API key authentication
+///
+/// Returns the pooled API key used, if any, so failures can be reported against it.
 pub async fn create_anthropic_client(
     config: Arc<Mutex<Config>>,
     prefer_oauth: bool,
-) -> Result<Anthropic, PrismError> {
+) -> Result<(Anthropic, Option<PooledKey>), PrismError> {
     if prefer_oauth {
         // Try OAuth authentication first for Claude Code
         let mut config_guard = config.lock().await;
@@ -107,7 +113,7 @@ pub async fn create_anthropic_client(
                             .await
                     {
                         info!("🔐 Anthropic → OAuth (subscription billing)");
-                        return Ok(Anthropic::builder().oauth_token(&access_token).build());
+                        return Ok((Anthropic::builder().oauth_token(&access_token).build(), None));
                     }
                 }
                 Err(e) => {
@@ -120,18 +126,14 @@ pub async fn create_anthropic_client(
         }
     }
 
-    // Fallback to API key authentication
-    if let Ok(api_key) = std::env::var("ANTHROPIC_API_KEY") {
-        info!("🔐 Anthropic → API key (pay-per-use billing)");
-        return Ok(Anthropic::builder().api_key(&api_key).build());
-    }
-
+    // Fallback to pooled API keys (ANTHROPIC_API_KEY, api_key, api_keys)
     let config_guard = config.lock().await;
-    if let Some(anthropic_provider) = config_guard.providers.get("anthropic")
-        && let Some(api_key) = &anthropic_provider.api_key
-    {
-        info!("🔐 Anthropic → API key via prism config (pay-per-use billing)");
-        return Ok(Anthropic::builder().api_key(api_key).build());
+    if let Some(key) = key_pool::acquire_api_key(&config_guard, "anthropic", "ANTHROPIC_API_KEY") {
+        info!(
+            "🔐 Anthropic → API key via {} (pay-per-use billing)",
+            key.label
+        );
+        return Ok((Anthropic::builder().api_key(&key.secret).build(), Some(key)));
     }
 
     Err(PrismError::Other(
@@ -0,0 +1,422 @@
+//! Shared handling for clouds that serve Anthropic's Messages API (Bedrock, Vertex AI).
+//!
+//! Requests are sent as Anthropic Messages JSON whatever the client spoke; responses and
+//! stream events are passed through to Anthropic clients and converted for the others.
+
+use axum::body::{Body, Bytes};
+use futures_util::StreamExt;
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+
+use crate::server::providers::local::{
+    self, DeltaParser, Finish, InboundFormat, LineStream, LocalChat, LocalDelta, LocalResponse,
+    LocalToolCall,
+};
+
+/// Default output limit when the client didn't send one (Anthropic requires `max_tokens`)
+const DEFAULT_MAX_TOKENS: u64 = 4096;
+
+/// Request for an Anthropic Messages upstream
+pub enum MessagesInput {
+    /// Already an Anthropic request; forwarded as-is
+    Anthropic(anthropic_ox::ChatRequest),
+    /// Converted from another client format
+    Local(LocalChat),
+}
+
+impl MessagesInput {
+    /// Anthropic Messages JSON body (still including `model` and `stream`)
+    pub fn into_body(self) -> Value {
+        match self {
+            MessagesInput::Anthropic(request) => {
+                serde_json::to_value(&request).unwrap_or_else(|_| json!({}))
+            }
+            MessagesInput::Local(chat) => messages_body(&chat),
+        }
+    }
+}
+
+/// Append content blocks to the last message if it has the same role, else start a new one
+///
+/// Anthropic requires alternating roles, and tool results travel in user messages.
+fn push_blocks(messages: &mut Vec<Value>, role: &str, blocks: Vec<Value>) {
+    if blocks.is_empty() {
+        return;
+    }
+    if let Some(last) = messages.last_mut()
+        && last["role"] == role
+        && let Some(content) = last["content"].as_array_mut()
+    {
+        content.extend(blocks);
+        return;
+    }
+    messages.push(json!({"role": role, "content": blocks}));
+}
+
+/// Build an Anthropic Messages body from a normalised chat request
+pub fn messages_body(chat: &LocalChat) -> Value {
+    let mut system = Vec::new();
+    let mut messages = Vec::new();
+
+    for message in &chat.messages {
+        let text = message["content"].as_str().unwrap_or_default();
+        match message["role"].as_str().unwrap_or("user") {
+            "system" => system.push(text.to_string()),
+            "assistant" => {
+                let mut blocks = Vec::new();
+                if !text.is_empty() {
+                    blocks.push(json!({"type": "text", "text": text}));
+                }
+                for call in message["tool_calls"].as_array().into_iter().flatten() {
+                    blocks.push(json!({
+                        "type": "tool_use",
+                        "id": call["id"],
+                        "name": call["function"]["name"],
+                        "input": call["function"]["arguments"],
+                    }));
+                }
+                push_blocks(&mut messages, "assistant", blocks);
+            }
+            "tool" => push_blocks(
+                &mut messages,
+                "user",
+                vec![json!({
+                    "type": "tool_result",
+                    "tool_use_id": message["tool_call_id"],
+                    "content": text,
+                })],
+            ),
+            _ => {
+                let mut blocks = Vec::new();
+                for image in message["images"].as_array().into_iter().flatten() {
+                    let Some(url) = image.as_str() else { continue };
+                    let media_type = url
+                        .strip_prefix("data:")
+                        .and_then(|rest| rest.split_once(';'))
+                        .map(|(media_type, _)| media_type)
+                        .unwrap_or("image/png");
+                    blocks.push(json!({
+                        "type": "image",
+                        "source": {
+                            "type": "base64",
+                            "media_type": media_type,
+                            "data": local::base64_payload(url),
+                        }
+                    }));
+                }
+                if !text.is_empty() {
+                    blocks.push(json!({"type": "text", "text": text}));
+                }
+                push_blocks(&mut messages, "user", blocks);
+            }
+        }
+    }
+
+    let mut body = json!({
+        "messages": messages,
+        "max_tokens": chat.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+        "stream": chat.stream,
+    });
+    if !system.is_empty() {
+        body["system"] = json!(system.join("\n"));
+    }
+    if !chat.tools.is_empty() {
+        body["tools"] = chat
+            .tools
+            .iter()
+            .map(|tool| {
+                let function = &tool["function"];
+                json!({
+                    "name": function["name"],
+                    "description": function["description"],
+                    "input_schema": function
+                        .get("parameters")
+                        .cloned()
+                        .unwrap_or_else(|| json!({"type": "object"})),
+                })
+            })
+            .collect();
+    }
+    if let Some(v) = chat.temperature {
+        body["temperature"] = json!(v);
+    }
+    if let Some(v) = chat.top_p {
+        body["top_p"] = json!(v);
+    }
+    if let Some(v) = chat.top_k {
+        body["top_k"] = json!(v);
+    }
+    if !chat.stop.is_empty() {
+        body["stop_sequences"] = json!(chat.stop);
+    }
+    body
+}
+
+fn finish_from_stop_reason(reason: Option<&str>) -> Finish {
+    match reason {
+        Some("max_tokens") => Finish::Length,
+        Some("tool_use") => Finish::ToolCalls,
+        _ => Finish::Stop,
+    }
+}
+
+/// Parse an Anthropic Messages response
+pub fn parse_response(value: &Value) -> LocalResponse {
+    let mut response = LocalResponse {
+        finish: finish_from_stop_reason(value["stop_reason"].as_str()),
+        input_tokens: value["usage"]["input_tokens"].as_u64().unwrap_or(0),
+        output_tokens: value["usage"]["output_tokens"].as_u64().unwrap_or(0),
+        ..Default::default()
+    };
+    for block in value["content"].as_array().into_iter().flatten() {
+        match block["type"].as_str() {
+            Some("text") => response
+                .text
+                .push_str(block["text"].as_str().unwrap_or_default()),
+            Some("thinking") => {
+                response
+                    .thinking
+                    .get_or_insert_with(String::new)
+                    .push_str(block["thinking"].as_str().unwrap_or_default());
+            }
+            Some("tool_use") => response.tool_calls.push(LocalToolCall {
+                id: block["id"].as_str().unwrap_or_default().to_string(),
+                name: block["name"].as_str().unwrap_or_default().to_string(),
+                arguments: block["input"].clone(),
+            }),
+            _ => {}
+        }
+    }
+    response
+}
+
+/// Render an Anthropic Messages response for the client
+pub fn render_response(value: Value, format: InboundFormat, model: &str) -> Value {
+    match format {
+        InboundFormat::Anthropic => value,
+        _ => parse_response(&value).render(format, model),
+    }
+}
+
+#[derive(Default)]
+struct PendingToolUse {
+    id: String,
+    name: String,
+    input: String,
+}
+
+/// Parses Anthropic stream events (raw JSON or SSE `data:` lines) into model deltas
+#[derive(Default)]
+pub struct AnthropicEventParser {
+    tool_uses: BTreeMap<u64, PendingToolUse>,
+    input_tokens: u64,
+    output_tokens: u64,
+    stop_reason: Option<String>,
+}
+
+impl DeltaParser for AnthropicEventParser {
+    fn push_line(&mut self, line: &str) -> Vec<LocalDelta> {
+        let data = match line.strip_prefix("data:") {
+            Some(data) => data.trim(),
+            None if line.starts_with('{') => line,
+            // `event:` lines repeat the type that is also in the data
+            None => return Vec::new(),
+        };
+        let Ok(event) = serde_json::from_str::<Value>(data) else {
+            return Vec::new();
+        };
+
+        let index = event["index"].as_u64().unwrap_or(0);
+        match event["type"].as_str() {
+            Some("message_start") => {
+                let usage = &event["message"]["usage"];
+                self.input_tokens = usage["input_tokens"].as_u64().unwrap_or(0);
+                Vec::new()
+            }
+            Some("content_block_start") if event["content_block"]["type"] == "tool_use" => {
+                let block = &event["content_block"];
+                self.tool_uses.insert(
+                    index,
+                    PendingToolUse {
+                        id: block["id"].as_str().unwrap_or_default().to_string(),
+                        name: block["name"].as_str().unwrap_or_default().to_string(),
+                        input: String::new(),
+                    },
+                );
+                Vec::new()
+            }
+            Some("content_block_delta") => {
+                let delta = &event["delta"];
+                match delta["type"].as_str() {
+                    Some("text_delta") => vec![LocalDelta::Text(
+                        delta["text"].as_str().unwrap_or_default().to_string(),
+                    )],
+                    Some("thinking_delta") => vec![LocalDelta::Thinking(
+                        delta["thinking"].as_str().unwrap_or_default().to_string(),
+                    )],
+                    Some("input_json_delta") => {
+                        if let Some(pending) = self.tool_uses.get_mut(&index) {
+                            pending
+                                .input
+                                .push_str(delta["partial_json"].as_str().unwrap_or_default());
+                        }
+                        Vec::new()
+                    }
+                    _ => Vec::new(),
+                }
+            }
+            Some("content_block_stop") => match self.tool_uses.remove(&index) {
+                Some(pending) => vec![LocalDelta::ToolCall(LocalToolCall {
+                    id: pending.id,
+                    name: pending.name,
+                    arguments: serde_json::from_str(&pending.input).unwrap_or_else(|_| json!({})),
+                })],
+                None => Vec::new(),
+            },
+            Some("message_delta") => {
+                if let Some(reason) = event["delta"]["stop_reason"].as_str() {
+                    self.stop_reason = Some(reason.to_string());
+                }
+                if let Some(tokens) = event["usage"]["output_tokens"].as_u64() {
+                    self.output_tokens = tokens;
+                }
+                Vec::new()
+            }
+            Some("message_stop") => vec![LocalDelta::Finish {
+                finish: finish_from_stop_reason(self.stop_reason.as_deref()),
+                input_tokens: self.input_tokens,
+                output_tokens: self.output_tokens,
+            }],
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Re-emit Anthropic stream event JSON as SSE for Anthropic clients
+pub fn event_passthrough(events: LineStream) -> Body {
+    let sse = events.map(|event| {
+        let text = match event {
+            Ok(json) => {
+                let name = serde_json::from_str::<Value>(&json)
+                    .ok()
+                    .and_then(|v| v["type"].as_str().map(str::to_string))
+                    .unwrap_or_else(|| "message".to_string());
+                format!("event: {}\ndata: {}\n\n", name, json)
+            }
+            Err(e) => {
+                tracing::error!("Upstream stream error: {}", e);
+                format!(
+                    "event: error\ndata: {}\n\n",
+                    json!({"type": "error", "error": {"type": "api_error", "message": e}})
+                )
+            }
+        };
+        Ok::<_, std::convert::Infallible>(Bytes::from(text))
+    });
+    Body::from_stream(sse)
+}
+
+/// Reduce SSE lines to the JSON of their `data:` fields (drops `event:` lines)
+pub fn sse_data(lines: LineStream) -> LineStream {
+    lines
+        .filter_map(|line| async move {
+            match line {
+                Ok(line) => line
+                    .strip_prefix("data:")
+                    .map(|data| Ok(data.trim().to_string())),
+                Err(e) => Some(Err(e)),
+            }
+        })
+        .boxed()
+}
+
+/// Stream body for a client given Anthropic stream events (one JSON object per item)
+pub fn stream_events(events: LineStream, format: InboundFormat, model: &str) -> Body {
+    match format {
+        InboundFormat::Anthropic => event_passthrough(events),
+        _ => local::stream_body(events, AnthropicEventParser::default(), format, model),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_messages_body_merges_tool_results() {
+        let chat = LocalChat::from_openai_value(&json!({
+            "model": "claude",
+            "messages": [
+                {"role": "system", "content": "sys"},
+                {"role": "user", "content": "run both"},
+                {"role": "assistant", "content": null, "tool_calls": [
+                    {"id": "a", "type": "function", "function": {"name": "x", "arguments": "{}"}},
+                    {"id": "b", "type": "function", "function": {"name": "y", "arguments": "{\"n\":1}"}}
+                ]},
+                {"role": "tool", "tool_call_id": "a", "content": "1"},
+                {"role": "tool", "tool_call_id": "b", "content": "2"}
+            ]
+        }));
+
+        let body = messages_body(&chat);
+        assert_eq!(body["system"], "sys");
+        assert_eq!(body["max_tokens"], 4096);
+        let messages = body["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[1]["content"][1]["input"]["n"], 1);
+        assert_eq!(messages[2]["role"], "user");
+        assert_eq!(messages[2]["content"].as_array().unwrap().len(), 2);
+        assert_eq!(messages[2]["content"][1]["tool_use_id"], "b");
+    }
+
+    #[test]
+    fn test_event_parser_builds_tool_call() {
+        let mut parser = AnthropicEventParser::default();
+        let events = [
+            r#"{"type":"message_start","message":{"usage":{"input_tokens":9}}}"#,
+            r#"{"type":"content_block_start","index":0,"content_block":{"type":"tool_use","id":"toolu_1","name":"ls","input":{}}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"input_json_delta","partial_json":"{\"path\":"}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"input_json_delta","partial_json":"\".\"}"}}"#,
+            r#"{"type":"content_block_stop","index":0}"#,
+            r#"{"type":"message_delta","delta":{"stop_reason":"tool_use"},"usage":{"output_tokens":4}}"#,
+            "event: message_stop",
+            r#"data: {"type":"message_stop"}"#,
+        ];
+        let deltas: Vec<LocalDelta> = events
+            .iter()
+            .flat_map(|line| parser.push_line(line))
+            .collect();
+
+        assert_eq!(
+            deltas,
+            vec![
+                LocalDelta::ToolCall(LocalToolCall {
+                    id: "toolu_1".to_string(),
+                    name: "ls".to_string(),
+                    arguments: json!({"path": "."}),
+                }),
+                LocalDelta::Finish {
+                    finish: Finish::ToolCalls,
+                    input_tokens: 9,
+                    output_tokens: 4,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_response_to_openai() {
+        let value = json!({
+            "content": [
+                {"type": "thinking", "thinking": "hmm", "signature": "s"},
+                {"type": "text", "text": "Done"}
+            ],
+            "stop_reason": "max_tokens",
+            "usage": {"input_tokens": 5, "output_tokens": 6}
+        });
+        let openai = render_response(value, InboundFormat::OpenAI, "claude");
+        assert_eq!(openai["choices"][0]["message"]["content"], "Done");
+        assert_eq!(openai["choices"][0]["message"]["reasoning_content"], "hmm");
+        assert_eq!(openai["choices"][0]["finish_reason"], "length");
+    }
+}
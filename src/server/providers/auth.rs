@@ -23,10 +23,72 @@ pub fn is_claude_code_request(headers: &HeaderMap) -> bool {
     false
 }
 
+/// Extract an explicit conversation/session identifier from request headers
+pub fn session_id_from_headers(headers: &HeaderMap) -> Option<String> {
+    ["x-prism-session", "x-session-id"]
+        .iter()
+        .filter_map(|name| headers.get(*name))
+        .filter_map(|value| value.to_str().ok())
+        .map(str::trim)
+        .find(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+/// Pool key under which Anthropic OAuth accounts are tracked
+const ANTHROPIC_OAUTH_POOL: &str = "anthropic-oauth";
+
+/// Pick an Anthropic OAuth credential set for this request
+///
+/// The startup-cached token is always a candidate; each name in the anthropic provider's
+/// `oauth_accounts` adds the token stored in `[auth.<name>]`.
+pub async fn select_anthropic_oauth(
+    cached: &crate::auth::AuthMethod,
+    config: &Arc<Mutex<Config>>,
+) -> crate::auth::AuthMethod {
+    use crate::auth::key_pool::{self, Credential};
+
+    let crate::auth::AuthMethod::OAuth { source, token } = cached else {
+        return cached.clone();
+    };
+
+    let config_guard = config.lock().await;
+    let provider = config_guard.providers.get("anthropic");
+    let accounts = provider.map(|p| p.oauth_accounts.as_slice()).unwrap_or_default();
+    if accounts.is_empty() {
+        return cached.clone();
+    }
+
+    let mut candidates = vec![Credential::new(source.clone(), token.clone())];
+    for name in accounts {
+        match config_guard
+            .auth
+            .get(name)
+            .and_then(|a| a.oauth_access_token.clone())
+        {
+            Some(token) => candidates.push(Credential::new(format!("auth.{}", name), token)),
+            None => tracing::warn!(
+                target: "prism::keys",
+                "OAuth account '{}' has no access token in [auth.{}]",
+                name,
+                name
+            ),
+        }
+    }
+
+    match key_pool::acquire_for_provider(provider, ANTHROPIC_OAUTH_POOL, &candidates) {
+        Some(key) => crate::auth::AuthMethod::OAuth {
+            source: key.label,
+            token: key.secret,
+        },
+        None => cached.clone(),
+    }
+}
+
 /// Handle OAuth authentication with automatic token refresh on failure
 pub async fn handle_oauth_request(
     auth_method: &crate::auth::AuthMethod,
     config: Arc<Mutex<Config>>,
+    auth_cache: &crate::auth::AuthCache,
     chat_request: anthropic_ox::ChatRequest,
     routing_decision: crate::router::name_based::RoutingDecision,
     parts: axum::http::request::Parts,
@@ -191,7 +253,9 @@ pub async fn handle_oauth_request(
 
                             match AnthropicOAuth::refresh_token(&mut auth_config).await {
                                 Ok(()) => {
-                                    if let Some(new_token) = auth_config.oauth_access_token {
+                                    if let Some(new_token) = auth_config.oauth_access_token.clone() {
+                                        publish_refreshed_tokens(&config, auth_cache, auth_config)
+                                            .await;
                                         oauth_token = new_token;
                                         tracing::info!(
                                             "Successfully refreshed OAuth token, retrying request"
@@ -216,6 +280,10 @@ pub async fn handle_oauth_request(
                         );
                     tracing::error!("Failed OAuth request (compacted): {}", compacted_request);
 
+                    if let crate::auth::AuthMethod::OAuth { source, .. } = auth_method {
+                        crate::auth::key_pool::report_error(ANTHROPIC_OAUTH_POOL, source, &e);
+                    }
+
                     return Err(error_handling::internal_error(
                         "Anthropic OAuth request failed",
                         &e,
@@ -232,6 +300,36 @@ pub async fn handle_oauth_request(
     ))
 }
 
+/// Store refreshed Anthropic tokens in the config and publish them to the auth cache
+///
+/// Refresh tokens rotate, so the config is saved right away.
+async fn publish_refreshed_tokens(
+    config: &Arc<Mutex<Config>>,
+    auth_cache: &crate::auth::AuthCache,
+    auth_config: crate::config::AuthConfig,
+) {
+    if let Some(token) = auth_config.oauth_access_token.clone() {
+        auth_cache.set(
+            crate::auth::OAuthProvider::Anthropic,
+            crate::auth::AuthMethod::OAuth {
+                source: crate::auth::CONFIG_SOURCE.to_string(),
+                token,
+            },
+        );
+    }
+
+    let mut config_guard = config.lock().await;
+    let Some(provider) = config_guard.providers.get_mut("anthropic") else {
+        return;
+    };
+    provider.auth = auth_config;
+    let config_to_save = config_guard.clone();
+    drop(config_guard);
+    if let Err(e) = config_to_save.save() {
+        tracing::error!("Failed to save refreshed tokens to config: {}", e);
+    }
+}
+
 /// Transform anthropic-beta header to include OAuth beta flag
 fn transform_anthropic_beta_header(existing_beta: Option<&str>) -> String {
     match existing_beta {
@@ -0,0 +1,226 @@
+//! Azure OpenAI provider (`type = "azure-openai"`).
+//!
+//! Uses the same Chat Completions and Responses paths as the OpenAI provider, with
+//! Azure URLs (`/openai/deployments/<deployment>/...?api-version=...`), `api-key`
+//! header auth and Azure error bodies rewritten to the client's error format.
+
+use axum::http::{HeaderMap, StatusCode};
+use serde_json::Value;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::auth::key_pool;
+use crate::config::{Config, ProviderConfig};
+use crate::router::name_based::RoutingDecision;
+use crate::server::error_handling::{self, ApiError};
+use crate::server::providers::openai::{self, ApiFlavor, OpenAITarget};
+
+/// Used when the provider doesn't set `api_version`; supports Chat Completions and Responses
+pub const DEFAULT_API_VERSION: &str = "2025-04-01-preview";
+
+/// Resource base URL: `endpoint` if set, else derived from `resource`
+pub fn base_url(provider: &ProviderConfig) -> Option<String> {
+    if !provider.endpoint.is_empty() {
+        return Some(provider.endpoint.trim_end_matches('/').to_string());
+    }
+    provider
+        .resource
+        .as_ref()
+        .map(|resource| format!("https://{}.openai.azure.com", resource))
+}
+
+/// Deployment serving `model`
+pub fn deployment_for(provider: &ProviderConfig, model: &str) -> String {
+    provider
+        .deployments
+        .get(model)
+        .cloned()
+        .unwrap_or_else(|| model.to_string())
+}
+
+pub fn chat_url(base: &str, deployment: &str, api_version: &str) -> String {
+    format!(
+        "{}/openai/deployments/{}/chat/completions?api-version={}",
+        base, deployment, api_version
+    )
+}
+
+pub fn responses_url(base: &str, api_version: &str) -> String {
+    format!("{}/openai/responses?api-version={}", base, api_version)
+}
+
+/// Read an Azure error body into an [`ApiError`]
+///
+/// Azure puts the useful code in `error.code` (e.g. `DeploymentNotFound`) and content
+/// filter details in `error.innererror`; the client gets them in its own error format.
+pub fn normalize_error(status: StatusCode, text: &str, deployment: &str) -> ApiError {
+    let mut error = ApiError::from_upstream(status, "Azure OpenAI", text);
+    let parsed: Value = serde_json::from_str(text).unwrap_or(Value::Null);
+    let inner_code = parsed["error"]["innererror"]["code"].as_str();
+    if error.code.is_none() {
+        error.code = inner_code.map(str::to_string);
+    }
+
+    if error.code.as_deref() == Some("content_filter")
+        || inner_code == Some("ResponsibleAIPolicyViolation")
+    {
+        error.code = Some("content_filter".to_string());
+    }
+    if error.code.as_deref() == Some("DeploymentNotFound") {
+        error.message = format!(
+            "{} (deployment '{}'; map the model under [providers.<name>.deployments])",
+            error.message, deployment
+        );
+    }
+    error
+}
+
+async fn azure_target(
+    config: Arc<Mutex<Config>>,
+    routing_decision: &RoutingDecision,
+) -> Result<OpenAITarget, StatusCode> {
+    let cfg = config.lock().await;
+    let Some(provider) = cfg.providers.get(&routing_decision.provider) else {
+        return Err(error_handling::internal_error(
+            "Azure OpenAI provider not configured",
+            &routing_decision.provider,
+        ));
+    };
+    let Some(base) = base_url(provider) else {
+        return Err(error_handling::internal_error(
+            "Azure OpenAI provider needs `resource` or `endpoint`",
+            &routing_decision.provider,
+        ));
+    };
+    let deployment = deployment_for(provider, &routing_decision.model);
+    let provider_api = provider.api;
+    let quirks = provider.quirks.clone();
+    let api_version = provider
+        .api_version
+        .clone()
+        .unwrap_or_else(|| DEFAULT_API_VERSION.to_string());
+
+    let Some(key) =
+        key_pool::acquire_api_key(&cfg, &routing_decision.provider, "AZURE_OPENAI_API_KEY")
+    else {
+        return Err(error_handling::unauthorized(
+            "No Azure OpenAI API key (set api_key or AZURE_OPENAI_API_KEY)",
+        ));
+    };
+    drop(cfg);
+
+    tracing::info!(
+        "☁️ Azure OpenAI → {} (deployment {}, api-version {})",
+        routing_decision.model,
+        deployment,
+        api_version
+    );
+
+    Ok(OpenAITarget {
+        name: "Azure OpenAI".to_string(),
+        flavor: ApiFlavor::Azure,
+        chat_url: chat_url(&base, &deployment, &api_version),
+        responses_url: responses_url(&base, &api_version),
+        auth_header: ("api-key", key.secret.clone()),
+        model: deployment,
+        key: Some(key),
+        api: provider_api,
+        quirks,
+        query_params: routing_decision.query_params.clone().unwrap_or_default(),
+    })
+}
+
+/// Send an OpenAI-format request to an Azure OpenAI deployment
+pub async fn handle_azure_request_from_openai(
+    config: Arc<Mutex<Config>>,
+    openai_request: openai_ox::request::ChatRequest,
+    routing_decision: RoutingDecision,
+    _headers: HeaderMap,
+) -> Result<axum::response::Response, StatusCode> {
+    let target = azure_target(config, &routing_decision).await?;
+    openai::send_chat_completions(&target, openai_request).await
+}
+
+/// Send an Anthropic-format request to Azure OpenAI (Responses API unless `api = "chat"`)
+pub async fn handle_azure_request_from_anthropic(
+    config: Arc<Mutex<Config>>,
+    anthropic_request: anthropic_ox::ChatRequest,
+    routing_decision: RoutingDecision,
+    _headers: HeaderMap,
+) -> Result<axum::response::Response, StatusCode> {
+    let target = azure_target(config, &routing_decision).await?;
+    openai::send_from_anthropic(&target, anthropic_request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::providers::local::InboundFormat;
+
+    fn provider(resource: Option<&str>, endpoint: &str) -> ProviderConfig {
+        let toml_str = format!(
+            "type = \"azure-openai\"\nendpoint = \"{}\"\n[deployments]\n\"gpt-4o\" = \"prod-gpt4o\"\n",
+            endpoint
+        );
+        let mut provider: ProviderConfig = toml::from_str(&toml_str).unwrap();
+        provider.resource = resource.map(str::to_string);
+        provider
+    }
+
+    #[test]
+    fn test_urls_from_resource() {
+        let provider = provider(Some("contoso"), "");
+        let base = base_url(&provider).unwrap();
+        assert_eq!(base, "https://contoso.openai.azure.com");
+        assert_eq!(
+            chat_url(&base, &deployment_for(&provider, "gpt-4o"), "2024-10-21"),
+            "https://contoso.openai.azure.com/openai/deployments/prod-gpt4o/chat/completions?api-version=2024-10-21"
+        );
+        assert_eq!(deployment_for(&provider, "o3-mini"), "o3-mini");
+    }
+
+    #[test]
+    fn test_endpoint_overrides_resource() {
+        let provider = provider(Some("contoso"), "http://127.0.0.1:9000/");
+        assert_eq!(base_url(&provider).unwrap(), "http://127.0.0.1:9000");
+    }
+
+    #[test]
+    fn test_normalize_deployment_not_found() {
+        let body = r#"{"error":{"code":"DeploymentNotFound","message":"The API deployment for this resource does not exist."}}"#;
+        let error = normalize_error(StatusCode::NOT_FOUND, body, "prod-gpt4o")
+            .to_value(InboundFormat::OpenAI);
+        assert_eq!(error["error"]["type"], "not_found_error");
+        assert_eq!(error["error"]["code"], "DeploymentNotFound");
+        assert!(
+            error["error"]["message"]
+                .as_str()
+                .unwrap()
+                .contains("prod-gpt4o")
+        );
+    }
+
+    #[test]
+    fn test_normalize_content_filter() {
+        let body = r#"{"error":{"code":"content_filter","message":"filtered","innererror":{"code":"ResponsibleAIPolicyViolation"}}}"#;
+        let error = normalize_error(StatusCode::BAD_REQUEST, body, "prod-gpt4o")
+            .to_value(InboundFormat::OpenAI);
+        assert_eq!(error["error"]["type"], "invalid_request_error");
+        assert_eq!(error["error"]["code"], "content_filter");
+    }
+
+    #[test]
+    fn test_normalize_error_in_anthropic_format() {
+        let body = r#"{"error":{"code":"DeploymentNotFound","message":"The API deployment for this resource does not exist."}}"#;
+        let error = normalize_error(StatusCode::NOT_FOUND, body, "prod-gpt4o")
+            .to_value(InboundFormat::Anthropic);
+        assert_eq!(error["type"], "error");
+        assert_eq!(error["error"]["type"], "not_found_error");
+        assert!(
+            error["error"]["message"]
+                .as_str()
+                .unwrap()
+                .contains("prod-gpt4o")
+        );
+    }
+}
@@ -0,0 +1,500 @@
+//! AWS Bedrock provider for Anthropic models (`type = "bedrock"`).
+//!
+//! Requests are Anthropic Messages bodies sent to `invoke` or
+//! `invoke-with-response-stream`, signed with SigV4. Streaming responses use AWS's
+//! binary event-stream framing; each `chunk` event wraps one Anthropic stream event.
+
+use axum::http::StatusCode;
+use base64::Engine;
+use futures_util::Stream;
+use futures_util::stream::{self, StreamExt};
+use serde_json::{Value, json};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::auth::aws::{self, AwsCredentials, SignableRequest};
+use crate::config::Config;
+use crate::router::name_based::RoutingDecision;
+use crate::server::error_handling::{self, ApiError};
+use crate::server::providers::anthropic_upstream::{self, MessagesInput};
+use crate::server::providers::local::{self, InboundFormat, LineStream};
+
+const BEDROCK_ANTHROPIC_VERSION: &str = "bedrock-2023-05-31";
+
+/// Short names accepted without a `model_ids` entry
+const BUILTIN_MODEL_IDS: &[(&str, &str)] = &[
+    (
+        "anthropic.claude-opus-4-1",
+        "anthropic.claude-opus-4-1-20250805-v1:0",
+    ),
+    (
+        "anthropic.claude-opus-4",
+        "anthropic.claude-opus-4-20250514-v1:0",
+    ),
+    (
+        "anthropic.claude-sonnet-4",
+        "anthropic.claude-sonnet-4-20250514-v1:0",
+    ),
+    (
+        "anthropic.claude-3-7-sonnet",
+        "anthropic.claude-3-7-sonnet-20250219-v1:0",
+    ),
+    (
+        "anthropic.claude-3-5-haiku",
+        "anthropic.claude-3-5-haiku-20241022-v1:0",
+    ),
+];
+
+/// Bedrock model ID for a requested model: config mapping, then built-in short names
+pub fn model_id(mapping: &rustc_hash::FxHashMap<String, String>, model: &str) -> String {
+    if let Some(id) = mapping.get(model) {
+        return id.clone();
+    }
+    BUILTIN_MODEL_IDS
+        .iter()
+        .find(|(short, _)| *short == model)
+        .map(|(_, id)| id.to_string())
+        .unwrap_or_else(|| model.to_string())
+}
+
+/// Anthropic Messages body as Bedrock expects it: no `model`/`stream`, plus `anthropic_version`
+pub fn invoke_body(mut body: Value) -> Value {
+    if let Some(map) = body.as_object_mut() {
+        map.remove("model");
+        map.remove("stream");
+        map.remove("metadata");
+        map.retain(|_, v| !v.is_null());
+        map.insert(
+            "anthropic_version".to_string(),
+            json!(BEDROCK_ANTHROPIC_VERSION),
+        );
+    }
+    body
+}
+
+/// CRC-32 (IEEE) as used by the event-stream prelude and message checksums
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+/// One decoded event-stream message; only string headers are kept
+#[derive(Debug, PartialEq)]
+pub struct Frame {
+    pub headers: HashMap<String, String>,
+    pub payload: Vec<u8>,
+}
+
+fn read_u16(bytes: &[u8], at: usize) -> Result<usize, String> {
+    bytes
+        .get(at..at + 2)
+        .map(|b| u16::from_be_bytes([b[0], b[1]]) as usize)
+        .ok_or_else(|| "truncated event-stream header".to_string())
+}
+
+fn parse_headers(mut bytes: &[u8]) -> Result<HashMap<String, String>, String> {
+    let mut headers = HashMap::new();
+    while !bytes.is_empty() {
+        let name_len = bytes[0] as usize;
+        let name = bytes
+            .get(1..1 + name_len)
+            .ok_or("truncated event-stream header name")?;
+        let name = String::from_utf8_lossy(name).to_string();
+        let mut at = 1 + name_len;
+        let value_type = *bytes.get(at).ok_or("missing event-stream header type")?;
+        at += 1;
+
+        let value_len = match value_type {
+            0 | 1 => 0,
+            2 => 1,
+            3 => 2,
+            4 => 4,
+            5 | 8 => 8,
+            9 => 16,
+            6 | 7 => {
+                let len = read_u16(bytes, at)?;
+                at += 2;
+                len
+            }
+            other => return Err(format!("unknown event-stream header type {}", other)),
+        };
+        let value = bytes
+            .get(at..at + value_len)
+            .ok_or("truncated event-stream header value")?;
+        if value_type == 7 {
+            headers.insert(name, String::from_utf8_lossy(value).to_string());
+        }
+        bytes = &bytes[at + value_len..];
+    }
+    Ok(headers)
+}
+
+/// Take one complete frame off the front of `buffer`; `Ok(None)` means more bytes are needed
+pub fn decode_frame(buffer: &mut Vec<u8>) -> Result<Option<Frame>, String> {
+    if buffer.len() < 12 {
+        return Ok(None);
+    }
+    let word = |at: usize| {
+        u32::from_be_bytes([buffer[at], buffer[at + 1], buffer[at + 2], buffer[at + 3]])
+    };
+    let total_len = word(0) as usize;
+    let headers_len = word(4) as usize;
+    if crc32(&buffer[..8]) != word(8) {
+        return Err("event-stream prelude checksum mismatch".to_string());
+    }
+    if total_len < 16 + headers_len {
+        return Err(format!("invalid event-stream frame length {}", total_len));
+    }
+    if buffer.len() < total_len {
+        return Ok(None);
+    }
+    if crc32(&buffer[..total_len - 4]) != word(total_len - 4) {
+        return Err("event-stream message checksum mismatch".to_string());
+    }
+
+    let headers = parse_headers(&buffer[12..12 + headers_len])?;
+    let payload = buffer[12 + headers_len..total_len - 4].to_vec();
+    buffer.drain(..total_len);
+    Ok(Some(Frame { headers, payload }))
+}
+
+/// The Anthropic event JSON carried by a frame, or the error it reports
+fn frame_event(frame: Frame) -> Option<Result<String, String>> {
+    let header = |name: &str| frame.headers.get(name).map(String::as_str);
+    match header(":message-type") {
+        Some("exception") | Some("error") => {
+            let kind = header(":exception-type")
+                .or_else(|| header(":error-code"))
+                .unwrap_or("error");
+            Some(Err(format!(
+                "{}: {}",
+                kind,
+                String::from_utf8_lossy(&frame.payload)
+            )))
+        }
+        _ if header(":event-type") == Some("chunk") => {
+            let payload: Value = serde_json::from_slice(&frame.payload).ok()?;
+            let bytes = base64::engine::general_purpose::STANDARD
+                .decode(payload["bytes"].as_str()?)
+                .ok()?;
+            Some(Ok(String::from_utf8_lossy(&bytes).to_string()))
+        }
+        _ => None,
+    }
+}
+
+/// Decode an event-stream body into Anthropic stream event JSON strings
+pub fn event_stream<S>(body: S) -> LineStream
+where
+    S: Stream<Item = reqwest::Result<axum::body::Bytes>> + Send + 'static,
+{
+    let state = (Box::pin(body), Vec::<u8>::new(), VecDeque::new(), false);
+    stream::unfold(
+        state,
+        |(mut body, mut buffer, mut pending, mut done)| async move {
+            loop {
+                if let Some(event) = pending.pop_front() {
+                    return Some((event, (body, buffer, pending, done)));
+                }
+                if done {
+                    return None;
+                }
+                match body.next().await {
+                    Some(Ok(chunk)) => {
+                        buffer.extend_from_slice(&chunk);
+                        loop {
+                            match decode_frame(&mut buffer) {
+                                Ok(Some(frame)) => pending.extend(frame_event(frame)),
+                                Ok(None) => break,
+                                Err(e) => {
+                                    pending.push_back(Err(e));
+                                    done = true;
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                    Some(Err(e)) => {
+                        pending.push_back(Err(e.to_string()));
+                        done = true;
+                    }
+                    None => done = true,
+                }
+            }
+        },
+    )
+    .boxed()
+}
+
+/// A Bedrock error response, with the exception name from `x-amzn-errortype` as its code
+///
+/// The header looks like `ThrottlingException:http://internal.amazon.com/coral/...`.
+fn upstream_error(status: StatusCode, error_type: &str, text: &str) -> ApiError {
+    let mut error = ApiError::from_upstream(status, "Bedrock", text);
+    let name = error_type.split(':').next().unwrap_or_default();
+    if !name.is_empty() {
+        error.code = Some(name.to_string());
+    }
+    error
+}
+
+/// Send a Messages request to Bedrock and answer in `format`
+pub async fn handle_bedrock_request(
+    config: Arc<Mutex<Config>>,
+    input: MessagesInput,
+    format: InboundFormat,
+    routing_decision: RoutingDecision,
+) -> Result<axum::response::Response, StatusCode> {
+    // Bedrock IDs end in `-v1:0`, which name-based routing splits off as a preference
+    let requested = local::local_model_name(&routing_decision);
+
+    let cfg = config.lock().await;
+    let provider = cfg.providers.get(&routing_decision.provider);
+    let model_id = model_id(
+        &provider.map(|p| p.deployments.clone()).unwrap_or_default(),
+        &requested,
+    );
+    let region = provider
+        .and_then(|p| p.region.clone())
+        .or_else(aws::default_region)
+        .unwrap_or_else(|| "us-east-1".to_string());
+    let base = provider
+        .map(|p| p.endpoint.trim_end_matches('/').to_string())
+        .filter(|e| !e.is_empty())
+        .unwrap_or_else(|| format!("https://bedrock-runtime.{}.amazonaws.com", region));
+    drop(cfg);
+
+    let credentials = match AwsCredentials::load() {
+        Ok(c) => c,
+        Err(e) => return Err(error_handling::unauthorized(&e.to_string())),
+    };
+
+    let body = input.into_body();
+    let stream = body["stream"].as_bool().unwrap_or(false);
+    let body = invoke_body(body);
+    let bytes = match serde_json::to_vec(&body) {
+        Ok(b) => b,
+        Err(e) => {
+            return Err(error_handling::internal_error(
+                "Failed to serialize Bedrock request",
+                &e,
+            ));
+        }
+    };
+
+    let action = if stream {
+        "invoke-with-response-stream"
+    } else {
+        "invoke"
+    };
+    let url = format!(
+        "{}/model/{}/{}",
+        base,
+        aws::encode_path_segment(&model_id),
+        action
+    );
+    let url = match reqwest::Url::parse(&url) {
+        Ok(u) => u,
+        Err(e) => return Err(error_handling::internal_error("Invalid Bedrock URL", &e)),
+    };
+
+    tracing::info!("☁️ Bedrock → {} ({}, {})", requested, model_id, region);
+    if let Some(req_str) = error_handling::prepare_request_log(&body) {
+        tracing::debug!(target: "setu::request", "Outgoing Bedrock request: {}", req_str);
+    }
+
+    let request = SignableRequest {
+        method: "POST",
+        url: &url,
+        headers: &[("content-type", "application/json")],
+        body: &bytes,
+    };
+    let signed = aws::sign(
+        &credentials,
+        &region,
+        "bedrock",
+        &request,
+        chrono::Utc::now(),
+    );
+
+    let mut http = reqwest::Client::new()
+        .post(url.clone())
+        .header("content-type", "application/json")
+        .body(bytes);
+    for (name, value) in signed {
+        http = http.header(name, value);
+    }
+    let resp = match http.send().await {
+        Ok(r) => r,
+        Err(e) => return Err(error_handling::bad_gateway("Bedrock request failed", &e)),
+    };
+
+    let status = resp.status();
+    if !status.is_success() {
+        let error_type = resp
+            .headers()
+            .get("x-amzn-errortype")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        let text = resp.text().await.unwrap_or_default();
+        tracing::error!("Bedrock returned {} {}: {}", status, error_type, text);
+        return Ok(upstream_error(status, &error_type, &text).into_response(format));
+    }
+
+    if stream {
+        let events = event_stream(resp.bytes_stream());
+        let body = anthropic_upstream::stream_events(events, format, &requested);
+        return Ok(local::sse_response(body));
+    }
+
+    let value: Value = match resp.json().await {
+        Ok(v) => v,
+        Err(e) => {
+            return Err(error_handling::bad_gateway(
+                "Failed to parse Bedrock response",
+                &e,
+            ));
+        }
+    };
+    Ok(local::json_response(&anthropic_upstream::render_response(
+        value, format, &requested,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode_frame(headers: &[(&str, &str)], payload: &[u8]) -> Vec<u8> {
+        let mut header_bytes = Vec::new();
+        for (name, value) in headers {
+            header_bytes.push(name.len() as u8);
+            header_bytes.extend_from_slice(name.as_bytes());
+            header_bytes.push(7);
+            header_bytes.extend_from_slice(&(value.len() as u16).to_be_bytes());
+            header_bytes.extend_from_slice(value.as_bytes());
+        }
+        let total = 16 + header_bytes.len() + payload.len();
+        let mut frame = Vec::new();
+        frame.extend_from_slice(&(total as u32).to_be_bytes());
+        frame.extend_from_slice(&(header_bytes.len() as u32).to_be_bytes());
+        let prelude_crc = crc32(&frame);
+        frame.extend_from_slice(&prelude_crc.to_be_bytes());
+        frame.extend_from_slice(&header_bytes);
+        frame.extend_from_slice(payload);
+        let message_crc = crc32(&frame);
+        frame.extend_from_slice(&message_crc.to_be_bytes());
+        frame
+    }
+
+    #[test]
+    fn test_crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_decode_chunk_frame_across_reads() {
+        let event = r#"{"type":"message_stop"}"#;
+        let payload = json!({
+            "bytes": base64::engine::general_purpose::STANDARD.encode(event)
+        })
+        .to_string();
+        let frame = encode_frame(
+            &[(":message-type", "event"), (":event-type", "chunk")],
+            payload.as_bytes(),
+        );
+
+        // First half alone is not a full frame
+        let mut buffer = frame[..frame.len() / 2].to_vec();
+        assert_eq!(decode_frame(&mut buffer), Ok(None));
+
+        buffer.extend_from_slice(&frame[frame.len() / 2..]);
+        let decoded = decode_frame(&mut buffer).unwrap().unwrap();
+        assert!(buffer.is_empty());
+        assert_eq!(frame_event(decoded), Some(Ok(event.to_string())));
+    }
+
+    #[test]
+    fn test_exception_frame_is_error() {
+        let frame = encode_frame(
+            &[
+                (":message-type", "exception"),
+                (":exception-type", "throttlingException"),
+            ],
+            br#"{"message":"Too many requests"}"#,
+        );
+        let mut buffer = frame;
+        let decoded = decode_frame(&mut buffer).unwrap().unwrap();
+        let event = frame_event(decoded).unwrap();
+        assert!(event.unwrap_err().starts_with("throttlingException"));
+    }
+
+    #[test]
+    fn test_corrupt_frame_rejected() {
+        let mut frame = encode_frame(&[(":event-type", "chunk")], b"{}");
+        let last = frame.len() - 5;
+        frame[last] ^= 0xFF;
+        assert!(decode_frame(&mut frame).is_err());
+    }
+
+    #[test]
+    fn test_model_id_mapping() {
+        let mut mapping = rustc_hash::FxHashMap::default();
+        mapping.insert(
+            "claude-sonnet".to_string(),
+            "us.anthropic.claude-sonnet-4-20250514-v1:0".to_string(),
+        );
+        assert_eq!(
+            model_id(&mapping, "claude-sonnet"),
+            "us.anthropic.claude-sonnet-4-20250514-v1:0"
+        );
+        assert_eq!(
+            model_id(&mapping, "anthropic.claude-sonnet-4"),
+            "anthropic.claude-sonnet-4-20250514-v1:0"
+        );
+        assert_eq!(
+            model_id(&mapping, "anthropic.claude-3-haiku-20240307-v1:0"),
+            "anthropic.claude-3-haiku-20240307-v1:0"
+        );
+    }
+
+    #[test]
+    fn test_invoke_body_shape() {
+        let body = invoke_body(json!({
+            "model": "claude",
+            "stream": true,
+            "max_tokens": 100,
+            "messages": [],
+            "temperature": null
+        }));
+        assert!(body.get("model").is_none());
+        assert!(body.get("stream").is_none());
+        assert!(body.get("temperature").is_none());
+        assert_eq!(body["anthropic_version"], BEDROCK_ANTHROPIC_VERSION);
+    }
+
+    #[test]
+    fn test_upstream_error_uses_exception_name() {
+        let error = upstream_error(
+            StatusCode::TOO_MANY_REQUESTS,
+            "ThrottlingException:http://internal.amazon.com/coral/com.amazon.bedrock/",
+            r#"{"message":"Too many requests, please wait before trying again."}"#,
+        );
+        assert_eq!(error.code.as_deref(), Some("ThrottlingException"));
+        assert_eq!(
+            error.message,
+            "Too many requests, please wait before trying again."
+        );
+        let body = error.to_value(InboundFormat::Anthropic);
+        assert_eq!(body["error"]["type"], "rate_limit_error");
+    }
+}
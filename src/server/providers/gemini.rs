@@ -4,20 +4,21 @@ use std::sync::Arc;
 use tokio::sync::Mutex;
 use tracing::info;
 
-use crate::auth::google::GoogleOAuth;
+use crate::auth::{AuthCache, AuthMethod, OAuthProvider};
 use crate::config::Config;
 use crate::error::PrismError;
 use crate::router::name_based::RoutingDecision;
 use crate::server::error_handling;
 
 /// Create Gemini client with appropriate authentication
-pub async fn create_gemini_client(config: Arc<Mutex<Config>>) -> Result<Gemini, PrismError> {
-    // Try Claude Code OAuth first (highest priority)
-    if let Ok(gemini_config) = GoogleOAuth::try_gemini_cli_credentials().await
-        && let Some(oauth_token) = gemini_config.oauth_access_token
-    {
-        info!("🔐 Gemini → OAuth via Gemini CLI (subscription billing)");
-        let client = Gemini::builder().oauth_token(&oauth_token).build();
+pub async fn create_gemini_client(
+    config: Arc<Mutex<Config>>,
+    auth_cache: &AuthCache,
+) -> Result<Gemini, PrismError> {
+    // Cached OAuth token first (kept fresh by the background refresh task)
+    if let AuthMethod::OAuth { source, token } = auth_cache.load().method(OAuthProvider::Gemini) {
+        info!("🔐 Gemini → OAuth via {} (subscription billing)", source);
+        let client = Gemini::builder().oauth_token(token).build();
         return Ok(client);
     }
 
@@ -46,11 +47,12 @@ pub async fn create_gemini_client(config: Arc<Mutex<Config>>) -> Result<Gemini,
 /// Handle Gemini requests (converted from OpenAI format)
 pub async fn handle_gemini_request_from_openai(
     config: Arc<Mutex<Config>>,
+    auth_cache: &AuthCache,
     openai_request: openai_ox::request::ChatRequest,
     routing_decision: RoutingDecision,
     _headers: HeaderMap,
 ) -> Result<axum::response::Response, StatusCode> {
-    let gemini_client = match create_gemini_client(config).await {
+    let gemini_client = match create_gemini_client(config, auth_cache).await {
         Ok(client) => client,
         Err(e) => {
             return Err(error_handling::internal_error(
@@ -72,14 +74,11 @@ pub async fn handle_gemini_request_from_openai(
     gemini_request.model = routing_decision.model.clone();
 
     // Apply URL parameters if present
-    if let Some(query_params) = routing_decision.query_params
-        && let Some(thinking_config) =
-            crate::server::parameter_mapping::create_gemini_thinking_config(&query_params)
-    {
-        gemini_request.generation_config = Some(gemini_ox::generate_content::GenerationConfig {
-            thinking_config: Some(thinking_config),
-            ..Default::default()
-        });
+    if let Some(query_params) = routing_decision.query_params {
+        gemini_request.generation_config = crate::server::parameter_mapping::apply_gemini_parameters(
+            gemini_request.generation_config.take(),
+            &query_params,
+        );
     }
 
     if let Some(req_str) = crate::server::error_handling::prepare_gemini_request_log(&gemini_request) {
@@ -120,11 +119,12 @@ pub async fn handle_gemini_request_from_openai(
 /// Handle Gemini requests (from Anthropic format)
 pub async fn handle_gemini_request(
     config: Arc<Mutex<Config>>,
+    auth_cache: &AuthCache,
     anthropic_request: anthropic_ox::ChatRequest,
     routing_decision: RoutingDecision,
     _headers: HeaderMap,
 ) -> Result<axum::response::Response, StatusCode> {
-    let gemini_client = match create_gemini_client(config).await {
+    let gemini_client = match create_gemini_client(config, auth_cache).await {
         Ok(client) => client,
         Err(e) => {
             return Err(error_handling::internal_error(
@@ -142,14 +142,11 @@ pub async fn handle_gemini_request(
     gemini_request.model = routing_decision.model.clone();
 
     // Apply URL parameters if present
-    if let Some(query_params) = routing_decision.query_params
-        && let Some(thinking_config) =
-            crate::server::parameter_mapping::create_gemini_thinking_config(&query_params)
-    {
-        gemini_request.generation_config = Some(gemini_ox::generate_content::GenerationConfig {
-            thinking_config: Some(thinking_config),
-            ..Default::default()
-        });
+    if let Some(query_params) = routing_decision.query_params {
+        gemini_request.generation_config = crate::server::parameter_mapping::apply_gemini_parameters(
+            gemini_request.generation_config.take(),
+            &query_params,
+        );
     }
 
     if let Some(req_str) = crate::server::error_handling::prepare_gemini_request_log(&gemini_request) {
@@ -217,7 +214,7 @@ pub async fn handle_openrouter_from_gemini(
         };
 
     // Create OpenRouter client and send request
-    let openrouter_client =
+    let (openrouter_client, key) =
         match crate::server::providers::openrouter::create_openrouter_client(config).await {
             Ok(client) => client,
             Err(e) => {
@@ -279,10 +276,13 @@ pub async fn handle_openrouter_from_gemini(
                 .body(axum::body::Body::from(json_body))
                 .unwrap())
         }
-        Err(e) => Err(error_handling::internal_error(
-            "OpenRouter API request failed",
-            &e,
-        )),
+        Err(e) => {
+            key.report_error(&e);
+            Err(error_handling::internal_error(
+                "OpenRouter API request failed",
+                &e,
+            ))
+        }
     }
 }
 
@@ -310,7 +310,7 @@ pub async fn handle_anthropic_from_gemini(
     }
 
     // Create Anthropic client and send request
-    let anthropic_client =
+    let (anthropic_client, key) =
         match crate::server::providers::anthropic::create_anthropic_client(config, true).await {
             Ok(client) => client,
             Err(e) => {
@@ -348,22 +348,28 @@ pub async fn handle_anthropic_from_gemini(
                 .body(axum::body::Body::from(json_body))
                 .unwrap())
         }
-        Err(e) => Err(error_handling::internal_error(
-            "Anthropic API request failed",
-            &e,
-        )),
+        Err(e) => {
+            if let Some(key) = &key {
+                key.report_error(&e);
+            }
+            Err(error_handling::internal_error(
+                "Anthropic API request failed",
+                &e,
+            ))
+        }
     }
 }
 
 /// Handle direct native Gemini requests
 pub async fn handle_direct_gemini_request(
     config: Arc<Mutex<Config>>,
+    auth_cache: &AuthCache,
     gemini_request_value: serde_json::Value,
     model: &str,
     routing_decision: RoutingDecision,
     _headers: HeaderMap,
 ) -> Result<axum::response::Response, StatusCode> {
-    let gemini_client = match create_gemini_client(config).await {
+    let gemini_client = match create_gemini_client(config, auth_cache).await {
         Ok(client) => client,
         Err(e) => {
             return Err(error_handling::internal_error(
@@ -377,14 +383,11 @@ pub async fn handle_direct_gemini_request(
     let mut gemini_request = parse_gemini_json_to_request(gemini_request_value, model.to_string())?;
 
     // Apply URL parameters if present
-    if let Some(query_params) = routing_decision.query_params
-        && let Some(thinking_config) =
-            crate::server::parameter_mapping::create_gemini_thinking_config(&query_params)
-    {
-        gemini_request.generation_config = Some(gemini_ox::generate_content::GenerationConfig {
-            thinking_config: Some(thinking_config),
-            ..Default::default()
-        });
+    if let Some(query_params) = routing_decision.query_params {
+        gemini_request.generation_config = crate::server::parameter_mapping::apply_gemini_parameters(
+            gemini_request.generation_config.take(),
+            &query_params,
+        );
     }
 
     if let Some(req_str) = crate::server::error_handling::prepare_gemini_request_log(&gemini_request) {
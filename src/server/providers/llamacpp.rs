@@ -0,0 +1,55 @@
+//! llama.cpp provider (`type = "llamacpp"`), talking to llama-server's
+//! OpenAI-compatible `/v1/chat/completions`.
+
+use axum::http::StatusCode;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::auth::key_pool;
+use crate::config::Config;
+use crate::router::name_based::RoutingDecision;
+use crate::server::parameter_mapping::apply_chat_completions_parameters;
+use crate::server::providers::local::{self, InboundFormat, LocalChat};
+use crate::server::providers::openai_compatible::{
+    DEFAULT_REASONING_FIELD, build_request, send_chat_completion,
+};
+
+const DEFAULT_ENDPOINT: &str = "http://localhost:8080";
+
+/// Send a normalised chat request to llama-server and answer in `format`
+pub async fn handle_llamacpp_request(
+    config: Arc<Mutex<Config>>,
+    chat: LocalChat,
+    format: InboundFormat,
+    routing_decision: RoutingDecision,
+) -> Result<axum::response::Response, StatusCode> {
+    let model = local::local_model_name(&routing_decision);
+
+    let cfg = config.lock().await;
+    let endpoint = cfg
+        .providers
+        .get(&routing_decision.provider)
+        .map(|p| p.endpoint.clone())
+        .filter(|e| !e.is_empty())
+        .unwrap_or_else(|| DEFAULT_ENDPOINT.to_string());
+    // llama-server only checks a key when started with --api-key
+    let key = key_pool::acquire_api_key(&cfg, &routing_decision.provider, "LLAMACPP_API_KEY");
+    drop(cfg);
+
+    let mut body = build_request(&chat, &model);
+    if let Some(params) = &routing_decision.query_params {
+        apply_chat_completions_parameters(&mut body, params);
+    }
+
+    let url = format!("{}/v1/chat/completions", endpoint.trim_end_matches('/'));
+    send_chat_completion(
+        "llama.cpp",
+        &url,
+        key,
+        body,
+        DEFAULT_REASONING_FIELD,
+        format,
+        &model,
+    )
+    .await
+}
@@ -0,0 +1,1343 @@
+//! Shared request/response conversion for local model servers (Ollama, llama.cpp).
+//!
+//! All three inbound formats are normalised into [`LocalChat`], an OpenAI-style message
+//! list that both servers understand with small tweaks. Responses come back as
+//! [`LocalResponse`] (or a stream of [`LocalDelta`]) and are rendered in the client's format.
+
+use axum::body::{Body, Bytes};
+use futures_util::stream::{self, BoxStream};
+use futures_util::{Stream, StreamExt};
+use serde_json::{Value, json};
+use std::collections::VecDeque;
+
+use crate::router::name_based::RoutingDecision;
+
+/// API format of the inbound request, used to render the response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InboundFormat {
+    Anthropic,
+    OpenAI,
+    Gemini,
+}
+
+/// Normalised chat request
+///
+/// `messages` use OpenAI chat shape; tool call arguments are kept as JSON objects and
+/// images as data URLs in an `images` array on the message.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LocalChat {
+    pub messages: Vec<Value>,
+    pub tools: Vec<Value>,
+    pub stream: bool,
+    pub temperature: Option<f64>,
+    pub top_p: Option<f64>,
+    pub top_k: Option<u64>,
+    pub max_tokens: Option<u64>,
+    pub stop: Vec<String>,
+    pub think: Option<bool>,
+}
+
+/// A tool call produced by the model
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalToolCall {
+    pub id: String,
+    pub name: String,
+    pub arguments: Value,
+}
+
+/// Why generation stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Finish {
+    #[default]
+    Stop,
+    Length,
+    ToolCalls,
+}
+
+impl Finish {
+    pub fn from_reason(reason: Option<&str>, has_tool_calls: bool) -> Self {
+        if has_tool_calls {
+            return Finish::ToolCalls;
+        }
+        match reason {
+            Some("length") => Finish::Length,
+            Some("tool_calls") => Finish::ToolCalls,
+            _ => Finish::Stop,
+        }
+    }
+
+    fn anthropic(self) -> &'static str {
+        match self {
+            Finish::Stop => "end_turn",
+            Finish::Length => "max_tokens",
+            Finish::ToolCalls => "tool_use",
+        }
+    }
+
+    fn openai(self) -> &'static str {
+        match self {
+            Finish::Stop => "stop",
+            Finish::Length => "length",
+            Finish::ToolCalls => "tool_calls",
+        }
+    }
+
+    fn gemini(self) -> &'static str {
+        match self {
+            Finish::Length => "MAX_TOKENS",
+            Finish::Stop | Finish::ToolCalls => "STOP",
+        }
+    }
+}
+
+/// Complete (non-streaming) model output
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LocalResponse {
+    pub text: String,
+    pub thinking: Option<String>,
+    pub tool_calls: Vec<LocalToolCall>,
+    pub finish: Finish,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+/// Incremental model output
+#[derive(Debug, Clone, PartialEq)]
+pub enum LocalDelta {
+    Text(String),
+    Thinking(String),
+    ToolCall(LocalToolCall),
+    Finish {
+        finish: Finish,
+        input_tokens: u64,
+        output_tokens: u64,
+    },
+}
+
+/// Model name for local servers, which use `:` for tags (e.g. `qwen2.5-coder:32b`)
+///
+/// Name-based routing treats the last `:` segment as a provider preference, so it is
+/// joined back on here.
+pub fn local_model_name(decision: &RoutingDecision) -> String {
+    match &decision.provider_preference {
+        Some(tag) if !tag.is_empty() => format!("{}:{}", decision.model, tag),
+        _ => decision.model.clone(),
+    }
+}
+
+pub fn new_tool_call_id() -> String {
+    format!("call_{:08x}", rand::random::<u32>())
+}
+
+fn text_of(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items
+            .iter()
+            .filter_map(|item| match item {
+                Value::String(s) => Some(s.clone()),
+                _ => item
+                    .get("text")
+                    .and_then(|t| t.as_str())
+                    .map(str::to_string),
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+fn parse_arguments(arguments: Option<&Value>) -> Value {
+    match arguments {
+        Some(Value::String(s)) => serde_json::from_str(s).unwrap_or_else(|_| json!({})),
+        Some(v @ Value::Object(_)) => v.clone(),
+        _ => json!({}),
+    }
+}
+
+fn stop_list(value: Option<&Value>) -> Vec<String> {
+    match value {
+        Some(Value::String(s)) => vec![s.clone()],
+        Some(Value::Array(items)) => items
+            .iter()
+            .filter_map(|s| s.as_str().map(str::to_string))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn assistant_message(text: String, tool_calls: Vec<Value>) -> Value {
+    let mut message = json!({"role": "assistant", "content": text});
+    if !tool_calls.is_empty() {
+        message["tool_calls"] = Value::Array(tool_calls);
+    }
+    message
+}
+
+fn user_message(text: String, images: Vec<String>) -> Value {
+    let mut message = json!({"role": "user", "content": text});
+    if !images.is_empty() {
+        message["images"] = json!(images);
+    }
+    message
+}
+
+fn tool_call_value(id: &str, name: &str, arguments: Value) -> Value {
+    json!({
+        "id": id,
+        "type": "function",
+        "function": {"name": name, "arguments": arguments}
+    })
+}
+
+impl LocalChat {
+    /// Convert an Anthropic Messages request
+    pub fn from_anthropic(request: &anthropic_ox::ChatRequest) -> Self {
+        let value = serde_json::to_value(request).unwrap_or(Value::Null);
+        Self::from_anthropic_value(&value)
+    }
+
+    pub fn from_anthropic_value(value: &Value) -> Self {
+        let mut chat = LocalChat::default();
+
+        if let Some(system) = value.get("system") {
+            let text = text_of(system);
+            if !text.is_empty() {
+                chat.messages
+                    .push(json!({"role": "system", "content": text}));
+            }
+        }
+
+        for message in value
+            .get("messages")
+            .and_then(|m| m.as_array())
+            .into_iter()
+            .flatten()
+        {
+            let role = message
+                .get("role")
+                .and_then(|r| r.as_str())
+                .unwrap_or("user");
+            let content = message.get("content").cloned().unwrap_or(Value::Null);
+
+            let blocks = match content {
+                Value::String(s) => vec![json!({"type": "text", "text": s})],
+                Value::Array(items) => items,
+                _ => Vec::new(),
+            };
+
+            let mut text = Vec::new();
+            let mut images = Vec::new();
+            let mut tool_calls = Vec::new();
+            let mut tool_results = Vec::new();
+
+            for block in &blocks {
+                match block.get("type").and_then(|t| t.as_str()) {
+                    Some("text") => {
+                        if let Some(t) = block.get("text").and_then(|t| t.as_str()) {
+                            text.push(t.to_string());
+                        }
+                    }
+                    Some("image") => {
+                        let source = block.get("source");
+                        let media_type = source
+                            .and_then(|s| s.get("media_type"))
+                            .and_then(|m| m.as_str())
+                            .unwrap_or("image/png");
+                        if let Some(data) =
+                            source.and_then(|s| s.get("data")).and_then(|d| d.as_str())
+                        {
+                            images.push(format!("data:{};base64,{}", media_type, data));
+                        }
+                    }
+                    Some("tool_use") => {
+                        let id = block.get("id").and_then(|i| i.as_str()).unwrap_or_default();
+                        let name = block
+                            .get("name")
+                            .and_then(|n| n.as_str())
+                            .unwrap_or_default();
+                        let input = block.get("input").cloned().unwrap_or_else(|| json!({}));
+                        tool_calls.push(tool_call_value(id, name, input));
+                    }
+                    Some("tool_result") => {
+                        let id = block
+                            .get("tool_use_id")
+                            .and_then(|i| i.as_str())
+                            .unwrap_or_default();
+                        let content = block.get("content").map(text_of).unwrap_or_default();
+                        tool_results.push(json!({
+                            "role": "tool",
+                            "tool_call_id": id,
+                            "name": tool_name_for(&chat.messages, id),
+                            "content": content,
+                        }));
+                    }
+                    // Thinking blocks are not replayed to local models
+                    _ => {}
+                }
+            }
+
+            // Tool results answer the previous assistant turn, so they go first
+            chat.messages.extend(tool_results);
+            if role == "assistant" {
+                if !text.is_empty() || !tool_calls.is_empty() {
+                    chat.messages
+                        .push(assistant_message(text.join("\n"), tool_calls));
+                }
+            } else if !text.is_empty() || !images.is_empty() {
+                chat.messages.push(user_message(text.join("\n"), images));
+            }
+        }
+
+        for tool in value
+            .get("tools")
+            .and_then(|t| t.as_array())
+            .into_iter()
+            .flatten()
+        {
+            if let Some(name) = tool.get("name").and_then(|n| n.as_str()) {
+                chat.tools.push(json!({
+                    "type": "function",
+                    "function": {
+                        "name": name,
+                        "description": tool.get("description").cloned().unwrap_or(json!("")),
+                        "parameters": tool.get("input_schema").cloned().unwrap_or(json!({"type": "object"})),
+                    }
+                }));
+            }
+        }
+
+        chat.stream = value
+            .get("stream")
+            .and_then(|s| s.as_bool())
+            .unwrap_or(false);
+        chat.temperature = value.get("temperature").and_then(|v| v.as_f64());
+        chat.top_p = value.get("top_p").and_then(|v| v.as_f64());
+        chat.top_k = value.get("top_k").and_then(|v| v.as_u64());
+        chat.max_tokens = value.get("max_tokens").and_then(|v| v.as_u64());
+        chat.stop = stop_list(value.get("stop_sequences"));
+        chat.think = value
+            .get("thinking")
+            .filter(|t| !t.is_null())
+            .map(|t| t.get("type").and_then(|v| v.as_str()) != Some("disabled"));
+        chat
+    }
+
+    /// Convert an OpenAI Chat Completions request
+    pub fn from_openai(request: &openai_ox::request::ChatRequest) -> Self {
+        let value = serde_json::to_value(request).unwrap_or(Value::Null);
+        Self::from_openai_value(&value)
+    }
+
+    pub fn from_openai_value(value: &Value) -> Self {
+        let mut chat = LocalChat::default();
+
+        for message in value
+            .get("messages")
+            .and_then(|m| m.as_array())
+            .into_iter()
+            .flatten()
+        {
+            let role = message
+                .get("role")
+                .and_then(|r| r.as_str())
+                .unwrap_or("user");
+            let mut images = Vec::new();
+            let text = match message.get("content") {
+                Some(Value::Array(parts)) => {
+                    let mut text = Vec::new();
+                    for part in parts {
+                        match part.get("type").and_then(|t| t.as_str()) {
+                            Some("image_url") => {
+                                if let Some(url) = part
+                                    .get("image_url")
+                                    .and_then(|i| i.get("url").or(Some(i)))
+                                    .and_then(|u| u.as_str())
+                                {
+                                    images.push(url.to_string());
+                                }
+                            }
+                            _ => {
+                                if let Some(t) = part.get("text").and_then(|t| t.as_str()) {
+                                    text.push(t.to_string());
+                                }
+                            }
+                        }
+                    }
+                    text.join("\n")
+                }
+                Some(other) => text_of(other),
+                None => String::new(),
+            };
+
+            match role {
+                "assistant" => {
+                    let tool_calls = message
+                        .get("tool_calls")
+                        .and_then(|t| t.as_array())
+                        .into_iter()
+                        .flatten()
+                        .map(|call| {
+                            let function = call.get("function");
+                            tool_call_value(
+                                call.get("id").and_then(|i| i.as_str()).unwrap_or_default(),
+                                function
+                                    .and_then(|f| f.get("name"))
+                                    .and_then(|n| n.as_str())
+                                    .unwrap_or_default(),
+                                parse_arguments(function.and_then(|f| f.get("arguments"))),
+                            )
+                        })
+                        .collect();
+                    chat.messages.push(assistant_message(text, tool_calls));
+                }
+                "tool" => {
+                    let id = message
+                        .get("tool_call_id")
+                        .and_then(|i| i.as_str())
+                        .unwrap_or_default();
+                    let name = message
+                        .get("name")
+                        .and_then(|n| n.as_str())
+                        .map(str::to_string)
+                        .unwrap_or_else(|| tool_name_for(&chat.messages, id));
+                    chat.messages.push(json!({
+                        "role": "tool",
+                        "tool_call_id": id,
+                        "name": name,
+                        "content": text,
+                    }));
+                }
+                "system" | "developer" => {
+                    chat.messages
+                        .push(json!({"role": "system", "content": text}));
+                }
+                _ => chat.messages.push(user_message(text, images)),
+            }
+        }
+
+        chat.tools = value
+            .get("tools")
+            .and_then(|t| t.as_array())
+            .cloned()
+            .unwrap_or_default();
+        chat.stream = value
+            .get("stream")
+            .and_then(|s| s.as_bool())
+            .unwrap_or(false);
+        chat.temperature = value.get("temperature").and_then(|v| v.as_f64());
+        chat.top_p = value.get("top_p").and_then(|v| v.as_f64());
+        chat.top_k = value.get("top_k").and_then(|v| v.as_u64());
+        chat.max_tokens = value
+            .get("max_completion_tokens")
+            .or_else(|| value.get("max_tokens"))
+            .and_then(|v| v.as_u64());
+        chat.stop = stop_list(value.get("stop"));
+        chat.think = value
+            .get("reasoning_effort")
+            .and_then(|v| v.as_str())
+            .map(|effort| effort != "none");
+        chat
+    }
+
+    /// Convert a Gemini generateContent request
+    pub fn from_gemini_value(value: &Value) -> Self {
+        let mut chat = LocalChat::default();
+
+        let system = value
+            .get("systemInstruction")
+            .or_else(|| value.get("system_instruction"));
+        if let Some(parts) = system.and_then(|s| s.get("parts")) {
+            let text = text_of(parts);
+            if !text.is_empty() {
+                chat.messages
+                    .push(json!({"role": "system", "content": text}));
+            }
+        }
+
+        for content in value
+            .get("contents")
+            .and_then(|c| c.as_array())
+            .into_iter()
+            .flatten()
+        {
+            let role = content
+                .get("role")
+                .and_then(|r| r.as_str())
+                .unwrap_or("user");
+            let mut text = Vec::new();
+            let mut images = Vec::new();
+            let mut tool_calls = Vec::new();
+            let mut tool_results = Vec::new();
+
+            for part in content
+                .get("parts")
+                .and_then(|p| p.as_array())
+                .into_iter()
+                .flatten()
+            {
+                if let Some(t) = part.get("text").and_then(|t| t.as_str()) {
+                    text.push(t.to_string());
+                } else if let Some(inline) =
+                    part.get("inlineData").or_else(|| part.get("inline_data"))
+                {
+                    let mime = inline
+                        .get("mimeType")
+                        .or_else(|| inline.get("mime_type"))
+                        .and_then(|m| m.as_str())
+                        .unwrap_or("image/png");
+                    if let Some(data) = inline.get("data").and_then(|d| d.as_str()) {
+                        images.push(format!("data:{};base64,{}", mime, data));
+                    }
+                } else if let Some(call) = part
+                    .get("functionCall")
+                    .or_else(|| part.get("function_call"))
+                {
+                    let name = call
+                        .get("name")
+                        .and_then(|n| n.as_str())
+                        .unwrap_or_default();
+                    let id = call
+                        .get("id")
+                        .and_then(|i| i.as_str())
+                        .map(str::to_string)
+                        .unwrap_or_else(new_tool_call_id);
+                    let args = call.get("args").cloned().unwrap_or_else(|| json!({}));
+                    tool_calls.push(tool_call_value(&id, name, args));
+                } else if let Some(response) = part
+                    .get("functionResponse")
+                    .or_else(|| part.get("function_response"))
+                {
+                    let name = response
+                        .get("name")
+                        .and_then(|n| n.as_str())
+                        .unwrap_or_default();
+                    let id = response
+                        .get("id")
+                        .and_then(|i| i.as_str())
+                        .map(str::to_string)
+                        .unwrap_or_else(|| tool_call_id_for(&chat.messages, name));
+                    let output = response.get("response").cloned().unwrap_or(Value::Null);
+                    tool_results.push(json!({
+                        "role": "tool",
+                        "tool_call_id": id,
+                        "name": name,
+                        "content": match output {
+                            Value::String(s) => s,
+                            other => other.to_string(),
+                        },
+                    }));
+                }
+            }
+
+            chat.messages.extend(tool_results);
+            if role == "model" {
+                if !text.is_empty() || !tool_calls.is_empty() {
+                    chat.messages
+                        .push(assistant_message(text.join("\n"), tool_calls));
+                }
+            } else if !text.is_empty() || !images.is_empty() {
+                chat.messages.push(user_message(text.join("\n"), images));
+            }
+        }
+
+        for tool in value
+            .get("tools")
+            .and_then(|t| t.as_array())
+            .into_iter()
+            .flatten()
+        {
+            let declarations = tool
+                .get("functionDeclarations")
+                .or_else(|| tool.get("function_declarations"))
+                .and_then(|d| d.as_array());
+            for declaration in declarations.into_iter().flatten() {
+                chat.tools.push(json!({
+                    "type": "function",
+                    "function": {
+                        "name": declaration.get("name").cloned().unwrap_or(json!("")),
+                        "description": declaration.get("description").cloned().unwrap_or(json!("")),
+                        "parameters": declaration.get("parameters").cloned().unwrap_or(json!({"type": "object"})),
+                    }
+                }));
+            }
+        }
+
+        if let Some(config) = value
+            .get("generationConfig")
+            .or_else(|| value.get("generation_config"))
+        {
+            chat.temperature = config.get("temperature").and_then(|v| v.as_f64());
+            chat.top_p = config.get("topP").and_then(|v| v.as_f64());
+            chat.top_k = config.get("topK").and_then(|v| v.as_u64());
+            chat.max_tokens = config.get("maxOutputTokens").and_then(|v| v.as_u64());
+            chat.stop = stop_list(config.get("stopSequences"));
+            chat.think = config
+                .get("thinkingConfig")
+                .and_then(|t| t.get("thinkingBudget"))
+                .and_then(|b| b.as_i64())
+                .map(|budget| budget != 0);
+        }
+        chat
+    }
+}
+
+/// Name of the tool called with `id` in an earlier assistant message
+fn tool_name_for(messages: &[Value], id: &str) -> String {
+    messages
+        .iter()
+        .rev()
+        .filter_map(|m| m.get("tool_calls").and_then(|t| t.as_array()))
+        .flatten()
+        .find(|call| call.get("id").and_then(|i| i.as_str()) == Some(id))
+        .and_then(|call| call.pointer("/function/name"))
+        .and_then(|n| n.as_str())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Id of the most recent call to tool `name` (Gemini responses are matched by name)
+fn tool_call_id_for(messages: &[Value], name: &str) -> String {
+    messages
+        .iter()
+        .rev()
+        .filter_map(|m| m.get("tool_calls").and_then(|t| t.as_array()))
+        .flat_map(|calls| calls.iter().rev())
+        .find(|call| call.pointer("/function/name").and_then(|n| n.as_str()) == Some(name))
+        .and_then(|call| call.get("id"))
+        .and_then(|i| i.as_str())
+        .map(str::to_string)
+        .unwrap_or_else(new_tool_call_id)
+}
+
+impl LocalResponse {
+    /// Render as an Anthropic Messages response
+    pub fn to_anthropic(&self, model: &str) -> Value {
+        let mut content = Vec::new();
+        if let Some(thinking) = self.thinking.as_ref().filter(|t| !t.is_empty()) {
+            content.push(json!({"type": "thinking", "thinking": thinking, "signature": ""}));
+        }
+        if !self.text.is_empty() {
+            content.push(json!({"type": "text", "text": self.text}));
+        }
+        for call in &self.tool_calls {
+            content.push(json!({
+                "type": "tool_use",
+                "id": call.id,
+                "name": call.name,
+                "input": call.arguments,
+            }));
+        }
+
+        json!({
+            "id": format!("msg_{:016x}", rand::random::<u64>()),
+            "type": "message",
+            "role": "assistant",
+            "model": model,
+            "content": content,
+            "stop_reason": self.finish.anthropic(),
+            "stop_sequence": null,
+            "usage": {
+                "input_tokens": self.input_tokens,
+                "output_tokens": self.output_tokens,
+            }
+        })
+    }
+
+    /// Render as an OpenAI Chat Completions response
+    pub fn to_openai(&self, model: &str) -> Value {
+        let mut message = json!({
+            "role": "assistant",
+            "content": if self.text.is_empty() && !self.tool_calls.is_empty() {
+                Value::Null
+            } else {
+                json!(self.text)
+            },
+        });
+        if let Some(thinking) = self.thinking.as_ref().filter(|t| !t.is_empty()) {
+            message["reasoning_content"] = json!(thinking);
+        }
+        if !self.tool_calls.is_empty() {
+            message["tool_calls"] = self
+                .tool_calls
+                .iter()
+                .map(|call| {
+                    json!({
+                        "id": call.id,
+                        "type": "function",
+                        "function": {"name": call.name, "arguments": call.arguments.to_string()},
+                    })
+                })
+                .collect();
+        }
+
+        json!({
+            "id": format!("chatcmpl-{:016x}", rand::random::<u64>()),
+            "object": "chat.completion",
+            "created": chrono::Utc::now().timestamp(),
+            "model": model,
+            "choices": [{
+                "index": 0,
+                "message": message,
+                "finish_reason": self.finish.openai(),
+            }],
+            "usage": {
+                "prompt_tokens": self.input_tokens,
+                "completion_tokens": self.output_tokens,
+                "total_tokens": self.input_tokens + self.output_tokens,
+            }
+        })
+    }
+
+    /// Render as a Gemini generateContent response
+    pub fn to_gemini(&self) -> Value {
+        let mut parts = Vec::new();
+        if !self.text.is_empty() {
+            parts.push(json!({"text": self.text}));
+        }
+        for call in &self.tool_calls {
+            parts.push(json!({"functionCall": {"name": call.name, "args": call.arguments}}));
+        }
+
+        json!({
+            "candidates": [{
+                "content": {"parts": parts, "role": "model"},
+                "finishReason": self.finish.gemini(),
+                "index": 0,
+            }],
+            "usageMetadata": {
+                "promptTokenCount": self.input_tokens,
+                "candidatesTokenCount": self.output_tokens,
+                "totalTokenCount": self.input_tokens + self.output_tokens,
+            }
+        })
+    }
+
+    /// Render in the inbound request's format
+    pub fn render(&self, format: InboundFormat, model: &str) -> Value {
+        match format {
+            InboundFormat::Anthropic => self.to_anthropic(model),
+            InboundFormat::OpenAI => self.to_openai(model),
+            InboundFormat::Gemini => self.to_gemini(),
+        }
+    }
+}
+
+/// Turns model deltas into the server-sent events a client expects
+pub trait StreamEncoder: Send + 'static {
+    fn encode(&mut self, delta: LocalDelta) -> Vec<String>;
+    fn error(&mut self, message: &str) -> Vec<String>;
+    /// Called once the upstream stream ends; closes anything still open
+    fn end(&mut self) -> Vec<String>;
+}
+
+/// Turns upstream lines (NDJSON or SSE) into model deltas
+pub trait DeltaParser: Send + 'static {
+    fn push_line(&mut self, line: &str) -> Vec<LocalDelta>;
+    fn finish(&mut self) -> Vec<LocalDelta> {
+        Vec::new()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BlockKind {
+    Text,
+    Thinking,
+}
+
+/// Anthropic Messages SSE encoder
+pub struct AnthropicStreamEncoder {
+    model: String,
+    started: bool,
+    finished: bool,
+    next_index: usize,
+    open_block: Option<BlockKind>,
+}
+
+impl AnthropicStreamEncoder {
+    pub fn new(model: impl Into<String>) -> Self {
+        Self {
+            model: model.into(),
+            started: false,
+            finished: false,
+            next_index: 0,
+            open_block: None,
+        }
+    }
+
+    fn event(name: &str, data: Value) -> String {
+        format!("event: {}\ndata: {}\n\n", name, data)
+    }
+
+    fn ensure_started(&mut self, out: &mut Vec<String>) {
+        if self.started {
+            return;
+        }
+        self.started = true;
+        out.push(Self::event(
+            "message_start",
+            json!({
+                "type": "message_start",
+                "message": {
+                    "id": format!("msg_{:016x}", rand::random::<u64>()),
+                    "type": "message",
+                    "role": "assistant",
+                    "model": self.model,
+                    "content": [],
+                    "stop_reason": null,
+                    "stop_sequence": null,
+                    "usage": {"input_tokens": 0, "output_tokens": 0}
+                }
+            }),
+        ));
+    }
+
+    fn close_block(&mut self, out: &mut Vec<String>) {
+        if self.open_block.take().is_some() {
+            out.push(Self::event(
+                "content_block_stop",
+                json!({"type": "content_block_stop", "index": self.next_index - 1}),
+            ));
+        }
+    }
+
+    fn open_block(&mut self, kind: BlockKind, out: &mut Vec<String>) -> usize {
+        if self.open_block == Some(kind) {
+            return self.next_index - 1;
+        }
+        self.close_block(out);
+        let index = self.next_index;
+        self.next_index += 1;
+        self.open_block = Some(kind);
+        let block = match kind {
+            BlockKind::Text => json!({"type": "text", "text": ""}),
+            BlockKind::Thinking => json!({"type": "thinking", "thinking": ""}),
+        };
+        out.push(Self::event(
+            "content_block_start",
+            json!({"type": "content_block_start", "index": index, "content_block": block}),
+        ));
+        index
+    }
+
+    fn finish(&mut self, finish: Finish, output_tokens: u64, out: &mut Vec<String>) {
+        if self.finished {
+            return;
+        }
+        self.ensure_started(out);
+        self.close_block(out);
+        self.finished = true;
+        out.push(Self::event(
+            "message_delta",
+            json!({
+                "type": "message_delta",
+                "delta": {"stop_reason": finish.anthropic(), "stop_sequence": null},
+                "usage": {"output_tokens": output_tokens}
+            }),
+        ));
+        out.push(Self::event("message_stop", json!({"type": "message_stop"})));
+    }
+}
+
+impl StreamEncoder for AnthropicStreamEncoder {
+    fn encode(&mut self, delta: LocalDelta) -> Vec<String> {
+        let mut out = Vec::new();
+        if self.finished {
+            return out;
+        }
+        self.ensure_started(&mut out);
+
+        match delta {
+            LocalDelta::Text(text) if !text.is_empty() => {
+                let index = self.open_block(BlockKind::Text, &mut out);
+                out.push(Self::event(
+                    "content_block_delta",
+                    json!({
+                        "type": "content_block_delta",
+                        "index": index,
+                        "delta": {"type": "text_delta", "text": text}
+                    }),
+                ));
+            }
+            LocalDelta::Thinking(thinking) if !thinking.is_empty() => {
+                let index = self.open_block(BlockKind::Thinking, &mut out);
+                out.push(Self::event(
+                    "content_block_delta",
+                    json!({
+                        "type": "content_block_delta",
+                        "index": index,
+                        "delta": {"type": "thinking_delta", "thinking": thinking}
+                    }),
+                ));
+            }
+            LocalDelta::ToolCall(call) => {
+                self.close_block(&mut out);
+                let index = self.next_index;
+                self.next_index += 1;
+                out.push(Self::event(
+                    "content_block_start",
+                    json!({
+                        "type": "content_block_start",
+                        "index": index,
+                        "content_block": {"type": "tool_use", "id": call.id, "name": call.name, "input": {}}
+                    }),
+                ));
+                out.push(Self::event(
+                    "content_block_delta",
+                    json!({
+                        "type": "content_block_delta",
+                        "index": index,
+                        "delta": {"type": "input_json_delta", "partial_json": call.arguments.to_string()}
+                    }),
+                ));
+                out.push(Self::event(
+                    "content_block_stop",
+                    json!({"type": "content_block_stop", "index": index}),
+                ));
+            }
+            LocalDelta::Finish {
+                finish,
+                output_tokens,
+                ..
+            } => self.finish(finish, output_tokens, &mut out),
+            _ => {}
+        }
+        out
+    }
+
+    fn error(&mut self, message: &str) -> Vec<String> {
+        vec![Self::event(
+            "error",
+            json!({"type": "error", "error": {"type": "api_error", "message": message}}),
+        )]
+    }
+
+    fn end(&mut self) -> Vec<String> {
+        let mut out = Vec::new();
+        self.finish(Finish::Stop, 0, &mut out);
+        out
+    }
+}
+
+/// OpenAI Chat Completions chunk encoder
+pub struct OpenAIStreamEncoder {
+    id: String,
+    model: String,
+    created: i64,
+    started: bool,
+    finished: bool,
+    tool_index: usize,
+}
+
+impl OpenAIStreamEncoder {
+    pub fn new(model: impl Into<String>) -> Self {
+        Self {
+            id: format!("chatcmpl-{:016x}", rand::random::<u64>()),
+            model: model.into(),
+            created: chrono::Utc::now().timestamp(),
+            started: false,
+            finished: false,
+            tool_index: 0,
+        }
+    }
+
+    fn chunk(&self, delta: Value, finish_reason: Option<&str>, usage: Option<Value>) -> String {
+        let mut chunk = json!({
+            "id": self.id,
+            "object": "chat.completion.chunk",
+            "created": self.created,
+            "model": self.model,
+            "choices": [{"index": 0, "delta": delta, "finish_reason": finish_reason}],
+        });
+        if let Some(usage) = usage {
+            chunk["usage"] = usage;
+        }
+        format!("data: {}\n\n", chunk)
+    }
+
+    fn delta(&mut self, mut delta: Value) -> String {
+        if !self.started {
+            self.started = true;
+            delta["role"] = json!("assistant");
+        }
+        self.chunk(delta, None, None)
+    }
+}
+
+impl StreamEncoder for OpenAIStreamEncoder {
+    fn encode(&mut self, delta: LocalDelta) -> Vec<String> {
+        if self.finished {
+            return Vec::new();
+        }
+        match delta {
+            LocalDelta::Text(text) if !text.is_empty() => {
+                vec![self.delta(json!({"content": text}))]
+            }
+            LocalDelta::Thinking(thinking) if !thinking.is_empty() => {
+                vec![self.delta(json!({"reasoning_content": thinking}))]
+            }
+            LocalDelta::ToolCall(call) => {
+                let index = self.tool_index;
+                self.tool_index += 1;
+                vec![self.delta(json!({
+                    "tool_calls": [{
+                        "index": index,
+                        "id": call.id,
+                        "type": "function",
+                        "function": {"name": call.name, "arguments": call.arguments.to_string()}
+                    }]
+                }))]
+            }
+            LocalDelta::Finish {
+                finish,
+                input_tokens,
+                output_tokens,
+            } => {
+                self.finished = true;
+                vec![
+                    self.chunk(
+                        json!({}),
+                        Some(finish.openai()),
+                        Some(json!({
+                            "prompt_tokens": input_tokens,
+                            "completion_tokens": output_tokens,
+                            "total_tokens": input_tokens + output_tokens,
+                        })),
+                    ),
+                    "data: [DONE]\n\n".to_string(),
+                ]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn error(&mut self, message: &str) -> Vec<String> {
+        vec![format!(
+            "data: {}\n\n",
+            json!({"error": {"message": message, "type": "upstream_error"}})
+        )]
+    }
+
+    fn end(&mut self) -> Vec<String> {
+        if self.finished {
+            return Vec::new();
+        }
+        self.encode(LocalDelta::Finish {
+            finish: Finish::Stop,
+            input_tokens: 0,
+            output_tokens: 0,
+        })
+    }
+}
+
+pub type LineStream = BoxStream<'static, Result<String, String>>;
+
+/// Split a byte stream into non-empty lines
+pub fn byte_lines<S>(body: S) -> LineStream
+where
+    S: Stream<Item = reqwest::Result<Bytes>> + Send + 'static,
+{
+    let state = (
+        Box::pin(body),
+        Vec::<u8>::new(),
+        VecDeque::<String>::new(),
+        false,
+    );
+    stream::unfold(
+        state,
+        |(mut body, mut buffer, mut pending, mut done)| async move {
+            loop {
+                if let Some(line) = pending.pop_front() {
+                    return Some((Ok(line), (body, buffer, pending, done)));
+                }
+                if done {
+                    if buffer.is_empty() {
+                        return None;
+                    }
+                    let line = String::from_utf8_lossy(&buffer).trim().to_string();
+                    buffer.clear();
+                    if line.is_empty() {
+                        return None;
+                    }
+                    return Some((Ok(line), (body, buffer, pending, done)));
+                }
+                match body.next().await {
+                    Some(Ok(chunk)) => {
+                        buffer.extend_from_slice(&chunk);
+                        while let Some(pos) = buffer.iter().position(|b| *b == b'\n') {
+                            let line: Vec<u8> = buffer.drain(..=pos).collect();
+                            let line = String::from_utf8_lossy(&line).trim().to_string();
+                            if !line.is_empty() {
+                                pending.push_back(line);
+                            }
+                        }
+                    }
+                    Some(Err(e)) => {
+                        done = true;
+                        buffer.clear();
+                        return Some((Err(e.to_string()), (body, buffer, pending, done)));
+                    }
+                    None => done = true,
+                }
+            }
+        },
+    )
+    .boxed()
+}
+
+/// Drive upstream lines through a parser and encoder into a response body
+pub fn encode_stream<P, E>(lines: LineStream, parser: P, encoder: E) -> Body
+where
+    P: DeltaParser,
+    E: StreamEncoder,
+{
+    let state = (lines, parser, encoder, VecDeque::<String>::new(), false);
+    let events = stream::unfold(
+        state,
+        |(mut lines, mut parser, mut encoder, mut queue, mut ended)| async move {
+            loop {
+                if let Some(event) = queue.pop_front() {
+                    return Some((event, (lines, parser, encoder, queue, ended)));
+                }
+                if ended {
+                    return None;
+                }
+                match lines.next().await {
+                    Some(Ok(line)) => {
+                        for delta in parser.push_line(&line) {
+                            queue.extend(encoder.encode(delta));
+                        }
+                    }
+                    Some(Err(e)) => {
+                        tracing::error!("Local model stream error: {}", e);
+                        queue.extend(encoder.error(&e));
+                        queue.extend(encoder.end());
+                        ended = true;
+                    }
+                    None => {
+                        for delta in parser.finish() {
+                            queue.extend(encoder.encode(delta));
+                        }
+                        queue.extend(encoder.end());
+                        ended = true;
+                    }
+                }
+            }
+        },
+    );
+
+    Body::from_stream(events.map(|event| Ok::<_, std::convert::Infallible>(Bytes::from(event))))
+}
+
+/// Pick the SSE encoder for an inbound format (Gemini clients here don't stream)
+pub fn stream_body<P: DeltaParser>(
+    lines: LineStream,
+    parser: P,
+    format: InboundFormat,
+    model: &str,
+) -> Body {
+    match format {
+        InboundFormat::OpenAI => encode_stream(lines, parser, OpenAIStreamEncoder::new(model)),
+        InboundFormat::Anthropic | InboundFormat::Gemini => {
+            encode_stream(lines, parser, AnthropicStreamEncoder::new(model))
+        }
+    }
+}
+
+/// Build an SSE response around a stream body
+pub fn sse_response(body: Body) -> axum::response::Response {
+    axum::response::Response::builder()
+        .status(200)
+        .header("content-type", "text/event-stream")
+        .header("cache-control", "no-cache")
+        .header("connection", "keep-alive")
+        .body(body)
+        .unwrap()
+}
+
+/// Build a JSON response
+pub fn json_response(value: &Value) -> axum::response::Response {
+    axum::response::Response::builder()
+        .status(200)
+        .header("content-type", "application/json")
+        .body(Body::from(value.to_string()))
+        .unwrap()
+}
+
+/// Strip a `data:<mime>;base64,` prefix, leaving raw base64
+pub fn base64_payload(data_url: &str) -> &str {
+    match data_url.split_once(";base64,") {
+        Some((prefix, data)) if prefix.starts_with("data:") => data,
+        _ => data_url,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_anthropic_tool_round_trip_to_local() {
+        let request = json!({
+            "model": "qwen",
+            "system": [{"type": "text", "text": "be brief"}],
+            "max_tokens": 512,
+            "stream": true,
+            "messages": [
+                {"role": "user", "content": "list files"},
+                {"role": "assistant", "content": [
+                    {"type": "text", "text": "Sure."},
+                    {"type": "tool_use", "id": "toolu_1", "name": "ls", "input": {"path": "."}}
+                ]},
+                {"role": "user", "content": [
+                    {"type": "tool_result", "tool_use_id": "toolu_1", "content": [{"type": "text", "text": "a.rs"}]}
+                ]}
+            ],
+            "tools": [{"name": "ls", "description": "list", "input_schema": {"type": "object"}}]
+        });
+
+        let chat = LocalChat::from_anthropic_value(&request);
+        assert!(chat.stream);
+        assert_eq!(chat.max_tokens, Some(512));
+        assert_eq!(chat.messages.len(), 4);
+        assert_eq!(chat.messages[0]["role"], "system");
+        assert_eq!(chat.messages[2]["tool_calls"][0]["function"]["name"], "ls");
+        assert_eq!(
+            chat.messages[2]["tool_calls"][0]["function"]["arguments"]["path"],
+            "."
+        );
+        assert_eq!(chat.messages[3]["role"], "tool");
+        assert_eq!(chat.messages[3]["tool_call_id"], "toolu_1");
+        assert_eq!(chat.messages[3]["name"], "ls");
+        assert_eq!(chat.messages[3]["content"], "a.rs");
+        assert_eq!(chat.tools[0]["function"]["parameters"]["type"], "object");
+    }
+
+    #[test]
+    fn test_openai_arguments_parsed() {
+        let request = json!({
+            "model": "qwen",
+            "messages": [
+                {"role": "assistant", "content": null, "tool_calls": [
+                    {"id": "call_1", "type": "function", "function": {"name": "ls", "arguments": "{\"path\":\"src\"}"}}
+                ]},
+                {"role": "tool", "tool_call_id": "call_1", "content": "main.rs"}
+            ],
+            "max_completion_tokens": 100,
+            "stop": "END"
+        });
+
+        let chat = LocalChat::from_openai_value(&request);
+        assert_eq!(
+            chat.messages[0]["tool_calls"][0]["function"]["arguments"]["path"],
+            "src"
+        );
+        assert_eq!(chat.messages[1]["name"], "ls");
+        assert_eq!(chat.max_tokens, Some(100));
+        assert_eq!(chat.stop, vec!["END".to_string()]);
+    }
+
+    #[test]
+    fn test_gemini_function_calls() {
+        let request = json!({
+            "systemInstruction": {"parts": [{"text": "sys"}]},
+            "contents": [
+                {"role": "user", "parts": [{"text": "weather?"}]},
+                {"role": "model", "parts": [{"functionCall": {"name": "weather", "args": {"city": "Oslo"}}}]},
+                {"role": "user", "parts": [{"functionResponse": {"name": "weather", "response": {"temp": 3}}}]}
+            ],
+            "tools": [{"functionDeclarations": [{"name": "weather", "parameters": {"type": "object"}}]}],
+            "generationConfig": {"temperature": 0.2, "topK": 20, "maxOutputTokens": 64}
+        });
+
+        let chat = LocalChat::from_gemini_value(&request);
+        let call_id = chat.messages[2]["tool_calls"][0]["id"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        assert_eq!(chat.messages[3]["tool_call_id"], call_id.as_str());
+        assert_eq!(chat.messages[3]["content"], "{\"temp\":3}");
+        assert_eq!(chat.top_k, Some(20));
+        assert_eq!(chat.tools[0]["function"]["name"], "weather");
+    }
+
+    #[test]
+    fn test_response_rendering() {
+        let response = LocalResponse {
+            text: "Checking".to_string(),
+            thinking: None,
+            tool_calls: vec![LocalToolCall {
+                id: "call_1".to_string(),
+                name: "ls".to_string(),
+                arguments: json!({"path": "."}),
+            }],
+            finish: Finish::ToolCalls,
+            input_tokens: 10,
+            output_tokens: 5,
+        };
+
+        let anthropic = response.to_anthropic("qwen");
+        assert_eq!(anthropic["stop_reason"], "tool_use");
+        assert_eq!(anthropic["content"][1]["type"], "tool_use");
+        assert_eq!(anthropic["usage"]["output_tokens"], 5);
+        assert!(serde_json::from_value::<anthropic_ox::ChatResponse>(anthropic).is_ok());
+
+        let openai = response.to_openai("qwen");
+        assert_eq!(openai["choices"][0]["finish_reason"], "tool_calls");
+        assert_eq!(
+            openai["choices"][0]["message"]["tool_calls"][0]["function"]["arguments"],
+            "{\"path\":\".\"}"
+        );
+
+        let gemini = response.to_gemini();
+        assert_eq!(
+            gemini["candidates"][0]["content"]["parts"][1]["functionCall"]["name"],
+            "ls"
+        );
+    }
+
+    #[test]
+    fn test_anthropic_stream_encoder_sequence() {
+        let mut encoder = AnthropicStreamEncoder::new("qwen");
+        let mut events = Vec::new();
+        events.extend(encoder.encode(LocalDelta::Text("Hel".to_string())));
+        events.extend(encoder.encode(LocalDelta::Text("lo".to_string())));
+        events.extend(encoder.encode(LocalDelta::Finish {
+            finish: Finish::Stop,
+            input_tokens: 3,
+            output_tokens: 2,
+        }));
+        events.extend(encoder.end());
+
+        let names: Vec<&str> = events
+            .iter()
+            .map(|e| e.lines().next().unwrap().trim_start_matches("event: "))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "message_start",
+                "content_block_start",
+                "content_block_delta",
+                "content_block_delta",
+                "content_block_stop",
+                "message_delta",
+                "message_stop"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_local_model_name_rejoins_tag() {
+        let decision = RoutingDecision {
+            provider: "ollama".to_string(),
+            model: "qwen2.5-coder".to_string(),
+            original_model: "ollama/qwen2.5-coder:32b".to_string(),
+            provider_preference: Some("32b".to_string()),
+            query_params: None,
+            param_policy: None,
+        };
+        assert_eq!(local_model_name(&decision), "qwen2.5-coder:32b");
+    }
+}
@@ -1,6 +1,15 @@
 pub mod anthropic;
 pub mod auth;
+pub mod anthropic_upstream;
+pub mod azure;
+pub mod bedrock;
 pub mod gemini;
+pub mod llamacpp;
+pub mod local;
+pub mod ollama;
 pub mod openrouter;
 pub mod openai;
+pub mod openai_compatible;
 pub mod parsing;
+pub mod responses;
+pub mod vertex;
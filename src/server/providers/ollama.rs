@@ -0,0 +1,302 @@
+//! Native Ollama provider (`type = "ollama"`), talking to `/api/chat`.
+
+use axum::http::StatusCode;
+use serde_json::{Map, Value, json};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::auth::key_pool;
+use crate::config::Config;
+use crate::router::name_based::RoutingDecision;
+use crate::server::error_handling::{self, ApiError};
+use crate::server::parameter_mapping::apply_ollama_parameters;
+use crate::server::providers::local::{
+    self, DeltaParser, Finish, InboundFormat, LocalChat, LocalDelta, LocalResponse, LocalToolCall,
+};
+
+const DEFAULT_ENDPOINT: &str = "http://localhost:11434";
+
+/// Build the `/api/chat` body
+///
+/// Ollama wants tool call arguments as objects, raw base64 images and `tool_name`
+/// on tool results.
+pub fn build_request(chat: &LocalChat, model: &str) -> Value {
+    let messages: Vec<Value> = chat
+        .messages
+        .iter()
+        .map(|message| {
+            let mut out = Map::new();
+            out.insert("role".to_string(), message["role"].clone());
+            out.insert(
+                "content".to_string(),
+                json!(message["content"].as_str().unwrap_or_default()),
+            );
+            if let Some(images) = message.get("images").and_then(|i| i.as_array()) {
+                let images: Vec<&str> = images
+                    .iter()
+                    .filter_map(|i| i.as_str())
+                    .map(local::base64_payload)
+                    .collect();
+                out.insert("images".to_string(), json!(images));
+            }
+            if let Some(calls) = message.get("tool_calls").and_then(|t| t.as_array()) {
+                let calls: Vec<Value> = calls
+                    .iter()
+                    .map(|call| json!({"function": call["function"].clone()}))
+                    .collect();
+                out.insert("tool_calls".to_string(), Value::Array(calls));
+            }
+            if message["role"] == "tool" {
+                out.insert("tool_name".to_string(), message["name"].clone());
+            }
+            Value::Object(out)
+        })
+        .collect();
+
+    let mut options = Map::new();
+    if let Some(v) = chat.temperature {
+        options.insert("temperature".to_string(), json!(v));
+    }
+    if let Some(v) = chat.top_p {
+        options.insert("top_p".to_string(), json!(v));
+    }
+    if let Some(v) = chat.top_k {
+        options.insert("top_k".to_string(), json!(v));
+    }
+    if let Some(v) = chat.max_tokens {
+        options.insert("num_predict".to_string(), json!(v));
+    }
+    if !chat.stop.is_empty() {
+        options.insert("stop".to_string(), json!(chat.stop));
+    }
+
+    let mut body = Map::new();
+    body.insert("model".to_string(), json!(model));
+    body.insert("messages".to_string(), Value::Array(messages));
+    body.insert("stream".to_string(), json!(chat.stream));
+    if !chat.tools.is_empty() {
+        body.insert("tools".to_string(), json!(chat.tools));
+    }
+    if !options.is_empty() {
+        body.insert("options".to_string(), Value::Object(options));
+    }
+    if let Some(think) = chat.think {
+        body.insert("think".to_string(), json!(think));
+    }
+    Value::Object(body)
+}
+
+fn tool_calls_from(message: &Value) -> Vec<LocalToolCall> {
+    message
+        .get("tool_calls")
+        .and_then(|t| t.as_array())
+        .into_iter()
+        .flatten()
+        .map(|call| LocalToolCall {
+            id: call
+                .get("id")
+                .and_then(|i| i.as_str())
+                .map(str::to_string)
+                .unwrap_or_else(local::new_tool_call_id),
+            name: call
+                .pointer("/function/name")
+                .and_then(|n| n.as_str())
+                .unwrap_or_default()
+                .to_string(),
+            arguments: call
+                .pointer("/function/arguments")
+                .cloned()
+                .unwrap_or_else(|| json!({})),
+        })
+        .collect()
+}
+
+/// Parse a non-streaming `/api/chat` response
+pub fn parse_response(value: &Value) -> LocalResponse {
+    let message = value.get("message").cloned().unwrap_or(Value::Null);
+    let tool_calls = tool_calls_from(&message);
+    LocalResponse {
+        text: message["content"].as_str().unwrap_or_default().to_string(),
+        thinking: message["thinking"].as_str().map(str::to_string),
+        finish: Finish::from_reason(value["done_reason"].as_str(), !tool_calls.is_empty()),
+        tool_calls,
+        input_tokens: value["prompt_eval_count"].as_u64().unwrap_or(0),
+        output_tokens: value["eval_count"].as_u64().unwrap_or(0),
+    }
+}
+
+/// Parses Ollama's NDJSON stream, one response object per line
+#[derive(Default)]
+pub struct OllamaStreamParser {
+    saw_tool_call: bool,
+}
+
+impl DeltaParser for OllamaStreamParser {
+    fn push_line(&mut self, line: &str) -> Vec<LocalDelta> {
+        let Ok(value) = serde_json::from_str::<Value>(line) else {
+            tracing::debug!("Skipping unparseable Ollama stream line: {}", line);
+            return Vec::new();
+        };
+
+        let mut deltas = Vec::new();
+        let message = value.get("message").cloned().unwrap_or(Value::Null);
+        if let Some(thinking) = message["thinking"].as_str() {
+            deltas.push(LocalDelta::Thinking(thinking.to_string()));
+        }
+        if let Some(text) = message["content"].as_str() {
+            deltas.push(LocalDelta::Text(text.to_string()));
+        }
+        for call in tool_calls_from(&message) {
+            self.saw_tool_call = true;
+            deltas.push(LocalDelta::ToolCall(call));
+        }
+        if value["done"].as_bool() == Some(true) {
+            deltas.push(LocalDelta::Finish {
+                finish: Finish::from_reason(value["done_reason"].as_str(), self.saw_tool_call),
+                input_tokens: value["prompt_eval_count"].as_u64().unwrap_or(0),
+                output_tokens: value["eval_count"].as_u64().unwrap_or(0),
+            });
+        }
+        deltas
+    }
+}
+
+/// Send a normalised chat request to an Ollama server and answer in `format`
+pub async fn handle_ollama_request(
+    config: Arc<Mutex<Config>>,
+    chat: LocalChat,
+    format: InboundFormat,
+    routing_decision: RoutingDecision,
+) -> Result<axum::response::Response, StatusCode> {
+    let model = local::local_model_name(&routing_decision);
+
+    let cfg = config.lock().await;
+    let endpoint = cfg
+        .providers
+        .get(&routing_decision.provider)
+        .map(|p| p.endpoint.clone())
+        .filter(|e| !e.is_empty())
+        .unwrap_or_else(|| DEFAULT_ENDPOINT.to_string());
+    // Only needed for Ollama behind an authenticating proxy
+    let key = key_pool::acquire_api_key(&cfg, &routing_decision.provider, "OLLAMA_API_KEY");
+    drop(cfg);
+
+    let mut body = build_request(&chat, &model);
+    if let Some(params) = &routing_decision.query_params {
+        apply_ollama_parameters(&mut body, params);
+    }
+
+    let url = format!("{}/api/chat", endpoint.trim_end_matches('/'));
+    tracing::debug!(target: "setu::request", "Outgoing Ollama request to {}: {}", url, body);
+
+    let mut request = reqwest::Client::new().post(&url).json(&body);
+    if let Some(key) = &key {
+        request = request.bearer_auth(&key.secret);
+    }
+    let resp = match request.send().await {
+        Ok(r) => r,
+        Err(e) => return Err(error_handling::bad_gateway("Ollama request failed", &e)),
+    };
+
+    let status = resp.status();
+    if let Some(key) = &key {
+        key.report_status(status.as_u16());
+    }
+    if !status.is_success() {
+        let text = resp.text().await.unwrap_or_default();
+        tracing::error!("Ollama returned {}: {}", status, text);
+        return Ok(ApiError::from_upstream(status, "Ollama", &text).into_response(format));
+    }
+
+    if chat.stream {
+        let lines = local::byte_lines(resp.bytes_stream());
+        let body = local::stream_body(lines, OllamaStreamParser::default(), format, &model);
+        return Ok(local::sse_response(body));
+    }
+
+    let value: Value = match resp.json().await {
+        Ok(v) => v,
+        Err(e) => {
+            return Err(error_handling::bad_gateway(
+                "Failed to parse Ollama response",
+                &e,
+            ));
+        }
+    };
+    let response = parse_response(&value);
+    Ok(local::json_response(&response.render(format, &model)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_request_shapes_tools_and_images() {
+        let chat = LocalChat {
+            messages: vec![
+                json!({"role": "user", "content": "what's this?", "images": ["data:image/png;base64,AAAA"]}),
+                json!({"role": "assistant", "content": "", "tool_calls": [
+                    {"id": "call_1", "type": "function", "function": {"name": "look", "arguments": {"x": 1}}}
+                ]}),
+                json!({"role": "tool", "tool_call_id": "call_1", "name": "look", "content": "a cat"}),
+            ],
+            max_tokens: Some(256),
+            top_k: Some(20),
+            ..Default::default()
+        };
+
+        let body = build_request(&chat, "llava:13b");
+        assert_eq!(body["model"], "llava:13b");
+        assert_eq!(body["messages"][0]["images"][0], "AAAA");
+        assert_eq!(
+            body["messages"][1]["tool_calls"][0]["function"]["arguments"]["x"],
+            1
+        );
+        assert_eq!(body["messages"][2]["tool_name"], "look");
+        assert_eq!(body["options"]["num_predict"], 256);
+        assert_eq!(body["options"]["top_k"], 20);
+    }
+
+    #[test]
+    fn test_parse_response_with_tool_call() {
+        let value = json!({
+            "model": "qwen2.5-coder:32b",
+            "message": {
+                "role": "assistant",
+                "content": "",
+                "tool_calls": [{"function": {"name": "ls", "arguments": {"path": "."}}}]
+            },
+            "done": true,
+            "done_reason": "stop",
+            "prompt_eval_count": 12,
+            "eval_count": 7
+        });
+
+        let response = parse_response(&value);
+        assert_eq!(response.finish, Finish::ToolCalls);
+        assert_eq!(response.tool_calls[0].name, "ls");
+        assert_eq!(response.input_tokens, 12);
+        assert_eq!(response.output_tokens, 7);
+    }
+
+    #[test]
+    fn test_stream_parser_emits_finish() {
+        let mut parser = OllamaStreamParser::default();
+        let first =
+            parser.push_line(r#"{"message":{"role":"assistant","content":"Hi"},"done":false}"#);
+        assert_eq!(first, vec![LocalDelta::Text("Hi".to_string())]);
+
+        let last = parser.push_line(
+            r#"{"message":{"role":"assistant","content":""},"done":true,"done_reason":"length","prompt_eval_count":3,"eval_count":9}"#,
+        );
+        assert_eq!(
+            last.last(),
+            Some(&LocalDelta::Finish {
+                finish: Finish::Length,
+                input_tokens: 3,
+                output_tokens: 9
+            })
+        );
+    }
+}
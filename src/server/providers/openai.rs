@@ -1,13 +1,20 @@
 use axum::http::{HeaderMap, StatusCode};
-use serde_json::Value;
+use serde::Serialize;
+use serde_json::{Value, json};
+use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
 // use crate::auth::openai::OpenAIOAuth; // Currently unused - OAuth not functional
-use crate::config::Config;
+use crate::auth::key_pool::{self, PooledKey};
+use crate::config::{Config, OpenAIApi, ProviderQuirks};
 use crate::error::PrismError;
 use crate::router::name_based::RoutingDecision;
-use crate::server::error_handling;
+use crate::server::error_handling::{self, ApiError};
+use crate::server::parameter_mapping;
+use crate::server::providers::local::{self, InboundFormat, LocalChat};
+use crate::server::providers::openai_compatible::{self, ChatCompletionStreamParser};
+use crate::server::providers::responses::{self, ResponsesStreamParser};
 
 #[allow(dead_code)]
 enum OpenAIAuth {
@@ -25,36 +32,40 @@ fn sanitize_instructions_for_openai(_text: String) -> String {
 }
 
 /// Resolve OpenAI auth using OAuth (codex/setu) or API key
-async fn resolve_openai_auth(config: Arc<Mutex<Config>>) -> Result<OpenAIAuth, PrismError> {
+///
+/// API keys come from `provider`'s pool (api_key, api_keys); the pooled key is returned
+/// alongside so rate-limit/auth failures can bench it. `OPENAI_API_KEY` is only pooled for
+/// the built-in `openai` provider, so it is never sent to another `type = "openai"` endpoint.
+async fn resolve_openai_auth(
+    config: Arc<Mutex<Config>>,
+    provider: &str,
+) -> Result<(OpenAIAuth, Option<PooledKey>), PrismError> {
     // Temporarily skip OAuth - go straight to API key
     // TODO: Re-enable OAuth after fixing Responses API issues
-    
-    // Primary: Environment API key
-    if let Ok(api_key) = std::env::var("OPENAI_API_KEY") {
-        tracing::info!("🔐 OpenAI → API key via OPENAI_API_KEY");
-        return Ok(OpenAIAuth::ApiKey(api_key));
-    }
 
-    // Fallback: Config API key
     let cfg = config.lock().await;
-    if let Some(provider) = cfg.providers.get("openai")
-        && let Some(api_key) = &provider.api_key
-    {
-        tracing::info!("🔐 OpenAI → API key via prism config");
-        return Ok(OpenAIAuth::ApiKey(api_key.clone()));
+    let key = if provider == "openai" {
+        key_pool::acquire_api_key(&cfg, provider, "OPENAI_API_KEY")
+    } else {
+        let candidates = key_pool::configured_key_candidates(&cfg, provider);
+        key_pool::acquire_for_provider(cfg.providers.get(provider), provider, &candidates)
+    };
+    if let Some(key) = key {
+        tracing::info!("🔐 {} → API key via {}", provider, key.label);
+        return Ok((OpenAIAuth::ApiKey(key.secret.clone()), Some(key)));
     }
     drop(cfg);
 
-    Err(PrismError::Other(
-        "No OpenAI credentials available (OAuth or API key)".to_string(),
-    ))
+    Err(PrismError::Other(format!(
+        "No OpenAI credentials available for provider '{}' (OAuth or API key)",
+        provider
+    )))
 }
 
-fn openai_base_url(config: &Config) -> String {
-    if let Some(provider) = config.providers.get("openai") {
-        provider.endpoint.clone()
-    } else {
-        "https://api.openai.com".to_string()
+fn openai_base_url(config: &Config, provider: &str) -> String {
+    match config.providers.get(provider) {
+        Some(provider) if !provider.endpoint.is_empty() => provider.endpoint.clone(),
+        _ => "https://api.openai.com".to_string(),
     }
 }
 
@@ -64,49 +75,157 @@ fn auth_header_value(auth: &OpenAIAuth) -> String {
     }
 }
 
-/// Send OpenAI-format request directly to OpenAI
-pub async fn handle_openai_request_from_openai(
+/// Which flavour of the OpenAI API a target speaks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ApiFlavor {
+    OpenAI,
+    Azure,
+}
+
+/// Where OpenAI API calls go and how they authenticate (api.openai.com or an Azure resource)
+pub(crate) struct OpenAITarget {
+    /// Provider name used in logs
+    pub name: String,
+    pub flavor: ApiFlavor,
+    pub chat_url: String,
+    pub responses_url: String,
+    /// Auth header name and value (`Authorization: Bearer ...` or Azure's `api-key`)
+    pub auth_header: (&'static str, String),
+    /// Model name sent in the body (for Azure, the deployment name)
+    pub model: String,
+    pub key: Option<PooledKey>,
+    /// API used for Anthropic-format requests
+    pub api: OpenAIApi,
+    /// Applied to Chat Completions bodies built from Anthropic requests
+    pub quirks: ProviderQuirks,
+    /// Model-string query parameters (`?effort=high&seed=1`)
+    pub query_params: HashMap<String, String>,
+}
+
+impl OpenAITarget {
+    /// Turn a non-success upstream response into the one returned to the client
+    ///
+    /// OpenAI error bodies pass through unchanged to OpenAI clients; anything else is
+    /// rewritten into the client's error format.
+    fn error_response(
+        &self,
+        format: InboundFormat,
+        status: u16,
+        text: String,
+    ) -> axum::response::Response {
+        tracing::error!("{} returned {}: {}", self.name, status, text);
+        let status = StatusCode::from_u16(status).unwrap_or(StatusCode::BAD_GATEWAY);
+        let error = match (self.flavor, format) {
+            (ApiFlavor::OpenAI, InboundFormat::OpenAI) => {
+                return axum::response::Response::builder()
+                    .status(status)
+                    .header("content-type", "application/json")
+                    .body(axum::body::Body::from(text))
+                    .unwrap();
+            }
+            (ApiFlavor::OpenAI, _) => ApiError::from_upstream(status, &self.name, &text),
+            (ApiFlavor::Azure, _) => {
+                crate::server::providers::azure::normalize_error(status, &text, &self.model)
+            }
+        };
+        error.into_response(format)
+    }
+}
+
+async fn openai_target(
     config: Arc<Mutex<Config>>,
-    openai_request: openai_ox::request::ChatRequest,
-    _routing_decision: RoutingDecision,
-    _headers: HeaderMap,
-) -> Result<axum::response::Response, StatusCode> {
-    // Prepare HTTP
-    let auth = match resolve_openai_auth(config.clone()).await {
+    routing_decision: &RoutingDecision,
+) -> Result<OpenAITarget, StatusCode> {
+    let (auth, key) = match resolve_openai_auth(config.clone(), &routing_decision.provider).await {
         Ok(a) => a,
         Err(e) => return Err(error_handling::unauthorized(&e.to_string())),
     };
     let cfg = config.lock().await;
-    let base = openai_base_url(&cfg);
+    let base = openai_base_url(&cfg, &routing_decision.provider);
+    let (api, quirks) = cfg
+        .providers
+        .get(&routing_decision.provider)
+        .map(|p| (p.api, p.quirks.clone()))
+        .unwrap_or_default();
     drop(cfg);
+    let base = base.trim_end_matches('/');
+
+    let responses_url = match &auth {
+        // ChatGPT Responses endpoint (kept for future use)
+        OpenAIAuth::OAuth(_) => "https://chatgpt.com/backend-api/codex/responses".to_string(),
+        // Platform Responses endpoint
+        OpenAIAuth::ApiKey(_) => format!("{}/v1/responses", base),
+    };
+
+    Ok(OpenAITarget {
+        name: "OpenAI".to_string(),
+        flavor: ApiFlavor::OpenAI,
+        chat_url: format!("{}/v1/chat/completions", base),
+        responses_url,
+        auth_header: ("Authorization", auth_header_value(&auth)),
+        model: routing_decision.model.clone(),
+        key,
+        api,
+        quirks,
+        query_params: routing_decision.query_params.clone().unwrap_or_default(),
+    })
+}
 
-    let url = format!("{}/v1/chat/completions", base.trim_end_matches('/'));
+/// Send OpenAI-format request directly to OpenAI
+pub async fn handle_openai_request_from_openai(
+    config: Arc<Mutex<Config>>,
+    openai_request: openai_ox::request::ChatRequest,
+    routing_decision: RoutingDecision,
+    _headers: HeaderMap,
+) -> Result<axum::response::Response, StatusCode> {
+    let target = openai_target(config, &routing_decision).await?;
+    send_chat_completions(&target, openai_request).await
+}
+
+/// POST a Chat Completions request to the target
+pub(crate) async fn send_chat_completions(
+    target: &OpenAITarget,
+    openai_request: openai_ox::request::ChatRequest,
+) -> Result<axum::response::Response, StatusCode> {
     if let Some(req_str) = crate::server::error_handling::prepare_openai_request_log(&openai_request) {
-        tracing::debug!(target = "setu::request", "Outgoing OpenAI request (detailed): {}", req_str);
+        tracing::debug!(target = "setu::request", "Outgoing {} request (detailed): {}", target.name, req_str);
     }
 
+    let mut body = match serde_json::to_value(&openai_request) {
+        Ok(v) => v,
+        Err(e) => return Err(error_handling::internal_error("Failed to serialize OpenAI request", &e)),
+    };
+    body["model"] = json!(target.model);
+    parameter_mapping::apply_openai_parameters(&mut body, &target.query_params);
+
     let client = reqwest::Client::new();
     let resp = match client
-        .post(&url)
-        .header("Authorization", auth_header_value(&auth))
+        .post(&target.chat_url)
+        .header(target.auth_header.0, &target.auth_header.1)
         .header("Content-Type", "application/json")
-        .json(&openai_request)
+        .json(&body)
         .send()
         .await
     {
         Ok(r) => r,
-        Err(e) => return Err(error_handling::bad_gateway("OpenAI request failed", &e)),
+        Err(e) => return Err(error_handling::bad_gateway(&format!("{} request failed", target.name), &e)),
     };
 
     let status = resp.status().as_u16();
+    if let Some(key) = &target.key {
+        key.report_status(status);
+    }
     let text = match resp.text().await {
         Ok(t) => t,
-        Err(e) => return Err(error_handling::bad_gateway("Failed to read OpenAI response", &e)),
+        Err(e) => return Err(error_handling::bad_gateway(&format!("Failed to read {} response", target.name), &e)),
     };
+    if !(200..300).contains(&status) {
+        return Ok(target.error_response(InboundFormat::OpenAI, status, text));
+    }
 
     if let Ok(val) = serde_json::from_str::<Value>(&text) {
         if let Some(resp_str) = crate::server::error_handling::prepare_response_log(&val) {
-            tracing::debug!(target = "setu::response", "OpenAI response: {}", resp_str);
+            tracing::debug!(target = "setu::response", "{} response: {}", target.name, resp_str);
         }
     }
 
@@ -121,14 +240,146 @@ pub async fn handle_openai_request_from_openai(
 pub async fn handle_openai_request_from_anthropic(
     config: Arc<Mutex<Config>>,
     anthropic_request: anthropic_ox::ChatRequest,
-    _routing_decision: RoutingDecision,
+    routing_decision: RoutingDecision,
     _headers: HeaderMap,
 ) -> Result<axum::response::Response, StatusCode> {
-    // Prepare auth
-    let auth = match resolve_openai_auth(config.clone()).await {
-        Ok(a) => a,
-        Err(e) => return Err(error_handling::unauthorized(&e.to_string())),
+    let target = openai_target(config, &routing_decision).await?;
+    send_from_anthropic(&target, anthropic_request).await
+}
+
+/// Send an Anthropic request through the target's configured API
+pub(crate) async fn send_from_anthropic(
+    target: &OpenAITarget,
+    anthropic_request: anthropic_ox::ChatRequest,
+) -> Result<axum::response::Response, StatusCode> {
+    match target.api {
+        OpenAIApi::Responses => send_responses_from_anthropic(target, anthropic_request).await,
+        OpenAIApi::Chat => send_chat_from_anthropic(target, anthropic_request).await,
+    }
+}
+
+/// Map Anthropic extended thinking to a Chat Completions `reasoning_effort`
+fn reasoning_effort(thinking: &Value) -> Option<&'static str> {
+    if thinking["type"].as_str() != Some("enabled") {
+        return None;
+    }
+    Some(match thinking["budget_tokens"].as_u64().unwrap_or(0) {
+        0..4096 => "low",
+        4096..16384 => "medium",
+        _ => "high",
+    })
+}
+
+/// Map an Anthropic `tool_choice` to the Chat Completions form
+fn chat_tool_choice(tool_choice: &Value) -> Option<Value> {
+    match tool_choice["type"].as_str()? {
+        "auto" => Some(json!("auto")),
+        "any" => Some(json!("required")),
+        "none" => Some(json!("none")),
+        "tool" => Some(json!({"type": "function", "function": {"name": tool_choice["name"]}})),
+        _ => None,
+    }
+}
+
+/// Chat Completions body for an Anthropic request (tools, tool results, images, thinking)
+pub(crate) fn chat_body_from_anthropic(
+    request: &Value,
+    model: &str,
+    quirks: &ProviderQuirks,
+    query_params: &HashMap<String, String>,
+) -> Value {
+    let chat = LocalChat::from_anthropic_value(request);
+    let mut body = openai_compatible::build_request(&chat, model);
+    if let Some(map) = body.as_object_mut() {
+        // OpenAI rejects top_k
+        map.remove("top_k");
+        if let Some(Value::Array(messages)) = map.get_mut("messages") {
+            for message in messages.iter_mut().filter(|m| m["role"] == "tool") {
+                if let Some(message) = message.as_object_mut() {
+                    message.remove("name");
+                }
+            }
+        }
+        if let Some(effort) = reasoning_effort(&request["thinking"]) {
+            map.insert("reasoning_effort".to_string(), json!(effort));
+        }
+        if let Some(choice) = chat_tool_choice(&request["tool_choice"]) {
+            map.insert("tool_choice".to_string(), choice);
+        }
+    }
+    parameter_mapping::apply_openai_parameters(&mut body, query_params);
+    openai_compatible::apply_quirks(&mut body, quirks);
+    body
+}
+
+/// Send an Anthropic request as Chat Completions and answer in Anthropic format
+pub(crate) async fn send_chat_from_anthropic(
+    target: &OpenAITarget,
+    anthropic_request: anthropic_ox::ChatRequest,
+) -> Result<axum::response::Response, StatusCode> {
+    let request = match serde_json::to_value(&anthropic_request) {
+        Ok(v) => v,
+        Err(e) => return Err(error_handling::internal_error("Failed to serialize Anthropic request", &e)),
+    };
+    let body = chat_body_from_anthropic(&request, &target.model, &target.quirks, &target.query_params);
+    let stream = body["stream"].as_bool().unwrap_or(false);
+    let reasoning_field = target
+        .quirks
+        .reasoning_field
+        .clone()
+        .unwrap_or_else(|| openai_compatible::DEFAULT_REASONING_FIELD.to_string());
+    let model = request["model"].as_str().unwrap_or(&target.model).to_string();
+
+    if let Some(req_str) = crate::server::error_handling::prepare_request_log(&body) {
+        tracing::debug!(target = "setu::request", "Outgoing {} Chat Completions (from Anthropic) request (detailed): {}", target.name, req_str);
+    }
+
+    let resp = match reqwest::Client::new()
+        .post(&target.chat_url)
+        .header(target.auth_header.0, &target.auth_header.1)
+        .header("Content-Type", "application/json")
+        .json(&body)
+        .send()
+        .await
+    {
+        Ok(r) => r,
+        Err(e) => return Err(error_handling::bad_gateway(&format!("{} request failed", target.name), &e)),
+    };
+
+    let status = resp.status().as_u16();
+    if let Some(key) = &target.key {
+        key.report_status(status);
+    }
+    if !(200..300).contains(&status) {
+        let text = resp.text().await.unwrap_or_default();
+        return Ok(target.error_response(InboundFormat::Anthropic, status, text));
+    }
+
+    if stream {
+        let lines = local::byte_lines(resp.bytes_stream());
+        let parser = ChatCompletionStreamParser::new(reasoning_field);
+        let body = local::stream_body(lines, parser, InboundFormat::Anthropic, &model);
+        return Ok(local::sse_response(body));
+    }
+
+    let value: Value = match resp.json().await {
+        Ok(v) => v,
+        Err(e) => return Err(error_handling::bad_gateway(&format!("Failed to parse {} response", target.name), &e)),
     };
+    if let Some(resp_str) = crate::server::error_handling::prepare_response_log(&value) {
+        tracing::debug!(target = "setu::response", "{} response: {}", target.name, resp_str);
+    }
+    let response = openai_compatible::parse_response(&value, &reasoning_field);
+    Ok(local::json_response(&response.to_anthropic(&model)))
+}
+
+/// Convert an Anthropic request to the Responses API, POST it to the target and convert
+/// the reply back to an Anthropic message
+pub(crate) async fn send_responses_from_anthropic(
+    target: &OpenAITarget,
+    anthropic_request: anthropic_ox::ChatRequest,
+) -> Result<axum::response::Response, StatusCode> {
+    let model = anthropic_request.model.clone();
     // Pass through original request without instruction sanitization (acting as proxy)
     // Convert Anthropic → OpenAI Responses API using ai-ox
     let mut responses_req = match conversion_ox::anthropic_openai::anthropic_to_openai_responses_request(
@@ -142,73 +393,150 @@ pub async fn handle_openai_request_from_anthropic(
             ))
         }
     };
-    responses_req.model = _routing_decision.model.clone();
-    
+    responses_req.model = target.model.clone();
+    let mut responses_req = match serde_json::to_value(&responses_req) {
+        Ok(v) => v,
+        Err(e) => return Err(error_handling::internal_error("Failed to serialize Responses request", &e)),
+    };
+    parameter_mapping::apply_openai_responses_parameters(&mut responses_req, &target.query_params);
+
     if let Some(req_str) = crate::server::error_handling::prepare_request_log(&responses_req) {
-        tracing::debug!(target = "setu::request", "Outgoing OpenAI Responses (from Anthropic) request (detailed): {}", req_str);
+        tracing::debug!(target = "setu::request", "Outgoing {} Responses (from Anthropic) request (detailed): {}", target.name, req_str);
     }
 
-    // Build OpenAI client targeting appropriate base URL
-    let client = match &auth {
-        OpenAIAuth::OAuth(token) => {
-            // ChatGPT Responses endpoint (kept for future use)
-            openai_ox::OpenAI::builder()
-                .api_key(token.clone())
-                .base_url("https://chatgpt.com/backend-api/codex")
-                .build()
-        }
-        OpenAIAuth::ApiKey(key) => {
-            // Platform Responses endpoint
-            let cfg = config.lock().await;
-            let base = openai_base_url(&cfg);
-            drop(cfg);
-            openai_ox::OpenAI::builder()
-                .api_key(key.clone())
-                .base_url(format!("{}/v1", base.trim_end_matches('/')))
-                .build()
-        }
-    };
+    send_responses(target, &responses_req, &model).await
+}
 
-    // Send via Responses API
-    match client.send_responses(&responses_req).await {
-        Ok(resp) => {
-            if let Some(resp_str) = crate::server::error_handling::prepare_response_log(&resp) {
-                tracing::debug!(target = "setu::response", "OpenAI Responses response: {}", resp_str);
-            }
-            let json_body = serde_json::to_string(&resp).unwrap_or_else(|_| "{}".to_string());
-            Ok(axum::response::Response::builder()
-                .status(200)
-                .header("content-type", "application/json")
-                .body(axum::body::Body::from(json_body))
-                .unwrap())
-        }
+/// POST a Responses API request to the target and answer in Anthropic format
+pub(crate) async fn send_responses(
+    target: &OpenAITarget,
+    responses_req: &impl Serialize,
+    model: &str,
+) -> Result<axum::response::Response, StatusCode> {
+    let stream = serde_json::to_value(responses_req)
+        .ok()
+        .and_then(|v| v["stream"].as_bool())
+        .unwrap_or(false);
+
+    let client = reqwest::Client::new();
+    let mut request = client
+        .post(&target.responses_url)
+        .header(target.auth_header.0, &target.auth_header.1)
+        .header("Content-Type", "application/json");
+    if target.flavor == ApiFlavor::OpenAI {
+        request = request.header("OpenAI-Beta", "responses=experimental");
+    }
+    let resp = match request.json(responses_req).send().await {
+        Ok(r) => r,
         Err(e) => {
-            tracing::error!("OpenAI Responses API error details: {:?}", e);
-            // Try to make a direct HTTP call to get more details
-            let cfg = config.lock().await;
-            let base = openai_base_url(&cfg);
-            drop(cfg);
-            
-            let url = format!("{}/v1/responses", base.trim_end_matches('/'));
-            let client = reqwest::Client::new();
-            
-            let resp = client
-                .post(&url)
-                .header("Authorization", auth_header_value(&auth))
-                .header("OpenAI-Beta", "responses=experimental")
-                .header("Content-Type", "application/json")
-                .json(&responses_req)
-                .send()
-                .await;
-                
-            if let Ok(r) = resp {
-                let status = r.status();
-                if let Ok(text) = r.text().await {
-                    tracing::error!("Raw OpenAI response (status {}): {}", status, text);
-                }
-            }
-            
-            Err(error_handling::bad_gateway("OpenAI Responses API request failed", &e))
+            return Err(error_handling::bad_gateway(
+                &format!("{} Responses API request failed", target.name),
+                &e,
+            ))
         }
+    };
+
+    let status = resp.status().as_u16();
+    if let Some(key) = &target.key {
+        key.report_status(status);
+    }
+    if !(200..300).contains(&status) {
+        let text = resp.text().await.unwrap_or_default();
+        return Ok(target.error_response(InboundFormat::Anthropic, status, text));
+    }
+
+    if stream {
+        let lines = local::byte_lines(resp.bytes_stream());
+        let body = local::stream_body(
+            lines,
+            ResponsesStreamParser::default(),
+            InboundFormat::Anthropic,
+            model,
+        );
+        return Ok(local::sse_response(body));
+    }
+
+    let value: Value = match resp.json().await {
+        Ok(v) => v,
+        Err(e) => return Err(error_handling::bad_gateway(&format!("Failed to parse {} response", target.name), &e)),
+    };
+    if let Some(resp_str) = crate::server::error_handling::prepare_response_log(&value) {
+        tracing::debug!(target = "setu::response", "{} Responses response: {}", target.name, resp_str);
+    }
+
+    Ok(local::json_response(&responses::to_anthropic(&value, model)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base_url_uses_routed_provider() {
+        let mut config = Config::default();
+        config.providers.insert(
+            "openai-eu".to_string(),
+            crate::config::ProviderConfig {
+                r#type: "openai".to_string(),
+                endpoint: "https://eu.api.openai.com/".to_string(),
+                ..Default::default()
+            },
+        );
+        assert_eq!(openai_base_url(&config, "openai-eu"), "https://eu.api.openai.com/");
+        assert_eq!(openai_base_url(&config, "openai"), "https://api.openai.com");
+    }
+
+    #[test]
+    fn test_chat_body_from_anthropic() {
+        let request = json!({
+            "model": "gpt-4o",
+            "max_tokens": 1024,
+            "top_k": 5,
+            "system": "Be brief",
+            "thinking": {"type": "enabled", "budget_tokens": 8000},
+            "tool_choice": {"type": "tool", "name": "weather"},
+            "tools": [{"name": "weather", "input_schema": {"type": "object"}}],
+            "messages": [
+                {"role": "user", "content": "Weather in Paris?"},
+                {"role": "assistant", "content": [
+                    {"type": "tool_use", "id": "toolu_1", "name": "weather", "input": {"city": "Paris"}}
+                ]},
+                {"role": "user", "content": [
+                    {"type": "tool_result", "tool_use_id": "toolu_1", "content": "sunny"}
+                ]}
+            ]
+        });
+        let body = chat_body_from_anthropic(
+            &request,
+            "prod-gpt4o",
+            &ProviderQuirks::default(),
+            &HashMap::new(),
+        );
+
+        assert_eq!(body["model"], "prod-gpt4o");
+        assert!(body.get("top_k").is_none());
+        assert_eq!(body["reasoning_effort"], "medium");
+        assert_eq!(body["tool_choice"]["function"]["name"], "weather");
+        assert_eq!(body["messages"][0]["role"], "system");
+        assert_eq!(
+            body["messages"][2]["tool_calls"][0]["function"]["arguments"],
+            "{\"city\":\"Paris\"}"
+        );
+        assert_eq!(body["messages"][3]["role"], "tool");
+        assert_eq!(body["messages"][3]["tool_call_id"], "toolu_1");
+        assert!(body["messages"][3].get("name").is_none());
+    }
+
+    #[test]
+    fn test_reasoning_effort_from_budget() {
+        assert_eq!(reasoning_effort(&json!({"type": "disabled"})), None);
+        assert_eq!(
+            reasoning_effort(&json!({"type": "enabled", "budget_tokens": 1024})),
+            Some("low")
+        );
+        assert_eq!(
+            reasoning_effort(&json!({"type": "enabled", "budget_tokens": 32000})),
+            Some("high")
+        );
     }
 }
@@ -0,0 +1,532 @@
+//! OpenAI-compatible vendors (`type = "openai-compatible"`): Groq, DeepSeek, Mistral, xAI,
+//! Together, Fireworks, vLLM and friends.
+//!
+//! Requests are sent to `<endpoint>/chat/completions` and shaped by the provider's
+//! `quirks` block, so a new vendor only needs configuration. The Chat Completions
+//! helpers here are shared with the llama.cpp provider.
+
+use axum::http::StatusCode;
+use serde_json::{Map, Value, json};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::auth::key_pool::{self, PooledKey};
+use crate::config::{Config, MaxTokensField, ProviderQuirks, ToolChoiceSupport};
+use crate::router::name_based::RoutingDecision;
+use crate::server::error_handling::{self, ApiError};
+use crate::server::parameter_mapping::apply_chat_completions_parameters;
+use crate::server::providers::local::{
+    self, DeltaParser, Finish, InboundFormat, LocalChat, LocalDelta, LocalResponse, LocalToolCall,
+};
+
+/// Reasoning field used when a provider doesn't configure one
+pub const DEFAULT_REASONING_FIELD: &str = "reasoning_content";
+
+/// Request accepted by Chat Completions upstreams
+pub enum ChatInput {
+    /// Already in Chat Completions format; forwarded with only the model replaced
+    OpenAI(openai_ox::request::ChatRequest),
+    /// Converted from another client format
+    Local(LocalChat),
+}
+
+impl ChatInput {
+    fn into_body(self, model: &str) -> Value {
+        match self {
+            ChatInput::OpenAI(request) => {
+                let mut body = serde_json::to_value(&request).unwrap_or_else(|_| json!({}));
+                body["model"] = json!(model);
+                if let Some(map) = body.as_object_mut() {
+                    map.retain(|_, v| !v.is_null());
+                }
+                body
+            }
+            ChatInput::Local(chat) => build_request(&chat, model),
+        }
+    }
+}
+
+/// Build the Chat Completions body
+///
+/// Tool call arguments go back to JSON strings and images become `image_url` parts.
+pub fn build_request(chat: &LocalChat, model: &str) -> Value {
+    let messages: Vec<Value> = chat
+        .messages
+        .iter()
+        .map(|message| {
+            let mut out = message.as_object().cloned().unwrap_or_default();
+            if let Some(images) = out.remove("images").and_then(|i| i.as_array().cloned()) {
+                let mut parts = vec![json!({"type": "text", "text": message["content"]})];
+                parts.extend(
+                    images
+                        .iter()
+                        .map(|url| json!({"type": "image_url", "image_url": {"url": url}})),
+                );
+                out.insert("content".to_string(), Value::Array(parts));
+            }
+            if let Some(Value::Array(calls)) = out.get_mut("tool_calls") {
+                for call in calls {
+                    if let Some(arguments) = call.pointer_mut("/function/arguments")
+                        && !arguments.is_string()
+                    {
+                        *arguments = json!(arguments.to_string());
+                    }
+                }
+            }
+            Value::Object(out)
+        })
+        .collect();
+
+    let mut body = Map::new();
+    body.insert("model".to_string(), json!(model));
+    body.insert("messages".to_string(), Value::Array(messages));
+    body.insert("stream".to_string(), json!(chat.stream));
+    if chat.stream {
+        body.insert("stream_options".to_string(), json!({"include_usage": true}));
+    }
+    if !chat.tools.is_empty() {
+        body.insert("tools".to_string(), json!(chat.tools));
+    }
+    if let Some(v) = chat.temperature {
+        body.insert("temperature".to_string(), json!(v));
+    }
+    if let Some(v) = chat.top_p {
+        body.insert("top_p".to_string(), json!(v));
+    }
+    if let Some(v) = chat.top_k {
+        body.insert("top_k".to_string(), json!(v));
+    }
+    if let Some(v) = chat.max_tokens {
+        body.insert("max_tokens".to_string(), json!(v));
+    }
+    if !chat.stop.is_empty() {
+        body.insert("stop".to_string(), json!(chat.stop));
+    }
+    Value::Object(body)
+}
+
+fn parse_arguments(arguments: &Value) -> Value {
+    match arguments {
+        Value::String(s) => serde_json::from_str(s).unwrap_or_else(|_| json!({})),
+        Value::Null => json!({}),
+        other => other.clone(),
+    }
+}
+
+/// Parse a non-streaming Chat Completions response, reading reasoning from `reasoning_field`
+pub fn parse_response(value: &Value, reasoning_field: &str) -> LocalResponse {
+    let choice = value.pointer("/choices/0").cloned().unwrap_or(Value::Null);
+    let message = &choice["message"];
+    let tool_calls: Vec<LocalToolCall> = message["tool_calls"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|call| LocalToolCall {
+            id: call["id"]
+                .as_str()
+                .map(str::to_string)
+                .unwrap_or_else(local::new_tool_call_id),
+            name: call["function"]["name"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            arguments: parse_arguments(&call["function"]["arguments"]),
+        })
+        .collect();
+
+    LocalResponse {
+        text: message["content"].as_str().unwrap_or_default().to_string(),
+        thinking: message[reasoning_field].as_str().map(str::to_string),
+        finish: Finish::from_reason(choice["finish_reason"].as_str(), !tool_calls.is_empty()),
+        tool_calls,
+        input_tokens: value["usage"]["prompt_tokens"].as_u64().unwrap_or(0),
+        output_tokens: value["usage"]["completion_tokens"].as_u64().unwrap_or(0),
+    }
+}
+
+#[derive(Default)]
+struct PendingToolCall {
+    id: String,
+    name: String,
+    arguments: String,
+}
+
+/// Parses Chat Completions SSE chunks; tool call fragments are buffered until the call completes
+pub struct ChatCompletionStreamParser {
+    reasoning_field: String,
+    tool_calls: BTreeMap<u64, PendingToolCall>,
+    finish_reason: Option<String>,
+    input_tokens: u64,
+    output_tokens: u64,
+    finished: bool,
+}
+
+impl ChatCompletionStreamParser {
+    pub fn new(reasoning_field: impl Into<String>) -> Self {
+        Self {
+            reasoning_field: reasoning_field.into(),
+            tool_calls: BTreeMap::new(),
+            finish_reason: None,
+            input_tokens: 0,
+            output_tokens: 0,
+            finished: false,
+        }
+    }
+
+    fn flush(&mut self) -> Vec<LocalDelta> {
+        if self.finished {
+            return Vec::new();
+        }
+        self.finished = true;
+
+        let calls = std::mem::take(&mut self.tool_calls);
+        let has_tool_calls = !calls.is_empty();
+        let mut deltas: Vec<LocalDelta> = calls
+            .into_values()
+            .map(|call| {
+                LocalDelta::ToolCall(LocalToolCall {
+                    id: if call.id.is_empty() {
+                        local::new_tool_call_id()
+                    } else {
+                        call.id
+                    },
+                    name: call.name,
+                    arguments: parse_arguments(&Value::String(call.arguments)),
+                })
+            })
+            .collect();
+        deltas.push(LocalDelta::Finish {
+            finish: Finish::from_reason(self.finish_reason.as_deref(), has_tool_calls),
+            input_tokens: self.input_tokens,
+            output_tokens: self.output_tokens,
+        });
+        deltas
+    }
+}
+
+impl DeltaParser for ChatCompletionStreamParser {
+    fn push_line(&mut self, line: &str) -> Vec<LocalDelta> {
+        let Some(data) = line.strip_prefix("data:").map(str::trim) else {
+            return Vec::new();
+        };
+        if data == "[DONE]" {
+            return self.flush();
+        }
+        let Ok(chunk) = serde_json::from_str::<Value>(data) else {
+            tracing::debug!("Skipping unparseable stream chunk: {}", data);
+            return Vec::new();
+        };
+
+        if let Some(usage) = chunk.get("usage").filter(|u| !u.is_null()) {
+            self.input_tokens = usage["prompt_tokens"].as_u64().unwrap_or(self.input_tokens);
+            self.output_tokens = usage["completion_tokens"]
+                .as_u64()
+                .unwrap_or(self.output_tokens);
+        }
+
+        let mut deltas = Vec::new();
+        let Some(choice) = chunk.pointer("/choices/0") else {
+            return deltas;
+        };
+        let delta = &choice["delta"];
+        if let Some(thinking) = delta[self.reasoning_field.as_str()].as_str() {
+            deltas.push(LocalDelta::Thinking(thinking.to_string()));
+        }
+        if let Some(text) = delta["content"].as_str() {
+            deltas.push(LocalDelta::Text(text.to_string()));
+        }
+        for fragment in delta["tool_calls"].as_array().into_iter().flatten() {
+            let index = fragment["index"].as_u64().unwrap_or(0);
+            let pending = self.tool_calls.entry(index).or_default();
+            if let Some(id) = fragment["id"].as_str() {
+                pending.id = id.to_string();
+            }
+            if let Some(name) = fragment["function"]["name"].as_str() {
+                pending.name.push_str(name);
+            }
+            if let Some(arguments) = fragment["function"]["arguments"].as_str() {
+                pending.arguments.push_str(arguments);
+            }
+        }
+        if let Some(reason) = choice["finish_reason"].as_str() {
+            self.finish_reason = Some(reason.to_string());
+        }
+        deltas
+    }
+
+    fn finish(&mut self) -> Vec<LocalDelta> {
+        self.flush()
+    }
+}
+
+/// Reshape a Chat Completions body for a vendor's quirks
+pub fn apply_quirks(body: &mut Value, quirks: &ProviderQuirks) {
+    let Some(body) = body.as_object_mut() else {
+        return;
+    };
+
+    let limit = body
+        .remove("max_completion_tokens")
+        .filter(|v| !v.is_null())
+        .or_else(|| body.remove("max_tokens").filter(|v| !v.is_null()));
+    if let Some(limit) = limit {
+        let field = match quirks.max_tokens_field {
+            MaxTokensField::MaxTokens => "max_tokens",
+            MaxTokensField::MaxCompletionTokens => "max_completion_tokens",
+        };
+        body.insert(field.to_string(), limit);
+    }
+
+    match quirks.tool_choice {
+        ToolChoiceSupport::Full => {}
+        ToolChoiceSupport::StringOnly => {
+            if let Some(choice) = body.get_mut("tool_choice")
+                && choice.is_object()
+            {
+                *choice = json!("required");
+            }
+        }
+        ToolChoiceSupport::Unsupported => {
+            body.remove("tool_choice");
+        }
+    }
+
+    for (key, value) in &quirks.extra_body {
+        body.insert(key.clone(), value.clone());
+    }
+    for param in &quirks.unsupported_params {
+        body.remove(param);
+    }
+}
+
+/// Move a vendor's reasoning field to `reasoning_content` in a Chat Completions response
+pub fn normalize_response(value: &mut Value, reasoning_field: &str) {
+    if reasoning_field == DEFAULT_REASONING_FIELD {
+        return;
+    }
+    for choice in value
+        .get_mut("choices")
+        .and_then(|c| c.as_array_mut())
+        .into_iter()
+        .flatten()
+    {
+        if let Some(message) = choice.get_mut("message").and_then(|m| m.as_object_mut())
+            && let Some(reasoning) = message.remove(reasoning_field)
+        {
+            message.insert(DEFAULT_REASONING_FIELD.to_string(), reasoning);
+        }
+    }
+}
+
+/// POST a Chat Completions body and render the answer in the client's `format`
+pub(crate) async fn send_chat_completion(
+    vendor: &str,
+    url: &str,
+    key: Option<PooledKey>,
+    body: Value,
+    reasoning_field: &str,
+    format: InboundFormat,
+    model: &str,
+) -> Result<axum::response::Response, StatusCode> {
+    tracing::debug!(target: "setu::request", "Outgoing {} request to {}: {}", vendor, url, body);
+
+    let stream = body["stream"].as_bool().unwrap_or(false);
+    let mut request = reqwest::Client::new().post(url).json(&body);
+    if let Some(key) = &key {
+        request = request.bearer_auth(&key.secret);
+    }
+    let resp = match request.send().await {
+        Ok(r) => r,
+        Err(e) => {
+            return Err(error_handling::bad_gateway(
+                &format!("{} request failed", vendor),
+                &e,
+            ));
+        }
+    };
+
+    let status = resp.status();
+    if let Some(key) = &key {
+        key.report_status(status.as_u16());
+    }
+    if !status.is_success() {
+        let text = resp.text().await.unwrap_or_default();
+        tracing::error!("{} returned {}: {}", vendor, status, text);
+        return Ok(ApiError::from_upstream(status, vendor, &text).into_response(format));
+    }
+
+    if stream {
+        let lines = local::byte_lines(resp.bytes_stream());
+        let parser = ChatCompletionStreamParser::new(reasoning_field);
+        let body = local::stream_body(lines, parser, format, model);
+        return Ok(local::sse_response(body));
+    }
+
+    let mut value: Value = match resp.json().await {
+        Ok(v) => v,
+        Err(e) => {
+            return Err(error_handling::bad_gateway(
+                &format!("Failed to parse {} response", vendor),
+                &e,
+            ));
+        }
+    };
+
+    let rendered = match format {
+        // Keep everything the vendor returned; only the reasoning field is normalised
+        InboundFormat::OpenAI => {
+            normalize_response(&mut value, reasoning_field);
+            value
+        }
+        _ => parse_response(&value, reasoning_field).render(format, model),
+    };
+    Ok(local::json_response(&rendered))
+}
+
+/// Send a request to an OpenAI-compatible vendor and answer in `format`
+pub async fn handle_openai_compatible_request(
+    config: Arc<Mutex<Config>>,
+    input: ChatInput,
+    format: InboundFormat,
+    routing_decision: RoutingDecision,
+) -> Result<axum::response::Response, StatusCode> {
+    let provider = routing_decision.provider.clone();
+    let model = local::local_model_name(&routing_decision);
+
+    let cfg = config.lock().await;
+    let Some(provider_config) = cfg.providers.get(&provider) else {
+        return Err(error_handling::internal_error(
+            "OpenAI-compatible provider not configured",
+            &provider,
+        ));
+    };
+    if provider_config.endpoint.is_empty() {
+        return Err(error_handling::internal_error(
+            "OpenAI-compatible provider has no endpoint",
+            &provider,
+        ));
+    }
+    let endpoint = provider_config.endpoint.clone();
+    let quirks = provider_config.quirks.clone();
+    // GROQ_API_KEY for [providers.groq], DEEP_SEEK_API_KEY for [providers.deep-seek]
+    let env_var = format!("{}_API_KEY", provider.to_uppercase().replace('-', "_"));
+    let key = key_pool::acquire_api_key(&cfg, &provider, &env_var);
+    drop(cfg);
+
+    let mut body = input.into_body(&model);
+    if let Some(params) = &routing_decision.query_params {
+        apply_chat_completions_parameters(&mut body, params);
+    }
+    apply_quirks(&mut body, &quirks);
+
+    let url = format!("{}/chat/completions", endpoint.trim_end_matches('/'));
+    let reasoning_field = quirks
+        .reasoning_field
+        .as_deref()
+        .unwrap_or(DEFAULT_REASONING_FIELD);
+    send_chat_completion(&provider, &url, key, body, reasoning_field, format, &model).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_request_stringifies_arguments() {
+        let chat = LocalChat {
+            messages: vec![json!({"role": "assistant", "content": "", "tool_calls": [
+                {"id": "call_1", "type": "function", "function": {"name": "ls", "arguments": {"path": "."}}}
+            ]})],
+            ..Default::default()
+        };
+        let body = build_request(&chat, "local");
+        assert_eq!(
+            body["messages"][0]["tool_calls"][0]["function"]["arguments"],
+            "{\"path\":\".\"}"
+        );
+    }
+
+    #[test]
+    fn test_stream_parser_accumulates_tool_call_fragments() {
+        let mut parser = ChatCompletionStreamParser::new(DEFAULT_REASONING_FIELD);
+        parser.push_line(r#"data: {"choices":[{"delta":{"tool_calls":[{"index":0,"id":"call_9","function":{"name":"ls","arguments":"{\"pa"}}]}}]}"#);
+        parser.push_line(r#"data: {"choices":[{"delta":{"tool_calls":[{"index":0,"function":{"arguments":"th\":\"src\"}"}}]},"finish_reason":"tool_calls"}]}"#);
+        let deltas = parser.push_line("data: [DONE]");
+
+        assert_eq!(
+            deltas[0],
+            LocalDelta::ToolCall(LocalToolCall {
+                id: "call_9".to_string(),
+                name: "ls".to_string(),
+                arguments: json!({"path": "src"}),
+            })
+        );
+        assert!(matches!(
+            deltas[1],
+            LocalDelta::Finish {
+                finish: Finish::ToolCalls,
+                ..
+            }
+        ));
+        assert!(parser.finish().is_empty());
+    }
+
+    #[test]
+    fn test_quirks_reshape_request() {
+        let quirks = ProviderQuirks {
+            unsupported_params: vec!["top_k".to_string(), "seed".to_string()],
+            max_tokens_field: MaxTokensField::MaxCompletionTokens,
+            tool_choice: ToolChoiceSupport::StringOnly,
+            extra_body: [("safe_prompt".to_string(), json!(true))]
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        };
+        let mut body = json!({
+            "model": "mistral-large-latest",
+            "max_tokens": 1024,
+            "top_k": 40,
+            "seed": 7,
+            "temperature": 0.3,
+            "tool_choice": {"type": "function", "function": {"name": "ls"}}
+        });
+
+        apply_quirks(&mut body, &quirks);
+        assert_eq!(body["max_completion_tokens"], 1024);
+        assert!(body.get("max_tokens").is_none());
+        assert!(body.get("top_k").is_none());
+        assert!(body.get("seed").is_none());
+        assert_eq!(body["temperature"], 0.3);
+        assert_eq!(body["tool_choice"], "required");
+        assert_eq!(body["safe_prompt"], true);
+    }
+
+    #[test]
+    fn test_unsupported_tool_choice_dropped() {
+        let quirks = ProviderQuirks {
+            tool_choice: ToolChoiceSupport::Unsupported,
+            ..Default::default()
+        };
+        let mut body = json!({"tool_choice": "auto", "max_completion_tokens": 10});
+        apply_quirks(&mut body, &quirks);
+        assert!(body.get("tool_choice").is_none());
+        assert_eq!(body["max_tokens"], 10);
+    }
+
+    #[test]
+    fn test_reasoning_field_normalised() {
+        let mut value = json!({
+            "choices": [{"message": {"role": "assistant", "content": "4", "reasoning": "2+2"}}]
+        });
+        normalize_response(&mut value, "reasoning");
+        assert_eq!(value["choices"][0]["message"]["reasoning_content"], "2+2");
+        assert!(value["choices"][0]["message"].get("reasoning").is_none());
+
+        let parsed = parse_response(
+            &json!({"choices": [{"message": {"content": "4", "reasoning": "2+2"}, "finish_reason": "stop"}]}),
+            "reasoning",
+        );
+        assert_eq!(parsed.thinking.as_deref(), Some("2+2"));
+    }
+}
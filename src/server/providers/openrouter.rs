@@ -5,36 +5,30 @@ use std::sync::Arc;
 use tokio::sync::Mutex;
 use tracing::info;
 
+use crate::auth::key_pool::{self, PooledKey};
 use crate::config::Config;
 use crate::error::PrismError;
 use crate::router::name_based::RoutingDecision;
 use crate::server::error_handling;
 
 /// Create OpenRouter client with API key authentication
-pub async fn create_openrouter_client(config: Arc<Mutex<Config>>) -> Result<OpenRouter, PrismError> {
-    // Try OPENROUTER_API_KEY first (correct OpenRouter key)
-    if let Ok(api_key) = std::env::var("OPENROUTER_API_KEY") {
-        info!("🔐 OpenRouter → API key via OPENROUTER_API_KEY");
-        return Ok(OpenRouter::builder().api_key(&api_key).build());
-    }
-
-    // Fallback to OPENAI_API_KEY for compatibility
-    if let Ok(api_key) = std::env::var("OPENAI_API_KEY") {
-        info!("🔐 OpenRouter → API key via OPENAI_API_KEY (fallback)");
-        return Ok(OpenRouter::builder().api_key(&api_key).build());
-    }
-
-    // Try config file
+///
+/// Keys from `OPENROUTER_API_KEY` and the provider's `api_key`/`api_keys` are pooled;
+/// the returned key should be told about rate-limit/auth failures.
+pub async fn create_openrouter_client(
+    config: Arc<Mutex<Config>>,
+) -> Result<(OpenRouter, PooledKey), PrismError> {
     let config_guard = config.lock().await;
-    if let Some(openai_provider) = config_guard.providers.get("openai")
-        && let Some(api_key) = &openai_provider.api_key
+
+    // Pooled OpenRouter keys (OPENROUTER_API_KEY, api_key, api_keys)
+    if let Some(key) = key_pool::acquire_api_key(&config_guard, "openrouter", "OPENROUTER_API_KEY")
     {
-        info!("🔐 OpenRouter → API key via prism config");
-        return Ok(OpenRouter::builder().api_key(api_key).build());
+        info!("🔐 OpenRouter → API key via {}", key.label);
+        return Ok((OpenRouter::builder().api_key(&key.secret).build(), key));
     }
 
     Err(PrismError::Other(
-        "No OpenRouter API key found (OPENROUTER_API_KEY or OPENAI_API_KEY environment variable or prism config)".to_string(),
+        "No OpenRouter API key found (set OPENROUTER_API_KEY, providers.openrouter.api_key, or run `prism auth openrouter`)".to_string(),
     ))
 }
 
@@ -45,7 +39,7 @@ pub async fn handle_openrouter_request_from_openai(
     routing_decision: RoutingDecision,
     _headers: HeaderMap,
 ) -> Result<axum::response::Response, StatusCode> {
-    let openrouter_client = match create_openrouter_client(config).await {
+    let (openrouter_client, key) = match create_openrouter_client(config).await {
         Ok(client) => client,
         Err(e) => {
             return Err(error_handling::internal_error(
@@ -179,10 +173,13 @@ pub async fn handle_openrouter_request_from_openai(
                 .body(axum::body::Body::from(json_body))
                 .unwrap())
         }
-        Err(e) => Err(error_handling::internal_error(
-            "OpenRouter API request failed",
-            &e,
-        )),
+        Err(e) => {
+            key.report_error(&e);
+            Err(error_handling::internal_error(
+                "OpenRouter API request failed",
+                &e,
+            ))
+        }
     }
 }
 
@@ -193,7 +190,7 @@ pub async fn handle_openrouter_request(
     routing_decision: RoutingDecision,
     _headers: HeaderMap,
 ) -> Result<axum::response::Response, StatusCode> {
-    let openrouter_client = match create_openrouter_client(config).await {
+    let (openrouter_client, key) = match create_openrouter_client(config).await {
         Ok(client) => client,
         Err(e) => {
             return Err(error_handling::internal_error(
@@ -285,10 +282,13 @@ pub async fn handle_openrouter_request(
                 .body(axum::body::Body::from(json_body))
                 .unwrap())
         }
-        Err(e) => Err(error_handling::internal_error(
-            "OpenRouter API request failed",
-            &e,
-        )),
+        Err(e) => {
+            key.report_error(&e);
+            Err(error_handling::internal_error(
+                "OpenRouter API request failed",
+                &e,
+            ))
+        }
     }
 }
 /// OpenAI models endpoint - return simple model list
@@ -0,0 +1,254 @@
+//! OpenAI Responses API output converted for Anthropic-format clients.
+//!
+//! Output items map to content blocks: `message` → `text`, `function_call` →
+//! `tool_use`, `reasoning` summaries → `thinking`. Streams are turned into model deltas
+//! and re-encoded as Anthropic SSE by the shared encoder.
+
+use serde_json::{Value, json};
+
+use crate::server::providers::local::{DeltaParser, Finish, LocalDelta, LocalToolCall};
+
+fn parse_arguments(arguments: &Value) -> Value {
+    match arguments {
+        Value::String(s) => serde_json::from_str(s).unwrap_or_else(|_| json!({})),
+        Value::Null => json!({}),
+        other => other.clone(),
+    }
+}
+
+/// Anthropic `stop_reason` for a Responses `status` / `incomplete_details`
+pub fn stop_reason(response: &Value, has_tool_calls: bool) -> &'static str {
+    if response["status"] == "incomplete" {
+        return match response["incomplete_details"]["reason"].as_str() {
+            Some("content_filter") => "refusal",
+            _ => "max_tokens",
+        };
+    }
+    if has_tool_calls {
+        "tool_use"
+    } else {
+        "end_turn"
+    }
+}
+
+/// Anthropic usage for a Responses `usage` object
+///
+/// Anthropic's `input_tokens` excludes cache reads, so cached tokens are moved to
+/// `cache_read_input_tokens`. Reasoning tokens stay in `output_tokens`, as Anthropic
+/// counts thinking there too.
+pub fn usage(usage: &Value) -> Value {
+    let input = usage["input_tokens"].as_u64().unwrap_or(0);
+    let cached = usage["input_tokens_details"]["cached_tokens"]
+        .as_u64()
+        .unwrap_or(0);
+    json!({
+        "input_tokens": input.saturating_sub(cached),
+        "output_tokens": usage["output_tokens"].as_u64().unwrap_or(0),
+        "cache_creation_input_tokens": 0,
+        "cache_read_input_tokens": cached,
+    })
+}
+
+fn summary_text(item: &Value) -> String {
+    item["summary"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|part| part["text"].as_str())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Convert a Responses API response into an Anthropic Messages response
+pub fn to_anthropic(response: &Value, model: &str) -> Value {
+    let mut content = Vec::new();
+    let mut has_tool_calls = false;
+
+    for item in response["output"].as_array().into_iter().flatten() {
+        match item["type"].as_str() {
+            Some("reasoning") => {
+                let thinking = summary_text(item);
+                if !thinking.is_empty() {
+                    content.push(json!({
+                        "type": "thinking",
+                        "thinking": thinking,
+                        "signature": item["encrypted_content"].as_str().unwrap_or_default(),
+                    }));
+                }
+            }
+            Some("message") => {
+                for part in item["content"].as_array().into_iter().flatten() {
+                    let text = match part["type"].as_str() {
+                        Some("output_text") => part["text"].as_str(),
+                        Some("refusal") => part["refusal"].as_str(),
+                        _ => None,
+                    };
+                    if let Some(text) = text {
+                        content.push(json!({"type": "text", "text": text}));
+                    }
+                }
+            }
+            Some("function_call") => {
+                has_tool_calls = true;
+                content.push(json!({
+                    "type": "tool_use",
+                    "id": item["call_id"].as_str().or_else(|| item["id"].as_str()).unwrap_or_default(),
+                    "name": item["name"],
+                    "input": parse_arguments(&item["arguments"]),
+                }));
+            }
+            _ => {}
+        }
+    }
+
+    json!({
+        "id": response["id"].as_str().unwrap_or("msg_responses"),
+        "type": "message",
+        "role": "assistant",
+        "model": model,
+        "content": content,
+        "stop_reason": stop_reason(response, has_tool_calls),
+        "stop_sequence": null,
+        "usage": usage(&response["usage"]),
+    })
+}
+
+/// Parses Responses API stream events (`data:` lines) into model deltas
+#[derive(Default)]
+pub struct ResponsesStreamParser {
+    saw_tool_call: bool,
+    finish: Option<Finish>,
+    input_tokens: u64,
+    output_tokens: u64,
+}
+
+impl DeltaParser for ResponsesStreamParser {
+    fn push_line(&mut self, line: &str) -> Vec<LocalDelta> {
+        let Some(data) = line.strip_prefix("data:") else {
+            return Vec::new();
+        };
+        let Ok(event) = serde_json::from_str::<Value>(data.trim()) else {
+            return Vec::new();
+        };
+
+        match event["type"].as_str().unwrap_or_default() {
+            "response.output_text.delta" | "response.refusal.delta" => event["delta"]
+                .as_str()
+                .map(|d| vec![LocalDelta::Text(d.to_string())])
+                .unwrap_or_default(),
+            "response.reasoning_summary_text.delta" => event["delta"]
+                .as_str()
+                .map(|d| vec![LocalDelta::Thinking(d.to_string())])
+                .unwrap_or_default(),
+            "response.output_item.done" if event["item"]["type"] == "function_call" => {
+                self.saw_tool_call = true;
+                let item = &event["item"];
+                vec![LocalDelta::ToolCall(LocalToolCall {
+                    id: item["call_id"].as_str().unwrap_or_default().to_string(),
+                    name: item["name"].as_str().unwrap_or_default().to_string(),
+                    arguments: parse_arguments(&item["arguments"]),
+                })]
+            }
+            "response.completed" | "response.incomplete" | "response.failed" => {
+                let response = &event["response"];
+                self.finish = Some(match stop_reason(response, self.saw_tool_call) {
+                    "max_tokens" => Finish::Length,
+                    "tool_use" => Finish::ToolCalls,
+                    _ => Finish::Stop,
+                });
+                let usage = usage(&response["usage"]);
+                self.input_tokens = usage["input_tokens"].as_u64().unwrap_or(0)
+                    + usage["cache_read_input_tokens"].as_u64().unwrap_or(0);
+                self.output_tokens = usage["output_tokens"].as_u64().unwrap_or(0);
+                Vec::new()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn finish(&mut self) -> Vec<LocalDelta> {
+        let finish = self.finish.unwrap_or(if self.saw_tool_call {
+            Finish::ToolCalls
+        } else {
+            Finish::Stop
+        });
+        vec![LocalDelta::Finish {
+            finish,
+            input_tokens: self.input_tokens,
+            output_tokens: self.output_tokens,
+        }]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_items_to_content_blocks() {
+        let response = json!({
+            "id": "resp_1",
+            "status": "completed",
+            "output": [
+                {"type": "reasoning", "id": "rs_1", "summary": [{"type": "summary_text", "text": "Check the weather"}]},
+                {"type": "message", "role": "assistant", "content": [{"type": "output_text", "text": "Let me look."}]},
+                {"type": "function_call", "id": "fc_1", "call_id": "call_1", "name": "weather", "arguments": "{\"city\":\"Paris\"}"}
+            ],
+            "usage": {
+                "input_tokens": 120,
+                "input_tokens_details": {"cached_tokens": 100},
+                "output_tokens": 50,
+                "output_tokens_details": {"reasoning_tokens": 30}
+            }
+        });
+        let message = to_anthropic(&response, "gpt-5");
+
+        assert_eq!(message["type"], "message");
+        assert_eq!(message["content"][0]["type"], "thinking");
+        assert_eq!(message["content"][0]["thinking"], "Check the weather");
+        assert_eq!(message["content"][1]["text"], "Let me look.");
+        assert_eq!(message["content"][2]["type"], "tool_use");
+        assert_eq!(message["content"][2]["id"], "call_1");
+        assert_eq!(message["content"][2]["input"]["city"], "Paris");
+        assert_eq!(message["stop_reason"], "tool_use");
+        assert_eq!(message["usage"]["input_tokens"], 20);
+        assert_eq!(message["usage"]["cache_read_input_tokens"], 100);
+        assert_eq!(message["usage"]["output_tokens"], 50);
+    }
+
+    #[test]
+    fn test_incomplete_status_maps_to_stop_reason() {
+        let truncated =
+            json!({"status": "incomplete", "incomplete_details": {"reason": "max_output_tokens"}});
+        assert_eq!(stop_reason(&truncated, false), "max_tokens");
+        let filtered =
+            json!({"status": "incomplete", "incomplete_details": {"reason": "content_filter"}});
+        assert_eq!(stop_reason(&filtered, false), "refusal");
+        assert_eq!(
+            stop_reason(&json!({"status": "completed"}), false),
+            "end_turn"
+        );
+    }
+
+    #[test]
+    fn test_stream_events_to_deltas() {
+        let mut parser = ResponsesStreamParser::default();
+        let lines = [
+            r#"event: response.output_text.delta"#,
+            r#"data: {"type":"response.output_text.delta","delta":"Hi"}"#,
+            r#"data: {"type":"response.output_item.done","item":{"type":"function_call","call_id":"call_1","name":"weather","arguments":"{}"}}"#,
+            r#"data: {"type":"response.completed","response":{"status":"completed","usage":{"input_tokens":10,"output_tokens":4}}}"#,
+        ];
+        let deltas: Vec<LocalDelta> = lines.iter().flat_map(|l| parser.push_line(l)).collect();
+        assert_eq!(deltas[0], LocalDelta::Text("Hi".to_string()));
+        assert!(matches!(&deltas[1], LocalDelta::ToolCall(call) if call.name == "weather"));
+        assert_eq!(
+            parser.finish(),
+            vec![LocalDelta::Finish {
+                finish: Finish::ToolCalls,
+                input_tokens: 10,
+                output_tokens: 4
+            }]
+        );
+    }
+}
@@ -0,0 +1,541 @@
+//! Google Vertex AI provider (`type = "vertex"`).
+//!
+//! Authenticates with a service-account key and serves two publishers from one project:
+//! Gemini models (`publishers/google`, `generateContent`) and Claude models
+//! (`publishers/anthropic`, `rawPredict` / `streamRawPredict` with Messages bodies).
+
+use axum::http::StatusCode;
+use serde_json::{Map, Value, json};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::auth::vertex::ServiceAccountKey;
+use crate::config::Config;
+use crate::router::name_based::RoutingDecision;
+use crate::server::error_handling::{self, ApiError};
+use crate::server::providers::anthropic_upstream::{self, MessagesInput};
+use crate::server::providers::local::{
+    self, DeltaParser, Finish, InboundFormat, LocalChat, LocalDelta, LocalResponse, LocalToolCall,
+};
+
+const VERTEX_ANTHROPIC_VERSION: &str = "vertex-2023-10-16";
+const DEFAULT_REGION: &str = "us-central1";
+
+/// Request for a Vertex AI model, in whichever format the client sent
+pub enum VertexInput {
+    Anthropic(anthropic_ox::ChatRequest),
+    Gemini(Value),
+    Local(LocalChat),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Publisher {
+    Google,
+    Anthropic,
+}
+
+impl Publisher {
+    /// Claude models are published by Anthropic; everything else is treated as Gemini
+    pub fn for_model(model: &str) -> Self {
+        if model.starts_with("claude") {
+            Publisher::Anthropic
+        } else {
+            Publisher::Google
+        }
+    }
+
+    fn path(self) -> &'static str {
+        match self {
+            Publisher::Google => "google",
+            Publisher::Anthropic => "anthropic",
+        }
+    }
+}
+
+/// Regional endpoint; the `global` location has no region prefix
+pub fn base_url(region: &str) -> String {
+    if region == "global" {
+        "https://aiplatform.googleapis.com".to_string()
+    } else {
+        format!("https://{}-aiplatform.googleapis.com", region)
+    }
+}
+
+pub fn model_url(
+    base: &str,
+    project: &str,
+    region: &str,
+    publisher: Publisher,
+    model: &str,
+    action: &str,
+) -> String {
+    format!(
+        "{}/v1/projects/{}/locations/{}/publishers/{}/models/{}:{}",
+        base,
+        project,
+        region,
+        publisher.path(),
+        model,
+        action
+    )
+}
+
+/// Split a `data:<mime>;base64,<data>` URL into mime type and data
+fn inline_data(url: &str) -> Value {
+    let mime = url
+        .strip_prefix("data:")
+        .and_then(|rest| rest.split_once(';'))
+        .map(|(mime, _)| mime)
+        .unwrap_or("image/png");
+    json!({"inlineData": {"mimeType": mime, "data": local::base64_payload(url)}})
+}
+
+/// Append parts to the last content if it has the same role, else start a new one
+fn push_parts(contents: &mut Vec<Value>, role: &str, parts: Vec<Value>) {
+    if parts.is_empty() {
+        return;
+    }
+    if let Some(last) = contents.last_mut()
+        && last["role"] == role
+        && let Some(existing) = last["parts"].as_array_mut()
+    {
+        existing.extend(parts);
+        return;
+    }
+    contents.push(json!({"role": role, "parts": parts}));
+}
+
+/// Gemini generateContent body for a normalised chat
+pub fn gemini_body(chat: &LocalChat) -> Value {
+    let mut system = Vec::new();
+    let mut contents = Vec::new();
+
+    for message in &chat.messages {
+        let content = message["content"].as_str().unwrap_or_default();
+        match message["role"].as_str().unwrap_or("user") {
+            "system" => system.push(json!({"text": content})),
+            "assistant" => {
+                let mut parts = Vec::new();
+                if !content.is_empty() {
+                    parts.push(json!({"text": content}));
+                }
+                for call in message["tool_calls"].as_array().into_iter().flatten() {
+                    parts.push(json!({"functionCall": {
+                        "name": call["function"]["name"],
+                        "args": call["function"]["arguments"],
+                    }}));
+                }
+                push_parts(&mut contents, "model", parts);
+            }
+            "tool" => {
+                // functionResponse.response must be an object
+                let response = match serde_json::from_str::<Value>(content) {
+                    Ok(Value::Object(map)) => Value::Object(map),
+                    _ => json!({"content": content}),
+                };
+                let part = json!({"functionResponse": {
+                    "name": message["name"],
+                    "response": response,
+                }});
+                push_parts(&mut contents, "user", vec![part]);
+            }
+            _ => {
+                let mut parts = Vec::new();
+                if !content.is_empty() {
+                    parts.push(json!({"text": content}));
+                }
+                for image in message["images"].as_array().into_iter().flatten() {
+                    if let Some(url) = image.as_str() {
+                        parts.push(inline_data(url));
+                    }
+                }
+                push_parts(&mut contents, "user", parts);
+            }
+        }
+    }
+
+    let mut generation = Map::new();
+    if let Some(v) = chat.temperature {
+        generation.insert("temperature".to_string(), json!(v));
+    }
+    if let Some(v) = chat.top_p {
+        generation.insert("topP".to_string(), json!(v));
+    }
+    if let Some(v) = chat.top_k {
+        generation.insert("topK".to_string(), json!(v));
+    }
+    if let Some(v) = chat.max_tokens {
+        generation.insert("maxOutputTokens".to_string(), json!(v));
+    }
+    if !chat.stop.is_empty() {
+        generation.insert("stopSequences".to_string(), json!(chat.stop));
+    }
+    if chat.think == Some(true) {
+        generation.insert(
+            "thinkingConfig".to_string(),
+            json!({"includeThoughts": true}),
+        );
+    }
+
+    let mut body = Map::new();
+    body.insert("contents".to_string(), Value::Array(contents));
+    if !system.is_empty() {
+        body.insert("systemInstruction".to_string(), json!({"parts": system}));
+    }
+    if !chat.tools.is_empty() {
+        let declarations: Vec<Value> = chat
+            .tools
+            .iter()
+            .map(|tool| tool["function"].clone())
+            .collect();
+        body.insert(
+            "tools".to_string(),
+            json!([{"functionDeclarations": declarations}]),
+        );
+    }
+    if !generation.is_empty() {
+        body.insert("generationConfig".to_string(), Value::Object(generation));
+    }
+    Value::Object(body)
+}
+
+fn finish_from_gemini(reason: Option<&str>, has_tool_calls: bool) -> Finish {
+    match reason {
+        _ if has_tool_calls => Finish::ToolCalls,
+        Some("MAX_TOKENS") => Finish::Length,
+        _ => Finish::Stop,
+    }
+}
+
+fn usage(value: &Value) -> (u64, u64) {
+    let usage = &value["usageMetadata"];
+    let output = usage["candidatesTokenCount"].as_u64().unwrap_or(0)
+        + usage["thoughtsTokenCount"].as_u64().unwrap_or(0);
+    (usage["promptTokenCount"].as_u64().unwrap_or(0), output)
+}
+
+/// Deltas carried by one generateContent response (or stream chunk)
+fn candidate_deltas(value: &Value) -> Vec<LocalDelta> {
+    let mut deltas = Vec::new();
+    for part in value["candidates"][0]["content"]["parts"]
+        .as_array()
+        .into_iter()
+        .flatten()
+    {
+        if let Some(text) = part["text"].as_str() {
+            if part["thought"].as_bool() == Some(true) {
+                deltas.push(LocalDelta::Thinking(text.to_string()));
+            } else {
+                deltas.push(LocalDelta::Text(text.to_string()));
+            }
+        } else if let Some(call) = part.get("functionCall") {
+            deltas.push(LocalDelta::ToolCall(LocalToolCall {
+                id: local::new_tool_call_id(),
+                name: call["name"].as_str().unwrap_or_default().to_string(),
+                arguments: call.get("args").cloned().unwrap_or_else(|| json!({})),
+            }));
+        }
+    }
+    deltas
+}
+
+/// Parse a Gemini generateContent response
+pub fn parse_gemini_response(value: &Value) -> LocalResponse {
+    let mut response = LocalResponse::default();
+    for delta in candidate_deltas(value) {
+        match delta {
+            LocalDelta::Text(text) => response.text.push_str(&text),
+            LocalDelta::Thinking(text) => response
+                .thinking
+                .get_or_insert_with(String::new)
+                .push_str(&text),
+            LocalDelta::ToolCall(call) => response.tool_calls.push(call),
+            LocalDelta::Finish { .. } => {}
+        }
+    }
+    response.finish = finish_from_gemini(
+        value["candidates"][0]["finishReason"].as_str(),
+        !response.tool_calls.is_empty(),
+    );
+    (response.input_tokens, response.output_tokens) = usage(value);
+    response
+}
+
+/// Parses `streamGenerateContent?alt=sse` chunks into model deltas
+#[derive(Default)]
+pub struct GeminiStreamParser {
+    finish_reason: Option<String>,
+    saw_tool_call: bool,
+    input_tokens: u64,
+    output_tokens: u64,
+}
+
+impl DeltaParser for GeminiStreamParser {
+    fn push_line(&mut self, line: &str) -> Vec<LocalDelta> {
+        let Some(data) = line.strip_prefix("data:") else {
+            return Vec::new();
+        };
+        let Ok(value) = serde_json::from_str::<Value>(data.trim()) else {
+            return Vec::new();
+        };
+        if let Some(reason) = value["candidates"][0]["finishReason"].as_str() {
+            self.finish_reason = Some(reason.to_string());
+        }
+        if value.get("usageMetadata").is_some() {
+            (self.input_tokens, self.output_tokens) = usage(&value);
+        }
+        let deltas = candidate_deltas(&value);
+        self.saw_tool_call |= deltas.iter().any(|d| matches!(d, LocalDelta::ToolCall(_)));
+        deltas
+    }
+
+    fn finish(&mut self) -> Vec<LocalDelta> {
+        vec![LocalDelta::Finish {
+            finish: finish_from_gemini(self.finish_reason.as_deref(), self.saw_tool_call),
+            input_tokens: self.input_tokens,
+            output_tokens: self.output_tokens,
+        }]
+    }
+}
+
+/// Claude request body for rawPredict: no `model`, plus Vertex's `anthropic_version`
+pub fn raw_predict_body(mut body: Value) -> Value {
+    if let Some(map) = body.as_object_mut() {
+        map.remove("model");
+        map.retain(|_, v| !v.is_null());
+        map.insert(
+            "anthropic_version".to_string(),
+            json!(VERTEX_ANTHROPIC_VERSION),
+        );
+    }
+    body
+}
+
+/// Send a request to a Vertex AI model and answer in `format`
+pub async fn handle_vertex_request(
+    config: Arc<Mutex<Config>>,
+    input: VertexInput,
+    format: InboundFormat,
+    routing_decision: RoutingDecision,
+) -> Result<axum::response::Response, StatusCode> {
+    let requested = routing_decision.model.clone();
+
+    let cfg = config.lock().await;
+    let provider = cfg.providers.get(&routing_decision.provider);
+    let model = provider
+        .and_then(|p| p.deployments.get(&requested).cloned())
+        .unwrap_or_else(|| requested.clone());
+    let region = provider
+        .and_then(|p| p.region.clone())
+        .unwrap_or_else(|| DEFAULT_REGION.to_string());
+    let base = provider
+        .map(|p| p.endpoint.trim_end_matches('/').to_string())
+        .filter(|e| !e.is_empty())
+        .unwrap_or_else(|| base_url(&region));
+    let project = provider.and_then(|p| p.project.clone());
+    let credentials = provider.and_then(|p| p.credentials.clone());
+    drop(cfg);
+
+    let key = match ServiceAccountKey::load(credentials.as_deref()) {
+        Ok(key) => key,
+        Err(e) => return Err(error_handling::unauthorized(&e.to_string())),
+    };
+    let Some(project) = project.or_else(|| key.project_id.clone()) else {
+        return Err(error_handling::internal_error(
+            "Vertex AI provider needs `project`",
+            &routing_decision.provider,
+        ));
+    };
+    let token = match key.access_token().await {
+        Ok(token) => token,
+        Err(e) => return Err(error_handling::unauthorized(&e.to_string())),
+    };
+
+    let publisher = Publisher::for_model(&model);
+    let (body, stream) = match (publisher, input) {
+        (Publisher::Anthropic, input) => {
+            let messages = match input {
+                VertexInput::Anthropic(request) => MessagesInput::Anthropic(request),
+                VertexInput::Gemini(value) => {
+                    MessagesInput::Local(LocalChat::from_gemini_value(&value))
+                }
+                VertexInput::Local(chat) => MessagesInput::Local(chat),
+            };
+            let body = messages.into_body();
+            let stream = body["stream"].as_bool().unwrap_or(false);
+            (raw_predict_body(body), stream)
+        }
+        (Publisher::Google, VertexInput::Gemini(value)) => (value, false),
+        (Publisher::Google, VertexInput::Anthropic(request)) => {
+            let chat = LocalChat::from_anthropic(&request);
+            (gemini_body(&chat), chat.stream)
+        }
+        (Publisher::Google, VertexInput::Local(chat)) => (gemini_body(&chat), chat.stream),
+    };
+
+    let action = match (publisher, stream) {
+        (Publisher::Anthropic, true) => "streamRawPredict",
+        (Publisher::Anthropic, false) => "rawPredict",
+        (Publisher::Google, true) => "streamGenerateContent?alt=sse",
+        (Publisher::Google, false) => "generateContent",
+    };
+    let url = model_url(&base, &project, &region, publisher, &model, action);
+
+    tracing::info!("☁️ Vertex AI → {} ({}, {})", model, project, region);
+    if let Some(req_str) = error_handling::prepare_request_log(&body) {
+        tracing::debug!(target: "setu::request", "Outgoing Vertex AI request: {}", req_str);
+    }
+
+    let resp = match reqwest::Client::new()
+        .post(&url)
+        .bearer_auth(&token)
+        .json(&body)
+        .send()
+        .await
+    {
+        Ok(r) => r,
+        Err(e) => return Err(error_handling::bad_gateway("Vertex AI request failed", &e)),
+    };
+
+    let status = resp.status();
+    if !status.is_success() {
+        let text = resp.text().await.unwrap_or_default();
+        tracing::error!("Vertex AI returned {}: {}", status, text);
+        return Ok(ApiError::from_upstream(status, "Vertex AI", &text).into_response(format));
+    }
+
+    if stream {
+        let lines = local::byte_lines(resp.bytes_stream());
+        let body = match publisher {
+            Publisher::Anthropic => anthropic_upstream::stream_events(
+                anthropic_upstream::sse_data(lines),
+                format,
+                &requested,
+            ),
+            Publisher::Google => {
+                local::stream_body(lines, GeminiStreamParser::default(), format, &requested)
+            }
+        };
+        return Ok(local::sse_response(body));
+    }
+
+    let value: Value = match resp.json().await {
+        Ok(v) => v,
+        Err(e) => {
+            return Err(error_handling::bad_gateway(
+                "Failed to parse Vertex AI response",
+                &e,
+            ));
+        }
+    };
+    let rendered = match (publisher, format) {
+        (Publisher::Anthropic, _) => anthropic_upstream::render_response(value, format, &requested),
+        (Publisher::Google, InboundFormat::Gemini) => value,
+        (Publisher::Google, _) => parse_gemini_response(&value).render(format, &requested),
+    };
+    Ok(local::json_response(&rendered))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_model_urls() {
+        assert_eq!(
+            model_url(
+                &base_url("us-east5"),
+                "my-project",
+                "us-east5",
+                Publisher::for_model("claude-sonnet-4@20250514"),
+                "claude-sonnet-4@20250514",
+                "streamRawPredict"
+            ),
+            "https://us-east5-aiplatform.googleapis.com/v1/projects/my-project/locations/us-east5/publishers/anthropic/models/claude-sonnet-4@20250514:streamRawPredict"
+        );
+        assert_eq!(
+            model_url(
+                &base_url("global"),
+                "p",
+                "global",
+                Publisher::for_model("gemini-2.5-pro"),
+                "gemini-2.5-pro",
+                "generateContent"
+            ),
+            "https://aiplatform.googleapis.com/v1/projects/p/locations/global/publishers/google/models/gemini-2.5-pro:generateContent"
+        );
+    }
+
+    #[test]
+    fn test_gemini_body_from_chat() {
+        let chat = LocalChat::from_openai_value(&json!({
+            "model": "gemini-2.5-pro",
+            "max_tokens": 256,
+            "messages": [
+                {"role": "system", "content": "Be brief"},
+                {"role": "user", "content": "Weather?"},
+                {"role": "assistant", "content": null, "tool_calls": [
+                    {"id": "a", "type": "function", "function": {"name": "weather", "arguments": "{\"city\":\"Paris\"}"}}
+                ]},
+                {"role": "tool", "tool_call_id": "a", "content": "sunny"}
+            ],
+            "tools": [{"type": "function", "function": {"name": "weather", "parameters": {"type": "object"}}}]
+        }));
+        let body = gemini_body(&chat);
+
+        assert_eq!(body["systemInstruction"]["parts"][0]["text"], "Be brief");
+        assert_eq!(body["contents"][1]["role"], "model");
+        assert_eq!(
+            body["contents"][1]["parts"][0]["functionCall"]["args"]["city"],
+            "Paris"
+        );
+        assert_eq!(
+            body["contents"][2]["parts"][0]["functionResponse"]["response"]["content"],
+            "sunny"
+        );
+        assert_eq!(
+            body["tools"][0]["functionDeclarations"][0]["name"],
+            "weather"
+        );
+        assert_eq!(body["generationConfig"]["maxOutputTokens"], 256);
+    }
+
+    #[test]
+    fn test_parse_gemini_response_and_stream() {
+        let value = json!({
+            "candidates": [{
+                "content": {"role": "model", "parts": [
+                    {"text": "plan", "thought": true},
+                    {"text": "Hello"}
+                ]},
+                "finishReason": "MAX_TOKENS"
+            }],
+            "usageMetadata": {"promptTokenCount": 7, "candidatesTokenCount": 3, "thoughtsTokenCount": 2}
+        });
+        let response = parse_gemini_response(&value);
+        assert_eq!(response.text, "Hello");
+        assert_eq!(response.thinking.as_deref(), Some("plan"));
+        assert_eq!(response.finish, Finish::Length);
+        assert_eq!((response.input_tokens, response.output_tokens), (7, 5));
+
+        let mut parser = GeminiStreamParser::default();
+        let deltas = parser.push_line(&format!("data: {}", value));
+        assert_eq!(deltas.len(), 2);
+        assert_eq!(
+            parser.finish(),
+            vec![LocalDelta::Finish {
+                finish: Finish::Length,
+                input_tokens: 7,
+                output_tokens: 5
+            }]
+        );
+    }
+
+    #[test]
+    fn test_raw_predict_body_keeps_stream() {
+        let body = raw_predict_body(json!({"model": "claude", "stream": true, "max_tokens": 10}));
+        assert!(body.get("model").is_none());
+        assert_eq!(body["stream"], true);
+        assert_eq!(body["anthropic_version"], VERTEX_ANTHROPIC_VERSION);
+    }
+}
@@ -1,13 +1,23 @@
 use axum::extract::{Path, Request, State};
-use axum::http::StatusCode;
+use axum::http::request::Parts;
+use axum::http::{HeaderMap, StatusCode};
 use axum::response::Json;
 use serde_json::Value;
 use std::sync::OnceLock;
+use std::time::Instant;
 
+use crate::config::{Config, ParamValidation};
+use crate::router::balancer;
 use crate::router::model_router::ModelRouter;
+use crate::router::name_based::RoutingDecision;
+use crate::router::rules::RequestFeatures;
 use regex::Regex;
-use crate::server::error_handling;
-use crate::server::providers::{anthropic, auth, gemini, openrouter, parsing};
+use crate::server::{error_handling, hedging, parameter_mapping, shadow};
+use crate::server::providers::anthropic_upstream::MessagesInput;
+use crate::server::providers::local::{InboundFormat, LocalChat};
+use crate::server::providers::openai_compatible::{self, ChatInput};
+use crate::server::providers::vertex::{self, VertexInput};
+use crate::server::providers::{anthropic, auth, azure, bedrock, gemini, llamacpp, ollama, openrouter, parsing};
 
 /// Main OpenAI chat completions endpoint handler
 pub async fn openai_chat_completions(
@@ -27,17 +37,14 @@ pub async fn openai_chat_completions(
 
     // Route based on model name
     let config = app_state.config.lock().await.clone();
-    let router = ModelRouter::new(config);
-    let routing_decision = match router.route_model(&openai_request.model) {
-        Ok(decisions) => {
-            // Use the first routing decision (primary route)
-            decisions.into_iter().next().ok_or_else(|| {
-                error_handling::internal_error(
-                    "No routing decisions available",
-                    &"Failed to get routing decision",
-                )
-            })?
-        }
+    let router = ModelRouter::new(config.clone());
+    let features = RequestFeatures::from_request(
+        &openai_request,
+        auth::is_claude_code_request(&parts.headers),
+    )
+    .with_session_id(auth::session_id_from_headers(&parts.headers));
+    let mut decisions = match router.route_request(&openai_request.model, &features) {
+        Ok(decisions) => decisions,
         Err(e) => {
             return Err(error_handling::bad_request(
                 &format!("Routing error for model {}", openai_request.model),
@@ -45,15 +52,98 @@ pub async fn openai_chat_completions(
             ));
         }
     };
+    if let Err(response) = check_query_params(&config, &mut decisions, InboundFormat::OpenAI) {
+        return Ok(response);
+    }
+    // Use the first routing decision (primary route)
+    let routing_decision = decisions.first().cloned().ok_or_else(|| {
+        error_handling::internal_error(
+            "No routing decisions available",
+            &"Failed to get routing decision",
+        )
+    })?;
+
+    // Shadow and hedge settings follow the alias the request routed through
+    let routed_alias = router.routed_alias(&openai_request.model, &features);
+
+    // Mirror to the shadow target, if configured
+    let shadow = shadow::shadow_target(&config, routed_alias)
+        .and_then(|target| route_shadow(&router, &target).map(|decision| (target, decision)))
+        .map(|(target, decision)| {
+            // Shadow responses are only recorded, so skip streaming
+            let mut shadow_request = openai_request.clone();
+            shadow_request.stream = Some(false);
+            shadow::spawn(
+                "openai",
+                openai_request.model.clone(),
+                routing_decision.target_key(),
+                target,
+                dispatch_openai_request(
+                    app_state.clone(),
+                    shadow_request,
+                    decision,
+                    parts.headers.clone(),
+                ),
+            )
+        });
 
     // Route to appropriate provider
-    match routing_decision.provider.as_str() {
+    let (target_key, result, latency) = match hedging::hedge_delay(&config, routed_alias) {
+        Some(delay) if decisions.len() > 1 => {
+            let attempts = decisions
+                .into_iter()
+                .map(|decision| {
+                    (
+                        decision.target_key(),
+                        dispatch_openai_request(
+                            app_state.clone(),
+                            openai_request.clone(),
+                            decision,
+                            parts.headers.clone(),
+                        ),
+                    )
+                })
+                .collect();
+            hedging::race(delay, attempts).await
+        }
+        _ => {
+            let started = Instant::now();
+            let target_key = routing_decision.target_key();
+            let result =
+                dispatch_openai_request(app_state, openai_request, routing_decision, parts.headers)
+                    .await;
+            (target_key, result, started.elapsed())
+        }
+    };
+
+    if result.as_ref().is_ok_and(|response| response.status().is_success()) {
+        balancer::record_latency(&target_key, latency);
+    }
+    match shadow {
+        Some(handle) => handle.observe(result),
+        None => result,
+    }
+}
+
+/// Send an OpenAI-format request to the provider chosen by routing
+async fn dispatch_openai_request(
+    app_state: crate::server::AppState,
+    openai_request: openai_ox::request::ChatRequest,
+    routing_decision: RoutingDecision,
+    headers: HeaderMap,
+) -> Result<axum::response::Response, StatusCode> {
+    let openai_request = parameter_mapping::apply_param_policy_to(
+        openai_request,
+        InboundFormat::OpenAI,
+        routing_decision.param_policy.as_ref(),
+    );
+    match provider_kind(&app_state, &routing_decision.provider).await.as_str() {
         "openrouter" => {
             openrouter::handle_openrouter_request_from_openai(
                 app_state.config.clone(),
                 openai_request,
                 routing_decision,
-                parts.headers,
+                headers,
             )
             .await
         }
@@ -62,16 +152,26 @@ pub async fn openai_chat_completions(
                 app_state.config.clone(),
                 openai_request,
                 routing_decision,
-                parts.headers,
+                headers,
+            )
+            .await
+        }
+        "azure-openai" => {
+            azure::handle_azure_request_from_openai(
+                app_state.config.clone(),
+                openai_request,
+                routing_decision,
+                headers,
             )
             .await
         }
         "gemini" | "google" => {
             gemini::handle_gemini_request_from_openai(
                 app_state.config.clone(),
+                &app_state.auth_cache,
                 openai_request,
                 routing_decision,
-                parts.headers,
+                headers,
             )
             .await
         }
@@ -79,6 +179,51 @@ pub async fn openai_chat_completions(
             "OpenAI → Anthropic conversion not yet implemented",
             &"Direct Anthropic provider not supported from OpenAI endpoint yet",
         )),
+        "ollama" => {
+            ollama::handle_ollama_request(
+                app_state.config.clone(),
+                LocalChat::from_openai(&openai_request),
+                InboundFormat::OpenAI,
+                routing_decision,
+            )
+            .await
+        }
+        "llamacpp" => {
+            llamacpp::handle_llamacpp_request(
+                app_state.config.clone(),
+                LocalChat::from_openai(&openai_request),
+                InboundFormat::OpenAI,
+                routing_decision,
+            )
+            .await
+        }
+        "openai-compatible" => {
+            openai_compatible::handle_openai_compatible_request(
+                app_state.config.clone(),
+                ChatInput::OpenAI(openai_request),
+                InboundFormat::OpenAI,
+                routing_decision,
+            )
+            .await
+        }
+        "bedrock" => {
+            bedrock::handle_bedrock_request(
+                app_state.config.clone(),
+                MessagesInput::Local(LocalChat::from_openai(&openai_request)),
+                InboundFormat::OpenAI,
+                routing_decision,
+            )
+            .await
+        }
+        "vertex" => {
+            vertex::handle_vertex_request(
+                app_state.config.clone(),
+                VertexInput::Local(LocalChat::from_openai(&openai_request)),
+                InboundFormat::OpenAI,
+                routing_decision,
+            )
+            .await
+        }
         provider_type => Err(error_handling::internal_error(
             "Custom providers not yet supported from OpenAI endpoint",
             &format!("Provider type: {}", provider_type),
@@ -212,20 +357,17 @@ pub async fn anthropic_messages(
 
     // Route based on model name (respect optional system directive)
     let config = app_state.config.lock().await.clone();
-    let router = ModelRouter::new(config);
+    let router = ModelRouter::new(config.clone());
     let override_owned = extract_model_override_from_system(&anthropic_request);
     let route_input_owned: String = override_owned.unwrap_or_else(|| anthropic_request.model.clone());
     let route_input = route_input_owned.as_str();
-    let routing_decision = match router.route_model(route_input) {
-        Ok(decisions) => {
-            // Use the first routing decision (primary route)
-            decisions.into_iter().next().ok_or_else(|| {
-                error_handling::internal_error(
-                    "No routing decisions available",
-                    &"Failed to get routing decision",
-                )
-            })?
-        }
+    let features = RequestFeatures::from_request(
+        &anthropic_request,
+        auth::is_claude_code_request(&parts.headers),
+    )
+    .with_session_id(auth::session_id_from_headers(&parts.headers));
+    let mut decisions = match router.route_request(route_input, &features) {
+        Ok(decisions) => decisions,
         Err(e) => {
             return Err(error_handling::bad_request(
                 &format!("Routing error for model {}", anthropic_request.model),
@@ -233,14 +375,109 @@ pub async fn anthropic_messages(
             ));
         }
     };
+    if let Err(response) = check_query_params(&config, &mut decisions, InboundFormat::Anthropic) {
+        return Ok(response);
+    }
+    // Use the first routing decision (primary route)
+    let routing_decision = decisions.first().cloned().ok_or_else(|| {
+        error_handling::internal_error(
+            "No routing decisions available",
+            &"Failed to get routing decision",
+        )
+    })?;
+
+    // Shadow and hedge settings follow the alias the request routed through
+    let routed_alias = router.routed_alias(route_input, &features);
+
+    // Mirror to the shadow target, if configured
+    let shadow = shadow::shadow_target(&config, routed_alias)
+        .and_then(|target| route_shadow(&router, &target).map(|decision| (target, decision)))
+        .map(|(target, decision)| {
+            // Shadow responses are only recorded, so skip streaming
+            let mut shadow_request = anthropic_request.clone();
+            shadow_request.stream = Some(false);
+            shadow::spawn(
+                "anthropic",
+                route_input.to_string(),
+                routing_decision.target_key(),
+                target,
+                dispatch_anthropic_request(
+                    app_state.clone(),
+                    shadow_request,
+                    decision,
+                    parts_with_headers(parts.headers.clone()),
+                ),
+            )
+        });
+
+    // Route to appropriate provider
+    let (target_key, result, latency) = match hedging::hedge_delay(&config, routed_alias) {
+        Some(delay) if decisions.len() > 1 => {
+            let attempts = decisions
+                .into_iter()
+                .map(|decision| {
+                    (
+                        decision.target_key(),
+                        dispatch_anthropic_request(
+                            app_state.clone(),
+                            anthropic_request.clone(),
+                            decision,
+                            parts_with_headers(parts.headers.clone()),
+                        ),
+                    )
+                })
+                .collect();
+            hedging::race(delay, attempts).await
+        }
+        _ => {
+            let started = Instant::now();
+            let target_key = routing_decision.target_key();
+            let result =
+                dispatch_anthropic_request(app_state, anthropic_request, routing_decision, parts)
+                    .await;
+            (target_key, result, started.elapsed())
+        }
+    };
 
+    if result.as_ref().is_ok_and(|response| response.status().is_success()) {
+        balancer::record_latency(&target_key, latency);
+    }
+    match shadow {
+        Some(handle) => handle.observe(result),
+        None => result,
+    }
+}
+
+/// Send an Anthropic-format request to the provider chosen by routing
+async fn dispatch_anthropic_request(
+    app_state: crate::server::AppState,
+    anthropic_request: anthropic_ox::ChatRequest,
+    routing_decision: RoutingDecision,
+    parts: Parts,
+) -> Result<axum::response::Response, StatusCode> {
+    let anthropic_request = parameter_mapping::apply_param_policy_to(
+        anthropic_request,
+        InboundFormat::Anthropic,
+        routing_decision.param_policy.as_ref(),
+    );
 
     // Check cached authentication FIRST for Anthropic provider
     if routing_decision.provider == "anthropic" {
         let is_claude_code = auth::is_claude_code_request(&parts.headers);
 
-        match &app_state.auth_cache.anthropic_method {
-            crate::auth::AuthMethod::OAuth { source, .. } => {
+        let auth_snapshot = app_state.auth_cache.load();
+        match &auth_snapshot.anthropic_method {
+            crate::auth::AuthMethod::OAuth { .. } => {
+                // Spread requests over configured OAuth accounts, if any
+                let oauth_method = auth::select_anthropic_oauth(
+                    &auth_snapshot.anthropic_method,
+                    &app_state.config,
+                )
+                .await;
+                let source = match &oauth_method {
+                    crate::auth::AuthMethod::OAuth { source, .. } => source.as_str(),
+                    _ => "unknown",
+                };
                 if is_claude_code {
                     tracing::info!(
                         "🔐 Claude Code → OAuth ({}, subscription billing) → {}",
@@ -255,8 +492,9 @@ pub async fn anthropic_messages(
                     );
                 }
                 return auth::handle_oauth_request(
-                    &app_state.auth_cache.anthropic_method,
+                    &oauth_method,
                     app_state.config.clone(),
+                    &app_state.auth_cache,
                     anthropic_request,
                     routing_decision,
                     parts,
@@ -271,17 +509,31 @@ pub async fn anthropic_messages(
                 // Fall through to regular provider routing
             }
             crate::auth::AuthMethod::Unavailable { reason } => {
-                tracing::error!("Anthropic authentication unavailable: {}", reason);
-                return Err(error_handling::unauthorized(&format!(
-                    "Anthropic authentication unavailable: {}",
-                    reason
-                )));
+                // A degraded start still serves Anthropic through configured API keys
+                let has_api_key = !crate::auth::key_pool::api_key_candidates(
+                    &*app_state.config.lock().await,
+                    "anthropic",
+                    "ANTHROPIC_API_KEY",
+                )
+                .is_empty();
+                if !has_api_key {
+                    tracing::error!("Anthropic authentication unavailable: {}", reason);
+                    return Err(error_handling::unauthorized(&format!(
+                        "Anthropic authentication unavailable: {}",
+                        reason
+                    )));
+                }
+                tracing::info!(
+                    "💳 Anthropic OAuth unavailable ({}) → API Key → {}",
+                    reason,
+                    anthropic_request.model
+                );
             }
         }
     }
 
     // Route to appropriate provider
-    match routing_decision.provider.as_str() {
+    match provider_kind(&app_state, &routing_decision.provider).await.as_str() {
         "anthropic" => {
             anthropic::handle_direct_anthropic_request(
                 app_state.config.clone(),
@@ -309,15 +561,70 @@ pub async fn anthropic_messages(
             )
             .await
         }
+        "azure-openai" => {
+            azure::handle_azure_request_from_anthropic(
+                app_state.config.clone(),
+                anthropic_request,
+                routing_decision,
+                parts.headers,
+            )
+            .await
+        }
         "gemini" | "google" => {
             gemini::handle_gemini_request(
                 app_state.config.clone(),
+                &app_state.auth_cache,
                 anthropic_request,
                 routing_decision,
                 parts.headers,
             )
             .await
         }
+        "ollama" => {
+            ollama::handle_ollama_request(
+                app_state.config.clone(),
+                LocalChat::from_anthropic(&anthropic_request),
+                InboundFormat::Anthropic,
+                routing_decision,
+            )
+            .await
+        }
+        "llamacpp" => {
+            llamacpp::handle_llamacpp_request(
+                app_state.config.clone(),
+                LocalChat::from_anthropic(&anthropic_request),
+                InboundFormat::Anthropic,
+                routing_decision,
+            )
+            .await
+        }
+        "openai-compatible" => {
+            openai_compatible::handle_openai_compatible_request(
+                app_state.config.clone(),
+                ChatInput::Local(LocalChat::from_anthropic(&anthropic_request)),
+                InboundFormat::Anthropic,
+                routing_decision,
+            )
+            .await
+        }
+        "bedrock" => {
+            bedrock::handle_bedrock_request(
+                app_state.config.clone(),
+                MessagesInput::Anthropic(anthropic_request),
+                InboundFormat::Anthropic,
+                routing_decision,
+            )
+            .await
+        }
+        "vertex" => {
+            vertex::handle_vertex_request(
+                app_state.config.clone(),
+                VertexInput::Anthropic(anthropic_request),
+                InboundFormat::Anthropic,
+                routing_decision,
+            )
+            .await
+        }
         provider_type => Err(error_handling::internal_error(
             "Custom providers not yet supported from Anthropic endpoint",
             &format!("Provider type: {}", provider_type),
@@ -361,17 +668,14 @@ pub async fn gemini_generate_content(
 
     // Route based on model name
     let config = app_state.config.lock().await.clone();
-    let router = ModelRouter::new(config);
-    let routing_decision = match router.route_model(model) {
-        Ok(decisions) => {
-            // Use the first routing decision (primary route)
-            decisions.into_iter().next().ok_or_else(|| {
-                error_handling::internal_error(
-                    "No routing decisions available",
-                    &"Failed to get routing decision",
-                )
-            })?
-        }
+    let router = ModelRouter::new(config.clone());
+    let features = RequestFeatures::from_value(
+        &gemini_request_value,
+        auth::is_claude_code_request(&parts.headers),
+    )
+    .with_session_id(auth::session_id_from_headers(&parts.headers));
+    let mut decisions = match router.route_request(model, &features) {
+        Ok(decisions) => decisions,
         Err(e) => {
             return Err(error_handling::bad_request(
                 &format!("Routing error for model {}", model),
@@ -379,16 +683,113 @@ pub async fn gemini_generate_content(
             ));
         }
     };
+    if let Err(response) = check_query_params(&config, &mut decisions, InboundFormat::Gemini) {
+        return Ok(response);
+    }
+    // Use the first routing decision (primary route)
+    let routing_decision = decisions.first().cloned().ok_or_else(|| {
+        error_handling::internal_error(
+            "No routing decisions available",
+            &"Failed to get routing decision",
+        )
+    })?;
+
+    // Shadow and hedge settings follow the alias the request routed through
+    let routed_alias = router.routed_alias(model, &features);
+
+    // Mirror to the shadow target, if configured
+    let shadow = shadow::shadow_target(&config, routed_alias)
+        .and_then(|target| route_shadow(&router, &target).map(|decision| (target, decision)))
+        .map(|(target, decision)| {
+            // Shadow responses are only recorded, so skip streaming: converted requests
+            // stream only when the body asks to, and Gemini itself has no body flag
+            let mut shadow_request = gemini_request_value.clone();
+            if let Some(fields) = shadow_request.as_object_mut() {
+                fields.remove("stream");
+            }
+            shadow::spawn(
+                "gemini",
+                model.to_string(),
+                routing_decision.target_key(),
+                target,
+                dispatch_gemini_request(
+                    app_state.clone(),
+                    shadow_request,
+                    model.to_string(),
+                    decision,
+                    parts.headers.clone(),
+                ),
+            )
+        });
 
     // Route to appropriate provider
-    match routing_decision.provider.as_str() {
+    let (target_key, result, latency) = match hedging::hedge_delay(&config, routed_alias) {
+        Some(delay) if decisions.len() > 1 => {
+            let attempts = decisions
+                .into_iter()
+                .map(|decision| {
+                    (
+                        decision.target_key(),
+                        dispatch_gemini_request(
+                            app_state.clone(),
+                            gemini_request_value.clone(),
+                            model.to_string(),
+                            decision,
+                            parts.headers.clone(),
+                        ),
+                    )
+                })
+                .collect();
+            hedging::race(delay, attempts).await
+        }
+        _ => {
+            let started = Instant::now();
+            let target_key = routing_decision.target_key();
+            let result = dispatch_gemini_request(
+                app_state,
+                gemini_request_value,
+                model.to_string(),
+                routing_decision,
+                parts.headers,
+            )
+            .await;
+            (target_key, result, started.elapsed())
+        }
+    };
+
+    if result.as_ref().is_ok_and(|response| response.status().is_success()) {
+        balancer::record_latency(&target_key, latency);
+    }
+    match shadow {
+        Some(handle) => handle.observe(result),
+        None => result,
+    }
+}
+
+/// Send a Gemini-format request to the provider chosen by routing
+async fn dispatch_gemini_request(
+    app_state: crate::server::AppState,
+    mut gemini_request_value: Value,
+    model: String,
+    routing_decision: RoutingDecision,
+    headers: HeaderMap,
+) -> Result<axum::response::Response, StatusCode> {
+    if let Some(policy) = &routing_decision.param_policy {
+        parameter_mapping::apply_param_policy(
+            &mut gemini_request_value,
+            InboundFormat::Gemini,
+            policy,
+        );
+    }
+    match provider_kind(&app_state, &routing_decision.provider).await.as_str() {
         "gemini" | "google" => {
             gemini::handle_direct_gemini_request(
                 app_state.config.clone(),
+                &app_state.auth_cache,
                 gemini_request_value,
-                model,
+                &model,
                 routing_decision,
-                parts.headers,
+                headers,
             )
             .await
         }
@@ -396,9 +797,9 @@ pub async fn gemini_generate_content(
             gemini::handle_openrouter_from_gemini(
                 app_state.config.clone(),
                 gemini_request_value.clone(),
-                model,
+                &model,
                 routing_decision,
-                parts.headers,
+                headers,
             )
             .await
         }
@@ -406,9 +807,54 @@ pub async fn gemini_generate_content(
             gemini::handle_anthropic_from_gemini(
                 app_state.config.clone(),
                 gemini_request_value.clone(),
-                model,
+                &model,
+                routing_decision,
+                headers,
+            )
+            .await
+        }
+        "ollama" => {
+            ollama::handle_ollama_request(
+                app_state.config.clone(),
+                LocalChat::from_gemini_value(&gemini_request_value),
+                InboundFormat::Gemini,
+                routing_decision,
+            )
+            .await
+        }
+        "llamacpp" => {
+            llamacpp::handle_llamacpp_request(
+                app_state.config.clone(),
+                LocalChat::from_gemini_value(&gemini_request_value),
+                InboundFormat::Gemini,
+                routing_decision,
+            )
+            .await
+        }
+        "openai-compatible" => {
+            openai_compatible::handle_openai_compatible_request(
+                app_state.config.clone(),
+                ChatInput::Local(LocalChat::from_gemini_value(&gemini_request_value)),
+                InboundFormat::Gemini,
+                routing_decision,
+            )
+            .await
+        }
+        "bedrock" => {
+            bedrock::handle_bedrock_request(
+                app_state.config.clone(),
+                MessagesInput::Local(LocalChat::from_gemini_value(&gemini_request_value)),
+                InboundFormat::Gemini,
+                routing_decision,
+            )
+            .await
+        }
+        "vertex" => {
+            vertex::handle_vertex_request(
+                app_state.config.clone(),
+                VertexInput::Gemini(gemini_request_value),
+                InboundFormat::Gemini,
                 routing_decision,
-                parts.headers,
             )
             .await
         }
@@ -418,3 +864,76 @@ pub async fn gemini_generate_content(
         )),
     }
 }
+
+/// Dispatch key for a provider, see [`Config::provider_kind`]
+async fn provider_kind(app_state: &crate::server::AppState, provider: &str) -> String {
+    app_state.config.lock().await.provider_kind(provider)
+}
+
+/// Check each decision's model-string parameters against its provider's schema
+///
+/// In strict mode an unknown or malformed parameter on the primary target rejects the
+/// request with a 400 in the client's error format. Bad parameters on fallback targets,
+/// and any in lenient mode, are logged and dropped so one fallback can't fail the route.
+fn check_query_params(
+    config: &Config,
+    decisions: &mut [RoutingDecision],
+    format: InboundFormat,
+) -> Result<(), axum::response::Response> {
+    for (index, decision) in decisions.iter_mut().enumerate() {
+        let Some(params) = decision.query_params.as_mut() else {
+            continue;
+        };
+        let kind = config.provider_kind(&decision.provider);
+        let errors = parameter_mapping::validate_params(&kind, params);
+        if errors.is_empty() {
+            continue;
+        }
+        let details = errors.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ");
+        match config.routing.param_validation {
+            ParamValidation::Strict if index == 0 => {
+                tracing::warn!(
+                    "Rejecting model '{}': invalid parameters {}",
+                    decision.original_model,
+                    details
+                );
+                return Err(error_handling::invalid_request_response(
+                    format,
+                    &format!(
+                        "Invalid parameters in model '{}' for provider '{}': {}",
+                        decision.original_model, decision.provider, details
+                    ),
+                ));
+            }
+            ParamValidation::Strict | ParamValidation::Lenient => {
+                tracing::warn!(
+                    "Ignoring invalid parameters in model '{}': {}",
+                    decision.original_model,
+                    details
+                );
+                for error in &errors {
+                    params.remove(&error.key);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Resolve a shadow target to the routing decision used for the mirrored request
+fn route_shadow(router: &ModelRouter, target: &str) -> Option<RoutingDecision> {
+    match router.route_model(target) {
+        Ok(decisions) => decisions.into_iter().next(),
+        Err(e) => {
+            tracing::warn!(target: "prism::shadow", "Cannot route shadow target '{}': {}", target, e);
+            None
+        }
+    }
+}
+
+/// Build request parts carrying only the given headers (for replayed requests)
+fn parts_with_headers(headers: HeaderMap) -> Parts {
+    let (mut parts, ()) = axum::http::Request::new(()).into_parts();
+    parts.headers = headers;
+    parts
+}
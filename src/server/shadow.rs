@@ -0,0 +1,431 @@
+//! Shadow (mirror) traffic: replay requests against a second model for offline comparison.
+//!
+//! The primary response is served untouched. The shadow request runs in its own task and
+//! both outcomes are appended as one JSON line to `<data_dir>/shadow/shadow-YYYY-MM-DD.jsonl`.
+
+use axum::body::Body;
+use axum::http::StatusCode;
+use axum::response::Response;
+use futures_util::StreamExt;
+use serde::Serialize;
+use serde_json::Value;
+use std::future::Future;
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::oneshot;
+
+use crate::auth::key_pool;
+use crate::config::Config;
+
+/// Maximum bytes of each response kept in the comparison record
+const MAX_CAPTURE_BYTES: usize = 256 * 1024;
+
+/// How long the recorder waits for the primary response body to finish
+const PRIMARY_CAPTURE_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
+/// Look up the shadow target configured for a requested model/alias
+pub fn shadow_target(config: &Config, model: &str) -> Option<String> {
+    config
+        .routing
+        .shadow
+        .get(model)
+        .filter(|target| !target.trim().is_empty())
+        .cloned()
+}
+
+/// Outcome of one side of a shadow comparison
+#[derive(Debug, Serialize)]
+pub struct ShadowOutcome {
+    pub target: String,
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input_tokens: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_tokens: Option<u64>,
+    pub output: String,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+}
+
+impl ShadowOutcome {
+    fn from_bytes(target: String, latency: Duration, status: u16, bytes: &[u8]) -> Self {
+        let (input_tokens, output_tokens) = extract_usage(bytes);
+        let truncated = bytes.len() > MAX_CAPTURE_BYTES;
+        let kept = &bytes[..bytes.len().min(MAX_CAPTURE_BYTES)];
+        Self {
+            target,
+            latency_ms: latency.as_millis() as u64,
+            status: Some(status),
+            input_tokens,
+            output_tokens,
+            output: String::from_utf8_lossy(kept).into_owned(),
+            truncated,
+        }
+    }
+
+    fn failed(target: String, latency: Duration, status: Option<u16>, error: String) -> Self {
+        Self {
+            target,
+            latency_ms: latency.as_millis() as u64,
+            status,
+            input_tokens: None,
+            output_tokens: None,
+            output: error,
+            truncated: false,
+        }
+    }
+}
+
+/// One line of the shadow comparison log
+#[derive(Debug, Serialize)]
+struct ShadowRecord {
+    timestamp: String,
+    endpoint: &'static str,
+    requested_model: String,
+    primary: ShadowOutcome,
+    shadow: ShadowOutcome,
+}
+
+/// Primary-side capture, delivered when the response body has been fully sent (or dropped)
+struct PrimaryCapture {
+    target: String,
+    status: u16,
+    latency: Duration,
+    bytes: Vec<u8>,
+    complete: bool,
+}
+
+/// Handle to an in-flight shadow request, waiting for the primary outcome
+pub struct ShadowHandle {
+    primary_target: String,
+    started: Instant,
+    sender: oneshot::Sender<PrimaryCapture>,
+}
+
+/// Start the shadow request immediately so both sides run concurrently
+///
+/// `endpoint` identifies the inbound API format ("anthropic", "openai", "gemini").
+pub fn spawn<F>(
+    endpoint: &'static str,
+    requested_model: String,
+    primary_target: String,
+    shadow_target: String,
+    shadow_request: F,
+) -> ShadowHandle
+where
+    F: Future<Output = Result<Response, StatusCode>> + Send + 'static,
+{
+    let (sender, receiver) = oneshot::channel();
+    let started = Instant::now();
+
+    tracing::debug!(
+        target: "prism::shadow",
+        "Mirroring '{}' to shadow target '{}'",
+        requested_model,
+        shadow_target
+    );
+
+    tokio::spawn(async move {
+        // Run in its own task so a panic in the shadow path stays contained, with key
+        // reporting muted so a failing shadow never benches a key the primary path uses
+        let shadow_started = Instant::now();
+        let shadow_call = key_pool::without_reporting(async move {
+            let response = shadow_request.await?;
+            let status = response.status().as_u16();
+            Ok::<_, StatusCode>((status, read_capped(response.into_body()).await))
+        });
+        let shadow = match tokio::spawn(shadow_call).await {
+            Ok(Ok((status, Ok(bytes)))) => {
+                ShadowOutcome::from_bytes(shadow_target, shadow_started.elapsed(), status, &bytes)
+            }
+            Ok(Ok((status, Err(e)))) => ShadowOutcome::failed(
+                shadow_target,
+                shadow_started.elapsed(),
+                Some(status),
+                format!("failed to read body: {}", e),
+            ),
+            Ok(Err(status)) => ShadowOutcome::failed(
+                shadow_target,
+                shadow_started.elapsed(),
+                Some(status.as_u16()),
+                "shadow request failed".to_string(),
+            ),
+            Err(e) => ShadowOutcome::failed(
+                shadow_target,
+                shadow_started.elapsed(),
+                None,
+                format!("shadow task failed: {}", e),
+            ),
+        };
+
+        let primary = match tokio::time::timeout(PRIMARY_CAPTURE_TIMEOUT, receiver).await {
+            Ok(Ok(capture)) => capture,
+            _ => {
+                tracing::debug!(target: "prism::shadow", "Primary response was not captured, skipping record");
+                return;
+            }
+        };
+
+        let record = ShadowRecord {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            endpoint,
+            requested_model,
+            primary: primary.into_outcome(),
+            shadow,
+        };
+
+        if let Err(e) = write_record(&record).await {
+            tracing::warn!(target: "prism::shadow", "Failed to write shadow record: {}", e);
+        }
+    });
+
+    ShadowHandle {
+        primary_target,
+        started,
+        sender,
+    }
+}
+
+impl PrimaryCapture {
+    fn into_outcome(self) -> ShadowOutcome {
+        let mut outcome =
+            ShadowOutcome::from_bytes(self.target, self.latency, self.status, &self.bytes);
+        // The capture buffer is capped, so anything not fully seen counts as truncated
+        outcome.truncated |= !self.complete;
+        outcome
+    }
+}
+
+impl ShadowHandle {
+    /// Attach the primary result; the response body is tapped as it streams to the client
+    pub fn observe(self, result: Result<Response, StatusCode>) -> Result<Response, StatusCode> {
+        let ShadowHandle {
+            primary_target,
+            started,
+            sender,
+        } = self;
+
+        let response = match result {
+            Ok(response) => response,
+            Err(status) => {
+                let _ = sender.send(PrimaryCapture {
+                    target: primary_target,
+                    status: status.as_u16(),
+                    latency: started.elapsed(),
+                    bytes: Vec::new(),
+                    complete: true,
+                });
+                return Err(status);
+            }
+        };
+
+        let (parts, body) = response.into_parts();
+        let mut tap = BodyTap {
+            target: primary_target,
+            status: parts.status.as_u16(),
+            started,
+            bytes: Vec::new(),
+            complete: true,
+            sender: Some(sender),
+        };
+
+        let stream = body.into_data_stream().map(move |chunk| {
+            if let Ok(bytes) = &chunk {
+                tap.push(bytes);
+            }
+            chunk
+        });
+
+        Ok(Response::from_parts(parts, Body::from_stream(stream)))
+    }
+}
+
+/// Copies response bytes as they pass through; reports when the stream is dropped
+struct BodyTap {
+    target: String,
+    status: u16,
+    started: Instant,
+    bytes: Vec<u8>,
+    complete: bool,
+    sender: Option<oneshot::Sender<PrimaryCapture>>,
+}
+
+impl BodyTap {
+    fn push(&mut self, chunk: &[u8]) {
+        let room = MAX_CAPTURE_BYTES + 1 - self.bytes.len().min(MAX_CAPTURE_BYTES + 1);
+        if chunk.len() > room {
+            self.complete = false;
+        }
+        self.bytes
+            .extend_from_slice(&chunk[..chunk.len().min(room)]);
+    }
+}
+
+impl Drop for BodyTap {
+    fn drop(&mut self) {
+        if let Some(sender) = self.sender.take() {
+            let _ = sender.send(PrimaryCapture {
+                target: std::mem::take(&mut self.target),
+                status: self.status,
+                latency: self.started.elapsed(),
+                bytes: std::mem::take(&mut self.bytes),
+                complete: self.complete,
+            });
+        }
+    }
+}
+
+/// Read a body up to one byte past the capture cap; the rest is never buffered
+async fn read_capped(body: Body) -> Result<Vec<u8>, axum::Error> {
+    let mut data = body.into_data_stream();
+    let mut bytes = Vec::new();
+    while let Some(chunk) = data.next().await {
+        let chunk = chunk?;
+        let room = MAX_CAPTURE_BYTES + 1 - bytes.len();
+        bytes.extend_from_slice(&chunk[..chunk.len().min(room)]);
+        if bytes.len() > MAX_CAPTURE_BYTES {
+            // Dropping the stream stops reading the upstream response
+            break;
+        }
+    }
+    Ok(bytes)
+}
+
+async fn write_record(record: &ShadowRecord) -> crate::error::Result<()> {
+    let dir = Config::data_dir()?.join("shadow");
+    tokio::fs::create_dir_all(&dir).await?;
+    let path = dir.join(format!(
+        "shadow-{}.jsonl",
+        chrono::Local::now().format("%Y-%m-%d")
+    ));
+
+    let mut line = serde_json::to_string(record)?;
+    line.push('\n');
+
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .await?;
+    file.write_all(line.as_bytes()).await?;
+    Ok(())
+}
+
+/// Pull token counts out of a JSON body or SSE stream in any supported format
+fn extract_usage(bytes: &[u8]) -> (Option<u64>, Option<u64>) {
+    let mut usage = (None, None);
+
+    if let Ok(value) = serde_json::from_slice::<Value>(bytes) {
+        collect_usage(&value, &mut usage);
+        return usage;
+    }
+
+    // Server-sent events: usage is spread over several events (e.g. message_start/message_delta)
+    let text = String::from_utf8_lossy(bytes);
+    for line in text.lines() {
+        if let Some(data) = line.strip_prefix("data:")
+            && let Ok(value) = serde_json::from_str::<Value>(data.trim())
+        {
+            collect_usage(&value, &mut usage);
+        }
+    }
+    usage
+}
+
+fn collect_usage(value: &Value, usage: &mut (Option<u64>, Option<u64>)) {
+    match value {
+        Value::Object(map) => {
+            for (key, v) in map {
+                match (key.as_str(), v.as_u64()) {
+                    ("input_tokens" | "prompt_tokens" | "promptTokenCount", Some(n)) => {
+                        usage.0 = Some(usage.0.unwrap_or(0).max(n));
+                    }
+                    ("output_tokens" | "completion_tokens" | "candidatesTokenCount", Some(n)) => {
+                        usage.1 = Some(usage.1.unwrap_or(0).max(n));
+                    }
+                    _ => collect_usage(v, usage),
+                }
+            }
+        }
+        Value::Array(arr) => arr.iter().for_each(|v| collect_usage(v, usage)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_usage_from_json_formats() {
+        let anthropic = br#"{"usage": {"input_tokens": 12, "output_tokens": 34}}"#;
+        assert_eq!(extract_usage(anthropic), (Some(12), Some(34)));
+
+        let openai =
+            br#"{"usage": {"prompt_tokens": 5, "completion_tokens": 7, "total_tokens": 12}}"#;
+        assert_eq!(extract_usage(openai), (Some(5), Some(7)));
+
+        let gemini = br#"{"usageMetadata": {"promptTokenCount": 3, "candidatesTokenCount": 4}}"#;
+        assert_eq!(extract_usage(gemini), (Some(3), Some(4)));
+    }
+
+    #[test]
+    fn test_extract_usage_from_sse_stream() {
+        let sse = b"event: message_start\n\
+data: {\"type\":\"message_start\",\"message\":{\"usage\":{\"input_tokens\":40,\"output_tokens\":1}}}\n\n\
+event: message_delta\n\
+data: {\"type\":\"message_delta\",\"usage\":{\"output_tokens\":99}}\n\n";
+        assert_eq!(extract_usage(sse), (Some(40), Some(99)));
+    }
+
+    #[test]
+    fn test_body_tap_caps_capture() {
+        let mut tap = BodyTap {
+            target: "anthropic/primary".to_string(),
+            status: 200,
+            started: Instant::now(),
+            bytes: Vec::new(),
+            complete: true,
+            sender: None,
+        };
+        tap.push(&vec![b'a'; MAX_CAPTURE_BYTES]);
+        assert!(tap.complete);
+        tap.push(b"more");
+        assert!(!tap.complete);
+        assert!(tap.bytes.len() <= MAX_CAPTURE_BYTES + 1);
+    }
+
+    #[tokio::test]
+    async fn test_read_capped_stops_at_cap() {
+        let chunks = (0..8).map(|_| Ok::<_, std::io::Error>(vec![b'a'; MAX_CAPTURE_BYTES / 2]));
+        let body = Body::from_stream(futures_util::stream::iter(chunks));
+        let bytes = read_capped(body).await.unwrap();
+        assert_eq!(bytes.len(), MAX_CAPTURE_BYTES + 1);
+
+        let outcome = ShadowOutcome::from_bytes("shadow".to_string(), Duration::ZERO, 200, &bytes);
+        assert!(outcome.truncated);
+        assert_eq!(outcome.output.len(), MAX_CAPTURE_BYTES);
+    }
+
+    #[test]
+    fn test_shadow_target_lookup() {
+        let mut config = Config::default();
+        config
+            .routing
+            .shadow
+            .insert("sonnet".to_string(), "openrouter/z-ai/glm-4.6".to_string());
+        config
+            .routing
+            .shadow
+            .insert("empty".to_string(), String::new());
+
+        assert_eq!(
+            shadow_target(&config, "sonnet").as_deref(),
+            Some("openrouter/z-ai/glm-4.6")
+        );
+        assert!(shadow_target(&config, "empty").is_none());
+        assert!(shadow_target(&config, "haiku").is_none());
+    }
+}
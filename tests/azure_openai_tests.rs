@@ -0,0 +1,180 @@
+use httpmock::prelude::*;
+use rustc_hash::FxHashMap;
+use serde_json::{Value, json};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use prism::{
+    config::{Config, ProviderConfig},
+    router::name_based::RoutingDecision,
+    server::providers::azure::{handle_azure_request_from_anthropic, handle_azure_request_from_openai},
+};
+
+fn azure_config(endpoint: &str) -> Arc<Mutex<Config>> {
+    let provider: ProviderConfig = toml::from_str(&format!(
+        r#"
+            type = "azure-openai"
+            endpoint = "{}"
+            api_key = "test-azure-key"
+            api_version = "2024-10-21"
+
+            [deployments]
+            "gpt-4o" = "prod-gpt4o"
+        "#,
+        endpoint
+    ))
+    .unwrap();
+
+    let mut providers = FxHashMap::default();
+    providers.insert("azure".to_string(), provider);
+    Arc::new(Mutex::new(Config {
+        providers,
+        ..Config::default()
+    }))
+}
+
+fn decision(model: &str) -> RoutingDecision {
+    RoutingDecision {
+        provider: "azure".to_string(),
+        model: model.to_string(),
+        original_model: format!("azure/{}", model),
+        provider_preference: None,
+        query_params: None,
+        param_policy: None,
+    }
+}
+
+fn chat_request(model: &str) -> openai_ox::request::ChatRequest {
+    serde_json::from_value(json!({
+        "model": model,
+        "messages": [{"role": "user", "content": "Hello"}]
+    }))
+    .unwrap()
+}
+
+async fn body_json(response: axum::response::Response) -> Value {
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&bytes).unwrap()
+}
+
+/// Chat Completions go to the mapped deployment with api-version and the api-key header
+#[tokio::test]
+async fn test_azure_chat_completions_uses_deployment_url() {
+    let server = MockServer::start_async().await;
+    let mock = server
+        .mock_async(|when, then| {
+            when.method(POST)
+                .path("/openai/deployments/prod-gpt4o/chat/completions")
+                .query_param("api-version", "2024-10-21")
+                .header_exists("api-key")
+                .json_body_partial(r#"{"model": "prod-gpt4o"}"#);
+            then.status(200).json_body(json!({
+                "id": "chatcmpl-1",
+                "object": "chat.completion",
+                "created": 0,
+                "model": "gpt-4o",
+                "choices": [{
+                    "index": 0,
+                    "message": {"role": "assistant", "content": "Hi there"},
+                    "finish_reason": "stop"
+                }]
+            }));
+        })
+        .await;
+
+    let response = handle_azure_request_from_openai(
+        azure_config(&server.base_url()),
+        chat_request("gpt-4o"),
+        decision("gpt-4o"),
+        Default::default(),
+    )
+    .await
+    .unwrap();
+
+    mock.assert_async().await;
+    assert_eq!(response.status(), 200);
+    let body = body_json(response).await;
+    assert_eq!(body["choices"][0]["message"]["content"], "Hi there");
+}
+
+/// Azure error bodies come back in OpenAI's error shape with the upstream status
+#[tokio::test]
+async fn test_azure_error_is_normalized() {
+    let server = MockServer::start_async().await;
+    server
+        .mock_async(|when, then| {
+            when.method(POST)
+                .path("/openai/deployments/o3-mini/chat/completions");
+            then.status(404).json_body(json!({
+                "error": {
+                    "code": "DeploymentNotFound",
+                    "message": "The API deployment for this resource does not exist."
+                }
+            }));
+        })
+        .await;
+
+    let response = handle_azure_request_from_openai(
+        azure_config(&server.base_url()),
+        chat_request("o3-mini"),
+        decision("o3-mini"),
+        Default::default(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(response.status(), 404);
+    let body = body_json(response).await;
+    assert_eq!(body["error"]["type"], "not_found_error");
+    assert_eq!(body["error"]["code"], "DeploymentNotFound");
+    assert!(body["error"]["message"].as_str().unwrap().contains("o3-mini"));
+}
+
+/// Anthropic clients get Responses output back as an Anthropic message
+#[tokio::test]
+async fn test_azure_responses_converted_for_anthropic_client() {
+    let server = MockServer::start_async().await;
+    server
+        .mock_async(|when, then| {
+            when.method(POST)
+                .path("/openai/responses")
+                .query_param("api-version", "2024-10-21");
+            then.status(200).json_body(json!({
+                "id": "resp_1",
+                "object": "response",
+                "status": "completed",
+                "output": [{
+                    "type": "message",
+                    "role": "assistant",
+                    "content": [{"type": "output_text", "text": "Hi there"}]
+                }],
+                "usage": {"input_tokens": 12, "output_tokens": 3}
+            }));
+        })
+        .await;
+
+    let anthropic_request: anthropic_ox::ChatRequest = serde_json::from_value(json!({
+        "model": "gpt-4o",
+        "max_tokens": 100,
+        "messages": [{"role": "user", "content": "Hello"}]
+    }))
+    .unwrap();
+
+    let response = handle_azure_request_from_anthropic(
+        azure_config(&server.base_url()),
+        anthropic_request,
+        decision("gpt-4o"),
+        Default::default(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(response.status(), 200);
+    let body = body_json(response).await;
+    assert_eq!(body["type"], "message");
+    assert_eq!(body["content"][0]["text"], "Hi there");
+    assert_eq!(body["stop_reason"], "end_turn");
+    assert_eq!(body["usage"]["input_tokens"], 12);
+}
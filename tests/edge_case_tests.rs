@@ -9,7 +9,7 @@ use std::sync::{Arc, atomic::AtomicU64};
 use tokio::sync::Mutex;
 
 use prism::{
-    auth::{AuthCache, AuthMethod, initialize_auth_cache},
+    auth::{AuthCache, AuthMethod, AuthSnapshot, initialize_auth_cache},
     config::{AuthConfig, Config, ProviderConfig, RetryConfig, RoutingConfig, ServerConfig},
     server::{
         AppState,
@@ -30,6 +30,7 @@ async fn create_test_app_state() -> AppState {
             api_key: None,
             api_key_fallback: false,
             fallback_on_errors: vec![429],
+            ..Default::default()
         },
     );
     providers.insert(
@@ -42,6 +43,7 @@ async fn create_test_app_state() -> AppState {
             api_key: None,
             api_key_fallback: false,
             fallback_on_errors: vec![429],
+            ..Default::default()
         },
     );
 
@@ -51,15 +53,17 @@ async fn create_test_app_state() -> AppState {
             providers,
             routing: RoutingConfig {
                 models: FxHashMap::default(),
+                ..Default::default()
             },
             auth: FxHashMap::default(),
+            ..Default::default()
         })),
-        auth_cache: Arc::new(initialize_auth_cache().await.unwrap_or_else(|_| AuthCache {
+        auth_cache: Arc::new(initialize_auth_cache().await.unwrap_or_else(|_| AuthCache::new(AuthSnapshot {
             anthropic_method: AuthMethod::ApiKey,
             gemini_method: AuthMethod::ApiKey,
             openai_method: AuthMethod::ApiKey,
             cached_at: SystemTime::now(),
-        })),
+        }))),
         last_config_check: Arc::new(AtomicU64::new(0)),
         config_path: std::path::PathBuf::from("/tmp/test_prism.toml"),
     }
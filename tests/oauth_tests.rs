@@ -11,7 +11,7 @@ use std::time::SystemTime;
 use tokio::sync::Mutex;
 
 use prism::{
-    auth::{AuthCache, AuthMethod},
+    auth::{AuthCache, AuthMethod, AuthSnapshot},
     config::{AuthConfig, Config, ProviderConfig, RetryConfig, RoutingConfig, ServerConfig},
     server::{AppState, routes::anthropic_messages},
 };
@@ -28,11 +28,12 @@ async fn create_test_app_state_with_oauth() -> AppState {
             api_key: None,
             api_key_fallback: false,
             fallback_on_errors: vec![429],
+            ..Default::default()
         },
     );
 
     // Create auth cache with mock OAuth token
-    let auth_cache = AuthCache {
+    let auth_cache = AuthCache::new(AuthSnapshot {
         anthropic_method: AuthMethod::OAuth {
             source: "Test OAuth".to_string(),
             token: "test-oauth-token-12345".to_string(),
@@ -40,7 +41,7 @@ async fn create_test_app_state_with_oauth() -> AppState {
         gemini_method: AuthMethod::ApiKey,
         openai_method: AuthMethod::ApiKey,
         cached_at: SystemTime::now(),
-    };
+    });
 
     AppState {
         config: Arc::new(Mutex::new(Config {
@@ -48,8 +49,10 @@ async fn create_test_app_state_with_oauth() -> AppState {
             providers,
             routing: RoutingConfig {
                 models: FxHashMap::default(),
+                ..Default::default()
             },
             auth: FxHashMap::default(),
+            ..Default::default()
         })),
         auth_cache: Arc::new(auth_cache),
         config_path: PathBuf::from("/tmp/prism.toml"),
@@ -130,15 +133,16 @@ async fn test_oauth_fallback_to_api_key() {
             api_key: None,
             api_key_fallback: false,
             fallback_on_errors: vec![429],
+            ..Default::default()
         },
     );
 
-    let auth_cache = AuthCache {
+    let auth_cache = AuthCache::new(AuthSnapshot {
         anthropic_method: AuthMethod::ApiKey,
         gemini_method: AuthMethod::ApiKey,
         openai_method: AuthMethod::ApiKey,
         cached_at: SystemTime::now(),
-    };
+    });
 
     let app_state = AppState {
         config: Arc::new(Mutex::new(Config {
@@ -146,8 +150,10 @@ async fn test_oauth_fallback_to_api_key() {
             providers,
             routing: RoutingConfig {
                 models: FxHashMap::default(),
+                ..Default::default()
             },
             auth: FxHashMap::default(),
+            ..Default::default()
         })),
         auth_cache: Arc::new(auth_cache),
         config_path: PathBuf::from("/tmp/prism.toml"),
@@ -177,3 +183,41 @@ async fn test_oauth_fallback_to_api_key() {
     let err = response.unwrap_err();
     assert_ne!(err, StatusCode::UNAUTHORIZED);
 }
+
+#[test]
+fn test_authorization_urls_carry_fresh_state() {
+    use prism::auth::anthropic::AnthropicOAuth;
+    use prism::auth::google::GoogleOAuth;
+
+    let redirect_uri = "http://localhost:43117/callback";
+    let anthropic = AnthropicOAuth::create_loopback_authorization_url(redirect_uri).unwrap();
+    let google = GoogleOAuth::create_loopback_authorization_url(redirect_uri).unwrap();
+
+    for (url, state, verifier) in [
+        (&anthropic.url, &anthropic.state, &anthropic.verifier),
+        (&google.url, &google.state, &google.verifier),
+    ] {
+        let url = url::Url::parse(url).unwrap();
+        let params: FxHashMap<String, String> = url.query_pairs().into_owned().collect();
+        assert_eq!(params["redirect_uri"], redirect_uri);
+        assert_eq!(&params["state"], state);
+        // The state is random, never the PKCE verifier
+        assert_ne!(state, verifier);
+    }
+    assert_ne!(
+        AnthropicOAuth::create_authorization_url().unwrap().state,
+        anthropic.state
+    );
+}
+
+#[test]
+fn test_anthropic_pasted_code_requires_matching_state() {
+    use prism::auth::anthropic::AnthropicOAuth;
+
+    assert_eq!(
+        AnthropicOAuth::parse_pasted_code(" abc123#state-1\n", "state-1").unwrap(),
+        "abc123"
+    );
+    assert!(AnthropicOAuth::parse_pasted_code("abc123#state-2", "state-1").is_err());
+    assert!(AnthropicOAuth::parse_pasted_code("abc123", "state-1").is_err());
+}